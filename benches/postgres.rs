@@ -0,0 +1,90 @@
+//! Loading the universe from the Postgres database in `DATABASE_URL`, once on a
+//! single connection and once in parallel chunks, and reading the jumps table in
+//! chunks split by `fromSolarSystemID % 4` and by ranges of ids as
+//! `DatabaseBuilder::connections()` does. The modulo needs a scan of the whole
+//! table per chunk, ranges read only their part of the index.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use diesel::{Connection, PgConnection, RunQueryDsl};
+use neweden::source::postgres::DatabaseBuilder;
+
+const CHUNKS: usize = 4;
+
+fn uri() -> String {
+    std::env::var("DATABASE_URL").expect("expected env variable DATABASE_URL set")
+}
+
+fn bench_build(c: &mut Criterion) {
+    let uri = uri();
+    let mut group = c.benchmark_group("build");
+    group.sample_size(10);
+    for connections in [1, CHUNKS] {
+        group.bench_function(format!("{connections} connections"), |b| {
+            b.iter(|| {
+                DatabaseBuilder::new(&uri)
+                    .connections(connections)
+                    .build()
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_chunks(c: &mut Criterion) {
+    let mut conn = PgConnection::establish(&uri()).unwrap();
+    // the first id of every chunk but the first, like `DatabaseBuilder` finds them
+    let starts = (1..CHUNKS)
+        .map(|chunk| {
+            diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>(&format!(
+                r#"(SELECT "fromSolarSystemID" FROM "mapSolarSystemJumps"
+                ORDER BY "fromSolarSystemID"
+                OFFSET (SELECT COUNT(*) FROM "mapSolarSystemJumps") * {chunk} / {CHUNKS}
+                LIMIT 1)"#
+            )))
+            .get_result::<i32>(&mut conn)
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("jump chunks");
+    group.sample_size(10);
+    group.bench_function("modulo", |b| {
+        b.iter(|| {
+            for chunk in 0..CHUNKS {
+                diesel::sql_query(format!(
+                    r#"SELECT * FROM "mapSolarSystemJumps"
+                    WHERE "fromSolarSystemID" % {CHUNKS} = {chunk}"#
+                ))
+                .execute(&mut conn)
+                .unwrap();
+            }
+        })
+    });
+    group.bench_function("ranges", |b| {
+        b.iter(|| {
+            for chunk in 0..CHUNKS {
+                let mut filter = Vec::new();
+                if chunk > 0 {
+                    filter.push(format!(r#""fromSolarSystemID" >= {}"#, starts[chunk - 1]));
+                }
+                if let Some(end) = starts.get(chunk) {
+                    filter.push(format!(r#""fromSolarSystemID" < {end}"#));
+                }
+                if filter.is_empty() {
+                    filter.push("TRUE".to_string());
+                }
+                diesel::sql_query(format!(
+                    r#"SELECT * FROM "mapSolarSystemJumps" WHERE {}"#,
+                    filter.join(" AND ")
+                ))
+                .execute(&mut conn)
+                .unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_build, bench_chunks);
+criterion_main!(benches);
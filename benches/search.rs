@@ -0,0 +1,39 @@
+//! Indexing all systems of a universe of SDE size, once with a single indexing
+//! thread and once with one thread per core, up to the maximum of 8.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use neweden::builder::UniverseBuilder;
+use neweden::{System, Universe};
+
+/// Roughly the number of known space and wormhole systems in the SDE.
+const SYSTEMS: u32 = 8_000;
+
+fn universe() -> Universe {
+    (0..SYSTEMS)
+        .fold(UniverseBuilder::default(), |builder, i| {
+            builder.system(System {
+                id: (30000000 + i).into(),
+                name: format!("{:X}-{:04}", i * 7919 % 4096, i),
+                coordinate: (i as f64, 0.0, 0.0).into(),
+                security: 0.5.into(),
+                region_name: "Region".to_string(),
+                faction_id: None,
+            })
+        })
+        .build()
+}
+
+fn bench_reindex(c: &mut Criterion) {
+    let mut universe = universe();
+    let mut group = c.benchmark_group("reindex");
+    group.sample_size(10);
+    for threads in [1, 8] {
+        group.bench_function(format!("{threads} threads"), |b| {
+            b.iter(|| universe.reindex(threads).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_reindex);
+criterion_main!(benches);
@@ -0,0 +1,234 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! NPC mission agents, e.g. to find the nearest level 4 security agent of a
+//! corporation. With the `sqlite` feature agents can be loaded from a dump using
+//! `DatabaseBuilder::agents()`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::navigation::{Distance, distance_report};
+use crate::types;
+
+/// The kind of missions an agent offers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Division {
+    Distribution,
+    Mining,
+    Security,
+    /// Research, storyline and other divisions by their SDE id.
+    Other(u32),
+}
+
+impl From<u32> for Division {
+    fn from(id: u32) -> Self {
+        match id {
+            22 => Self::Distribution,
+            23 => Self::Mining,
+            24 => Self::Security,
+            id => Self::Other(id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Agent {
+    pub id: u32,
+    pub name: String,
+    pub level: u8,
+    pub division: Division,
+    pub corporation_id: u32,
+    pub corporation_name: String,
+    pub station_id: u32,
+    pub system: types::SystemId,
+}
+
+/// An agent and its distance from the origin of a query.
+#[derive(Debug)]
+pub struct AgentDistance<'a, 'b> {
+    pub agent: &'b Agent,
+    pub distance: Distance<'a>,
+}
+
+/// All agents, indexed by their system.
+///
+/// # Example
+/// ```
+/// use neweden::agents::{Agent, Agents, Division};
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::{Navigatable, System};
+///
+/// let universe = UniverseBuilder::default()
+///     .system(System {
+///         id: 30000142.into(),
+///         name: "Jita".to_string(),
+///         coordinate: (0.0, 0.0, 0.0).into(),
+///         security: 0.9459.into(),
+///         region_name: "The Forge".to_string(),
+///         faction_id: None,
+///     })
+///     .build();
+/// let agents = Agents::new(vec![Agent {
+///     id: 3008416,
+///     name: "Antaken Kamola".to_string(),
+///     level: 4,
+///     division: Division::Security,
+///     corporation_id: 1000035,
+///     corporation_name: "Caldari Navy".to_string(),
+///     station_id: 60003760,
+///     system: 30000142.into(),
+/// }]);
+/// let jita = universe.get_system(&30000142.into()).unwrap();
+/// let found = agents.nearest(&universe, jita, 5, |agent| {
+///     agent.level == 4
+///         && agent.division == Division::Security
+///         && agent.corporation_name == "Caldari Navy"
+/// });
+/// assert_eq!(Some(0), found[0].distance.jumps);
+/// ```
+#[derive(Debug, Default)]
+pub struct Agents {
+    agents: Vec<Agent>,
+    by_system: HashMap<types::SystemId, Vec<usize>>,
+}
+
+impl Agents {
+    pub fn new(agents: Vec<Agent>) -> Self {
+        let mut by_system: HashMap<_, Vec<_>> = HashMap::new();
+        for (i, agent) in agents.iter().enumerate() {
+            by_system.entry(agent.system).or_default().push(i);
+        }
+        Self { agents, by_system }
+    }
+
+    pub fn len(&self) -> usize {
+        self.agents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Agent> {
+        self.agents.iter()
+    }
+
+    pub fn in_system(&self, id: &types::SystemId) -> impl Iterator<Item = &Agent> {
+        self.by_system
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|i| &self.agents[*i])
+    }
+
+    /// Matching agents within `max_jumps` of `origin`, nearest first. Agents in the
+    /// same system are ordered by level, highest first.
+    pub fn nearest<'a, 'b>(
+        &'b self,
+        universe: &'a dyn types::Navigatable,
+        origin: &'a types::System,
+        max_jumps: usize,
+        predicate: impl Fn(&Agent) -> bool,
+    ) -> Vec<AgentDistance<'a, 'b>> {
+        let matching = self
+            .agents
+            .iter()
+            .filter(|agent| predicate(agent))
+            .collect::<Vec<_>>();
+        let systems = matching
+            .iter()
+            .map(|agent| agent.system)
+            .collect::<HashSet<_>>();
+        let targets = systems.iter().filter_map(|id| universe.get_system(id));
+        let distances = distance_report(universe, origin, targets)
+            .into_iter()
+            .filter(|distance| distance.jumps.is_some_and(|jumps| jumps <= max_jumps))
+            .map(|distance| (distance.target.id, distance))
+            .collect::<HashMap<_, _>>();
+
+        let mut found = matching
+            .into_iter()
+            .filter_map(|agent| {
+                Some(AgentDistance {
+                    agent,
+                    distance: distances.get(&agent.system)?.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        found.sort_by(|a, b| {
+            a.distance
+                .jumps
+                .cmp(&b.distance.jumps)
+                .then_with(|| b.agent.level.cmp(&a.agent.level))
+                .then_with(|| a.agent.name.cmp(&b.agent.name))
+        });
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::types::Navigatable;
+
+    fn agent(id: u32, level: u8, division: u32, corporation: &str, system: u32) -> Agent {
+        Agent {
+            id,
+            name: format!("Agent {id}"),
+            level,
+            division: division.into(),
+            corporation_id: 1000000,
+            corporation_name: corporation.to_string(),
+            station_id: 60000000 + id,
+            system: system.into(),
+        }
+    }
+
+    #[test]
+    fn test_nearest() {
+        // 1 - 2 - 3 - 4
+        let mut builder = UniverseBuilder::default().system(system(1));
+        for id in 2..=4 {
+            builder = builder.system(system(id));
+            for (from, to) in [(id - 1, id), (id, id - 1)] {
+                builder = builder.connection(types::Connection {
+                    from: from.into(),
+                    to: to.into(),
+                    r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+                });
+            }
+        }
+        let universe = builder.build();
+        let agents = Agents::new(vec![
+            agent(1, 4, 24, "Caldari Navy", 4),
+            agent(2, 3, 24, "Caldari Navy", 2),
+            agent(3, 4, 24, "Caldari Navy", 2),
+            agent(4, 4, 22, "Caldari Navy", 1),
+            agent(5, 4, 24, "Lai Dai Corporation", 1),
+            agent(6, 1, 7, "Caldari Navy", 3),
+        ]);
+        assert_eq!(2, agents.in_system(&2.into()).count());
+        assert_eq!(Division::Other(7), agents.agents[5].division);
+
+        let origin = universe.get_system(&1.into()).unwrap();
+        let navy = |agent: &Agent| {
+            agent.corporation_name == "Caldari Navy" && agent.division == Division::Security
+        };
+        let found = agents
+            .nearest(&universe, origin, 5, navy)
+            .iter()
+            .map(|found| (found.agent.id, found.distance.jumps))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(3, Some(1)), (2, Some(1)), (1, Some(3))], found);
+
+        let level4 = agents.nearest(&universe, origin, 2, |agent| {
+            navy(agent) && agent.level == 4
+        });
+        assert_eq!(1, level4.len());
+        assert_eq!(3, level4[0].agent.id);
+    }
+}
@@ -0,0 +1,718 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Analysis of the structure of highsec space, e.g. detecting highsec islands such as
+//! the Solitude pocket and planning logistics between them and mainland highsec, and
+//! of the gate graph in general, e.g. finding chokepoints.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use crate::types;
+
+fn is_highsec(system: &types::System) -> bool {
+    types::SecurityClass::from(system.security) == types::SecurityClass::Highsec
+}
+
+fn neighbours<'a>(
+    universe: &'a dyn types::Navigatable,
+    id: &types::SystemId,
+) -> impl Iterator<Item = &'a types::System> {
+    universe
+        .get_connections(id)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(move |conn| universe.get_system(&conn.to))
+}
+
+/// Returns all highsec systems reachable from `start` without leaving highsec.
+fn highsec_component(
+    universe: &dyn types::Navigatable,
+    start: &types::SystemId,
+) -> HashSet<types::SystemId> {
+    let mut island = HashSet::new();
+    let Some(start) = universe.get_system(start).filter(|s| is_highsec(s)) else {
+        return island;
+    };
+    let mut queue = VecDeque::from([start.id]);
+    island.insert(start.id);
+    while let Some(id) = queue.pop_front() {
+        for next in neighbours(universe, &id) {
+            if is_highsec(next) && island.insert(next.id) {
+                queue.push_back(next.id);
+            }
+        }
+    }
+    island
+}
+
+/// Identifies a highsec island. Islands are numbered by size, `IslandId(0)` is the
+/// largest island, i.e. mainland highsec.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IslandId(pub usize);
+
+/// Partition of highsec into islands, groups of highsec systems that are connected
+/// without passing through lowsec or nullsec.
+///
+/// # Example
+/// ```
+/// use neweden::analysis::HighsecIslands;
+/// use neweden::builder::UniverseBuilder;
+///
+/// let universe = UniverseBuilder::default().build();
+/// let islands = HighsecIslands::new(&universe);
+/// assert_eq!(0, islands.len());
+/// assert_eq!(None, islands.island(&30000142.into()));
+/// ```
+#[derive(Debug)]
+pub struct HighsecIslands {
+    labels: HashMap<types::SystemId, IslandId>,
+    islands: Vec<Vec<types::SystemId>>,
+}
+
+impl HighsecIslands {
+    pub fn new(universe: &dyn types::Navigatable) -> Self {
+        let mut systems = universe
+            .all_systems()
+            .into_iter()
+            .filter(|s| is_highsec(s))
+            .map(|s| s.id)
+            .collect::<Vec<_>>();
+        systems.sort_by_key(|id| id.0);
+
+        let mut seen = HashSet::new();
+        let mut islands = Vec::new();
+        for id in systems {
+            if seen.contains(&id) {
+                continue;
+            }
+            let mut island = highsec_component(universe, &id)
+                .into_iter()
+                .collect::<Vec<_>>();
+            island.sort_by_key(|id| id.0);
+            seen.extend(island.iter().copied());
+            islands.push(island);
+        }
+        // stable sort keeps islands of equal size ordered by their lowest system id
+        islands.sort_by_key(|island| std::cmp::Reverse(island.len()));
+
+        let labels = islands
+            .iter()
+            .enumerate()
+            .flat_map(|(i, island)| island.iter().map(move |id| (*id, IslandId(i))))
+            .collect();
+        Self { labels, islands }
+    }
+
+    /// Returns the island of a system, or `None` if the system is not in highsec.
+    pub fn island(&self, id: &types::SystemId) -> Option<IslandId> {
+        self.labels.get(id).copied()
+    }
+
+    /// Returns the systems on an island, ordered by id.
+    pub fn systems(&self, island: IslandId) -> &[types::SystemId] {
+        self.islands.get(island.0).map_or(&[], |island| island)
+    }
+
+    /// Returns true if the system is in highsec but not on the largest island.
+    pub fn is_island(&self, id: &types::SystemId) -> bool {
+        self.island(id).is_some_and(|island| island != IslandId(0))
+    }
+
+    /// Returns true if both systems are in highsec and can reach each other without
+    /// leaving highsec.
+    pub fn same_island(&self, a: &types::SystemId, b: &types::SystemId) -> bool {
+        match (self.island(a), self.island(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Number of islands.
+    pub fn len(&self) -> usize {
+        self.islands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.islands.is_empty()
+    }
+}
+
+/// A way between two highsec islands crossing only lowsec and nullsec systems.
+#[derive(Debug)]
+pub struct Corridor<'a> {
+    /// The last highsec system on the first island.
+    pub exit: &'a types::System,
+    /// The first highsec system on the second island.
+    pub entry: &'a types::System,
+    /// The lowsec and nullsec systems in between.
+    pub crossed: Vec<&'a types::System>,
+}
+
+/// Finds the corridors between the highsec islands containing `from` and `to`, ordered
+/// by the number of lowsec and nullsec systems crossed. There is one corridor for each
+/// combination of exit system, first system crossed and entry system. Returns an empty
+/// list if either system is not in highsec or both are on the same island.
+pub fn corridors<'a>(
+    universe: &'a dyn types::Navigatable,
+    from: &types::SystemId,
+    to: &types::SystemId,
+) -> Vec<Corridor<'a>> {
+    let origin = highsec_component(universe, from);
+    let target = highsec_component(universe, to);
+    if origin.is_empty() || target.is_empty() || origin.contains(to) {
+        return Vec::new();
+    }
+
+    let mut corridors = Vec::new();
+    let mut exits = origin
+        .iter()
+        .filter_map(|id| universe.get_system(id))
+        .collect::<Vec<_>>();
+    exits.sort_by_key(|system| system.id.0);
+    for exit in exits {
+        for first in neighbours(universe, &exit.id).filter(|s| !is_highsec(s)) {
+            // breadth first search through low and nullsec only, remembering the
+            // parent of each system to reconstruct the crossing
+            let mut parents = HashMap::from([(first.id, None)]);
+            let mut queue = VecDeque::from([first.id]);
+            let mut entries = HashSet::new();
+            while let Some(id) = queue.pop_front() {
+                for next in neighbours(universe, &id) {
+                    if target.contains(&next.id) {
+                        if entries.insert(next.id) {
+                            corridors.push(Corridor {
+                                exit,
+                                entry: next,
+                                crossed: crossing(universe, &parents, id),
+                            });
+                        }
+                    } else if !is_highsec(next) && !parents.contains_key(&next.id) {
+                        parents.insert(next.id, Some(id));
+                        queue.push_back(next.id);
+                    }
+                }
+            }
+        }
+    }
+
+    corridors.sort_by_key(|corridor| corridor.crossed.len());
+    corridors
+}
+
+fn crossing<'a>(
+    universe: &'a dyn types::Navigatable,
+    parents: &HashMap<types::SystemId, Option<types::SystemId>>,
+    last: types::SystemId,
+) -> Vec<&'a types::System> {
+    let mut crossed = Vec::new();
+    let mut cur = Some(last);
+    while let Some(id) = cur {
+        crossed.extend(universe.get_system(&id));
+        cur = parents[&id];
+    }
+    crossed.reverse();
+    crossed
+}
+
+type Gate = (types::SystemId, types::SystemId);
+
+/// Returns the smallest set of gates whose camping disconnects `to` from `from`,
+/// i.e. every route from `from` to `to` passes at least one of the gates. Gates are
+/// given as the system they are in and the system they lead to. Parallel connections
+/// between the same systems, e.g. a stargate and an Ansiblex, are returned as a single
+/// gate. Returns an empty list if `to` cannot be reached at all.
+pub fn min_cut(
+    universe: &dyn types::Navigatable,
+    from: &types::SystemId,
+    to: &types::SystemId,
+) -> Vec<Gate> {
+    if from == to {
+        return Vec::new();
+    }
+
+    // unit capacity per connection, plus the reverse edges of the residual graph
+    let mut capacity: HashMap<Gate, i32> = HashMap::new();
+    let mut adjacent: HashMap<types::SystemId, Vec<types::SystemId>> = HashMap::new();
+    let mut seen = HashSet::from([*from]);
+    let mut queue = VecDeque::from([*from]);
+    while let Some(id) = queue.pop_front() {
+        for conn in universe.get_connections(&id).unwrap_or_default() {
+            *capacity.entry((id, conn.to)).or_default() += 1;
+            adjacent.entry(id).or_default().push(conn.to);
+            adjacent.entry(conn.to).or_default().push(id);
+            if seen.insert(conn.to) {
+                queue.push_back(conn.to);
+            }
+        }
+    }
+
+    // Edmonds-Karp, augmenting along shortest paths in the residual graph
+    let mut flow: HashMap<Gate, i32> = HashMap::new();
+    let residual = |flow: &HashMap<Gate, i32>, edge: Gate| {
+        capacity.get(&edge).copied().unwrap_or(0) - flow.get(&edge).copied().unwrap_or(0)
+    };
+    let reachable = |flow: &HashMap<Gate, i32>| {
+        let mut parents = HashMap::from([(*from, *from)]);
+        let mut queue = VecDeque::from([*from]);
+        while let Some(id) = queue.pop_front() {
+            for next in adjacent.get(&id).into_iter().flatten() {
+                if !parents.contains_key(next) && residual(flow, (id, *next)) > 0 {
+                    parents.insert(*next, id);
+                    queue.push_back(*next);
+                }
+            }
+        }
+        parents
+    };
+    loop {
+        let parents = reachable(&flow);
+        if !parents.contains_key(to) {
+            let mut cut = capacity
+                .keys()
+                .filter(|(a, b)| parents.contains_key(a) && !parents.contains_key(b))
+                .copied()
+                .collect::<Vec<_>>();
+            cut.sort();
+            return if seen.contains(to) { cut } else { Vec::new() };
+        }
+        let mut path = Vec::new();
+        let mut cur = *to;
+        while cur != *from {
+            let prev = parents[&cur];
+            path.push((prev, cur));
+            cur = prev;
+        }
+        let bottleneck = path
+            .iter()
+            .map(|edge| residual(&flow, *edge))
+            .min()
+            .unwrap_or(0);
+        for (a, b) in path {
+            *flow.entry((a, b)).or_default() += bottleneck;
+            *flow.entry((b, a)).or_default() -= bottleneck;
+        }
+    }
+}
+
+/// Summary of a region for dashboards and wiki pages. Gates are stargates leaving
+/// a system of the region, each direction counted separately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegionReport {
+    pub region_name: String,
+    pub systems: usize,
+    pub highsec: usize,
+    pub lowsec: usize,
+    pub nullsec: usize,
+    /// Systems owned by an NPC faction.
+    pub npc_systems: usize,
+    pub gates: usize,
+    /// Gates leading into another region.
+    pub region_gates: usize,
+    /// Systems with a stargate to a single other system only, sorted by id.
+    pub dead_ends: Vec<types::SystemId>,
+    /// Systems with a stargate into another region, sorted by id.
+    pub border_systems: Vec<types::SystemId>,
+    /// NPC stations in the region, zero if the stations are unknown.
+    pub stations: usize,
+    /// Systems with at least one NPC station.
+    pub station_systems: usize,
+}
+
+impl RegionReport {
+    /// Returns `None` if the universe has no systems in the region.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::analysis::RegionReport;
+    /// use neweden::builder::UniverseBuilder;
+    /// use neweden::{Connection, ConnectionType, StargateType, System, SystemId};
+    ///
+    /// let system = |id: u32, region: &str| System {
+    ///     id: id.into(),
+    ///     name: id.to_string(),
+    ///     coordinate: (0.0, 0.0, 0.0).into(),
+    ///     security: 0.9.into(),
+    ///     region_name: region.to_string(),
+    ///     faction_id: None,
+    /// };
+    /// let universe = UniverseBuilder::default()
+    ///     .system(system(30000142, "The Forge"))
+    ///     .system(system(30002187, "Domain"))
+    ///     .connection(Connection {
+    ///         from: 30000142.into(),
+    ///         to: 30002187.into(),
+    ///         r#type: ConnectionType::Stargate(StargateType::Regional),
+    ///     })
+    ///     .build();
+    /// let report = universe.region_report("The Forge").unwrap();
+    /// assert_eq!(1, report.highsec);
+    /// assert_eq!(vec![SystemId(30000142)], report.border_systems);
+    /// assert!(RegionReport::new(&universe, "Delve").is_none());
+    /// ```
+    pub fn new(universe: &dyn types::Navigatable, region: &str) -> Option<Self> {
+        Self::with_stations(universe, region, &Default::default())
+    }
+
+    /// Like `new()`, but also counts the stations of the region, see
+    /// `station_coverage()`. `Universe::region_report()` uses the stations of the
+    /// universe.
+    pub fn with_stations(
+        universe: &dyn types::Navigatable,
+        region: &str,
+        stations: &crate::stations::Stations,
+    ) -> Option<Self> {
+        let mut systems = universe
+            .all_systems()
+            .into_iter()
+            .filter(|system| system.region_name == region)
+            .collect::<Vec<_>>();
+        if systems.is_empty() {
+            return None;
+        }
+        systems.sort_by_key(|system| system.id);
+
+        let mut report = Self {
+            region_name: region.to_string(),
+            systems: systems.len(),
+            highsec: 0,
+            lowsec: 0,
+            nullsec: 0,
+            npc_systems: 0,
+            gates: 0,
+            region_gates: 0,
+            dead_ends: Vec::new(),
+            border_systems: Vec::new(),
+            stations: 0,
+            station_systems: 0,
+        };
+        for system in systems {
+            match types::SecurityClass::from(system.security) {
+                types::SecurityClass::Highsec => report.highsec += 1,
+                types::SecurityClass::Lowsec => report.lowsec += 1,
+                types::SecurityClass::Nullsec => report.nullsec += 1,
+            }
+            if system.faction_id.is_some() {
+                report.npc_systems += 1;
+            }
+            let docks = stations.in_system(&system.id).count();
+            report.stations += docks;
+            if docks > 0 {
+                report.station_systems += 1;
+            }
+
+            let gates = universe
+                .get_connections(&system.id)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|conn| matches!(conn.r#type, types::ConnectionType::Stargate(_)))
+                .collect::<Vec<_>>();
+            let leaving = gates
+                .iter()
+                .filter(|conn| {
+                    universe
+                        .get_system(&conn.to)
+                        .is_some_and(|to| to.region_name != region)
+                })
+                .count();
+            report.gates += gates.len();
+            report.region_gates += leaving;
+            if leaving > 0 {
+                report.border_systems.push(system.id);
+            }
+            let mut neighbours = gates.iter().map(|conn| conn.to).collect::<Vec<_>>();
+            neighbours.sort();
+            neighbours.dedup();
+            if neighbours.len() == 1 {
+                report.dead_ends.push(system.id);
+            }
+        }
+        Some(report)
+    }
+
+    /// The share of systems with a station, between 0 and 1.
+    pub fn station_coverage(&self) -> f64 {
+        self.station_systems as f64 / self.systems as f64
+    }
+}
+
+/// Topology of a set of systems. Only connections between the systems are counted
+/// and connections are treated as undirected.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GraphMetrics {
+    pub systems: usize,
+    /// Pairs of connected systems.
+    pub edges: usize,
+    /// Most jumps between two systems that are connected at all.
+    pub diameter: usize,
+    /// Average jumps between two systems that are connected at all, 0 if there are
+    /// no such pairs.
+    pub average_path_length: f64,
+    /// Number of systems by their number of neighbours.
+    pub degrees: BTreeMap<usize, usize>,
+    /// Average local clustering coefficient, i.e. how often two neighbours of a
+    /// system are neighbours themselves. Systems with less than two neighbours
+    /// count as 0.
+    pub clustering: f64,
+}
+
+impl GraphMetrics {
+    /// Computes the metrics of the given systems. Runs a breadth-first search from
+    /// every system, which takes a few seconds for all of New Eden.
+    pub fn new(universe: &dyn types::Navigatable, systems: &[&types::System]) -> Self {
+        let index = systems
+            .iter()
+            .enumerate()
+            .map(|(i, system)| (system.id, i))
+            .collect::<HashMap<_, _>>();
+        let mut adjacent = vec![HashSet::new(); systems.len()];
+        for (i, system) in systems.iter().enumerate() {
+            for id in universe.neighbors(&system.id) {
+                if let Some(&j) = index.get(&id)
+                    && i != j
+                {
+                    adjacent[i].insert(j);
+                    adjacent[j].insert(i);
+                }
+            }
+        }
+
+        let mut degrees = BTreeMap::new();
+        let mut clustering = 0.0;
+        for neighbours in &adjacent {
+            *degrees.entry(neighbours.len()).or_insert(0) += 1;
+            let k = neighbours.len();
+            if k >= 2 {
+                let links = neighbours
+                    .iter()
+                    .map(|a| adjacent[*a].intersection(neighbours).count())
+                    .sum::<usize>();
+                // every link is counted from both ends
+                clustering += links as f64 / (k * (k - 1)) as f64;
+            }
+        }
+
+        let (mut diameter, mut total, mut pairs) = (0, 0, 0);
+        for start in 0..systems.len() {
+            let mut distance = vec![usize::MAX; systems.len()];
+            distance[start] = 0;
+            let mut queue = VecDeque::from([start]);
+            while let Some(i) = queue.pop_front() {
+                for &j in &adjacent[i] {
+                    if distance[j] == usize::MAX {
+                        distance[j] = distance[i] + 1;
+                        diameter = diameter.max(distance[j]);
+                        total += distance[j];
+                        pairs += 1;
+                        queue.push_back(j);
+                    }
+                }
+            }
+        }
+
+        Self {
+            systems: systems.len(),
+            edges: adjacent.iter().map(HashSet::len).sum::<usize>() / 2,
+            diameter,
+            average_path_length: if pairs > 0 {
+                total as f64 / pairs as f64
+            } else {
+                0.0
+            },
+            degrees,
+            clustering: if systems.is_empty() {
+                0.0
+            } else {
+                clustering / systems.len() as f64
+            },
+        }
+    }
+}
+
+/// Graph metrics of a whole universe and of each region on its own.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UniverseMetrics {
+    pub galaxy: GraphMetrics,
+    pub regions: BTreeMap<String, GraphMetrics>,
+}
+
+impl UniverseMetrics {
+    pub fn new(universe: &dyn types::Navigatable) -> Self {
+        let mut systems = universe.all_systems();
+        systems.sort_by_key(|system| system.id);
+        let mut regions: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for system in &systems {
+            regions
+                .entry(system.region_name.clone())
+                .or_default()
+                .push(*system);
+        }
+        Self {
+            galaxy: GraphMetrics::new(universe, &systems),
+            regions: regions
+                .into_iter()
+                .map(|(region, systems)| {
+                    let metrics = GraphMetrics::new(universe, &systems);
+                    (region, metrics)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::types::Navigatable;
+
+    /// Two highsec islands 1-2 and 6-7, connected through lowsec 3 - 4 and 5.
+    ///
+    /// 1 - 2 - 3 - 4 - 6 - 7
+    ///      \         /
+    ///       --- 5 ---
+    fn islands() -> types::Universe {
+        let mut builder = UniverseBuilder::default()
+            .system(system(1).with_security(0.9))
+            .system(system(2).with_security(0.8))
+            .system(system(3).with_security(0.4))
+            .system(system(4).with_security(0.2))
+            .system(system(5).with_security(-0.1))
+            .system(system(6).with_security(0.7))
+            .system(system(7).with_security(1.0));
+        for (a, b) in [(1, 2), (2, 3), (3, 4), (4, 6), (6, 7), (2, 5), (5, 6)] {
+            for (from, to) in [(a, b), (b, a)] {
+                builder = builder.connection(types::Connection {
+                    from: types::SystemId(from),
+                    to: types::SystemId(to),
+                    r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+                });
+            }
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn test_corridors() {
+        let universe = islands();
+        let corridors = corridors(&universe, &1.into(), &7.into());
+        assert_eq!(2, corridors.len());
+        assert_eq!(types::SystemId(2), corridors[0].exit.id);
+        assert_eq!(types::SystemId(6), corridors[0].entry.id);
+        assert_eq!(1, corridors[0].crossed.len());
+        assert_eq!(2, corridors[1].crossed.len());
+
+        assert!(super::corridors(&universe, &1.into(), &2.into()).is_empty());
+    }
+
+    #[test]
+    fn test_region_report() {
+        // islands() with 1, 2 and 3 in region A and the other systems in region B
+        let islands = islands();
+        let mut builder = UniverseBuilder::default();
+        for system in islands.all_systems() {
+            let region = if system.id.0 <= 3 { "A" } else { "B" };
+            builder = builder.system(system.clone().in_region(region));
+            for conn in islands.get_connections(&system.id).unwrap_or_default() {
+                builder = builder.connection(conn);
+            }
+        }
+        let universe = builder.build();
+        let report = RegionReport::new(&universe, "A").unwrap();
+        assert_eq!(3, report.systems);
+        assert_eq!((2, 1, 0), (report.highsec, report.lowsec, report.nullsec));
+        // 1 - 2, 2 - 3, 2 - 5 and 3 - 4
+        assert_eq!(6, report.gates);
+        assert_eq!(2, report.region_gates);
+        assert_eq!(vec![types::SystemId(1)], report.dead_ends);
+        assert_eq!(
+            vec![types::SystemId(2), types::SystemId(3)],
+            report.border_systems
+        );
+        assert!(RegionReport::new(&universe, "C").is_none());
+        assert_eq!(0.0, report.station_coverage());
+
+        let station = |id: u32, system: u32| crate::stations::Station {
+            id,
+            name: id.to_string(),
+            system: system.into(),
+            services: Default::default(),
+        };
+        let universe = universe.with_stations(crate::stations::Stations::new(vec![
+            station(60000001, 1),
+            station(60000002, 1),
+            station(60000003, 3),
+            station(60000004, 4),
+        ]));
+        let report = universe.region_report("A").unwrap();
+        assert_eq!((3, 2), (report.stations, report.station_systems));
+        assert!((report.station_coverage() - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_min_cut() {
+        let universe = islands();
+        assert_eq!(
+            vec![(1.into(), 2.into())],
+            min_cut(&universe, &1.into(), &7.into())
+        );
+        assert_eq!(
+            vec![(2.into(), 3.into()), (2.into(), 5.into())],
+            min_cut(&universe, &2.into(), &6.into())
+        );
+        assert!(min_cut(&universe, &1.into(), &8.into()).is_empty());
+    }
+
+    #[test]
+    fn test_highsec_islands() {
+        let universe = islands();
+        let islands = HighsecIslands::new(&universe);
+        assert_eq!(2, islands.len());
+        assert_eq!(Some(IslandId(0)), islands.island(&1.into()));
+        assert_eq!(Some(IslandId(1)), islands.island(&7.into()));
+        assert_eq!(None, islands.island(&3.into()));
+        assert!(islands.same_island(&6.into(), &7.into()));
+        assert!(!islands.same_island(&2.into(), &6.into()));
+        assert!(islands.is_island(&6.into()));
+        assert_eq!(
+            &[types::SystemId(6), types::SystemId(7)],
+            islands.systems(IslandId(1))
+        );
+    }
+
+    #[test]
+    fn test_graph_metrics() {
+        // a triangle 1 - 2 - 3 in region A with a tail 3 - 4 - 5 into region B
+        let mut builder = UniverseBuilder::default();
+        for (id, region) in [(1, "A"), (2, "A"), (3, "A"), (4, "B"), (5, "B")] {
+            builder = builder.system(system(id).with_security(0.9).in_region(region));
+        }
+        for (a, b) in [(1, 2), (2, 3), (3, 1), (3, 4), (4, 5)] {
+            for (from, to) in [(a, b), (b, a)] {
+                builder = builder.connection(types::Connection {
+                    from: types::SystemId(from),
+                    to: types::SystemId(to),
+                    r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+                });
+            }
+        }
+        let metrics = builder.build().graph_metrics();
+
+        let galaxy = &metrics.galaxy;
+        assert_eq!((5, 5, 3), (galaxy.systems, galaxy.edges, galaxy.diameter));
+        // 5 pairs at 1 jump, 3 at 2 jumps and 2 at 3 jumps
+        assert_eq!(1.7, galaxy.average_path_length);
+        assert_eq!(BTreeMap::from([(1, 1), (2, 3), (3, 1)]), galaxy.degrees);
+        // 1 and 2 are fully clustered, 3 has one link between its three neighbours
+        assert!((galaxy.clustering - (2.0 + 1.0 / 3.0) / 5.0).abs() < 1e-9);
+
+        let a = &metrics.regions["A"];
+        assert_eq!((3, 1, 1.0), (a.edges, a.diameter, a.clustering));
+        assert_eq!(1, metrics.regions["B"].edges);
+    }
+}
@@ -0,0 +1,247 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Health of an Ansiblex network over time. Snapshots of the jump gates, e.g. from
+//! daily ESI structure scans, are compared to find gates that were added, removed
+//! or went offline and how that changes the routes between important systems.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::builder::ExtendedUniverseBuilder;
+use crate::navigation::PathBuilder;
+use crate::types;
+
+type Gate = (types::SystemId, types::SystemId);
+
+/// A single Ansiblex leading from one system to another. Gates without fuel or
+/// with a reinforced structure are offline and cannot be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JumpGate {
+    pub from: types::SystemId,
+    pub to: types::SystemId,
+    pub online: bool,
+}
+
+/// All jump gates seen at a point in time.
+#[derive(Debug, Clone)]
+pub struct GateSnapshot {
+    pub time: DateTime<Utc>,
+    gates: BTreeMap<Gate, bool>,
+}
+
+impl GateSnapshot {
+    pub fn new(time: DateTime<Utc>, gates: impl IntoIterator<Item = JumpGate>) -> Self {
+        Self {
+            time,
+            gates: gates
+                .into_iter()
+                .map(|gate| ((gate.from, gate.to), gate.online))
+                .collect(),
+        }
+    }
+
+    pub fn gates(&self) -> impl Iterator<Item = JumpGate> + '_ {
+        self.gates.iter().map(|((from, to), online)| JumpGate {
+            from: *from,
+            to: *to,
+            online: *online,
+        })
+    }
+
+    /// Extends the universe with the online gates of the snapshot.
+    pub fn extend<'a, U: types::Galaxy + types::Navigatable>(
+        &self,
+        universe: &'a U,
+    ) -> types::ExtendedUniverse<'a, U> {
+        self.gates()
+            .filter(|gate| gate.online)
+            .fold(ExtendedUniverseBuilder::new(universe), |builder, gate| {
+                builder.connection(types::Connection {
+                    from: gate.from,
+                    to: gate.to,
+                    r#type: types::ConnectionType::Ansiblex,
+                })
+            })
+            .build()
+    }
+}
+
+/// Change of the number of jumps between two systems, `None` if there was no route.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutingImpact {
+    pub from: types::SystemId,
+    pub to: types::SystemId,
+    pub before: Option<usize>,
+    pub after: Option<usize>,
+}
+
+impl RoutingImpact {
+    /// Additional jumps needed after the change, negative if the route got shorter.
+    pub fn delta(&self) -> Option<i64> {
+        Some(self.after? as i64 - self.before? as i64)
+    }
+}
+
+/// Difference between two gate snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GateDiff {
+    pub before: DateTime<Utc>,
+    pub after: DateTime<Utc>,
+    pub added: Vec<Gate>,
+    pub removed: Vec<Gate>,
+    /// Gates that exist in both snapshots but went offline.
+    pub offlined: Vec<Gate>,
+    /// Gates that exist in both snapshots and came back online.
+    pub onlined: Vec<Gate>,
+    pub impact: Vec<RoutingImpact>,
+}
+
+impl GateDiff {
+    /// Compares two snapshots and the routes between each of the key pairs.
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Duration, Utc};
+    /// use neweden::ansiblex::{GateDiff, GateSnapshot, JumpGate};
+    /// use neweden::builder::UniverseBuilder;
+    /// use neweden::System;
+    ///
+    /// let system = |id: u32| System {
+    ///     id: id.into(),
+    ///     name: id.to_string(),
+    ///     coordinate: (0.0, 0.0, 0.0).into(),
+    ///     security: (-0.4).into(),
+    ///     region_name: "Delve".to_string(),
+    ///     faction_id: None,
+    /// };
+    /// let universe = UniverseBuilder::default()
+    ///     .system(system(30004759))
+    ///     .system(system(30004760))
+    ///     .build();
+    /// let gate = |online| JumpGate {
+    ///     from: 30004759.into(),
+    ///     to: 30004760.into(),
+    ///     online,
+    /// };
+    /// let now = Utc::now();
+    /// let yesterday = GateSnapshot::new(now - Duration::days(1), vec![gate(true)]);
+    /// let today = GateSnapshot::new(now, vec![gate(false)]);
+    /// let pairs = [(30004759.into(), 30004760.into())];
+    /// let diff = GateDiff::new(&universe, &yesterday, &today, &pairs);
+    /// assert_eq!(1, diff.offlined.len());
+    /// assert_eq!(Some(1), diff.impact[0].before);
+    /// assert_eq!(None, diff.impact[0].after);
+    /// ```
+    pub fn new<U: types::Galaxy + types::Navigatable>(
+        universe: &U,
+        before: &GateSnapshot,
+        after: &GateSnapshot,
+        key_pairs: &[(types::SystemId, types::SystemId)],
+    ) -> Self {
+        let mut diff = Self {
+            before: before.time,
+            after: after.time,
+            added: Vec::new(),
+            removed: Vec::new(),
+            offlined: Vec::new(),
+            onlined: Vec::new(),
+            impact: Vec::new(),
+        };
+        for (gate, online) in &after.gates {
+            match before.gates.get(gate) {
+                None => diff.added.push(*gate),
+                Some(true) if !online => diff.offlined.push(*gate),
+                Some(false) if *online => diff.onlined.push(*gate),
+                Some(_) => {}
+            }
+        }
+        diff.removed = before
+            .gates
+            .keys()
+            .filter(|gate| !after.gates.contains_key(gate))
+            .copied()
+            .collect();
+
+        let old = before.extend(universe);
+        let new = after.extend(universe);
+        let jumps = |universe: &dyn types::Navigatable, from, to| {
+            PathBuilder::new(universe)
+                .waypoint(universe.get_system(from)?)
+                .waypoint(universe.get_system(to)?)
+                .build()
+                .map(|path| path.jumps())
+        };
+        diff.impact = key_pairs
+            .iter()
+            .map(|(from, to)| RoutingImpact {
+                from: *from,
+                to: *to,
+                before: jumps(&old, from, to),
+                after: jumps(&new, from, to),
+            })
+            .collect();
+        diff
+    }
+
+    /// Key pairs whose route changed.
+    pub fn affected(&self) -> impl Iterator<Item = &RoutingImpact> {
+        self.impact
+            .iter()
+            .filter(|impact| impact.before != impact.after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+
+    fn gate(from: u32, to: u32, online: bool) -> JumpGate {
+        JumpGate {
+            from: from.into(),
+            to: to.into(),
+            online,
+        }
+    }
+
+    #[test]
+    fn test_gate_diff() {
+        // 1 - 2 - 3 - 4 connected by stargates
+        let mut builder = UniverseBuilder::default().system(system(1));
+        for id in 2..=4 {
+            builder = builder.system(system(id)).connection(types::Connection {
+                from: (id - 1).into(),
+                to: id.into(),
+                r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+            });
+        }
+        let universe = builder.build();
+
+        let now = Utc::now();
+        let before = GateSnapshot::new(
+            now - Duration::days(1),
+            vec![gate(1, 4, true), gate(2, 3, true), gate(3, 1, false)],
+        );
+        let after = GateSnapshot::new(
+            now,
+            vec![gate(1, 4, false), gate(3, 1, true), gate(1, 3, true)],
+        );
+        let pairs = [(1.into(), 4.into()), (1.into(), 2.into())];
+        let diff = GateDiff::new(&universe, &before, &after, &pairs);
+
+        assert_eq!(vec![(1.into(), 3.into())], diff.added);
+        assert_eq!(vec![(2.into(), 3.into())], diff.removed);
+        assert_eq!(vec![(1.into(), 4.into())], diff.offlined);
+        assert_eq!(vec![(3.into(), 1.into())], diff.onlined);
+        assert_eq!(Some(1), diff.impact[0].delta());
+        assert_eq!(Some(0), diff.impact[1].delta());
+        assert_eq!(1, diff.affected().count());
+    }
+}
@@ -29,6 +29,9 @@ impl UniverseBuilder {
 pub struct ExtendedUniverseBuilder<'a, U> {
     universe: &'a U,
     connections: types::AdjacentMap,
+    annotations: Vec<(types::SystemId, types::SystemId, types::Annotation)>,
+    avoid: Vec<types::SystemId>,
+    structures: Vec<crate::structures::Structure>,
 }
 
 impl<'a, U: types::Galaxy + types::Navigatable> ExtendedUniverseBuilder<'a, U> {
@@ -36,6 +39,9 @@ impl<'a, U: types::Galaxy + types::Navigatable> ExtendedUniverseBuilder<'a, U> {
         Self {
             universe,
             connections: Default::default(),
+            annotations: Default::default(),
+            avoid: Default::default(),
+            structures: Default::default(),
         }
     }
 
@@ -66,7 +72,62 @@ impl<'a, U: types::Galaxy + types::Navigatable> ExtendedUniverseBuilder<'a, U> {
         self
     }
 
+    /// Adds a scouted drifter wormhole between `entrance` and the hive system, in both
+    /// directions. The connections are tagged with the name of the hive.
+    pub fn drifter(
+        mut self,
+        entrance: types::SystemId,
+        hive_system: types::SystemId,
+        hive: types::DrifterHive,
+        signature: &str,
+        spawned: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        let wormhole = hive.wormhole_type().wormhole(signature, spawned);
+        for (from, to) in [(entrance, hive_system), (hive_system, entrance)] {
+            self = self
+                .connection(types::Connection {
+                    from,
+                    to,
+                    r#type: types::ConnectionType::Wormhole(wormhole.clone()),
+                })
+                .annotate(from, to, types::Annotation::Tag(hive.name().to_string()));
+        }
+        self
+    }
+
+    pub fn annotate(
+        mut self,
+        from: types::SystemId,
+        to: types::SystemId,
+        annotation: types::Annotation,
+    ) -> Self {
+        self.annotations.push((from, to, annotation));
+        self
+    }
+
+    /// Avoid the system by default when pathfinding in the extended universe.
+    pub fn avoid(mut self, id: types::SystemId) -> Self {
+        self.avoid.push(id);
+        self
+    }
+
+    /// Registers a player-owned structure, see `ExtendedUniverse::add_structure()`.
+    pub fn structure(mut self, structure: crate::structures::Structure) -> Self {
+        self.structures.push(structure);
+        self
+    }
+
     pub fn build(self) -> types::ExtendedUniverse<'a, U> {
-        types::ExtendedUniverse::new(self.universe, self.connections)
+        let mut universe = types::ExtendedUniverse::new(self.universe, self.connections);
+        for (from, to, annotation) in self.annotations {
+            universe.annotate(from, to, annotation);
+        }
+        for id in self.avoid {
+            universe.avoid(id);
+        }
+        for structure in self.structures {
+            universe.add_structure(structure);
+        }
+        universe
     }
 }
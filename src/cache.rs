@@ -0,0 +1,309 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Persistent cache of computed routes in a SQLite file, so a long running bot
+//! doesn't have to recompute every route after a restart.
+//!
+//! Routes are keyed by their endpoints, the `PathBuilder::cache_key()` of the
+//! builder that computed them and the version of the universe, which includes the
+//! systems an `ExtendedUniverse` avoids. Only the systems and the kind of each hop
+//! are stored, the connections are looked up in the universe when a route is
+//! loaded. Entries of other universe versions are never returned and can be removed
+//! with `RouteCache::purge()`.
+
+use rusqlite::OptionalExtension;
+
+use crate::navigation::{Layer, PathBuilder, Route, RouteHop};
+use crate::types;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS routes (
+        from_id INTEGER NOT NULL,
+        to_id INTEGER NOT NULL,
+        options INTEGER NOT NULL,
+        version INTEGER NOT NULL,
+        hops TEXT NOT NULL,
+        PRIMARY KEY (from_id, to_id, options, version)
+    )
+";
+
+fn layer_name(layer: Layer) -> &'static str {
+    match layer {
+        Layer::Stargates => "stargate",
+        Layer::Bridges => "bridge",
+        Layer::Wormholes => "wormhole",
+        Layer::Ansiblex => "ansiblex",
+    }
+}
+
+/// Stores hops as `from>to>layer`, separated by commas.
+fn encode(route: &Route) -> String {
+    route
+        .hops
+        .iter()
+        .map(|hop| {
+            format!(
+                "{}>{}>{}",
+                hop.from.0,
+                hop.to.0,
+                layer_name(Layer::from(&hop.connection))
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Returns `None` if any hop no longer exists in the universe.
+fn decode(universe: &dyn types::Navigatable, hops: &str) -> Option<Route> {
+    let hops = hops
+        .split(',')
+        .filter(|hop| !hop.is_empty())
+        .map(|hop| {
+            let mut parts = hop.split('>');
+            let from: types::SystemId = parts.next()?.parse::<u32>().ok()?.into();
+            let to: types::SystemId = parts.next()?.parse::<u32>().ok()?.into();
+            let layer = parts.next()?;
+            let connection = universe
+                .get_connections(&from)?
+                .into_iter()
+                .find(|conn| conn.to == to && layer_name(Layer::from(&conn.r#type)) == layer)?;
+            Some(RouteHop {
+                from,
+                to,
+                connection: connection.r#type,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(Route {
+        version: universe.version(),
+        hops,
+    })
+}
+
+/// A route cache backed by a SQLite database.
+///
+/// Routes are looked up by the `PathBuilder` that computes them, using
+/// `PathBuilder::cache_key()`. Builders with predicates have no key, their routes
+/// are computed every time and never cached.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::cache::RouteCache;
+/// use neweden::navigation::PathBuilder;
+/// use neweden::{Connection, ConnectionType, Navigatable, StargateType, System};
+///
+/// let system = |id: u32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: 0.9.into(),
+///     region_name: "The Forge".to_string(),
+///     faction_id: None,
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(30000142))
+///     .system(system(30000144))
+///     .connection(Connection {
+///         from: 30000142.into(),
+///         to: 30000144.into(),
+///         r#type: ConnectionType::Stargate(StargateType::Local),
+///     })
+///     .build();
+/// let jita = universe.get_system(&30000142.into()).unwrap();
+/// let perimeter = universe.get_system(&30000144.into()).unwrap();
+/// let builder = || PathBuilder::new(&universe).waypoint(jita).waypoint(perimeter);
+///
+/// let cache = RouteCache::in_memory().unwrap();
+/// let route = cache.get_or_build(builder()).unwrap().unwrap();
+/// assert_eq!(Some(route), cache.get(&builder()).unwrap());
+/// ```
+pub struct RouteCache {
+    conn: rusqlite::Connection,
+}
+
+impl RouteCache {
+    /// Opens or creates the cache file at `path`.
+    pub fn open(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        Self::from_connection(rusqlite::Connection::open(path)?)
+    }
+
+    /// A cache that only lives as long as the value, mostly useful for tests.
+    pub fn in_memory() -> anyhow::Result<Self> {
+        Self::from_connection(rusqlite::Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: rusqlite::Connection) -> anyhow::Result<Self> {
+        conn.execute(SCHEMA, [])?;
+        Ok(Self { conn })
+    }
+
+    /// Returns the route cached for the builder and the current universe version, or
+    /// `None` if there is none or one of its connections no longer exists.
+    pub fn get(&self, builder: &PathBuilder) -> anyhow::Result<Option<Route>> {
+        let (Some(key), Some((universe, from, to))) = (builder.cache_key(), builder.endpoints())
+        else {
+            return Ok(None);
+        };
+        let hops = self
+            .conn
+            .query_row(
+                "SELECT hops FROM routes
+                 WHERE from_id = ?1 AND to_id = ?2 AND options = ?3 AND version = ?4",
+                (from.0, to.0, key as i64, universe.version() as i64),
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(hops.and_then(|hops| decode(universe, &hops)))
+    }
+
+    /// Stores the route built by the builder, replacing any route cached for it.
+    /// Routes of builders without a key are not stored.
+    pub fn insert(&self, builder: &PathBuilder, route: &Route) -> anyhow::Result<()> {
+        match (builder.cache_key(), builder.endpoints()) {
+            (Some(key), Some((_, from, to))) => self.store(from, to, key, route),
+            _ => Ok(()),
+        }
+    }
+
+    fn store(
+        &self,
+        from: types::SystemId,
+        to: types::SystemId,
+        key: u64,
+        route: &Route,
+    ) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO routes (from_id, to_id, options, version, hops)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            (
+                from.0,
+                to.0,
+                key as i64,
+                route.version as i64,
+                encode(route),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Returns the cached route or builds and stores it. Routes that could not be
+    /// built are not cached.
+    pub fn get_or_build(&self, builder: PathBuilder) -> anyhow::Result<Option<Route>> {
+        if let Some(route) = self.get(&builder)? {
+            return Ok(Some(route));
+        }
+        let key = builder.cache_key();
+        let endpoints = builder.endpoints();
+        let route = match builder.build() {
+            Some(path) => path.to_route(),
+            None => return Ok(None),
+        };
+        if let (Some(key), Some((_, from, to))) = (key, endpoints) {
+            self.store(from, to, key, &route)?;
+        }
+        Ok(Some(route))
+    }
+
+    /// Removes all routes computed for other universe versions. Returns the number
+    /// of removed routes.
+    pub fn purge(&self, universe: &dyn types::Navigatable) -> anyhow::Result<usize> {
+        Ok(self.conn.execute(
+            "DELETE FROM routes WHERE version != ?1",
+            [universe.version() as i64],
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{ExtendedUniverseBuilder, UniverseBuilder};
+    use crate::fixtures::system;
+    use crate::navigation::Preference;
+    use crate::types::Navigatable;
+
+    fn line(len: u32) -> types::Universe {
+        let mut builder = UniverseBuilder::default().system(system(1));
+        for id in 2..=len {
+            builder = builder.system(system(id)).connection(types::Connection {
+                from: (id - 1).into(),
+                to: id.into(),
+                r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+            });
+        }
+        builder.build()
+    }
+
+    fn builder(universe: &dyn Navigatable, to: u32) -> PathBuilder<'_> {
+        PathBuilder::new(universe)
+            .waypoint(universe.get_system(&1.into()).unwrap())
+            .waypoint(universe.get_system(&to.into()).unwrap())
+    }
+
+    #[test]
+    fn test_cache() {
+        let universe = line(3);
+        let route = builder(&universe, 3).build().unwrap().to_route();
+        let cache = RouteCache::in_memory().unwrap();
+        cache.insert(&builder(&universe, 3), &route).unwrap();
+
+        assert_eq!(Some(route), cache.get(&builder(&universe, 3)).unwrap());
+        let other = builder(&universe, 3).prefer(Preference::Highsec);
+        assert_eq!(None, cache.get(&other).unwrap());
+        let other = builder(&universe, 3).avoid(universe.get_system(&2.into()).unwrap());
+        assert_eq!(None, cache.get(&other).unwrap());
+
+        let changed = line(4);
+        assert_eq!(None, cache.get(&builder(&changed, 3)).unwrap());
+        assert!(cache.get_or_build(builder(&changed, 3)).unwrap().is_some());
+        assert!(cache.get(&builder(&changed, 3)).unwrap().is_some());
+        let unreachable = builder(&changed, 4).avoid(changed.get_system(&3.into()).unwrap());
+        assert_eq!(None, cache.get_or_build(unreachable).unwrap());
+        assert_eq!(1, cache.purge(&changed).unwrap());
+    }
+
+    #[test]
+    fn test_avoid() {
+        let universe = line(3);
+        let mut extended = ExtendedUniverseBuilder::new(&universe).build();
+        let route = builder(&extended, 3).build().unwrap().to_route();
+        let cache = RouteCache::in_memory().unwrap();
+        cache.insert(&builder(&extended, 3), &route).unwrap();
+        assert_eq!(
+            Some(route.clone()),
+            cache.get(&builder(&extended, 3)).unwrap()
+        );
+
+        // the cached route passes a system avoided after it was stored
+        extended.avoid(2.into());
+        assert_eq!(None, cache.get(&builder(&extended, 3)).unwrap());
+        extended.unavoid(&2.into());
+        assert_eq!(Some(route), cache.get(&builder(&extended, 3)).unwrap());
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let universe = line(3);
+        let key = |builder: PathBuilder| builder.cache_key();
+        assert_eq!(key(builder(&universe, 3)), key(builder(&universe, 3)));
+        assert_ne!(key(builder(&universe, 3)), key(builder(&universe, 2)));
+        assert_ne!(
+            key(builder(&universe, 3)),
+            key(builder(&universe, 3).prefer(Preference::Lightyears))
+        );
+        assert_ne!(
+            key(builder(&universe, 3)),
+            key(builder(&universe, 3).disable(Layer::Wormholes))
+        );
+        // predicates can't be compared
+        assert_eq!(None, key(builder(&universe, 3).avoid_where(|_| false)));
+
+        let cache = RouteCache::in_memory().unwrap();
+        let uncached = builder(&universe, 3).avoid_where(|_| false);
+        assert!(cache.get_or_build(uncached).unwrap().is_some());
+        assert_eq!(0, cache.purge(&line(4)).unwrap());
+    }
+}
@@ -0,0 +1,215 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Estimates how likely a gate is camped at a given time based on historical kills.
+//!
+//! The model buckets kills per gate by day of week and hour of day and reports the
+//! fraction of observed weeks in which a kill happened in the same bucket.
+//! `avoid_camps()` makes paths prefer gates that are unlikely to be camped.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Datelike, Timelike, Utc, Weekday};
+
+use crate::navigation::PathBuilder;
+use crate::types;
+
+/// A kill that happened on a gate. The gate is identified by the system it is in
+/// (`from`) and the system it leads to (`to`), matching a `Connection`.
+#[derive(Debug, Clone)]
+pub struct Kill {
+    pub from: types::SystemId,
+    pub to: types::SystemId,
+    pub time: DateTime<Utc>,
+}
+
+type Gate = (types::SystemId, types::SystemId);
+type Slot = (Gate, Weekday, u32);
+
+#[derive(Default)]
+pub struct CampModelBuilder {
+    kills: Vec<Kill>,
+}
+
+impl CampModelBuilder {
+    pub fn kill(mut self, kill: Kill) -> Self {
+        self.kills.push(kill);
+        self
+    }
+
+    pub fn kills(mut self, kills: impl IntoIterator<Item = Kill>) -> Self {
+        self.kills.extend(kills);
+        self
+    }
+
+    pub fn build(self) -> CampModel {
+        let first = self.kills.iter().map(|k| k.time).min();
+        let last = self.kills.iter().map(|k| k.time).max();
+        let weeks = match (first, last) {
+            (Some(first), Some(last)) => (last - first).num_weeks() as u32 + 1,
+            _ => 1,
+        };
+
+        let mut slots: HashMap<Slot, HashSet<i64>> = HashMap::new();
+        if let Some(first) = first {
+            for kill in self.kills {
+                let slot = ((kill.from, kill.to), kill.time.weekday(), kill.time.hour());
+                slots
+                    .entry(slot)
+                    .or_default()
+                    .insert((kill.time - first).num_weeks());
+            }
+        }
+
+        CampModel { slots, weeks }
+    }
+}
+
+/// Time-of-day and day-of-week camp probabilities per gate.
+///
+/// # Example
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use neweden::camps::{CampModelBuilder, Kill};
+/// use neweden::{Connection, ConnectionType, StargateType};
+///
+/// let time = Utc.with_ymd_and_hms(2024, 5, 4, 19, 12, 0).unwrap();
+/// let model = CampModelBuilder::default()
+///     .kill(Kill { from: 30002813.into(), to: 30002812.into(), time })
+///     .build();
+/// let gate = Connection {
+///     from: 30002813.into(), // Tama
+///     to: 30002812.into(),   // Nourvukaiken
+///     r#type: ConnectionType::Stargate(StargateType::Constellation),
+/// };
+/// assert_eq!(1.0, model.camp_probability(&gate, time));
+/// ```
+pub struct CampModel {
+    slots: HashMap<Slot, HashSet<i64>>,
+    weeks: u32,
+}
+
+impl CampModel {
+    /// Returns the probability between 0.0 and 1.0 that the gate is camped at the given time.
+    pub fn camp_probability(&self, connection: &types::Connection, time: DateTime<Utc>) -> f64 {
+        let slot = (
+            (connection.from, connection.to),
+            time.weekday(),
+            time.hour(),
+        );
+        match self.slots.get(&slot) {
+            Some(weeks) => weeks.len() as f64 / f64::from(self.weeks),
+            None => 0.0,
+        }
+    }
+}
+
+/// Penalizes gates by their camp probability at the departure time, see
+/// `PathBuilder::penalize_connection()`. Taking a gate camped every week costs as
+/// much as `jumps` additional jumps.
+pub fn avoid_camps<'a>(
+    builder: PathBuilder<'a>,
+    model: &'a CampModel,
+    jumps: u32,
+) -> PathBuilder<'a> {
+    builder.penalize_connection(move |connection, time| {
+        (model.camp_probability(connection, time) * f64::from(jumps)).round() as u32
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::navigation::TravelTimes;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_camp_probability() {
+        let from: types::SystemId = 30002813.into();
+        let to: types::SystemId = 30002812.into();
+        let start = Utc.with_ymd_and_hms(2024, 5, 4, 19, 12, 0).unwrap();
+        // camped on saturday evenings in two out of four weeks
+        let model = CampModelBuilder::default()
+            .kill(Kill {
+                from,
+                to,
+                time: start,
+            })
+            .kill(Kill {
+                from,
+                to,
+                time: start + Duration::minutes(5),
+            })
+            .kill(Kill {
+                from,
+                to,
+                time: start + Duration::weeks(2),
+            })
+            .kill(Kill {
+                from: to,
+                to: from,
+                time: start + Duration::weeks(3),
+            })
+            .build();
+        let gate = types::Connection {
+            from,
+            to,
+            r#type: types::ConnectionType::Stargate(types::StargateType::Constellation),
+        };
+
+        assert_eq!(
+            0.5,
+            model.camp_probability(&gate, start + Duration::weeks(5))
+        );
+        assert_eq!(
+            0.0,
+            model.camp_probability(&gate, start + Duration::hours(1))
+        );
+    }
+
+    #[test]
+    fn test_avoid_camps() {
+        // 1 - 2 - 4 is short, 1 - 3 - 5 - 4 avoids the gate from 2 to 4
+        let mut builder = UniverseBuilder::default();
+        for id in 1..=5 {
+            builder = builder.system(system(id));
+        }
+        for (from, to) in [(1, 2), (2, 4), (1, 3), (3, 5), (5, 4)] {
+            builder = builder.connection(types::Connection {
+                from: from.into(),
+                to: to.into(),
+                r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+            });
+        }
+        let universe = builder.build();
+        let start = Utc.with_ymd_and_hms(2024, 5, 4, 19, 12, 0).unwrap();
+        let model = CampModelBuilder::default()
+            .kill(Kill {
+                from: 2.into(),
+                to: 4.into(),
+                time: start,
+            })
+            .build();
+
+        let route = |time| {
+            let builder = PathBuilder::new(&universe)
+                .waypoint(universe.get_system(&1.into()).unwrap())
+                .waypoint(universe.get_system(&4.into()).unwrap())
+                .depart_at(time, TravelTimes::default());
+            avoid_camps(builder, &model, 5)
+                .build()
+                .unwrap()
+                .systems()
+                .map(|system| system.id.0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![1, 3, 5, 4], route(start + Duration::weeks(1)));
+        assert_eq!(vec![1, 2, 4], route(start + Duration::hours(3)));
+    }
+}
@@ -0,0 +1,374 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Celestials of systems, e.g. to estimate the scanning effort along a wormhole
+//! route or to rank systems by the number of places a target can hide at.
+//!
+//! Counts are kept in an `Overlay` keyed by `SystemId`. They are computed from the
+//! individual celestials with `Celestials::counts()`, with the `sqlite` feature
+//! they can be loaded from a dump using `DatabaseBuilder::celestial_counts()`.
+//!
+//! The individual planets, moons and belts with their positions are `Celestials`,
+//! attached to the universe by data sources that know them, see
+//! `Universe::celestials()`. The `sqlite` source only loads them if enabled with
+//! `DatabaseBuilder::load_celestials()`.
+
+use std::collections::HashMap;
+
+use crate::overlay::Overlay;
+use crate::types;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CelestialKind {
+    Planet,
+    Moon,
+    Belt,
+}
+
+impl CelestialKind {
+    /// The kind of a celestial by its `groupID` in `mapDenormalize`.
+    pub fn from_group_id(group_id: u32) -> Option<Self> {
+        match group_id {
+            7 => Some(Self::Planet),
+            8 => Some(Self::Moon),
+            9 => Some(Self::Belt),
+            _ => None,
+        }
+    }
+
+    /// The `groupID` of the kind in `mapDenormalize`.
+    pub fn group_id(&self) -> u32 {
+        match self {
+            Self::Planet => 7,
+            Self::Moon => 8,
+            Self::Belt => 9,
+        }
+    }
+}
+
+/// A planet, moon or asteroid belt.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Celestial {
+    pub id: u32,
+    pub name: String,
+    pub system: types::SystemId,
+    pub kind: CelestialKind,
+    /// The position within the system in meters.
+    pub position: types::Coordinate,
+}
+
+/// All celestials, indexed by their system.
+#[derive(Debug, Clone, Default)]
+pub struct Celestials {
+    celestials: Vec<Celestial>,
+    by_system: HashMap<types::SystemId, Vec<usize>>,
+}
+
+impl Celestials {
+    pub fn new(celestials: Vec<Celestial>) -> Self {
+        let mut by_system: HashMap<_, Vec<_>> = HashMap::new();
+        for (i, celestial) in celestials.iter().enumerate() {
+            by_system.entry(celestial.system).or_default().push(i);
+        }
+        Self {
+            celestials,
+            by_system,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.celestials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.celestials.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Celestial> {
+        self.celestials.iter()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Celestial> {
+        self.celestials.iter().find(|celestial| celestial.id == id)
+    }
+
+    pub fn in_system(&self, id: &types::SystemId) -> impl Iterator<Item = &Celestial> {
+        self.by_system
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|i| &self.celestials[*i])
+    }
+
+    /// The number of planets, moons and belts of every system with celestials. The
+    /// signatures are unknown.
+    pub fn counts(&self) -> Overlay<types::SystemId, CelestialCounts> {
+        let mut overlay = Overlay::default();
+        for (system, celestials) in &self.by_system {
+            let mut counts = CelestialCounts::default();
+            for celestial in celestials.iter().map(|i| &self.celestials[*i]) {
+                let count = match celestial.kind {
+                    CelestialKind::Planet => &mut counts.planets,
+                    CelestialKind::Moon => &mut counts.moons,
+                    CelestialKind::Belt => &mut counts.belts,
+                };
+                *count = count.saturating_add(1);
+            }
+            overlay.insert(*system, counts);
+        }
+        overlay
+    }
+
+    /// The celestial of the system closest to `position`, e.g. to name the location
+    /// of a ship on dscan.
+    pub fn nearest(
+        &self,
+        system: &types::SystemId,
+        position: &types::Coordinate,
+    ) -> Option<&Celestial> {
+        self.in_system(system).min_by(|a, b| {
+            a.position
+                .distance(position)
+                .0
+                .total_cmp(&b.position.distance(position).0)
+        })
+    }
+
+    /// The celestials of the system within `range` of `position`, closest first.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::celestials::{Celestial, CelestialKind, Celestials};
+    /// use neweden::{Au, Meters};
+    ///
+    /// let planet = |id: u32, x: f64| Celestial {
+    ///     id,
+    ///     name: id.to_string(),
+    ///     system: 30000142.into(),
+    ///     kind: CelestialKind::Planet,
+    ///     position: (x, 0.0, 0.0).into(),
+    /// };
+    /// let celestials = Celestials::new(vec![planet(40009077, 1.0e11), planet(40009078, 1.0e13)]);
+    /// // dscan reaches 14.3 AU
+    /// let range: Meters = Au(14.3).into();
+    /// let visible = celestials.within(&30000142.into(), &(0.0, 0.0, 0.0).into(), range);
+    /// assert_eq!(vec![40009077], visible.iter().map(|c| c.id).collect::<Vec<_>>());
+    /// ```
+    pub fn within(
+        &self,
+        system: &types::SystemId,
+        position: &types::Coordinate,
+        range: types::Meters,
+    ) -> Vec<&Celestial> {
+        let mut celestials = self
+            .in_system(system)
+            .map(|celestial| (celestial, celestial.position.distance(position)))
+            .filter(|(_, distance)| *distance <= range)
+            .collect::<Vec<_>>();
+        celestials.sort_by(|a, b| a.1.0.total_cmp(&b.1.0));
+        celestials
+            .into_iter()
+            .map(|(celestial, _)| celestial)
+            .collect()
+    }
+}
+
+/// Number of celestials in a system and, if known, the current number of cosmic
+/// signatures.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CelestialCounts {
+    pub planets: u16,
+    pub moons: u16,
+    pub belts: u16,
+    /// Cosmic signatures currently in the system, e.g. from a mapping tool.
+    pub signatures: Option<u16>,
+}
+
+impl CelestialCounts {
+    /// Celestials a ship can warp to without bookmarks.
+    pub fn hiding_spots(&self) -> u32 {
+        u32::from(self.planets) + u32::from(self.moons) + u32::from(self.belts)
+    }
+
+    /// Signatures per planet. Signatures spawn around planets, so a high density
+    /// means little flying between scans. `None` if the signatures are unknown or
+    /// the system has no planets.
+    pub fn signature_density(&self) -> Option<f32> {
+        match (self.signatures, self.planets) {
+            (Some(signatures), planets) if planets > 0 => {
+                Some(f32::from(signatures) / f32::from(planets))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Scanning effort along a route.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanEffort {
+    /// Signatures in systems with known signature counts.
+    pub signatures: u32,
+    /// Planets of all systems, a measure of how spread out the systems are.
+    pub planets: u32,
+    /// Systems without celestial counts or a known number of signatures.
+    pub unknown: usize,
+}
+
+/// Sums up the signatures and planets of the given systems, e.g. `path.systems()`.
+///
+/// # Example
+/// ```
+/// use neweden::celestials::{scan_effort, CelestialCounts};
+/// use neweden::overlay::Overlay;
+/// use neweden::System;
+///
+/// let system = |id: u32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: (-1.0).into(),
+///     region_name: "A-R00001".to_string(),
+///     faction_id: None,
+/// };
+/// let mut counts = Overlay::default();
+/// counts.insert(
+///     31000001.into(),
+///     CelestialCounts { planets: 4, moons: 10, belts: 0, signatures: Some(8) },
+/// );
+/// let systems = [system(31000001), system(31000002)];
+/// let effort = scan_effort(&counts, &systems);
+/// assert_eq!(8, effort.signatures);
+/// assert_eq!(1, effort.unknown);
+/// ```
+pub fn scan_effort<'a>(
+    counts: &Overlay<types::SystemId, CelestialCounts>,
+    systems: impl IntoIterator<Item = &'a types::System>,
+) -> ScanEffort {
+    systems
+        .into_iter()
+        .fold(ScanEffort::default(), |mut effort, system| {
+            match counts.get(&system.id) {
+                Some(counts) => {
+                    effort.planets += u32::from(counts.planets);
+                    match counts.signatures {
+                        Some(signatures) => effort.signatures += u32::from(signatures),
+                        None => effort.unknown += 1,
+                    }
+                }
+                None => effort.unknown += 1,
+            }
+            effort
+        })
+}
+
+/// Orders systems by their number of hiding spots, most first. Systems without
+/// celestial counts are skipped.
+pub fn rank_by_hiding_spots<'a>(
+    counts: &Overlay<types::SystemId, CelestialCounts>,
+    systems: impl IntoIterator<Item = &'a types::System>,
+) -> Vec<(&'a types::System, u32)> {
+    let mut ranked = counts
+        .along(systems)
+        .into_iter()
+        .map(|(system, counts)| (system, counts.hiding_spots()))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name.cmp(&b.0.name)));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::system;
+
+    fn counts(planets: u16, moons: u16, signatures: Option<u16>) -> CelestialCounts {
+        CelestialCounts {
+            planets,
+            moons,
+            belts: 1,
+            signatures,
+        }
+    }
+
+    #[test]
+    fn test_celestials() {
+        let systems = [
+            system(1).named("A"),
+            system(2).named("B"),
+            system(3).named("C"),
+            system(4).named("D"),
+        ];
+        let mut overlay = Overlay::default();
+        overlay.insert(1.into(), counts(2, 3, Some(6)));
+        overlay.insert(2.into(), counts(8, 40, None));
+        overlay.insert(3.into(), counts(0, 5, Some(2)));
+
+        assert_eq!(
+            Some(3.0),
+            overlay.get(&1.into()).unwrap().signature_density()
+        );
+        assert_eq!(None, overlay.get(&2.into()).unwrap().signature_density());
+        assert_eq!(None, overlay.get(&3.into()).unwrap().signature_density());
+        assert_eq!(
+            ScanEffort {
+                signatures: 8,
+                planets: 10,
+                unknown: 2,
+            },
+            scan_effort(&overlay, &systems)
+        );
+
+        let ranked = rank_by_hiding_spots(&overlay, &systems)
+            .into_iter()
+            .map(|(system, spots)| (system.name.as_str(), spots))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![("B", 49), ("A", 6), ("C", 6)], ranked);
+    }
+
+    #[test]
+    fn test_celestial_positions() {
+        let celestial = |id: u32, system: u32, kind: CelestialKind, x: f64| Celestial {
+            id,
+            name: id.to_string(),
+            system: system.into(),
+            kind,
+            position: (x, 0.0, 0.0).into(),
+        };
+        let universe = crate::builder::UniverseBuilder::default()
+            .build()
+            .with_celestials(Celestials::new(vec![
+                celestial(40009077, 30000142, CelestialKind::Planet, 0.0),
+                celestial(40009078, 30000142, CelestialKind::Moon, 4.0e8),
+                celestial(40009079, 30000142, CelestialKind::Belt, 1.0e9),
+                celestial(40009100, 30000144, CelestialKind::Planet, 5.0e8),
+            ]));
+
+        assert_eq!(3, universe.celestials(&30000142.into()).len());
+        assert!(universe.celestials(&30000138.into()).is_empty());
+        let nearest = universe
+            .all_celestials()
+            .nearest(&30000142.into(), &(6.0e8, 0.0, 0.0).into())
+            .unwrap();
+        assert_eq!(CelestialKind::Moon, nearest.kind);
+        let counts = universe.all_celestials().counts();
+        assert_eq!(2, counts.len());
+        let jita = counts.get(&30000142.into()).unwrap();
+        assert_eq!(
+            (1, 1, 1, None),
+            (jita.planets, jita.moons, jita.belts, jita.signatures)
+        );
+        let within = universe.all_celestials().within(
+            &30000142.into(),
+            &(6.0e8, 0.0, 0.0).into(),
+            types::Meters(4.5e8),
+        );
+        assert_eq!(
+            vec![40009078, 40009079],
+            within.iter().map(|c| c.id).collect::<Vec<_>>()
+        );
+    }
+}
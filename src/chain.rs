@@ -0,0 +1,342 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Wormhole chains as mapped in an `ExtendedUniverse`, exported as a tree rooted at
+//! the home system for chain visualizers.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+
+use crate::types;
+
+/// A system in a wormhole chain, together with the connection leading to it.
+#[derive(Debug)]
+pub struct ChainNode<'a> {
+    pub system: &'a types::System,
+    /// The wormhole leading from the parent to this system, `None` for the root.
+    pub connection: Option<types::Wormhole>,
+    pub annotations: &'a [types::Annotation],
+    pub children: Vec<ChainNode<'a>>,
+}
+
+/// A tree of all systems reachable from a home system through wormholes. Each
+/// system appears once, at the first wormhole found leading to it. Stargates of
+/// known space exits are not followed, their other wormholes are.
+///
+/// # Example
+/// ```
+/// use chrono::{Duration, Utc};
+/// use neweden::builder::{ExtendedUniverseBuilder, UniverseBuilder};
+/// use neweden::chain::Chain;
+/// use neweden::{Connection, ConnectionType, System, Wormhole, WormholeMaxShipSize};
+///
+/// let system = |id: u32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: (-1.0).into(),
+///     region_name: "C-R00083".to_string(),
+///     faction_id: None,
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(31000001))
+///     .system(system(31000002))
+///     .build();
+/// let extended = ExtendedUniverseBuilder::new(&universe)
+///     .connection(Connection {
+///         from: 31000001.into(),
+///         to: 31000002.into(),
+///         r#type: ConnectionType::Wormhole(Wormhole {
+///             expires: Utc::now() + Duration::hours(16),
+///             remaining_hours: 16,
+///             signature: "ABC-123".to_string(),
+///             exit_signature: None,
+///             max_ship_size: WormholeMaxShipSize::Large,
+///             shattered: None,
+///         }),
+///     })
+///     .build();
+/// let chain = Chain::new(&extended, &31000001.into()).unwrap();
+/// assert_eq!(1, chain.root.children.len());
+/// # #[cfg(feature = "json")]
+/// assert!(chain.to_json().contains(r#""signature":"ABC-123""#));
+/// ```
+#[derive(Debug)]
+pub struct Chain<'a> {
+    pub root: ChainNode<'a>,
+}
+
+impl<'a> Chain<'a> {
+    /// Builds the chain starting at `home`, following wormhole connections only.
+    /// Wormholes that have already expired are left out. Returns `None` if the home
+    /// system is unknown.
+    pub fn new(universe: &'a dyn types::Navigatable, home: &types::SystemId) -> Option<Self> {
+        Self::at(universe, home, Utc::now())
+    }
+
+    /// Like `new()`, leaving out the wormholes expired at `time` instead of now.
+    pub fn at(
+        universe: &'a dyn types::Navigatable,
+        home: &types::SystemId,
+        time: DateTime<Utc>,
+    ) -> Option<Self> {
+        let root = universe.get_system(home)?;
+        let mut visited = HashSet::new();
+        visited.insert(root.id);
+        Some(Self {
+            root: Self::node(universe, root, None, &[], time, &mut visited),
+        })
+    }
+
+    fn node(
+        universe: &'a dyn types::Navigatable,
+        system: &'a types::System,
+        connection: Option<types::Wormhole>,
+        annotations: &'a [types::Annotation],
+        time: DateTime<Utc>,
+        visited: &mut HashSet<types::SystemId>,
+    ) -> ChainNode<'a> {
+        let mut children = Vec::new();
+        for conn in universe.get_connections(&system.id).unwrap_or_default() {
+            let types::ConnectionType::Wormhole(wormhole) = conn.r#type else {
+                continue;
+            };
+            if wormhole.expires <= time {
+                continue;
+            }
+            let Some(to) = universe.get_system(&conn.to) else {
+                continue;
+            };
+            if !visited.insert(to.id) {
+                continue;
+            }
+            let annotations = universe.get_annotations(&conn.from, &conn.to);
+            children.push(Self::node(
+                universe,
+                to,
+                Some(wormhole),
+                annotations,
+                time,
+                visited,
+            ));
+        }
+
+        ChainNode {
+            system,
+            connection,
+            annotations,
+            children,
+        }
+    }
+
+    /// Exports the chain as nested JSON objects.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&json::Node::from(&self.root)).expect("chains are valid JSON")
+    }
+}
+
+/// The shape of `Chain::to_json()`.
+#[cfg(feature = "json")]
+mod json {
+    use serde::Serialize;
+
+    use crate::types;
+
+    #[derive(Serialize)]
+    pub(super) struct Node<'a> {
+        id: u32,
+        name: &'a str,
+        security: f32,
+        connection: Option<Connection<'a>>,
+        annotations: Vec<Annotation<'a>>,
+        children: Vec<Node<'a>>,
+    }
+
+    #[derive(Serialize)]
+    struct Connection<'a> {
+        signature: &'a str,
+        exit_signature: Option<&'a str>,
+        max_ship_size: String,
+        remaining_hours: u16,
+        expires: String,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Annotation<'a> {
+        Scout(&'a str),
+        Bookmark { system: u32, name: &'a str },
+        Expires(String),
+        Tag(&'a str),
+    }
+
+    impl<'a> From<&'a super::ChainNode<'a>> for Node<'a> {
+        fn from(node: &'a super::ChainNode<'a>) -> Self {
+            Self {
+                id: node.system.id.0,
+                name: &node.system.name,
+                security: node.system.security.display_value(),
+                connection: node.connection.as_ref().map(|wh| Connection {
+                    signature: &wh.signature,
+                    exit_signature: wh.exit_signature.as_deref(),
+                    max_ship_size: wh.max_ship_size.to_string(),
+                    remaining_hours: wh.remaining_hours,
+                    expires: wh.expires.to_rfc3339(),
+                }),
+                annotations: node
+                    .annotations
+                    .iter()
+                    .map(|annotation| match annotation {
+                        types::Annotation::Scout(name) => Annotation::Scout(name),
+                        types::Annotation::Bookmark { system, name } => Annotation::Bookmark {
+                            system: system.0,
+                            name,
+                        },
+                        types::Annotation::Expires(time) => Annotation::Expires(time.to_rfc3339()),
+                        types::Annotation::Tag(tag) => Annotation::Tag(tag),
+                    })
+                    .collect(),
+                children: node.children.iter().map(Node::from).collect(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::builder::{ExtendedUniverseBuilder, UniverseBuilder};
+    use crate::fixtures::system;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 4, 12, 0, 0).unwrap()
+    }
+
+    fn wormhole(from: u32, to: u32, hours: i64) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: types::ConnectionType::Wormhole(types::Wormhole {
+                expires: now() + Duration::hours(hours),
+                remaining_hours: hours.max(0) as u16,
+                signature: format!("SIG-{to}"),
+                exit_signature: None,
+                max_ship_size: types::WormholeMaxShipSize::Large,
+                shattered: None,
+            }),
+        }
+    }
+
+    fn gate(from: u32, to: u32) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+        }
+    }
+
+    /// Ids of the children of a node, sorted.
+    fn children(node: &ChainNode) -> Vec<u32> {
+        let mut ids = node
+            .children
+            .iter()
+            .map(|child| child.system.id.0)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Ids of all systems of the chain, sorted.
+    fn systems(node: &ChainNode) -> Vec<u32> {
+        let mut ids = vec![node.system.id.0];
+        ids.extend(node.children.iter().flat_map(systems));
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn test_cycles() {
+        let universe = UniverseBuilder::default()
+            .system(system(31000001))
+            .system(system(31000002))
+            .system(system(31000003))
+            .build();
+        // a triangle, with both directions of each wormhole mapped
+        let extended = [
+            (31000001, 31000002),
+            (31000002, 31000003),
+            (31000003, 31000001),
+        ]
+        .into_iter()
+        .fold(
+            ExtendedUniverseBuilder::new(&universe),
+            |builder, (a, b)| {
+                builder
+                    .connection(wormhole(a, b, 16))
+                    .connection(wormhole(b, a, 16))
+            },
+        )
+        .build();
+        let chain = Chain::at(&extended, &31000001.into(), now()).unwrap();
+
+        // every system once, no matter which wormhole reached it first
+        assert_eq!(vec![31000001, 31000002, 31000003], systems(&chain.root));
+        assert!(chain.root.connection.is_none());
+    }
+
+    #[test]
+    fn test_kspace_exit() {
+        // home - highsec exit, which has a gate to 2 and another wormhole to 31000002
+        let universe = UniverseBuilder::default()
+            .system(system(31000001))
+            .system(system(1))
+            .system(system(2))
+            .system(system(31000002))
+            .connection(gate(1, 2))
+            .build();
+        let extended = ExtendedUniverseBuilder::new(&universe)
+            .connection(wormhole(31000001, 1, 16))
+            .connection(wormhole(1, 31000002, 16))
+            .build();
+        let chain = Chain::at(&extended, &31000001.into(), now()).unwrap();
+
+        assert_eq!(vec![1], children(&chain.root));
+        let exit = &chain.root.children[0];
+        assert_eq!(
+            Some("SIG-1"),
+            exit.connection.as_ref().map(|wh| wh.signature.as_str())
+        );
+        assert_eq!(vec![31000002], children(exit));
+        assert_eq!(vec![1, 31000001, 31000002], systems(&chain.root));
+        assert!(Chain::at(&extended, &31000003.into(), now()).is_none());
+    }
+
+    #[test]
+    fn test_expired() {
+        let universe = UniverseBuilder::default()
+            .system(system(31000001))
+            .system(system(31000002))
+            .system(system(31000003))
+            .build();
+        let extended = ExtendedUniverseBuilder::new(&universe)
+            .connection(wormhole(31000001, 31000003, -1))
+            .connection(wormhole(31000001, 31000002, 1))
+            .connection(wormhole(31000002, 31000003, 4))
+            .build();
+
+        // the expired shortcut is gone, 31000003 is reached through 31000002
+        let chain = Chain::at(&extended, &31000001.into(), now()).unwrap();
+        assert_eq!(vec![31000002], children(&chain.root));
+        assert_eq!(vec![31000003], children(&chain.root.children[0]));
+        let later = Chain::at(&extended, &31000001.into(), now() + Duration::hours(2)).unwrap();
+        assert_eq!(vec![31000001], systems(&later.root));
+        let earlier = Chain::at(&extended, &31000001.into(), now() - Duration::hours(2)).unwrap();
+        assert_eq!(vec![31000002, 31000003], children(&earlier.root));
+    }
+}
@@ -0,0 +1,263 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Declarative extensions files describing the infrastructure of a group, such as
+//! jump bridges, Ansiblex gates and mapped wormholes, so it can be kept under version
+//! control and loaded into an `ExtendedUniverse` in one call.
+//!
+//! Systems are referenced either by id or by name. Connections are one-way, list both
+//! directions of an Ansiblex pair or wormhole if needed.
+//!
+//! ```toml
+//! avoid = ["Tama", 30002813]
+//!
+//! [[bridge]]
+//! system = "1DQ1-A"
+//! type = "titan"
+//! jump_drive_calibration = 5
+//! fuel_conservation = 4
+//!
+//! [[ansiblex]]
+//! from = "1DQ1-A"
+//! to = "8WA-Z6"
+//!
+//! [[wormhole]]
+//! from = "Jita"
+//! to = 31000005
+//! signature = "ABC-123"
+//! exit_signature = "XYZ-987"
+//! max_ship_size = "Large"
+//! expires = "2024-05-04T19:12:00Z"
+//! ```
+
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::builder::ExtendedUniverseBuilder;
+use crate::names;
+use crate::types;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read extensions file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid extensions file: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("unknown system {0}")]
+    UnknownSystem(String),
+}
+
+/// A system referenced by id or by name.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum SystemRef {
+    Id(u32),
+    Name(String),
+}
+
+impl SystemRef {
+    fn resolve<U: types::Galaxy + types::Navigatable>(
+        &self,
+        universe: &U,
+    ) -> Result<types::SystemId, ConfigError> {
+        let system = match self {
+            Self::Id(id) => universe.get_system(&(*id).into()),
+            Self::Name(name) => universe
+                .systems()
+                .into_iter()
+                .find(|system| names::matches(&system.name, name)),
+        };
+        system.map(|system| system.id).ok_or_else(|| {
+            ConfigError::UnknownSystem(match self {
+                Self::Id(id) => id.to_string(),
+                Self::Name(name) => name.clone(),
+            })
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BridgeKind {
+    Titan,
+    BlackOps,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BridgeConfig {
+    pub system: SystemRef,
+    pub r#type: BridgeKind,
+    pub jump_drive_calibration: u8,
+    pub fuel_conservation: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AnsiblexConfig {
+    pub from: SystemRef,
+    pub to: SystemRef,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WormholeConfig {
+    pub from: SystemRef,
+    pub to: SystemRef,
+    pub signature: String,
+    pub exit_signature: Option<String>,
+    pub max_ship_size: types::WormholeMaxShipSize,
+    /// Set if `to` is a shattered system, e.g. `"Frigate"`.
+    pub shattered: Option<types::Shattered>,
+    pub expires: DateTime<Utc>,
+}
+
+/// The contents of an extensions file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ExtensionsConfig {
+    /// Systems avoided by default when pathfinding.
+    pub avoid: Vec<SystemRef>,
+    pub bridge: Vec<BridgeConfig>,
+    pub ansiblex: Vec<AnsiblexConfig>,
+    pub wormhole: Vec<WormholeConfig>,
+}
+
+impl FromStr for ExtensionsConfig {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(toml::from_str(s)?)
+    }
+}
+
+impl ExtensionsConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// Builds an `ExtendedUniverse` containing the configured connections. Fails if
+    /// any referenced system does not exist in the universe.
+    pub fn apply<'a, U: types::Galaxy + types::Navigatable>(
+        &self,
+        universe: &'a U,
+    ) -> Result<types::ExtendedUniverse<'a, U>, ConfigError> {
+        let mut builder = ExtendedUniverseBuilder::new(universe);
+        for system in &self.avoid {
+            builder = builder.avoid(system.resolve(universe)?);
+        }
+        for bridge in &self.bridge {
+            let skills = types::JumpdriveSkills::new(
+                bridge.jump_drive_calibration,
+                bridge.fuel_conservation,
+            );
+            let type_ = match bridge.r#type {
+                BridgeKind::Titan => types::BridgeType::Titan(skills),
+                BridgeKind::BlackOps => types::BridgeType::BlackOps(skills),
+            };
+            builder = builder.bridge(bridge.system.resolve(universe)?, type_);
+        }
+        for ansiblex in &self.ansiblex {
+            builder = builder.connection(types::Connection {
+                from: ansiblex.from.resolve(universe)?,
+                to: ansiblex.to.resolve(universe)?,
+                r#type: types::ConnectionType::Ansiblex,
+            });
+        }
+        let now = Utc::now();
+        for wormhole in &self.wormhole {
+            builder = builder.connection(types::Connection {
+                from: wormhole.from.resolve(universe)?,
+                to: wormhole.to.resolve(universe)?,
+                r#type: types::ConnectionType::Wormhole(types::Wormhole {
+                    expires: wormhole.expires,
+                    remaining_hours: (wormhole.expires - now).num_hours().max(0) as u16,
+                    signature: wormhole.signature.clone(),
+                    exit_signature: wormhole.exit_signature.clone(),
+                    max_ship_size: wormhole.max_ship_size.clone(),
+                    shattered: wormhole.shattered,
+                }),
+            });
+        }
+        Ok(builder.build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::navigation::PathBuilder;
+    use crate::types::Navigatable;
+
+    fn universe() -> types::Universe {
+        UniverseBuilder::default()
+            .system(system(1).named("1DQ1-A"))
+            .system(system(2).named("8WA-Z6"))
+            .system(system(3).named("J5A-IX"))
+            .build()
+    }
+
+    #[test]
+    fn test_apply() {
+        let universe = universe();
+        let config: ExtensionsConfig = r#"
+            avoid = ["j5a-ix"]
+
+            [[ansiblex]]
+            from = "1DQ1-A"
+            to = 2
+
+            [[ansiblex]]
+            from = 1
+            to = 3
+
+            [[wormhole]]
+            from = 2
+            to = 3
+            signature = "ABC-123"
+            max_ship_size = "Large"
+            expires = "2024-05-04T19:12:00Z"
+        "#
+        .parse()
+        .unwrap();
+        let mut extended = config.apply(&universe).unwrap();
+
+        assert!(extended.avoided_by_default(&3.into()));
+        assert!(
+            extended
+                .connection_by_signature(&2.into(), "abc-123")
+                .is_some()
+        );
+        let route = |extended: &types::ExtendedUniverse<'_, types::Universe>, default_avoid| {
+            PathBuilder::new(extended)
+                .default_avoid(default_avoid)
+                .waypoint(universe.get_system(&1.into()).unwrap())
+                .waypoint(universe.get_system(&3.into()).unwrap())
+                .build()
+                .map(|path| path.jumps())
+        };
+        assert_eq!(None, route(&extended, true));
+        // the avoided systems can be overridden per path or removed
+        assert_eq!(Some(1), route(&extended, false));
+        let version = extended.version();
+        assert!(extended.unavoid(&3.into()));
+        assert!(!extended.unavoid(&3.into()));
+        assert_eq!(Some(1), route(&extended, true));
+        // routes cached for the file's avoided systems are no longer returned
+        assert_ne!(version, extended.version());
+    }
+
+    #[test]
+    fn test_unknown_system() {
+        let config: ExtensionsConfig = "avoid = [\"Jita\"]".parse().unwrap();
+        assert!(matches!(
+            config.apply(&universe()),
+            Err(ConfigError::UnknownSystem(name)) if name == "Jita"
+        ));
+    }
+}
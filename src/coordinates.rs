@@ -0,0 +1,171 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Conversions of the raw coordinates of the SDE into units and origins that are
+//! convenient for visualizations and range math.
+//!
+//! Coordinates in the SDE are in meters. The origin is close to the center of New
+//! Eden, referred to as the galactic center here. Seen from above like the in-game
+//! map, `x` grows to the east and `z` to the north, `y` is the height above the
+//! galactic plane.
+
+use crate::types;
+
+/// The origin of SDE coordinates.
+pub const GALACTIC_CENTER: types::Coordinate = types::Coordinate {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+};
+
+impl types::Coordinate {
+    /// The coordinate with every axis in lightyears instead of meters.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::Coordinate;
+    ///
+    /// let coordinate = Coordinate::from((9.4607304725808e15, 0.0, -1.89214609451616e16));
+    /// let [x, y, z] = coordinate.to_lightyears();
+    /// assert!((x - 1.0).abs() < 1e-9 && y == 0.0 && (z + 2.0).abs() < 1e-9);
+    /// ```
+    pub fn to_lightyears(&self) -> [f64; 3] {
+        [self.x, self.y, self.z].map(|meters| meters / types::METERS_PER_LIGHTYEAR)
+    }
+
+    /// Converts a coordinate given in lightyears into SDE meters.
+    pub fn from_lightyears([x, y, z]: [f64; 3]) -> Self {
+        let [x, y, z] = [x, y, z].map(|ly| ly * types::METERS_PER_LIGHTYEAR);
+        Self { x, y, z }
+    }
+
+    pub fn distance(&self, other: &types::Coordinate) -> types::Meters {
+        let (dx, dy, dz) = (self.x - other.x, self.y - other.y, self.z - other.z);
+        types::Meters((dx * dx + dy * dy + dz * dz).sqrt())
+    }
+
+    /// The straight-line distance from the galactic center.
+    pub fn distance_from_center(&self) -> types::Lightyears {
+        self.distance(&GALACTIC_CENTER).into()
+    }
+}
+
+/// Projects coordinates onto a square, top-down map with normalized coordinates,
+/// scaled so that the given systems fill the map.
+///
+/// Normalized coordinates are `(u, v)` with both between 0 and 1 for systems within
+/// the bounds. `u` grows to the east and `v` to the south, like pixels in an image,
+/// so `(u * width, v * height)` is the position on a map image. Both axes use the
+/// same scale, the narrower axis is centered.
+///
+/// # Example
+/// ```
+/// use neweden::coordinates::MapProjection;
+/// use neweden::Coordinate;
+///
+/// let west: Coordinate = (-1e17, 0.0, 0.0).into();
+/// let east: Coordinate = (1e17, 0.0, 5e16).into();
+/// let map = MapProjection::from_coordinates([&west, &east]).unwrap();
+/// assert_eq!((0.0, 0.625), map.normalize(&west));
+/// assert_eq!((1.0, 0.375), map.normalize(&east));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MapProjection {
+    /// Center of the bounds on the `x` and `z` axis.
+    center: (f64, f64),
+    /// Length of the sides of the square in meters.
+    size: f64,
+}
+
+impl MapProjection {
+    /// A projection fitting the given systems, e.g. `universe.all_systems()` or the
+    /// systems of a region. Returns `None` if there are no systems.
+    pub fn new<'a>(systems: impl IntoIterator<Item = &'a types::System>) -> Option<Self> {
+        Self::from_coordinates(systems.into_iter().map(|system| &system.coordinate))
+    }
+
+    pub fn from_coordinates<'a>(
+        coordinates: impl IntoIterator<Item = &'a types::Coordinate>,
+    ) -> Option<Self> {
+        let mut coordinates = coordinates.into_iter();
+        let first = coordinates.next()?;
+        let (mut min, mut max) = ((first.x, first.z), (first.x, first.z));
+        for coordinate in coordinates {
+            min = (min.0.min(coordinate.x), min.1.min(coordinate.z));
+            max = (max.0.max(coordinate.x), max.1.max(coordinate.z));
+        }
+        let size = (max.0 - min.0).max(max.1 - min.1);
+        Some(Self {
+            center: ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0),
+            // a single system is drawn in the middle of the map
+            size: if size > 0.0 { size } else { 1.0 },
+        })
+    }
+
+    /// The position of a coordinate on the map.
+    pub fn normalize(&self, coordinate: &types::Coordinate) -> (f64, f64) {
+        (
+            0.5 + (coordinate.x - self.center.0) / self.size,
+            0.5 - (coordinate.z - self.center.1) / self.size,
+        )
+    }
+
+    /// The coordinate at a position on the map, in the galactic plane.
+    pub fn denormalize(&self, (u, v): (f64, f64)) -> types::Coordinate {
+        types::Coordinate {
+            x: self.center.0 + (u - 0.5) * self.size,
+            y: 0.0,
+            z: self.center.1 - (v - 0.5) * self.size,
+        }
+    }
+
+    /// The length of the sides of the map.
+    pub fn size(&self) -> types::Lightyears {
+        types::Meters(self.size).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::system;
+
+    #[test]
+    fn test_lightyears() {
+        let coordinate = types::Coordinate::from_lightyears([3.0, -4.0, 0.0]);
+        assert_eq!(3.0 * types::METERS_PER_LIGHTYEAR, coordinate.x);
+        assert!((coordinate.distance_from_center().0 - 5.0).abs() < 1e-12);
+        let [x, y, z] = coordinate.to_lightyears();
+        assert_eq!([3.0, -4.0, 0.0], [x.round(), y.round(), z]);
+        assert_eq!(
+            types::Lightyears(1.0),
+            types::Meters(types::METERS_PER_LIGHTYEAR).into()
+        );
+    }
+
+    #[test]
+    fn test_projection() {
+        assert!(MapProjection::new([]).is_none());
+
+        // 4 ly wide from west to east, 2 ly from south to north
+        let ly = types::METERS_PER_LIGHTYEAR;
+        let systems = [
+            system(1).at(-2.0 * ly, 1e15, 0.0),
+            system(2).at(2.0 * ly, 1e15, 2.0 * ly),
+            system(3).at(0.0, 1e15, ly),
+        ];
+        let map = MapProjection::new(&systems).unwrap();
+        assert_eq!(types::Lightyears(4.0), map.size());
+        assert_eq!((0.0, 0.75), map.normalize(&systems[0].coordinate));
+        assert_eq!((1.0, 0.25), map.normalize(&systems[1].coordinate));
+        assert_eq!((0.5, 0.5), map.normalize(&systems[2].coordinate));
+
+        let center = map.denormalize((0.5, 0.5));
+        assert_eq!((0.0, ly), (center.x, center.z));
+
+        let single = MapProjection::new(&systems[..1]).unwrap();
+        assert_eq!((0.5, 0.5), single.normalize(&systems[0].coordinate));
+    }
+}
@@ -0,0 +1,441 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Differences between two versions of the universe, e.g. between the SDE before
+//! and after an expansion, so tools can react to new systems, renames, security
+//! and faction changes and new or removed stargates, and deltas to patch a universe with small
+//! updates without loading it again.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::types;
+
+/// A system whose name changed.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Renamed {
+    pub id: types::SystemId,
+    pub old: String,
+    pub new: String,
+}
+
+/// A system whose security status changed.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SecurityChange {
+    pub id: types::SystemId,
+    pub old: types::Security,
+    pub new: types::Security,
+}
+
+/// A system whose owning faction changed, e.g. when the Triglavians took over
+/// Pochven. `None` is player-claimable or wormhole space.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FactionChange {
+    pub id: types::SystemId,
+    pub old: Option<u32>,
+    pub new: Option<u32>,
+}
+
+/// A system that moved to another region, such as the Pochven systems.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Moved {
+    pub id: types::SystemId,
+    pub old_region: String,
+    pub new_region: String,
+}
+
+/// What changed from one universe to another. All lists are sorted by system id,
+/// connections are `(from, to)` pairs.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::System;
+///
+/// let system = |name: &str| System {
+///     id: 30000142.into(),
+///     name: name.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: 0.9459.into(),
+///     region_name: "The Forge".to_string(),
+///     faction_id: None,
+/// };
+/// let old = UniverseBuilder::default().system(system("Jita")).build();
+/// let new = UniverseBuilder::default().system(system("Jita Prime")).build();
+///
+/// let diff = old.diff(&new);
+/// assert_eq!("Jita Prime", diff.renamed[0].new);
+/// assert!(diff.added_systems.is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UniverseDiff {
+    pub added_systems: Vec<types::SystemId>,
+    pub removed_systems: Vec<types::SystemId>,
+    pub renamed: Vec<Renamed>,
+    pub security_changes: Vec<SecurityChange>,
+    pub faction_changes: Vec<FactionChange>,
+    pub moved: Vec<Moved>,
+    pub added_connections: Vec<(types::SystemId, types::SystemId)>,
+    pub removed_connections: Vec<(types::SystemId, types::SystemId)>,
+}
+
+impl UniverseDiff {
+    pub fn new(old: &dyn types::Navigatable, new: &dyn types::Navigatable) -> Self {
+        let (old_systems, new_systems) = (systems(old), systems(new));
+
+        let mut diff = Self::default();
+        for (id, system) in &new_systems {
+            let Some(previous) = old_systems.get(id) else {
+                diff.added_systems.push(*id);
+                continue;
+            };
+            if previous.name != system.name {
+                diff.renamed.push(Renamed {
+                    id: *id,
+                    old: previous.name.clone(),
+                    new: system.name.clone(),
+                });
+            }
+            if previous.security != system.security {
+                diff.security_changes.push(SecurityChange {
+                    id: *id,
+                    old: previous.security,
+                    new: system.security,
+                });
+            }
+            if previous.faction_id != system.faction_id {
+                diff.faction_changes.push(FactionChange {
+                    id: *id,
+                    old: previous.faction_id,
+                    new: system.faction_id,
+                });
+            }
+            if previous.region_name != system.region_name {
+                diff.moved.push(Moved {
+                    id: *id,
+                    old_region: previous.region_name.clone(),
+                    new_region: system.region_name.clone(),
+                });
+            }
+        }
+        diff.removed_systems = old_systems
+            .keys()
+            .filter(|id| !new_systems.contains_key(id))
+            .copied()
+            .collect();
+
+        let (old_connections, new_connections) = (connections(old), connections(new));
+        diff.added_connections = new_connections
+            .difference(&old_connections)
+            .copied()
+            .collect();
+        diff.removed_connections = old_connections
+            .difference(&new_connections)
+            .copied()
+            .collect();
+
+        diff.added_systems.sort_unstable();
+        diff.removed_systems.sort_unstable();
+        diff.renamed.sort_by_key(|change| change.id);
+        diff.security_changes.sort_by_key(|change| change.id);
+        diff.faction_changes.sort_by_key(|change| change.id);
+        diff.moved.sort_by_key(|change| change.id);
+        diff
+    }
+
+    /// Returns true if both universes have the same systems and connections.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Changes to apply to a universe with `Universe::apply()`, e.g. a renamed system or
+/// a new connection.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::diff::UniverseDelta;
+/// use neweden::{Navigatable, System};
+///
+/// let jita = System {
+///     id: 30000142.into(),
+///     name: "Jita".to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: 0.9459.into(),
+///     region_name: "The Forge".to_string(),
+///     faction_id: None,
+/// };
+/// let universe = UniverseBuilder::default().system(jita.clone()).build();
+///
+/// let renamed = System {
+///     name: "Jita Prime".to_string(),
+///     ..jita
+/// };
+/// let universe = universe.apply(UniverseDelta::default().system(renamed));
+/// assert_eq!("Jita Prime", universe.get_system(&30000142.into()).unwrap().name);
+/// ```
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UniverseDelta {
+    /// New systems and systems that replace the system with the same id.
+    pub systems: Vec<types::System>,
+    pub removed_systems: Vec<types::SystemId>,
+    pub connections: Vec<types::Connection>,
+    /// Connections removed as `(from, to)`.
+    pub removed_connections: Vec<(types::SystemId, types::SystemId)>,
+}
+
+impl UniverseDelta {
+    /// Adds a system or replaces the system with the same id.
+    pub fn system(mut self, system: types::System) -> Self {
+        self.systems.push(system);
+        self
+    }
+
+    /// Removes a system and all connections from and to it.
+    pub fn remove_system(mut self, id: types::SystemId) -> Self {
+        self.removed_systems.push(id);
+        self
+    }
+
+    pub fn connection(mut self, connection: types::Connection) -> Self {
+        self.connections.push(connection);
+        self
+    }
+
+    pub fn remove_connection(mut self, from: types::SystemId, to: types::SystemId) -> Self {
+        self.removed_connections.push((from, to));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+            && self.removed_systems.is_empty()
+            && self.connections.is_empty()
+            && self.removed_connections.is_empty()
+    }
+
+    /// Builds a new universe from `universe` with the changes applied. Removals are
+    /// applied before additions.
+    pub(crate) fn apply(self, universe: &types::Universe) -> types::Universe {
+        let removed_systems = self.removed_systems.iter().collect::<HashSet<_>>();
+        let replaced = self.systems.iter().map(|s| s.id).collect::<HashSet<_>>();
+        let systems = universe
+            .systems
+            .systems()
+            .into_iter()
+            .filter(|s| !removed_systems.contains(&s.id) && !replaced.contains(&s.id))
+            .cloned()
+            .chain(self.systems)
+            .collect::<Vec<_>>();
+
+        let removed_connections = self.removed_connections.iter().collect::<HashSet<_>>();
+        let connections = universe
+            .connections
+            .0
+            .values()
+            .flatten()
+            .filter(|c| {
+                !removed_connections.contains(&(c.from, c.to))
+                    && !removed_systems.contains(&c.from)
+                    && !removed_systems.contains(&c.to)
+            })
+            .cloned()
+            .chain(self.connections)
+            .collect::<Vec<_>>();
+
+        types::Universe::new(systems.into(), connections.into())
+            .with_hierarchy(universe.hierarchy.clone())
+            .with_stations(universe.stations.clone())
+            .with_stargates(universe.stargates.clone())
+            .with_celestials(universe.celestials.clone())
+            .with_wormhole_classes(universe.wormhole_classes.clone())
+    }
+}
+
+fn systems(universe: &dyn types::Navigatable) -> HashMap<types::SystemId, &types::System> {
+    universe
+        .all_systems()
+        .into_iter()
+        .map(|system| (system.id, system))
+        .collect()
+}
+
+fn connections(universe: &dyn types::Navigatable) -> BTreeSet<(types::SystemId, types::SystemId)> {
+    universe
+        .all_systems()
+        .into_iter()
+        .flat_map(|system| universe.get_connections(&system.id).unwrap_or_default())
+        .map(|conn| (conn.from, conn.to))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+
+    fn stargate(from: u32, to: u32) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = UniverseBuilder::default()
+            .system(
+                system(1)
+                    .named("Arvasaras")
+                    .with_security(0.3)
+                    .in_region("Lonetrek")
+                    .with_faction(Some(500001)),
+            )
+            .system(
+                system(2)
+                    .named("Nani")
+                    .with_security(0.5)
+                    .in_region("The Citadel"),
+            )
+            .system(
+                system(3)
+                    .named("Ignebaener")
+                    .with_security(0.6)
+                    .in_region("Placid"),
+            )
+            .connection(stargate(1, 2))
+            .connection(stargate(2, 1))
+            .connection(stargate(2, 3))
+            .build();
+        let new = UniverseBuilder::default()
+            .system(
+                system(1)
+                    .named("Arvasaras")
+                    .with_security(-1.0)
+                    .in_region("Pochven")
+                    .with_faction(Some(500026)),
+            )
+            .system(
+                system(2)
+                    .named("Nani Prime")
+                    .with_security(0.5)
+                    .in_region("The Citadel"),
+            )
+            .system(
+                system(4)
+                    .named("Zarzakh")
+                    .with_security(-1.0)
+                    .in_region("G-R00031"),
+            )
+            .connection(stargate(2, 1))
+            .connection(stargate(2, 4))
+            .build();
+
+        assert!(old.diff(&old).is_empty());
+        let diff = old.diff(&new);
+        assert_eq!(vec![types::SystemId(4)], diff.added_systems);
+        assert_eq!(vec![types::SystemId(3)], diff.removed_systems);
+        assert_eq!(
+            vec![Renamed {
+                id: 2.into(),
+                old: "Nani".to_string(),
+                new: "Nani Prime".to_string()
+            }],
+            diff.renamed
+        );
+        assert_eq!(1, diff.security_changes.len());
+        assert_eq!(-1.0, diff.security_changes[0].new.0);
+        assert_eq!(
+            vec![FactionChange {
+                id: 1.into(),
+                old: Some(500001),
+                new: Some(500026)
+            }],
+            diff.faction_changes
+        );
+        assert_eq!("Pochven", diff.moved[0].new_region);
+        assert_eq!(vec![(2.into(), 4.into())], diff.added_connections);
+        assert_eq!(
+            vec![(1.into(), 2.into()), (2.into(), 3.into())],
+            diff.removed_connections
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        use types::Navigatable;
+
+        let universe = UniverseBuilder::default()
+            .system(
+                system(1)
+                    .named("Arvasaras")
+                    .with_security(0.3)
+                    .in_region("Lonetrek"),
+            )
+            .system(
+                system(2)
+                    .named("Nani")
+                    .with_security(0.5)
+                    .in_region("The Citadel"),
+            )
+            .system(
+                system(3)
+                    .named("Ignebaener")
+                    .with_security(0.6)
+                    .in_region("Placid"),
+            )
+            .connection(stargate(1, 2))
+            .connection(stargate(2, 1))
+            .connection(stargate(2, 3))
+            .connection(stargate(3, 2))
+            .build();
+
+        let unchanged = universe.apply(UniverseDelta::default());
+        assert!(universe.diff(&unchanged).is_empty());
+        assert_eq!(universe.version(), unchanged.version());
+
+        let delta = UniverseDelta::default()
+            .system(
+                system(2)
+                    .named("Nani Prime")
+                    .with_security(0.5)
+                    .in_region("The Citadel"),
+            )
+            .system(
+                system(4)
+                    .named("Zarzakh")
+                    .with_security(-1.0)
+                    .in_region("G-R00031"),
+            )
+            .remove_system(3.into())
+            .remove_connection(1.into(), 2.into())
+            .connection(stargate(2, 4));
+        let updated = universe.apply(delta);
+
+        assert_eq!("Nani Prime", updated.get_system(&2.into()).unwrap().name);
+        assert!(updated.get_system(&3.into()).is_none());
+        assert!(updated.get_connections(&1.into()).is_none());
+        let diff = universe.diff(&updated);
+        assert_eq!(vec![types::SystemId(4)], diff.added_systems);
+        assert_eq!(vec![(2.into(), 4.into())], diff.added_connections);
+        assert_eq!(
+            vec![
+                (1.into(), 2.into()),
+                (2.into(), 3.into()),
+                (3.into(), 2.into())
+            ],
+            diff.removed_connections
+        );
+        assert_ne!(universe.version(), updated.version());
+    }
+}
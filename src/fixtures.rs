@@ -0,0 +1,50 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Systems for the unit tests. `system()` makes a highsec system named after its
+//! id and the methods change the fields a test cares about, e.g.
+//! `system(30000142).named("Jita").with_security(0.9)`.
+
+use crate::types;
+
+/// A system named after its id at the origin, in "Region" with a security of 0.5
+/// and no faction.
+pub(crate) fn system(id: u32) -> types::System {
+    types::System::new(
+        id.into(),
+        id.to_string(),
+        (0.0, 0.0, 0.0).into(),
+        0.5.into(),
+        "Region".to_string(),
+        None,
+    )
+}
+
+impl types::System {
+    pub(crate) fn named(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub(crate) fn in_region(mut self, region: &str) -> Self {
+        self.region_name = region.to_string();
+        self
+    }
+
+    pub(crate) fn with_security(mut self, security: f32) -> Self {
+        self.security = security.into();
+        self
+    }
+
+    pub(crate) fn at(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.coordinate = (x, y, z).into();
+        self
+    }
+
+    pub(crate) fn with_faction(mut self, faction_id: Option<u32>) -> Self {
+        self.faction_id = faction_id;
+        self
+    }
+}
@@ -0,0 +1,51 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Renderers for routes and helpers for the hand written CSV exports.
+
+pub mod html;
+pub mod table;
+
+use crate::navigation::{Path, RouteWarning};
+
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Short labels of the warnings of the system at `hop` of a path.
+pub(crate) fn warning_labels(warnings: &[RouteWarning<'_>], hop: usize) -> Vec<String> {
+    warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            RouteWarning::FirstLowsec(at, _) if *at == hop => Some("first lowsec".to_string()),
+            RouteWarning::FirstNullsec(at, _) if *at == hop => Some("first nullsec".to_string()),
+            RouteWarning::ConsecutiveNullsec { hop: at, count, .. } if *at == hop => {
+                Some(format!("{count} nullsec in a row"))
+            }
+            RouteWarning::WormholeAfterDowntime(at, _) if *at == hop => {
+                Some("wormhole after downtime".to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Short name of the connection used to reach a system of a path, empty for the
+/// origin.
+pub(crate) fn via(path: &Path<'_>) -> Vec<String> {
+    let hops = path.hops().into_iter().map(|hop| match hop.connection {
+        crate::types::ConnectionType::Stargate(_) => "Stargate".to_string(),
+        connection => connection.to_string(),
+    });
+    path.from()
+        .map(|_| String::new())
+        .into_iter()
+        .chain(hops)
+        .collect()
+}
@@ -0,0 +1,171 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Renders systems and paths as HTML fragments for web tools.
+//!
+//! All text is escaped, so names from user supplied extensions can't inject markup.
+//! Elements carry `neweden-*` classes for styling, the security is colored inline
+//! in the in-game palette. Systems link to Dotlan, with an additional link to the
+//! kills on zKillboard.
+
+use crate::navigation::Path;
+use crate::types;
+
+pub const DOTLAN_URL: &str = "https://evemaps.dotlan.net";
+pub const ZKILLBOARD_URL: &str = "https://zkillboard.com";
+
+pub(crate) fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Dotlan uses underscores for spaces, everything else is percent encoded.
+fn dotlan_segment(name: &str) -> String {
+    let mut out = String::new();
+    for byte in name.bytes() {
+        match byte {
+            b' ' => out.push('_'),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' => out.push(byte as char),
+            byte => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+pub fn dotlan_url(system: &types::System) -> String {
+    format!("{DOTLAN_URL}/system/{}", dotlan_segment(&system.name))
+}
+
+pub fn zkillboard_url(system: &types::System) -> String {
+    format!("{ZKILLBOARD_URL}/system/{}/", system.id.0)
+}
+
+/// A system with its security and region, linked to Dotlan and zKillboard.
+///
+/// # Example
+/// ```
+/// use neweden::format::html;
+/// use neweden::System;
+///
+/// let jita = System {
+///     id: 30000142.into(),
+///     name: "Jita".to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: 0.9459.into(),
+///     region_name: "The Forge".to_string(),
+///     faction_id: None,
+/// };
+/// assert!(html::system(&jita).contains(r#"href="https://evemaps.dotlan.net/system/Jita""#));
+/// ```
+pub fn system(system: &types::System) -> String {
+    format!(
+        concat!(
+            r#"<span class="neweden-system">"#,
+            r#"<a href="{dotlan}">{name}</a> "#,
+            r#"<span class="neweden-security" style="color: {color}">{security}</span> "#,
+            r#"<span class="neweden-region">{region}</span> "#,
+            r#"<a class="neweden-kills" href="{zkillboard}">kills</a>"#,
+            "</span>",
+        ),
+        dotlan = escape(&dotlan_url(system)),
+        name = escape(&system.name),
+        color = system.security.hex(),
+        security = system.security,
+        region = escape(&system.region_name),
+        zkillboard = escape(&zkillboard_url(system)),
+    )
+}
+
+/// A path as an ordered list with one item per system, the connection used to
+/// reach it and its route warnings.
+pub fn route(path: &Path<'_>) -> String {
+    let warnings = path.warnings();
+    let mut out = String::from(r#"<ol class="neweden-route">"#);
+    for (hop, (system, via)) in path.systems().zip(super::via(path)).enumerate() {
+        out.push_str("<li>");
+        if !via.is_empty() {
+            out.push_str(&format!(
+                r#"<span class="neweden-via">{}</span> "#,
+                escape(&via)
+            ));
+        }
+        out.push_str(&self::system(system));
+        for warning in super::warning_labels(&warnings, hop) {
+            out.push_str(&format!(
+                r#" <span class="neweden-warning">{}</span>"#,
+                escape(&warning)
+            ));
+        }
+        out.push_str("</li>");
+    }
+    out.push_str("</ol>");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::navigation::PathBuilder;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_system() {
+        let html = super::system(
+            &system(30045328)
+                .named("<b>Ichoriya</b> V")
+                .with_security(0.4)
+                .in_region("Black Rise"),
+        );
+        assert_eq!(
+            concat!(
+                r#"<span class="neweden-system">"#,
+                r#"<a href="https://evemaps.dotlan.net/system/%3Cb%3EIchoriya%3C%2Fb%3E_V">"#,
+                "&lt;b&gt;Ichoriya&lt;/b&gt; V</a> ",
+                r#"<span class="neweden-security" style="color: #d77700">0.4</span> "#,
+                r#"<span class="neweden-region">Black Rise</span> "#,
+                r#"<a class="neweden-kills" href="https://zkillboard.com/system/30045328/">kills</a>"#,
+                "</span>",
+            ),
+            html
+        );
+    }
+
+    #[test]
+    fn test_route() {
+        let universe = UniverseBuilder::default()
+            .system(system(1).named("Tama").with_security(0.3))
+            .system(system(2).named("Nourvukaiken").with_security(0.8))
+            .connection(types::Connection {
+                from: 2.into(),
+                to: 1.into(),
+                r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+            })
+            .build();
+        let path = PathBuilder::new(&universe)
+            .waypoint(universe.get_system(&2.into()).unwrap())
+            .waypoint(universe.get_system(&1.into()).unwrap())
+            .build()
+            .unwrap();
+        let html = route(&path);
+        assert!(html.starts_with(r#"<ol class="neweden-route"><li><span class="neweden-system">"#));
+        assert_eq!(2, html.matches("<li>").count());
+        assert!(html.contains(
+            r#"<li><span class="neweden-via">Stargate</span> <span class="neweden-system">"#
+        ));
+        assert!(html.ends_with(r#" <span class="neweden-warning">first lowsec</span></li></ol>"#));
+    }
+}
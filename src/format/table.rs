@@ -0,0 +1,210 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Renders a path as an aligned table for terminals, one row per system.
+
+use std::fmt;
+
+use crate::navigation::Path;
+use crate::types;
+
+const HEADER: [&str; 6] = ["#", "System", "Sec", "Region", "Via", "Warnings"];
+
+const RESET: &str = "\x1b[0m";
+const BOLD_RED: &str = "\x1b[1;31m";
+
+struct Row {
+    cells: [String; 6],
+    security: types::Security,
+}
+
+/// A path rendered as a table with the hop number, system, security, region, the
+/// connection used to reach the system and the route warnings of the system.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::format::table::RouteTable;
+/// use neweden::navigation::PathBuilder;
+/// use neweden::{Connection, ConnectionType, Navigatable, StargateType, System};
+///
+/// let system = |id: u32, name: &str, security: f32| System {
+///     id: id.into(),
+///     name: name.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: security.into(),
+///     region_name: "The Forge".to_string(),
+///     faction_id: None,
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(30000142, "Jita", 0.9459))
+///     .system(system(30000144, "Perimeter", 0.9072))
+///     .connection(Connection {
+///         from: 30000142.into(),
+///         to: 30000144.into(),
+///         r#type: ConnectionType::Stargate(StargateType::Local),
+///     })
+///     .build();
+/// let path = PathBuilder::new(&universe)
+///     .waypoint(universe.get_system(&30000142.into()).unwrap())
+///     .waypoint(universe.get_system(&30000144.into()).unwrap())
+///     .build()
+///     .unwrap();
+/// println!("{}", RouteTable::new(&path).colors(true));
+/// ```
+pub struct RouteTable<'a, 'b> {
+    path: &'b Path<'a>,
+    colors: bool,
+}
+
+impl<'a, 'b> RouteTable<'a, 'b> {
+    pub fn new(path: &'b Path<'a>) -> Self {
+        Self {
+            path,
+            colors: false,
+        }
+    }
+
+    /// Colors the security in the in-game palette and the warnings in red using
+    /// ANSI escape codes. Off by default.
+    pub fn colors(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let warnings = self.path.warnings();
+        self.path
+            .systems()
+            .zip(super::via(self.path))
+            .enumerate()
+            .map(|(hop, (system, via))| Row {
+                cells: [
+                    hop.to_string(),
+                    system.name.clone(),
+                    system.security.to_string(),
+                    system.region_name.clone(),
+                    via,
+                    super::warning_labels(&warnings, hop).join(", "),
+                ],
+                security: system.security,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for RouteTable<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self.rows();
+        let mut widths = HEADER.map(|title| title.chars().count());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(&row.cells) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        // the last column is not padded to avoid trailing whitespace
+        let last = widths.len() - 1;
+
+        let header = HEADER
+            .iter()
+            .enumerate()
+            .map(|(i, title)| match i {
+                _ if i == last => title.to_string(),
+                0 | 2 => format!("{title:>width$}", width = widths[i]),
+                _ => format!("{title:<width$}", width = widths[i]),
+            })
+            .collect::<Vec<_>>();
+        writeln!(f, "{}", header.join("  "))?;
+        let rule = widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<_>>();
+        writeln!(f, "{}", rule.join("  "))?;
+
+        for row in &rows {
+            let cells = row
+                .cells
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    let padded = match i {
+                        _ if i == last => cell.clone(),
+                        0 | 2 => format!("{cell:>width$}", width = widths[i]),
+                        _ => format!("{cell:<width$}", width = widths[i]),
+                    };
+                    match i {
+                        2 if self.colors => {
+                            let types::Rgb(r, g, b) = row.security.color();
+                            format!("\x1b[38;2;{r};{g};{b}m{padded}{RESET}")
+                        }
+                        _ if i == last && self.colors && !cell.is_empty() => {
+                            format!("{BOLD_RED}{padded}{RESET}")
+                        }
+                        _ => padded,
+                    }
+                })
+                .collect::<Vec<_>>();
+            writeln!(f, "{}", cells.join("  ").trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::navigation::PathBuilder;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_render() {
+        let mut builder = UniverseBuilder::default();
+        for (id, name, security) in [(1, "Jita", 0.95), (2, "Sivala", 0.3), (3, "Ala", -0.2)] {
+            builder = builder.system(
+                system(id)
+                    .named(name)
+                    .with_security(security)
+                    .in_region("The Forge"),
+            );
+        }
+        for (from, to, r#type) in [
+            (
+                1,
+                2,
+                types::ConnectionType::Stargate(types::StargateType::Local),
+            ),
+            (2, 3, types::ConnectionType::Ansiblex),
+        ] {
+            builder = builder.connection(types::Connection {
+                from: types::SystemId(from),
+                to: types::SystemId(to),
+                r#type,
+            });
+        }
+        let universe = builder.build();
+        let path = PathBuilder::new(&universe)
+            .waypoint(universe.get_system(&1.into()).unwrap())
+            .waypoint(universe.get_system(&3.into()).unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            "\
+#  System   Sec  Region     Via       Warnings
+-  ------  ----  ---------  --------  -------------
+0  Jita     1.0  The Forge
+1  Sivala   0.3  The Forge  Stargate  first lowsec
+2  Ala     -0.2  The Forge  Ansiblex  first nullsec
+",
+            RouteTable::new(&path).to_string()
+        );
+
+        let colored = RouteTable::new(&path).colors(true).to_string();
+        assert!(colored.contains(&format!("{BOLD_RED}first nullsec{RESET}")));
+        assert!(colored.contains("\x1b[38;2;"));
+    }
+}
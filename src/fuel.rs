@@ -0,0 +1,242 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Fuel consumption of jump drives, jump portals (titan and black ops bridges) and
+//! Ansiblex jump gates. Ship masses are in kilograms.
+//!
+//! The numbers are estimates for logistics planning. The client remains the source
+//! of truth for the exact amount of fuel used.
+
+use crate::types;
+
+/// Liquid ozone used by an Ansiblex for every jump, regardless of the ship mass.
+pub const ANSIBLEX_BASE_LIQUID_OZONE: u64 = 50;
+
+/// Additional liquid ozone used by an Ansiblex per kilogram of ship mass.
+pub const ANSIBLEX_LIQUID_OZONE_PER_KG: f64 = 0.000_003;
+
+/// The isotopes burned by jump drives and jump portals. Each race's hulls use the
+/// isotope of that race.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Isotope {
+    Helium,
+    Hydrogen,
+    Nitrogen,
+    Oxygen,
+}
+
+impl Isotope {
+    /// The inventory type id, e.g. to look up market prices.
+    pub fn type_id(&self) -> u32 {
+        match self {
+            Self::Helium => 16274,
+            Self::Hydrogen => 17889,
+            Self::Nitrogen => 17888,
+            Self::Oxygen => 17887,
+        }
+    }
+}
+
+/// Jump capable hulls: carriers, supercarriers, force auxiliaries, dreadnoughts,
+/// titans, jump freighters and black ops.
+const HULLS: [(&str, Race); 28] = [
+    ("Archon", Race::Amarr),
+    ("Aeon", Race::Amarr),
+    ("Apostle", Race::Amarr),
+    ("Revelation", Race::Amarr),
+    ("Avatar", Race::Amarr),
+    ("Ark", Race::Amarr),
+    ("Redeemer", Race::Amarr),
+    ("Chimera", Race::Caldari),
+    ("Wyvern", Race::Caldari),
+    ("Minokawa", Race::Caldari),
+    ("Phoenix", Race::Caldari),
+    ("Leviathan", Race::Caldari),
+    ("Rhea", Race::Caldari),
+    ("Widow", Race::Caldari),
+    ("Thanatos", Race::Gallente),
+    ("Nyx", Race::Gallente),
+    ("Ninazu", Race::Gallente),
+    ("Moros", Race::Gallente),
+    ("Erebus", Race::Gallente),
+    ("Anshar", Race::Gallente),
+    ("Sin", Race::Gallente),
+    ("Nidhoggur", Race::Minmatar),
+    ("Hel", Race::Minmatar),
+    ("Lif", Race::Minmatar),
+    ("Naglfar", Race::Minmatar),
+    ("Ragnarok", Race::Minmatar),
+    ("Nomad", Race::Minmatar),
+    ("Panther", Race::Minmatar),
+];
+
+/// The race of a hull.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Race {
+    Amarr,
+    Caldari,
+    Gallente,
+    Minmatar,
+}
+
+impl Race {
+    pub fn isotope(&self) -> Isotope {
+        match self {
+            Self::Amarr => Isotope::Helium,
+            Self::Caldari => Isotope::Nitrogen,
+            Self::Gallente => Isotope::Oxygen,
+            Self::Minmatar => Isotope::Hydrogen,
+        }
+    }
+
+    /// The race of a jump capable hull by its name, case insensitive. Faction and
+    /// special edition hulls are not covered.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::fuel::{Isotope, Race};
+    ///
+    /// assert_eq!(Some(Race::Minmatar), Race::from_hull("Naglfar"));
+    /// assert_eq!(Isotope::Oxygen, Race::from_hull("anshar").unwrap().isotope());
+    /// ```
+    pub fn from_hull(name: &str) -> Option<Self> {
+        HULLS
+            .iter()
+            .find(|(hull, _)| hull.eq_ignore_ascii_case(name.trim()))
+            .map(|(_, race)| *race)
+    }
+}
+
+/// Fuel needed for a route or jump plan.
+///
+/// # Example
+/// ```
+/// use neweden::fuel::FuelCost;
+///
+/// let total: FuelCost = vec![FuelCost::isotopes(3000), FuelCost::liquid_ozone(350)]
+///     .into_iter()
+///     .sum();
+/// assert_eq!(FuelCost { isotopes: 3000, liquid_ozone: 350 }, total);
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct FuelCost {
+    pub isotopes: u64,
+    pub liquid_ozone: u64,
+}
+
+impl FuelCost {
+    pub fn isotopes(isotopes: u64) -> Self {
+        Self {
+            isotopes,
+            liquid_ozone: 0,
+        }
+    }
+
+    pub fn liquid_ozone(liquid_ozone: u64) -> Self {
+        Self {
+            isotopes: 0,
+            liquid_ozone,
+        }
+    }
+}
+
+impl std::ops::Add for FuelCost {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            isotopes: self.isotopes + rhs.isotopes,
+            liquid_ozone: self.liquid_ozone + rhs.liquid_ozone,
+        }
+    }
+}
+
+impl std::iter::Sum for FuelCost {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |total, cost| total + cost)
+    }
+}
+
+/// Isotopes used by a jump drive for a jump of the given distance.
+pub fn jump_isotopes(ship: &types::JumpdriveShip, ly: types::Lightyears) -> u64 {
+    (ship.fuel_per_ly() * ly.0).ceil() as u64
+}
+
+/// Isotopes used by the titan or black ops to bridge a ship of the given mass over
+/// the given distance.
+pub fn bridge_isotopes(bridge: &types::BridgeType, ly: types::Lightyears, mass: u64) -> u64 {
+    (bridge.fuel_per_ly_kg() * ly.0 * mass as f64).ceil() as u64
+}
+
+/// Liquid ozone used by an Ansiblex to send a ship of the given mass.
+pub fn ansiblex_liquid_ozone(mass: u64) -> u64 {
+    ANSIBLEX_BASE_LIQUID_OZONE + (ANSIBLEX_LIQUID_OZONE_PER_KG * mass as f64).ceil() as u64
+}
+
+/// Fuel used to send a ship of the given mass through the connection. Stargates
+/// and wormholes are free. `ly` is the distance between both systems and only used
+/// for bridges.
+pub fn connection_fuel(
+    connection: &types::ConnectionType,
+    ly: types::Lightyears,
+    mass: u64,
+) -> FuelCost {
+    match connection {
+        types::ConnectionType::Stargate(_) | types::ConnectionType::Wormhole(_) => {
+            FuelCost::default()
+        }
+        types::ConnectionType::Ansiblex => FuelCost::liquid_ozone(ansiblex_liquid_ozone(mass)),
+        types::ConnectionType::Bridge(bridge) => {
+            FuelCost::isotopes(bridge_isotopes(bridge, ly, mass))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isotopes() {
+        assert_eq!(Some(Race::Amarr), Race::from_hull(" ARCHON "));
+        assert_eq!(Some(Race::Caldari), Race::from_hull("Rhea"));
+        assert_eq!(None, Race::from_hull("Rorqual"));
+        assert_eq!(Isotope::Hydrogen, Race::Minmatar.isotope());
+        assert_eq!(16274, Race::Amarr.isotope().type_id());
+    }
+
+    #[test]
+    fn test_fuel() {
+        let skills = |jfc| types::JumpdriveSkills::new(5, jfc);
+        let ly = types::Lightyears(5.0);
+        assert_eq!(
+            15000,
+            jump_isotopes(&types::JumpdriveShip::Carrier(skills(0)), ly)
+        );
+        assert_eq!(
+            7500,
+            jump_isotopes(&types::JumpdriveShip::Carrier(skills(5)), ly)
+        );
+        assert_eq!(350, ansiblex_liquid_ozone(100_000_000));
+        assert_eq!(
+            FuelCost::isotopes(1250),
+            connection_fuel(
+                &types::ConnectionType::Bridge(types::BridgeType::Titan(skills(5))),
+                ly,
+                100_000_000
+            )
+        );
+        assert_eq!(
+            FuelCost::default(),
+            connection_fuel(
+                &types::ConnectionType::Stargate(types::StargateType::Local),
+                ly,
+                100_000_000
+            )
+        );
+    }
+}
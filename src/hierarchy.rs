@@ -0,0 +1,361 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Regions and constellations and the systems they consist of, e.g. to group the
+//! systems of a route by region or to restrict a search to a constellation.
+//!
+//! Systems only carry the name of their region. Universes loaded from the databases,
+//! the SDE, ESI and remote sources, and from CSV and Parquet files with
+//! constellations carry the full hierarchy, see `Universe::hierarchy()` and
+//! `Universe::region_of()`. With the `sqlite` feature it
+//! can also be loaded on its own using `DatabaseBuilder::hierarchy()`.
+
+use std::collections::HashMap;
+
+use crate::overlay::Overlay;
+use crate::types;
+
+/// The region and constellation of a system.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Membership {
+    pub region_id: types::RegionId,
+    pub constellation_id: types::ConstellationId,
+}
+
+#[derive(Debug, Clone)]
+struct Constellation {
+    region_id: types::RegionId,
+    name: String,
+}
+
+/// Names of regions and constellations and the membership of systems.
+///
+/// # Example
+/// ```
+/// use neweden::hierarchy::Hierarchy;
+/// use neweden::{RegionId, SystemId};
+///
+/// let mut hierarchy = Hierarchy::default();
+/// hierarchy.insert_region(10000002.into(), "The Forge");
+/// hierarchy.insert_constellation(20000020.into(), 10000002.into(), "Kimotoro");
+/// hierarchy.insert_system(30000142.into(), 20000020.into());
+///
+/// assert_eq!(Some(RegionId(10000002)), hierarchy.region_of(&30000142.into()));
+/// assert_eq!(Some("Kimotoro"), hierarchy.constellation_name(&20000020.into()));
+/// assert_eq!(vec![SystemId(30000142)], hierarchy.systems_in_region(&10000002.into()));
+/// ```
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Serialized", into = "Serialized")
+)]
+pub struct Hierarchy {
+    regions: HashMap<types::RegionId, types::Region>,
+    constellations: HashMap<types::ConstellationId, Constellation>,
+    systems: Overlay<types::SystemId, Membership>,
+}
+
+impl Hierarchy {
+    pub fn insert_region(&mut self, id: types::RegionId, name: &str) {
+        self.regions.insert(
+            id,
+            types::Region {
+                id,
+                name: name.to_string(),
+            },
+        );
+    }
+
+    pub fn insert_constellation(
+        &mut self,
+        id: types::ConstellationId,
+        region_id: types::RegionId,
+        name: &str,
+    ) {
+        self.constellations.insert(
+            id,
+            Constellation {
+                region_id,
+                name: name.to_string(),
+            },
+        );
+    }
+
+    /// Adds a system to a constellation and its region. Returns false if the
+    /// constellation is unknown.
+    pub fn insert_system(
+        &mut self,
+        id: types::SystemId,
+        constellation_id: types::ConstellationId,
+    ) -> bool {
+        let Some(constellation) = self.constellations.get(&constellation_id) else {
+            return false;
+        };
+        self.systems.insert(
+            id,
+            Membership {
+                region_id: constellation.region_id,
+                constellation_id,
+            },
+        );
+        true
+    }
+
+    /// The systems matching the predicate with the regions and constellations they
+    /// are in, see `Universe::filter()`.
+    pub(crate) fn subset(&self, keep: impl Fn(&types::SystemId) -> bool) -> Hierarchy {
+        let mut subset = Hierarchy::default();
+        for (id, membership) in self.systems.iter().filter(|(id, _)| keep(id)) {
+            if let Some(region) = self.regions.get(&membership.region_id) {
+                subset.regions.insert(membership.region_id, region.clone());
+            }
+            if let Some(constellation) = self.constellations.get(&membership.constellation_id) {
+                subset
+                    .constellations
+                    .insert(membership.constellation_id, constellation.clone());
+            }
+            subset.systems.insert(*id, *membership);
+        }
+        subset
+    }
+
+    /// Adds the regions, constellations and systems of another hierarchy, replacing
+    /// entries with the same id.
+    pub fn extend(&mut self, other: &Hierarchy) {
+        self.regions.extend(other.regions.clone());
+        self.constellations.extend(other.constellations.clone());
+        for (id, membership) in other.systems.iter() {
+            self.systems.insert(*id, *membership);
+        }
+    }
+
+    pub fn region(&self, id: &types::RegionId) -> Option<types::Region> {
+        self.region_ref(id).cloned()
+    }
+
+    /// Like `region()`, for results borrowing from the universe, e.g. search hits.
+    pub(crate) fn region_ref(&self, id: &types::RegionId) -> Option<&types::Region> {
+        self.regions.get(id)
+    }
+
+    pub fn constellation(&self, id: &types::ConstellationId) -> Option<types::Constellation> {
+        let constellation = self.constellations.get(id)?;
+        Some(types::Constellation {
+            id: *id,
+            region_id: constellation.region_id,
+            name: constellation.name.clone(),
+        })
+    }
+
+    pub fn region_name(&self, id: &types::RegionId) -> Option<&str> {
+        self.regions.get(id).map(|r| r.name.as_str())
+    }
+
+    pub fn constellation_name(&self, id: &types::ConstellationId) -> Option<&str> {
+        self.constellations.get(id).map(|c| c.name.as_str())
+    }
+
+    /// The region a constellation belongs to.
+    pub fn region_of_constellation(&self, id: &types::ConstellationId) -> Option<types::RegionId> {
+        self.constellations.get(id).map(|c| c.region_id)
+    }
+
+    pub fn membership(&self, id: &types::SystemId) -> Option<&Membership> {
+        self.systems.get(id)
+    }
+
+    pub fn region_of(&self, id: &types::SystemId) -> Option<types::RegionId> {
+        self.membership(id).map(|m| m.region_id)
+    }
+
+    pub fn constellation_of(&self, id: &types::SystemId) -> Option<types::ConstellationId> {
+        self.membership(id).map(|m| m.constellation_id)
+    }
+
+    /// All regions as `(id, name)`, sorted by id.
+    pub fn regions(&self) -> Vec<(types::RegionId, &str)> {
+        let mut regions = self
+            .regions
+            .iter()
+            .map(|(id, region)| (*id, region.name.as_str()))
+            .collect::<Vec<_>>();
+        regions.sort_unstable();
+        regions
+    }
+
+    /// All constellations, sorted by id.
+    pub fn constellations(&self) -> Vec<types::Constellation> {
+        let mut constellations = self
+            .constellations
+            .keys()
+            .filter_map(|id| self.constellation(id))
+            .collect::<Vec<_>>();
+        constellations.sort_unstable_by_key(|c| c.id);
+        constellations
+    }
+
+    /// The constellation of every system, sorted by system id.
+    pub fn memberships(&self) -> Vec<(types::SystemId, types::ConstellationId)> {
+        let mut systems = self
+            .systems
+            .iter()
+            .map(|(id, m)| (*id, m.constellation_id))
+            .collect::<Vec<_>>();
+        systems.sort_unstable();
+        systems
+    }
+
+    /// The constellations of a region, sorted by id.
+    pub fn constellations_in_region(
+        &self,
+        region_id: &types::RegionId,
+    ) -> Vec<types::ConstellationId> {
+        let mut constellations = self
+            .constellations
+            .iter()
+            .filter(|(_, c)| c.region_id == *region_id)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        constellations.sort_unstable();
+        constellations
+    }
+
+    /// The systems of a region, sorted by id.
+    pub fn systems_in_region(&self, region_id: &types::RegionId) -> Vec<types::SystemId> {
+        self.systems_where(|m| m.region_id == *region_id)
+    }
+
+    /// The systems of a constellation, sorted by id.
+    pub fn systems_in_constellation(
+        &self,
+        constellation_id: &types::ConstellationId,
+    ) -> Vec<types::SystemId> {
+        self.systems_where(|m| m.constellation_id == *constellation_id)
+    }
+
+    fn systems_where(&self, predicate: impl Fn(&Membership) -> bool) -> Vec<types::SystemId> {
+        let mut systems = self
+            .systems
+            .iter()
+            .filter(|(_, m)| predicate(m))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        systems.sort_unstable();
+        systems
+    }
+
+    /// Number of systems with a known region and constellation.
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+}
+
+/// The form a `Hierarchy` is serialized in.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Serialized {
+    regions: Vec<types::Region>,
+    constellations: Vec<types::Constellation>,
+    systems: Vec<(types::SystemId, types::ConstellationId)>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Hierarchy> for Serialized {
+    fn from(hierarchy: Hierarchy) -> Self {
+        Self {
+            regions: hierarchy
+                .regions()
+                .into_iter()
+                .map(|(id, name)| types::Region {
+                    id,
+                    name: name.to_string(),
+                })
+                .collect(),
+            constellations: hierarchy.constellations(),
+            systems: hierarchy.memberships(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Serialized> for Hierarchy {
+    fn from(serialized: Serialized) -> Self {
+        let mut hierarchy = Hierarchy::default();
+        for region in serialized.regions {
+            hierarchy.insert_region(region.id, &region.name);
+        }
+        for constellation in serialized.constellations {
+            hierarchy.insert_constellation(
+                constellation.id,
+                constellation.region_id,
+                &constellation.name,
+            );
+        }
+        for (id, constellation_id) in serialized.systems {
+            hierarchy.insert_system(id, constellation_id);
+        }
+        hierarchy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ConstellationId, RegionId};
+
+    #[test]
+    fn test_hierarchy() {
+        let mut hierarchy = Hierarchy::default();
+        hierarchy.insert_region(10000002.into(), "The Forge");
+        hierarchy.insert_region(10000033.into(), "The Citadel");
+        hierarchy.insert_constellation(20000020.into(), 10000002.into(), "Kimotoro");
+        hierarchy.insert_constellation(20000021.into(), 10000002.into(), "Otsabaira");
+        hierarchy.insert_constellation(20000390.into(), 10000033.into(), "Okkamon");
+        assert!(hierarchy.insert_system(30000144.into(), 20000020.into()));
+        assert!(hierarchy.insert_system(30000142.into(), 20000020.into()));
+        assert!(hierarchy.insert_system(30000150.into(), 20000021.into()));
+        assert!(hierarchy.insert_system(30002813.into(), 20000390.into()));
+        assert!(!hierarchy.insert_system(30000001.into(), 20000001.into()));
+
+        assert_eq!(4, hierarchy.len());
+        assert_eq!(
+            Some(&Membership {
+                region_id: RegionId(10000002),
+                constellation_id: ConstellationId(20000020)
+            }),
+            hierarchy.membership(&30000142.into())
+        );
+        assert_eq!(None, hierarchy.region_of(&30000001.into()));
+        assert_eq!(Some("The Citadel"), hierarchy.region_name(&10000033.into()));
+        assert_eq!(
+            vec![
+                (RegionId(10000002), "The Forge"),
+                (RegionId(10000033), "The Citadel")
+            ],
+            hierarchy.regions()
+        );
+        assert_eq!(
+            vec![ConstellationId(20000020), ConstellationId(20000021)],
+            hierarchy.constellations_in_region(&10000002.into())
+        );
+        assert_eq!(
+            vec![
+                types::SystemId(30000142),
+                types::SystemId(30000144),
+                types::SystemId(30000150)
+            ],
+            hierarchy.systems_in_region(&10000002.into())
+        );
+        assert_eq!(
+            vec![types::SystemId(30000142), types::SystemId(30000144)],
+            hierarchy.systems_in_constellation(&20000020.into())
+        );
+    }
+}
@@ -0,0 +1,194 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Multiple universe snapshots, e.g. loaded from old SDE dumps, that can be queried
+//! as of a given date to compare routes before and after map changes.
+
+use std::collections::BTreeMap;
+
+use chrono::{Days, NaiveDate};
+
+use crate::navigation::PathBuilder;
+use crate::types::{self, Navigatable};
+
+/// Universe snapshots keyed by the date they became valid.
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::history::UniverseHistory;
+///
+/// let mut history = UniverseHistory::default();
+/// history.insert(NaiveDate::from_ymd_opt(2019, 5, 1).unwrap(), UniverseBuilder::default().build());
+/// history.insert(NaiveDate::from_ymd_opt(2020, 8, 1).unwrap(), UniverseBuilder::default().build());
+///
+/// let (valid_from, _universe) = history.as_of(NaiveDate::from_ymd_opt(2020, 1, 1).unwrap()).unwrap();
+/// assert_eq!(NaiveDate::from_ymd_opt(2019, 5, 1).unwrap(), valid_from);
+/// ```
+#[derive(Default)]
+pub struct UniverseHistory {
+    snapshots: BTreeMap<NaiveDate, types::Universe>,
+}
+
+impl UniverseHistory {
+    /// Adds a snapshot valid from `date` until the next snapshot. Replaces an
+    /// existing snapshot for the same date.
+    pub fn insert(&mut self, date: NaiveDate, universe: types::Universe) {
+        self.snapshots.insert(date, universe);
+    }
+
+    /// Returns the snapshot that was valid at the given date, together with the date
+    /// it became valid. Returns `None` if the date predates all snapshots.
+    pub fn as_of(&self, date: NaiveDate) -> Option<(NaiveDate, &types::Universe)> {
+        self.snapshots
+            .range(..=date)
+            .next_back()
+            .map(|(date, universe)| (*date, universe))
+    }
+
+    /// Returns the most recent snapshot.
+    pub fn latest(&self) -> Option<(NaiveDate, &types::Universe)> {
+        self.snapshots
+            .iter()
+            .next_back()
+            .map(|(date, universe)| (*date, universe))
+    }
+
+    /// Iterates over all snapshots, oldest first.
+    pub fn snapshots(&self) -> impl Iterator<Item = (NaiveDate, &types::Universe)> {
+        self.snapshots
+            .iter()
+            .map(|(date, universe)| (*date, universe))
+    }
+
+    /// Compares the shortest route between two systems on the day before `date` with
+    /// the route on `date`, e.g. to see how a map change released on that day
+    /// affected a trade route.
+    pub fn compare_route(
+        &self,
+        from: types::SystemId,
+        to: types::SystemId,
+        date: NaiveDate,
+    ) -> RouteComparison {
+        let jumps = |date: Option<NaiveDate>| {
+            let (_, universe) = self.as_of(date?)?;
+            PathBuilder::new(universe)
+                .waypoint(universe.get_system(&from)?)
+                .waypoint(universe.get_system(&to)?)
+                .build()
+                .map(|path| path.jumps())
+        };
+        RouteComparison {
+            before: jumps(date.checked_sub_days(Days::new(1))),
+            after: jumps(Some(date)),
+        }
+    }
+}
+
+/// Jump counts of the shortest route between two systems before and after a date,
+/// see `UniverseHistory::compare_route()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteComparison {
+    /// Jumps on the day before, `None` without a snapshot or route at that time.
+    pub before: Option<usize>,
+    /// Jumps on the date, `None` without a snapshot or route at that time.
+    pub after: Option<usize>,
+}
+
+impl RouteComparison {
+    /// Jumps the route got longer by, negative if it got shorter. `None` if either
+    /// route does not exist.
+    pub fn added_jumps(&self) -> Option<isize> {
+        Some(self.after? as isize - self.before? as isize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::types::Galaxy;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 8, day).unwrap()
+    }
+
+    fn gate(from: u32, to: u32) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+        }
+    }
+
+    /// 1 - 2 - 3, with a shortcut from 1 to 3 if `shortcut` is set.
+    fn universe(shortcut: bool) -> types::Universe {
+        let builder = UniverseBuilder::default()
+            .system(system(1))
+            .system(system(2))
+            .system(system(3))
+            .connection(gate(1, 2))
+            .connection(gate(2, 3));
+        match shortcut {
+            true => builder.connection(gate(1, 3)).build(),
+            false => builder.build(),
+        }
+    }
+
+    #[test]
+    fn test_as_of() {
+        let mut history = UniverseHistory::default();
+        assert!(history.latest().is_none());
+        history.insert(date(1), universe(false));
+        history.insert(date(10), universe(true));
+
+        assert!(history.as_of(date(1).pred_opt().unwrap()).is_none());
+        assert_eq!(Some(date(1)), history.as_of(date(1)).map(|(date, _)| date));
+        assert_eq!(Some(date(1)), history.as_of(date(9)).map(|(date, _)| date));
+        assert_eq!(
+            Some(date(10)),
+            history.as_of(date(10)).map(|(date, _)| date)
+        );
+        assert_eq!(
+            Some(date(10)),
+            history.as_of(date(31)).map(|(date, _)| date)
+        );
+        assert_eq!(Some(date(10)), history.latest().map(|(date, _)| date));
+
+        // replacing a snapshot keeps the boundaries
+        history.insert(date(10), universe(false));
+        assert_eq!(2, history.snapshots().count());
+        assert_eq!(2, history.as_of(date(10)).unwrap().1.connections().len());
+    }
+
+    #[test]
+    fn test_compare_route() {
+        let mut history = UniverseHistory::default();
+        history.insert(date(1), universe(false));
+        history.insert(date(10), universe(true));
+
+        let comparison = history.compare_route(1.into(), 3.into(), date(10));
+        assert_eq!(Some(2), comparison.before);
+        assert_eq!(Some(1), comparison.after);
+        assert_eq!(Some(-1), comparison.added_jumps());
+        // both days use the same snapshot
+        assert_eq!(
+            Some(0),
+            history
+                .compare_route(1.into(), 3.into(), date(11))
+                .added_jumps()
+        );
+        // there is no snapshot before the first one
+        let first = history.compare_route(1.into(), 3.into(), date(1));
+        assert_eq!((None, Some(2)), (first.before, first.after));
+        assert_eq!(None, first.added_jumps());
+        assert_eq!(
+            None,
+            history.compare_route(1.into(), 4.into(), date(10)).after
+        );
+    }
+}
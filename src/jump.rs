@@ -0,0 +1,362 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Jump drive routes for capitals, jumping from cyno to cyno instead of using gates.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Utc};
+use pathfinding::prelude::dijkstra;
+
+use crate::fuel;
+use crate::rules;
+use crate::types;
+
+/// Cost of a single jump in millilightyears. Fewer jumps are always preferred over
+/// a shorter total distance.
+const JUMP_COST: u64 = 100_000;
+
+/// A single jump drive jump.
+#[derive(Debug)]
+pub struct Jump<'a> {
+    pub from: &'a types::System,
+    pub to: &'a types::System,
+    pub lightyears: types::Lightyears,
+}
+
+/// A sequence of jumps of a jump drive capable ship.
+#[derive(Debug)]
+pub struct JumpPlan<'a> {
+    pub ship: types::JumpdriveShip,
+    /// The isotope burned by the hull, if the race of the hull is known.
+    pub isotope: Option<fuel::Isotope>,
+    pub jumps: Vec<Jump<'a>>,
+    departure: Option<Departure>,
+}
+
+/// When the first jump is made and how long each jump takes, see
+/// `JumpPlanner::depart_at()`.
+#[derive(Debug, Copy, Clone)]
+struct Departure {
+    time: DateTime<Utc>,
+    per_jump: Duration,
+}
+
+impl JumpPlan<'_> {
+    pub fn len(&self) -> usize {
+        self.jumps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jumps.is_empty()
+    }
+
+    /// Total distance jumped.
+    pub fn lightyears(&self) -> types::Lightyears {
+        self.jumps
+            .iter()
+            .fold(types::Lightyears(0.0), |total, jump| {
+                total + jump.lightyears
+            })
+    }
+
+    /// Isotopes needed for all jumps of the plan.
+    pub fn fuel(&self) -> fuel::FuelCost {
+        self.jumps
+            .iter()
+            .map(|jump| fuel::jump_isotopes(&self.ship, jump.lightyears))
+            .map(fuel::FuelCost::isotopes)
+            .sum()
+    }
+
+    /// The price of the isotopes needed for the plan, given the price of one unit
+    /// of each isotope. Returns `None` if the isotope of the hull is unknown.
+    pub fn fuel_cost_isk(&self, price: impl Fn(fuel::Isotope) -> f64) -> Option<f64> {
+        let isotope = self.isotope?;
+        Some(self.fuel().isotopes as f64 * price(isotope))
+    }
+
+    /// Returns the number of jumps that can be made before the next downtime, using
+    /// the departure given to `JumpPlanner::depart_at()`. The ship should wait in
+    /// the system reached by these jumps. Returns `None` if the plan has no departure
+    /// or all jumps are made before downtime.
+    pub fn downtime_split(&self) -> Option<usize> {
+        let departure = self.departure?;
+        rules::steps_before_downtime(
+            departure.time,
+            std::iter::repeat_n(departure.per_jump, self.jumps.len()),
+        )
+    }
+}
+
+/// Plans jumps between two systems, only jumping into systems that allow cynos.
+/// Plans use the least number of jumps, ties are broken by the total distance.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::jump::JumpPlanner;
+/// use neweden::{JumpdriveShip, JumpdriveSkills, Lightyears, Meters, Navigatable, System};
+///
+/// let system = |id: u32, ly: f64| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (Meters::from(Lightyears(ly)).0, 0.0, 0.0).into(),
+///     security: (-0.5).into(),
+///     region_name: "Delve".to_string(),
+///     faction_id: None,
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(30000001, 0.0))
+///     .system(system(30000002, 5.0))
+///     .system(system(30000003, 10.0))
+///     .build();
+/// let carrier = JumpdriveShip::Carrier(JumpdriveSkills::new(5, 5));
+/// let plan = JumpPlanner::new(&universe, carrier)
+///     .from(universe.get_system(&30000001.into()).unwrap())
+///     .to(universe.get_system(&30000003.into()).unwrap())
+///     .build()
+///     .unwrap();
+/// assert_eq!(2, plan.len());
+/// assert_eq!(15000, plan.fuel().isotopes);
+/// ```
+///
+/// With the race of the hull the plan also knows the isotope to haul and its price:
+/// ```
+/// # use neweden::builder::UniverseBuilder;
+/// # use neweden::jump::JumpPlanner;
+/// # use neweden::{JumpdriveShip, JumpdriveSkills, Lightyears, Meters, Navigatable, System};
+/// use neweden::fuel::{Isotope, Race};
+///
+/// # let system = |id: u32, ly: f64| System {
+/// #     id: id.into(),
+/// #     name: id.to_string(),
+/// #     coordinate: (Meters::from(Lightyears(ly)).0, 0.0, 0.0).into(),
+/// #     security: (-0.5).into(),
+/// #     region_name: "Delve".to_string(),
+/// #     faction_id: None,
+/// # };
+/// # let universe = UniverseBuilder::default()
+/// #     .system(system(30000001, 0.0))
+/// #     .system(system(30000002, 5.0))
+/// #     .build();
+/// let carrier = JumpdriveShip::Carrier(JumpdriveSkills::new(5, 5));
+/// let plan = JumpPlanner::new(&universe, carrier)
+///     .race(Race::from_hull("Thanatos").unwrap())
+///     .from(universe.get_system(&30000001.into()).unwrap())
+///     .to(universe.get_system(&30000002.into()).unwrap())
+///     .build()
+///     .unwrap();
+/// assert_eq!(Some(Isotope::Oxygen), plan.isotope);
+/// let isk = plan.fuel_cost_isk(|isotope| match isotope {
+///     Isotope::Oxygen => 600.0,
+///     _ => 500.0,
+/// });
+/// assert_eq!(Some(7500.0 * 600.0), isk);
+/// ```
+pub struct JumpPlanner<'a> {
+    universe: &'a dyn types::Navigatable,
+    ship: types::JumpdriveShip,
+    isotope: Option<fuel::Isotope>,
+    from: Option<&'a types::System>,
+    to: Option<&'a types::System>,
+    avoid: HashSet<types::SystemId>,
+    departure: Option<Departure>,
+}
+
+impl<'a> JumpPlanner<'a> {
+    pub fn new(universe: &'a dyn types::Navigatable, ship: types::JumpdriveShip) -> Self {
+        Self {
+            universe,
+            ship,
+            isotope: None,
+            from: None,
+            to: None,
+            avoid: Default::default(),
+            departure: None,
+        }
+    }
+
+    pub fn from(mut self, system: &'a types::System) -> Self {
+        self.from = Some(system);
+        self
+    }
+
+    pub fn to(mut self, system: &'a types::System) -> Self {
+        self.to = Some(system);
+        self
+    }
+
+    /// The race of the hull, which decides the isotope it burns.
+    pub fn race(mut self, race: fuel::Race) -> Self {
+        self.isotope = Some(race.isotope());
+        self
+    }
+
+    /// Plan for a pilot with the given skills instead of the skills of the ship.
+    /// Fails for skills above level 5 or implants above 100%.
+    pub fn skills(mut self, skills: &types::PilotSkills) -> Result<Self, types::SkillError> {
+        self.ship = self.ship.with_skills(skills)?;
+        Ok(self)
+    }
+
+    /// Never jump into the system, e.g. because it is hostile staging.
+    pub fn avoid(mut self, system: &'a types::System) -> Self {
+        self.avoid.insert(system.id);
+        self
+    }
+
+    /// Makes the first jump at `time` and takes `per_jump` for each jump, including
+    /// waiting for jump fatigue and the next cyno. See `JumpPlan::downtime_split()`.
+    pub fn depart_at(mut self, time: DateTime<Utc>, per_jump: Duration) -> Self {
+        self.departure = Some(Departure { time, per_jump });
+        self
+    }
+
+    /// Returns `None` if the destination cannot be reached or the start or the
+    /// destination was not set.
+    pub fn build(self) -> Option<JumpPlan<'a>> {
+        let from = self.from?;
+        let to = self.to?;
+        let range: types::Meters = self.ship.clone().into();
+        let successors = |id: &types::SystemId| {
+            let origin = self.universe.get_system(id);
+            self.universe
+                .get_systems_by_range(id, range)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|system| system.id != *id && !self.avoid.contains(&system.id))
+                .filter(|system| rules::allows_cynos(system))
+                .filter_map(move |system| {
+                    let ly = types::Lightyears::from(origin?.distance(system));
+                    Some((system.id, JUMP_COST + (ly.0 * 1000.0).round() as u64))
+                })
+                .collect::<Vec<_>>()
+        };
+        let (ids, _) = dijkstra(&from.id, successors, |id| *id == to.id)?;
+
+        let jumps = ids
+            .windows(2)
+            .map(|pair| {
+                let from = self.universe.get_system(&pair[0]).unwrap();
+                let to = self.universe.get_system(&pair[1]).unwrap();
+                Jump {
+                    from,
+                    to,
+                    lightyears: from.distance(to).into(),
+                }
+            })
+            .collect();
+        Some(JumpPlan {
+            ship: self.ship,
+            isotope: self.isotope,
+            jumps,
+            departure: self.departure,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_pilot_skills() {
+        let ly = |ly: f64| types::Meters::from(types::Lightyears(ly)).0;
+        let universe = UniverseBuilder::default()
+            .system(system(1).with_security(-0.5))
+            .system(system(2).with_security(-0.5).at(ly(4.0), 0.0, 0.0))
+            .system(system(3).with_security(-0.5).at(ly(6.0), 0.0, 0.0))
+            .build();
+        let plan = |skills: types::PilotSkills| {
+            let ship = types::JumpdriveShip::Jumpfreighter(types::JumpdriveSkills::new(0, 0));
+            JumpPlanner::new(&universe, ship)
+                .skills(&skills)
+                .unwrap()
+                .from(universe.get_system(&1.into()).unwrap())
+                .to(universe.get_system(&3.into()).unwrap())
+                .build()
+                .unwrap()
+        };
+
+        let max = plan(types::PilotSkills::default());
+        assert_eq!(1, max.len());
+        // JFC 5 and Jump Freighters 5 each halve the fuel
+        assert_eq!(15000, max.fuel().isotopes);
+
+        let untrained = plan(
+            types::PilotSkills::default()
+                .jump_drive_calibration(0)
+                .jump_fuel_conservation(0)
+                .jump_freighters(0)
+                .fuel_implant(10),
+        );
+        assert_eq!(2, untrained.len());
+        // 9000 isotopes per lightyear for 4 and 2 lightyears
+        assert_eq!(54000, untrained.fuel().isotopes);
+
+        let ship = types::JumpdriveShip::Jumpfreighter(types::JumpdriveSkills::new(0, 0));
+        assert_eq!(
+            types::SkillError::Level {
+                skill: "Jump Fuel Conservation",
+                level: 6
+            },
+            JumpPlanner::new(&universe, ship.clone())
+                .skills(&types::PilotSkills::default().jump_fuel_conservation(6))
+                .err()
+                .unwrap()
+        );
+        assert!(
+            JumpPlanner::new(&universe, ship)
+                .skills(&types::PilotSkills::default().fuel_implant(101))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_depart_at() {
+        use chrono::TimeZone;
+
+        let ly = |ly: f64| types::Meters::from(types::Lightyears(ly)).0;
+        let universe = (1..=4)
+            .fold(UniverseBuilder::default(), |builder, i| {
+                builder.system(
+                    system(i)
+                        .with_security(-0.5)
+                        .at(ly(5.0 * f64::from(i)), 0.0, 0.0),
+                )
+            })
+            .build();
+        let carrier = types::JumpdriveShip::Carrier(types::JumpdriveSkills::new(5, 5));
+        let plan = |time| {
+            JumpPlanner::new(&universe, carrier.clone())
+                .from(universe.get_system(&1.into()).unwrap())
+                .to(universe.get_system(&4.into()).unwrap())
+                .depart_at(time, Duration::minutes(20))
+                .build()
+                .unwrap()
+        };
+
+        // downtime at 11:00, the second jump ends at 10:50
+        let departure = Utc.with_ymd_and_hms(2024, 1, 1, 10, 10, 0).unwrap();
+        assert_eq!(Some(2), plan(departure).downtime_split());
+        assert_eq!(None, plan(departure - Duration::hours(1)).downtime_split());
+        assert_eq!(None, plan(departure + Duration::hours(2)).downtime_split());
+        // no jumps during downtime
+        assert_eq!(
+            Some(0),
+            plan(departure + Duration::hours(1)).downtime_split()
+        );
+        let undated = JumpPlanner::new(&universe, carrier.clone())
+            .from(universe.get_system(&1.into()).unwrap())
+            .to(universe.get_system(&4.into()).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(None, undated.downtime_split());
+    }
+}
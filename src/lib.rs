@@ -12,23 +12,85 @@
 //! The library must be compiled with the appropriate features depending
 //! on the desired backend for universe data. The `postgres` feature
 //! allows for loading from Postgres via the Diesel ORM tool, while the
-//! `sqlite` feature allows loading from a local SQLite file.
+//! `sqlite` feature allows loading from a local SQLite file. The `mysql` feature
+//! loads from a MySQL or MariaDB import of the dump, see `source::mysql`, and the
+//! `csv` feature from Fuzzwork's CSV exports via `source::csv::CsvBuilder`. The `parquet` feature loads Parquet files of the
+//! SDE tables, see `source::parquet`.
 //!
-//! The `rpc` feature is for internal use at the moment as the dependent
-//! crate is not open sourced.
+//! The `serde` feature allows serializing saved routes and connection types.
+//! The `config` feature allows loading bridges, wormholes and avoided systems
+//! from a TOML extensions file via `ExtendedUniverse::from_config`.
+//! The `sqlite-memory` feature reads SQLite dumps from memory, e.g. embedded in the
+//! binary, via `DatabaseBuilder::from_static()` and `from_bytes()`.
+//! With the `sqlite` feature computed routes can be kept in a `cache::RouteCache`.
+//! The `fuzzwork` feature keeps the latest Fuzzwork dump up to date in a cache
+//! directory via `source::fuzzwork::FuzzworkBuilder`.
+//! The `sde` feature loads the universe from an unpacked official CCP static data
+//! export via `source::sde::SdeBuilder`.
+//! The `cache` feature writes a universe into a binary cache file, so short-lived
+//! tools only load the database on the first run, see `Universe::cached()`.
+//! The `mmap` feature writes universes into a file that several processes can
+//! memory map and share, see `source::mmap`.
+//! The `json` feature saves and loads universes as JSON snapshots, see `source::json`,
+//! and exports range rings and wormhole chains as JSON.
+//! The `esi` feature loads the universe from CCP's ESI via `source::esi::EsiBuilder`.
+//! The `stats` feature fetches live kills and jumps per system from ESI into
+//! `stats::SystemStats`.
+//!
+//! Other backends, e.g. network services, can be plugged in by implementing
+//! `source::remote::RemoteSource`. The `rpc` feature loads from a JSON HTTP service
+//! via `source::remote::HttpSource`.
 
 // Must be at the crate root
 #[cfg(feature = "postgres")]
 #[macro_use]
 extern crate diesel;
 
+pub mod agents;
+pub mod analysis;
+pub mod ansiblex;
 pub mod builder;
+#[cfg(feature = "sqlite")]
+pub mod cache;
+pub mod camps;
+pub mod celestials;
+pub mod chain;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod coordinates;
+pub mod diff;
+pub mod format;
+pub mod fuel;
+pub mod hierarchy;
+pub mod history;
+pub mod jump;
+pub mod locale;
+pub mod logistics;
+pub mod merge;
+pub mod names;
 pub mod navigation;
+pub mod overlay;
+pub mod placement;
+pub mod range;
+pub mod renames;
+pub mod resources;
 pub mod rules;
+pub mod sampling;
+pub mod scanner;
+#[cfg(feature = "search")]
+pub mod search;
 pub mod source;
+pub mod standings;
+pub mod stargates;
+pub mod stations;
+#[cfg(feature = "stats")]
+pub mod stats;
+pub mod structures;
+pub mod warfare;
+pub mod workspace;
 
-#[cfg(feature = "search")]
-mod search;
+#[cfg(test)]
+mod fixtures;
 mod types;
 
 pub use types::*;
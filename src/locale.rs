@@ -0,0 +1,131 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Localized system names, e.g. for German or Japanese community tools.
+//!
+//! The SDE ships translations in `trnTranslations`. With the `sqlite` feature they
+//! can be loaded using `DatabaseBuilder::localized_names()`. Languages are the SDE
+//! language ids such as `de`, `fr`, `ja`, `ru`, `zh` or `ko`. Systems keep their
+//! English name, translations are looked up with `System::localized_name()`.
+
+use std::collections::HashMap;
+
+use crate::overlay::Overlay;
+use crate::types;
+
+/// Translated names of systems by language.
+///
+/// # Example
+/// ```
+/// use neweden::locale::LocalizedNames;
+/// use neweden::System;
+///
+/// let jita = System {
+///     id: 30000142.into(),
+///     name: "Jita".to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: 0.9459.into(),
+///     region_name: "The Forge".to_string(),
+///     faction_id: None,
+/// };
+/// let mut names = LocalizedNames::default();
+/// names.insert(jita.id, "ja", "ジタ");
+/// assert_eq!("ジタ", jita.localized_name(&names, "ja"));
+/// assert_eq!("Jita", jita.localized_name(&names, "de"));
+/// ```
+#[derive(Debug, Default)]
+pub struct LocalizedNames {
+    names: Overlay<types::SystemId, HashMap<String, String>>,
+}
+
+impl LocalizedNames {
+    pub fn insert(&mut self, id: types::SystemId, language: &str, name: &str) {
+        match self.names.get_mut(&id) {
+            Some(names) => {
+                names.insert(language.to_string(), name.to_string());
+            }
+            None => {
+                self.names.insert(
+                    id,
+                    HashMap::from([(language.to_string(), name.to_string())]),
+                );
+            }
+        }
+    }
+
+    /// The name of the system in the language, `None` if there is no translation.
+    pub fn get(&self, id: &types::SystemId, language: &str) -> Option<&str> {
+        self.names.get(id)?.get(language).map(String::as_str)
+    }
+
+    /// All translations of the system's name as `(language, name)`.
+    pub fn names(&self, id: &types::SystemId) -> impl Iterator<Item = (&str, &str)> {
+        self.names
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|(language, name)| (language.as_str(), name.as_str()))
+    }
+
+    /// The languages with at least one translation, sorted.
+    pub fn languages(&self) -> Vec<&str> {
+        let mut languages = self
+            .names
+            .iter()
+            .flat_map(|(_, names)| names.keys().map(String::as_str))
+            .collect::<Vec<_>>();
+        languages.sort_unstable();
+        languages.dedup();
+        languages
+    }
+
+    /// Number of systems with translations.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localized_names() {
+        let mut names = LocalizedNames::default();
+        names.insert(30000142.into(), "ru", "Джита");
+        names.insert(30000142.into(), "de", "Jita");
+        names.insert(30002187.into(), "ja", "アマー");
+        names.insert(30002187.into(), "ja", "アマー ");
+
+        assert_eq!(2, names.len());
+        assert_eq!(Some("Джита"), names.get(&30000142.into(), "ru"));
+        assert_eq!(None, names.get(&30000142.into(), "ja"));
+        assert_eq!(Some("アマー "), names.get(&30002187.into(), "ja"));
+        assert_eq!(2, names.names(&30000142.into()).count());
+        assert_eq!(vec!["de", "ja", "ru"], names.languages());
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_search() {
+        use crate::fixtures::system;
+
+        let mut universe = crate::builder::UniverseBuilder::default()
+            .system(system(30000142).named("Jita"))
+            .build();
+        let mut names = LocalizedNames::default();
+        names.insert(30000142.into(), "ru", "Джита");
+        assert!(universe.search_one("Джита").is_none());
+        universe.index_localized_names(&names).unwrap();
+        assert_eq!("Jita", universe.search_one("Джита").unwrap().name);
+        assert_eq!("Jita", universe.search_one("Jita").unwrap().name);
+        universe.reindex(2).unwrap();
+        assert_eq!("Jita", universe.search_one("Джита").unwrap().name);
+    }
+}
@@ -0,0 +1,380 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Planning of hauling runs. Contracts are grouped into trips that fit into the
+//! cargo hold and the pickups and deliveries of each trip are ordered into a route.
+//!
+//! The planner is a greedy heuristic: each trip starts with the contract picked up
+//! closest to the current location and is filled with the contracts causing the
+//! smallest detour. Stops are visited nearest first.
+
+use std::collections::{HashMap, VecDeque};
+
+use thiserror::Error;
+
+use crate::navigation::{Path, PathBuilder};
+use crate::types;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum LogisticsError {
+    #[error("contract {0} needs {1} m3, but the cargo hold only fits {2} m3")]
+    TooLarge(usize, f64, f64),
+    #[error("unknown system {0:?}")]
+    UnknownSystem(types::SystemId),
+    #[error("no route from {0:?} to {1:?}")]
+    Unreachable(types::SystemId, types::SystemId),
+    #[error("contract {0} is picked up and delivered in the same system")]
+    NoTravel(usize),
+}
+
+/// Cargo that has to be picked up in one system and delivered to another. Volumes
+/// are in m3.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contract {
+    pub pickup: types::SystemId,
+    pub delivery: types::SystemId,
+    pub volume: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StopKind {
+    Pickup,
+    Delivery,
+}
+
+/// A stop of a trip. `contract` is the index of the contract in the order the
+/// contracts were added to the planner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stop<'a> {
+    pub system: &'a types::System,
+    pub kind: StopKind,
+    pub contract: usize,
+}
+
+/// A single run of the hauler, starting where the previous trip ended.
+pub struct Trip<'a> {
+    /// Indices of the contracts hauled on this trip.
+    pub contracts: Vec<usize>,
+    pub stops: Vec<Stop<'a>>,
+    /// The route visiting all stops in order.
+    pub path: Path<'a>,
+    /// Total volume of the contracts, the cargo hold is never fuller than this.
+    pub volume: f64,
+}
+
+impl Trip<'_> {
+    pub fn jumps(&self) -> usize {
+        self.path.jumps()
+    }
+}
+
+impl std::fmt::Display for Trip<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} contracts, {} stops, {:.0} m3, {}j",
+            self.contracts.len(),
+            self.stops.len(),
+            self.volume,
+            self.jumps()
+        )
+    }
+}
+
+/// Number of jumps from `from` to every reachable system.
+fn jump_distances(
+    universe: &dyn types::Navigatable,
+    from: types::SystemId,
+) -> HashMap<types::SystemId, usize> {
+    let mut distances = HashMap::from([(from, 0)]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(id) = queue.pop_front() {
+        let distance = distances[&id];
+        for conn in universe.get_connections(&id).unwrap_or_default() {
+            if universe.avoided_by_default(&conn.to) || distances.contains_key(&conn.to) {
+                continue;
+            }
+            distances.insert(conn.to, distance + 1);
+            queue.push_back(conn.to);
+        }
+    }
+    distances
+}
+
+/// Groups contracts into trips for a ship with the given cargo capacity.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::logistics::{Contract, LogisticsPlanner};
+/// use neweden::{Connection, ConnectionType, Navigatable, StargateType, System};
+///
+/// let system = |id: u32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: 0.9.into(),
+///     region_name: "The Forge".to_string(),
+///     faction_id: None,
+/// };
+/// let gate = |from: u32, to: u32| Connection {
+///     from: from.into(),
+///     to: to.into(),
+///     r#type: ConnectionType::Stargate(StargateType::Local),
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(30000142))
+///     .system(system(30000144))
+///     .connection(gate(30000142, 30000144))
+///     .connection(gate(30000144, 30000142))
+///     .build();
+/// let contract = |volume| Contract {
+///     pickup: 30000142.into(),
+///     delivery: 30000144.into(),
+///     volume,
+/// };
+/// let trips = LogisticsPlanner::new(&universe, 60000.0)
+///     .contract(contract(40000.0))
+///     .contract(contract(30000.0))
+///     .build()
+///     .unwrap();
+/// assert_eq!(2, trips.len());
+/// assert_eq!(3, trips.iter().map(|trip| trip.jumps()).sum::<usize>());
+/// ```
+pub struct LogisticsPlanner<'a> {
+    universe: &'a dyn types::Navigatable,
+    capacity: f64,
+    start: Option<&'a types::System>,
+    contracts: Vec<Contract>,
+}
+
+impl<'a> LogisticsPlanner<'a> {
+    pub fn new(universe: &'a dyn types::Navigatable, capacity: f64) -> Self {
+        Self {
+            universe,
+            capacity,
+            start: None,
+            contracts: Default::default(),
+        }
+    }
+
+    /// The system the hauler starts in. Defaults to the pickup of the first contract.
+    pub fn start(mut self, system: &'a types::System) -> Self {
+        self.start = Some(system);
+        self
+    }
+
+    pub fn contract(mut self, contract: Contract) -> Self {
+        self.contracts.push(contract);
+        self
+    }
+
+    pub fn contracts(mut self, contracts: impl IntoIterator<Item = Contract>) -> Self {
+        self.contracts.extend(contracts);
+        self
+    }
+
+    fn system(&self, id: &types::SystemId) -> Result<&'a types::System, LogisticsError> {
+        self.universe
+            .get_system(id)
+            .ok_or(LogisticsError::UnknownSystem(*id))
+    }
+
+    pub fn build(self) -> Result<Vec<Trip<'a>>, LogisticsError> {
+        for (i, contract) in self.contracts.iter().enumerate() {
+            if contract.volume > self.capacity {
+                return Err(LogisticsError::TooLarge(i, contract.volume, self.capacity));
+            }
+            // would leave a trip without a route if it starts there
+            if contract.pickup == contract.delivery {
+                return Err(LogisticsError::NoTravel(i));
+            }
+            self.system(&contract.pickup)?;
+            self.system(&contract.delivery)?;
+        }
+        let Some(first) = self.contracts.first() else {
+            return Ok(Vec::new());
+        };
+        let mut location = match self.start {
+            Some(start) => start.id,
+            None => first.pickup,
+        };
+
+        let mut distances = HashMap::new();
+        let mut jumps = |from: types::SystemId, to: types::SystemId| {
+            distances
+                .entry(from)
+                .or_insert_with(|| jump_distances(self.universe, from))
+                .get(&to)
+                .copied()
+                .ok_or(LogisticsError::Unreachable(from, to))
+        };
+
+        let mut unassigned = (0..self.contracts.len()).collect::<Vec<_>>();
+        let mut trips = Vec::new();
+        while !unassigned.is_empty() {
+            // start with the closest pickup, preferring large contracts
+            let mut seed = unassigned[0];
+            let mut closest = jumps(location, self.contracts[seed].pickup)?;
+            for &i in &unassigned[1..] {
+                let distance = jumps(location, self.contracts[i].pickup)?;
+                if distance < closest
+                    || distance == closest && self.contracts[i].volume > self.contracts[seed].volume
+                {
+                    seed = i;
+                    closest = distance;
+                }
+            }
+            unassigned.retain(|i| *i != seed);
+
+            let mut detours = Vec::new();
+            for &i in &unassigned {
+                let seed = &self.contracts[seed];
+                let contract = &self.contracts[i];
+                let detour =
+                    jumps(seed.pickup, contract.pickup)? + jumps(seed.delivery, contract.delivery)?;
+                detours.push((detour, i));
+            }
+            detours.sort();
+
+            let mut contracts = vec![seed];
+            let mut volume = self.contracts[seed].volume;
+            for (_, i) in detours {
+                if volume + self.contracts[i].volume <= self.capacity {
+                    volume += self.contracts[i].volume;
+                    contracts.push(i);
+                }
+            }
+            unassigned.retain(|i| !contracts.contains(i));
+
+            let mut pending = contracts
+                .iter()
+                .map(|&i| (self.contracts[i].pickup, StopKind::Pickup, i))
+                .collect::<Vec<_>>();
+            let mut stops = Vec::new();
+            let mut waypoints = vec![self.system(&location)?];
+            while !pending.is_empty() {
+                let mut next = 0;
+                let mut best = usize::MAX;
+                for (n, (system, _, _)) in pending.iter().enumerate() {
+                    let distance = jumps(location, *system)?;
+                    if distance < best {
+                        best = distance;
+                        next = n;
+                    }
+                }
+                let (system, kind, i) = pending.remove(next);
+                if kind == StopKind::Pickup {
+                    pending.push((self.contracts[i].delivery, StopKind::Delivery, i));
+                }
+                let system = self.system(&system)?;
+                stops.push(Stop {
+                    system,
+                    kind,
+                    contract: i,
+                });
+                waypoints.push(system);
+                location = system.id;
+            }
+
+            // consecutive stops in the same system are a single waypoint, there are
+            // always at least two as no contract is delivered where it is picked up
+            waypoints.dedup();
+            let from = waypoints[0].id;
+            let path = PathBuilder::new(self.universe)
+                .waypoints(waypoints)
+                .build()
+                .ok_or(LogisticsError::Unreachable(from, location))?;
+            trips.push(Trip {
+                contracts,
+                stops,
+                path,
+                volume,
+            });
+        }
+        Ok(trips)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::types::Navigatable;
+
+    fn gate(from: u32, to: u32) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+        }
+    }
+
+    fn contract(pickup: u32, delivery: u32, volume: f64) -> Contract {
+        Contract {
+            pickup: pickup.into(),
+            delivery: delivery.into(),
+            volume,
+        }
+    }
+
+    #[test]
+    fn test_trips() {
+        // 1 - 2 - 3 - 4 - 5
+        let mut builder = UniverseBuilder::default().system(system(1));
+        for id in 2..=5 {
+            builder = builder
+                .system(system(id))
+                .connection(gate(id - 1, id))
+                .connection(gate(id, id - 1));
+        }
+        let universe = builder.system(system(6)).build();
+
+        let trips = LogisticsPlanner::new(&universe, 100.0)
+            .contract(contract(1, 5, 60.0))
+            .contract(contract(2, 4, 30.0))
+            .contract(contract(1, 3, 50.0))
+            .build()
+            .unwrap();
+        assert_eq!(2, trips.len());
+        assert_eq!(vec![0, 1], trips[0].contracts);
+        assert_eq!(
+            vec![1, 2, 4, 5],
+            trips[0]
+                .stops
+                .iter()
+                .map(|stop| stop.system.id.0)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("2 contracts, 4 stops, 90 m3, 4j", trips[0].to_string());
+        assert_eq!(vec![2], trips[1].contracts);
+        assert_eq!(6, trips[1].jumps());
+
+        assert_eq!(
+            Some(LogisticsError::TooLarge(0, 200.0, 100.0)),
+            LogisticsPlanner::new(&universe, 100.0)
+                .contract(contract(1, 5, 200.0))
+                .build()
+                .err()
+        );
+        assert_eq!(
+            Some(LogisticsError::Unreachable(1.into(), 6.into())),
+            LogisticsPlanner::new(&universe, 100.0)
+                .contract(contract(1, 6, 10.0))
+                .build()
+                .err()
+        );
+        assert_eq!(
+            Some(LogisticsError::NoTravel(1)),
+            LogisticsPlanner::new(&universe, 100.0)
+                .start(universe.get_system(&3.into()).unwrap())
+                .contract(contract(1, 5, 10.0))
+                .contract(contract(3, 3, 10.0))
+                .build()
+                .err()
+        );
+    }
+}
@@ -0,0 +1,361 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Merges universes loaded from different sources into one, e.g. k-space from the
+//! SDE and wormhole space from a mapping tool, see `Universe::merge()`.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::types;
+
+/// What to do when both universes contain a system with the same id, or
+/// connections between the same systems of different types. The policy applies the
+/// same way to regions, constellations, stations, stargates, celestials and
+/// wormhole classes with the same id. Identical connections and entries are merged
+/// silently.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Conflict {
+    /// Fail with a `MergeError`.
+    #[default]
+    Fail,
+    /// Keep the system or connection of the universe `merge()` is called on.
+    KeepOurs,
+    /// Take the system or connection of the other universe.
+    TakeTheirs,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum MergeError {
+    #[error("both universes contain system {0:?}")]
+    DuplicateSystem(types::SystemId),
+    #[error("both universes connect {0:?} to {1:?} with different connection types")]
+    DuplicateConnection(types::SystemId, types::SystemId),
+    #[error("both universes contain {0} {1} with different data")]
+    DuplicateEntry(&'static str, u32),
+}
+
+/// Merges the entries of a side table, such as the stations, by their id.
+fn merge_entries<T: PartialEq>(
+    ours: impl IntoIterator<Item = T>,
+    theirs: impl IntoIterator<Item = T>,
+    id: impl Fn(&T) -> u32,
+    conflict: Conflict,
+    table: &'static str,
+) -> Result<Vec<T>, MergeError> {
+    let mut entries = ours.into_iter().collect::<Vec<_>>();
+    let index = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| (id(entry), i))
+        .collect::<HashMap<_, _>>();
+    for entry in theirs {
+        let Some(&i) = index.get(&id(&entry)) else {
+            entries.push(entry);
+            continue;
+        };
+        if entries[i] == entry {
+            continue;
+        }
+        match conflict {
+            Conflict::Fail => return Err(MergeError::DuplicateEntry(table, id(&entry))),
+            Conflict::KeepOurs => {}
+            Conflict::TakeTheirs => entries[i] = entry,
+        }
+    }
+    Ok(entries)
+}
+
+fn merge_hierarchies(
+    ours: &crate::hierarchy::Hierarchy,
+    theirs: &crate::hierarchy::Hierarchy,
+    conflict: Conflict,
+) -> Result<crate::hierarchy::Hierarchy, MergeError> {
+    let mut hierarchy = crate::hierarchy::Hierarchy::default();
+    for (id, name) in merge_entries(
+        ours.regions(),
+        theirs.regions(),
+        |(id, _)| id.0,
+        conflict,
+        "region",
+    )? {
+        hierarchy.insert_region(id, name);
+    }
+    for constellation in merge_entries(
+        ours.constellations(),
+        theirs.constellations(),
+        |c| c.id.0,
+        conflict,
+        "constellation",
+    )? {
+        hierarchy.insert_constellation(
+            constellation.id,
+            constellation.region_id,
+            &constellation.name,
+        );
+    }
+    for (id, constellation) in merge_entries(
+        ours.memberships(),
+        theirs.memberships(),
+        |(id, _)| id.0,
+        conflict,
+        "system membership",
+    )? {
+        hierarchy.insert_system(id, constellation);
+    }
+    Ok(hierarchy)
+}
+
+pub(crate) fn merge(
+    ours: &types::Universe,
+    theirs: &types::Universe,
+    conflict: Conflict,
+) -> Result<types::Universe, MergeError> {
+    let mut systems = ours
+        .systems
+        .systems()
+        .into_iter()
+        .map(|system| (system.id, system))
+        .collect::<HashMap<_, _>>();
+    for system in theirs.systems.systems() {
+        if systems.contains_key(&system.id) {
+            match conflict {
+                Conflict::Fail => return Err(MergeError::DuplicateSystem(system.id)),
+                Conflict::KeepOurs => continue,
+                Conflict::TakeTheirs => {}
+            }
+        }
+        systems.insert(system.id, system);
+    }
+
+    let mut connections: HashMap<_, Vec<&types::Connection>> = HashMap::new();
+    for connection in ours.connections.0.values().flatten() {
+        connections
+            .entry((connection.from, connection.to))
+            .or_default()
+            .push(connection);
+    }
+    let mut replaced = Vec::new();
+    for connection in theirs.connections.0.values().flatten() {
+        let key = (connection.from, connection.to);
+        let existing = connections.entry(key).or_default();
+        if existing.iter().any(|c| c.r#type == connection.r#type) {
+            continue;
+        }
+        if existing.is_empty() || replaced.contains(&key) {
+            existing.push(connection);
+            continue;
+        }
+        match conflict {
+            Conflict::Fail => return Err(MergeError::DuplicateConnection(key.0, key.1)),
+            Conflict::KeepOurs => {}
+            Conflict::TakeTheirs => {
+                // replace ours, but keep all connections of theirs between the systems
+                *existing = vec![connection];
+                replaced.push(key);
+            }
+        }
+    }
+
+    let hierarchy = merge_hierarchies(&ours.hierarchy, &theirs.hierarchy, conflict)?;
+    let stations = merge_entries(
+        ours.stations.iter().cloned(),
+        theirs.stations.iter().cloned(),
+        |station| station.id,
+        conflict,
+        "station",
+    )?;
+    let stargates = merge_entries(
+        ours.stargates.iter().cloned(),
+        theirs.stargates.iter().cloned(),
+        |stargate| stargate.id,
+        conflict,
+        "stargate",
+    )?;
+    let celestials = merge_entries(
+        ours.celestials.iter().cloned(),
+        theirs.celestials.iter().cloned(),
+        |celestial| celestial.id,
+        conflict,
+        "celestial",
+    )?;
+    let mut wormhole_classes = crate::overlay::Overlay::default();
+    for (id, class) in merge_entries(
+        ours.wormhole_classes
+            .iter()
+            .map(|(id, class)| (*id, *class)),
+        theirs
+            .wormhole_classes
+            .iter()
+            .map(|(id, class)| (*id, *class)),
+        |(id, _)| id.0,
+        conflict,
+        "wormhole class",
+    )? {
+        wormhole_classes.insert(id, class);
+    }
+    Ok(types::Universe::new(
+        systems.into_values().cloned().collect(),
+        connections.into_values().flatten().cloned().collect(),
+    )
+    .with_hierarchy(hierarchy)
+    .with_stations(crate::stations::Stations::new(stations))
+    .with_stargates(crate::stargates::Stargates::new(stargates))
+    .with_celestials(crate::celestials::Celestials::new(celestials))
+    .with_wormhole_classes(wormhole_classes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::types::Navigatable;
+
+    fn connection(from: u32, to: u32, r#type: types::ConnectionType) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type,
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let gate = types::ConnectionType::Stargate(types::StargateType::Local);
+        let ansiblex = types::ConnectionType::Ansiblex;
+        let kspace = UniverseBuilder::default()
+            .system(system(30000001).named("Tanoo"))
+            .system(system(30000002).named("Lashesih"))
+            .connection(connection(30000001, 30000002, gate.clone()))
+            .build();
+        let wspace = UniverseBuilder::default()
+            .system(system(31000001).named("J100001"))
+            .system(system(31000002).named("J100002"))
+            .connection(connection(31000001, 31000002, gate.clone()))
+            .build();
+
+        let merged = kspace.merge(&wspace, Conflict::Fail).unwrap();
+        assert_eq!(4, merged.all_systems().len());
+        assert!(
+            merged
+                .connection_between(&31000001.into(), &31000002.into())
+                .is_some()
+        );
+        assert!(kspace.merge(&kspace, Conflict::Fail).is_err());
+
+        let theirs = UniverseBuilder::default()
+            .system(system(30000002).named("Renamed"))
+            .connection(connection(30000001, 30000002, gate.clone()))
+            .connection(connection(30000002, 30000001, ansiblex.clone()))
+            .build();
+        let merged = kspace.merge(&theirs, Conflict::KeepOurs).unwrap();
+        assert_eq!(
+            "Lashesih",
+            merged.get_system(&30000002.into()).unwrap().name
+        );
+        assert_eq!(1, merged.get_connections(&30000001.into()).unwrap().len());
+        let merged = kspace.merge(&theirs, Conflict::TakeTheirs).unwrap();
+        assert_eq!("Renamed", merged.get_system(&30000002.into()).unwrap().name);
+        assert!(
+            merged
+                .connection_between(&30000002.into(), &30000001.into())
+                .is_some()
+        );
+
+        let conflicting = UniverseBuilder::default()
+            .connection(connection(30000001, 30000002, ansiblex.clone()))
+            .build();
+        assert_eq!(
+            MergeError::DuplicateConnection(30000001.into(), 30000002.into()),
+            kspace.merge(&conflicting, Conflict::Fail).err().unwrap()
+        );
+        let merged = kspace.merge(&conflicting, Conflict::TakeTheirs).unwrap();
+        assert_eq!(
+            vec![ansiblex],
+            merged
+                .get_connections(&30000001.into())
+                .unwrap()
+                .into_iter()
+                .map(|c| c.r#type)
+                .collect::<Vec<_>>()
+        );
+        let merged = kspace.merge(&conflicting, Conflict::KeepOurs).unwrap();
+        assert_eq!(
+            gate,
+            merged.get_connections(&30000001.into()).unwrap()[0].r#type
+        );
+    }
+
+    #[test]
+    fn test_merge_side_tables() {
+        use crate::hierarchy::Hierarchy;
+        use crate::stations::{Services, Station, Stations};
+
+        let universe = |id: u32, region: &str, station: &str| {
+            let mut hierarchy = Hierarchy::default();
+            hierarchy.insert_region(10000002.into(), region);
+            hierarchy.insert_constellation(20000020.into(), 10000002.into(), "Kimotoro");
+            hierarchy.insert_system(id.into(), 20000020.into());
+            UniverseBuilder::default()
+                .system(system(id))
+                .build()
+                .with_hierarchy(hierarchy)
+                .with_stations(Stations::new(vec![Station {
+                    id: 60003760,
+                    name: station.to_string(),
+                    system: 30000142.into(),
+                    services: Services::default(),
+                }]))
+        };
+        let ours = universe(30000142, "The Forge", "Jita IV - Moon 4");
+        let station =
+            |universe: &types::Universe| universe.stations().get(60003760).unwrap().name.clone();
+        let region =
+            |universe: &types::Universe| universe.region_of(&30000142.into()).unwrap().name;
+
+        // identical regions, constellations and stations are merged silently
+        let same = ours
+            .merge(
+                &universe(30000144, "The Forge", "Jita IV - Moon 4"),
+                Conflict::Fail,
+            )
+            .unwrap();
+        assert_eq!(1, same.stations().len());
+        assert_eq!(
+            Some("The Forge".to_string()),
+            same.region_of(&30000144.into()).map(|r| r.name)
+        );
+
+        let theirs = universe(30000144, "Forge", "Jita IV - Moon 4");
+        assert_eq!(
+            MergeError::DuplicateEntry("region", 10000002),
+            ours.merge(&theirs, Conflict::Fail).err().unwrap()
+        );
+        assert_eq!(
+            "The Forge",
+            region(&ours.merge(&theirs, Conflict::KeepOurs).unwrap())
+        );
+        assert_eq!(
+            "Forge",
+            region(&ours.merge(&theirs, Conflict::TakeTheirs).unwrap())
+        );
+
+        let theirs = universe(30000144, "The Forge", "Jita 4-4");
+        assert_eq!(
+            MergeError::DuplicateEntry("station", 60003760),
+            ours.merge(&theirs, Conflict::Fail).err().unwrap()
+        );
+        assert_eq!(
+            "Jita IV - Moon 4",
+            station(&ours.merge(&theirs, Conflict::KeepOurs).unwrap())
+        );
+        assert_eq!(
+            "Jita 4-4",
+            station(&ours.merge(&theirs, Conflict::TakeTheirs).unwrap())
+        );
+    }
+}
@@ -0,0 +1,127 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Normalization of user input before looking up systems or stations by name, so
+//! bots and tools accept the same spellings.
+//!
+//! Like the in-game search, normalized names ignore case, surrounding and repeated
+//! whitespace, and the kind of dash used. Planet numbers can be given as roman or
+//! arabic numerals, "Oursulaert III" and "oursulaert 3" are the same name.
+
+/// Highest number converted from or to roman numerals. Systems have at most a few
+/// dozen planets and moons.
+const MAX_ROMAN: u32 = 39;
+
+const ROMAN: [(u32, &str); 5] = [(10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I")];
+
+/// Converts a roman numeral up to XXXIX to a number, ignoring case. Returns `None`
+/// for anything else, including non-canonical forms such as "IIII".
+///
+/// # Example
+/// ```
+/// use neweden::names::from_roman;
+///
+/// assert_eq!(Some(3), from_roman("III"));
+/// assert_eq!(Some(14), from_roman("xiv"));
+/// assert_eq!(None, from_roman("IIII"));
+/// ```
+pub fn from_roman(numeral: &str) -> Option<u32> {
+    if numeral.is_empty() || !numeral.chars().all(|c| "ivxIVX".contains(c)) {
+        return None;
+    }
+    let upper = numeral.to_ascii_uppercase();
+    let mut rest = upper.as_str();
+    let mut value = 0;
+    for (number, symbol) in ROMAN {
+        while let Some(tail) = rest.strip_prefix(symbol) {
+            value += number;
+            rest = tail;
+        }
+    }
+    // round trip to reject non-canonical numerals
+    (rest.is_empty() && value <= MAX_ROMAN && to_roman(value).as_deref() == Some(upper.as_str()))
+        .then_some(value)
+}
+
+/// Converts a number from 1 to 39 to a roman numeral, e.g. for planet names.
+pub fn to_roman(mut value: u32) -> Option<String> {
+    if !(1..=MAX_ROMAN).contains(&value) {
+        return None;
+    }
+    let mut numeral = String::new();
+    for (number, symbol) in ROMAN {
+        while value >= number {
+            numeral.push_str(symbol);
+            value -= number;
+        }
+    }
+    Some(numeral)
+}
+
+/// Normalizes a system, planet or station name for comparisons: trims and
+/// collapses whitespace, folds case, replaces other dashes with `-` and converts
+/// roman numerals to numbers.
+///
+/// # Example
+/// ```
+/// use neweden::names::normalize;
+///
+/// assert_eq!("jita 4 - moon 4", normalize("  Jita IV – Moon 4 "));
+/// assert_eq!("6vdt-h", normalize("6VDT-H"));
+/// ```
+pub fn normalize(name: &str) -> String {
+    name.split_whitespace()
+        .map(|word| {
+            let word = word.replace(
+                [
+                    '\u{2010}', '\u{2011}', '\u{2012}', '\u{2013}', '\u{2014}', '\u{2212}',
+                ],
+                "-",
+            );
+            match from_roman(&word) {
+                Some(value) => value.to_string(),
+                None => word.to_lowercase(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns true if both names are the same after normalization.
+pub fn matches(a: &str, b: &str) -> bool {
+    normalize(a) == normalize(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roman() {
+        for value in 1..=MAX_ROMAN {
+            assert_eq!(Some(value), from_roman(&to_roman(value).unwrap()));
+        }
+        assert_eq!(Some("XXIV".to_string()), to_roman(24));
+        assert_eq!(None, to_roman(0));
+        assert_eq!(None, to_roman(40));
+        for invalid in ["", "IIII", "VX", "IC", "XL", "Jita"] {
+            assert_eq!(None, from_roman(invalid), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert!(matches("Oursulaert III", "oursulaert 3"));
+        assert!(matches("  Ichoriya \t V ", "ichoriya v"));
+        assert!(matches(
+            "Amarr VIII (Oris) - Emperor Family Academy",
+            "amarr 8 (oris) - emperor family academy"
+        ));
+        assert!(matches("Ôursulaert", "ôURSULAERT"));
+        assert!(!matches("Jita", "Jita IV"));
+        // words that only look like numerals stay words
+        assert_eq!("mix vix", normalize("MIX vix"));
+    }
+}
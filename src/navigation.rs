@@ -3,10 +3,14 @@
  * All rights reserved.
  */
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use pathfinding::prelude::dijkstra;
+use chrono::{DateTime, Duration, Utc};
+use itertools::Itertools;
+use pathfinding::prelude::{build_path, dijkstra, dijkstra_all};
 
+use crate::fuel;
+use crate::rules;
 use crate::types;
 
 #[derive(PartialEq)]
@@ -22,12 +26,31 @@ pub enum PathElement<'a> {
     Connection(types::ConnectionType),
 }
 
+/// A single jump of a path together with the annotations of the connection used.
+#[derive(Debug)]
+pub struct Hop<'a> {
+    pub from: &'a types::System,
+    pub to: &'a types::System,
+    pub connection: types::ConnectionType,
+    pub annotations: &'a [types::Annotation],
+    /// Straight-line distance between both systems.
+    pub lightyears: types::Lightyears,
+}
+
 pub struct Path<'a> {
     cur: usize,
     jump_count: usize,
     path: Vec<PathElementInternal>,
     universe: &'a dyn types::Navigatable,
     waypoints: Vec<&'a types::System>,
+    departure: Option<Departure>,
+}
+
+/// When and how fast a path is travelled, see `PathBuilder::depart_at()`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Departure {
+    time: DateTime<Utc>,
+    times: TravelTimes,
 }
 
 impl<'a> Path<'a> {
@@ -36,6 +59,7 @@ impl<'a> Path<'a> {
         waypoints: Vec<&'a types::System>,
         path: Vec<PathElementInternal>,
         jump_count: usize,
+        departure: Option<Departure>,
     ) -> Self {
         Self {
             cur: 0,
@@ -43,6 +67,7 @@ impl<'a> Path<'a> {
             path,
             universe,
             waypoints,
+            departure,
         }
     }
 
@@ -83,6 +108,501 @@ impl<'a> Path<'a> {
     pub fn waypoints(&self) -> Vec<&'a types::System> {
         self.waypoints.clone()
     }
+
+    /// Like `systems()`, but the references live as long as the universe.
+    fn system_refs(&self) -> Vec<&'a types::System> {
+        let universe = self.universe;
+        self.path
+            .iter()
+            .filter_map(|element| match element {
+                PathElementInternal::System(id) | PathElementInternal::Waypoint(id) => {
+                    universe.get_system(id)
+                }
+                PathElementInternal::Connection(_) => None,
+            })
+            .collect()
+    }
+
+    /// Returns the jumps of the path, including connection annotations.
+    pub fn hops(&self) -> Vec<Hop<'a>> {
+        let mut hops = Vec::with_capacity(self.jump_count);
+        let mut from: Option<&types::SystemId> = None;
+        let mut via = None;
+        for element in &self.path {
+            match element {
+                PathElementInternal::Connection(type_) => via = Some(type_),
+                PathElementInternal::System(id) | PathElementInternal::Waypoint(id) => {
+                    if let (Some(from), Some(type_)) = (from, via.take()) {
+                        let from_system = self.universe.get_system(from).unwrap();
+                        let to = self.universe.get_system(id).unwrap();
+                        hops.push(Hop {
+                            from: from_system,
+                            to,
+                            connection: type_.clone(),
+                            annotations: self.universe.get_annotations(from, id),
+                            lightyears: from_system.distance(to).into(),
+                        });
+                    }
+                    from = Some(id);
+                }
+            }
+        }
+        hops
+    }
+
+    /// Splits the path into legs of consecutive systems with the same security class
+    /// or in the same region.
+    pub fn legs(&self, split: LegSplit) -> Vec<Leg<'a>> {
+        let key = |system: &types::System| match split {
+            LegSplit::Security => LegKey::Security(system.security.into()),
+            LegSplit::Region => LegKey::Region(system.region_name.clone()),
+        };
+        let systems = self.system_refs();
+        let mut legs: Vec<Leg<'a>> = Vec::new();
+        for (i, system) in systems.iter().enumerate() {
+            let key = key(system);
+            match legs.last_mut() {
+                Some(leg) if leg.key == key => leg.systems.push(system),
+                _ => {
+                    if let Some(leg) = legs.last_mut() {
+                        leg.exit = Some(system);
+                    }
+                    legs.push(Leg {
+                        key,
+                        systems: vec![system],
+                        entry: i.checked_sub(1).map(|prev| systems[prev]),
+                        exit: None,
+                    });
+                }
+            }
+        }
+        legs
+    }
+
+    /// Returns warnings about the security of the systems along the path.
+    pub fn warnings(&self) -> Vec<RouteWarning<'a>> {
+        let systems = self.system_refs();
+        let class = |system: &types::System| types::SecurityClass::from(system.security);
+        let first = |level: types::SecurityClass| {
+            // only warn about entering, not when starting in low or nullsec
+            if systems.first().is_none_or(|start| class(start) >= level) {
+                return None;
+            }
+            systems.iter().position(|system| class(system) >= level)
+        };
+
+        let mut warnings = Vec::new();
+        if let Some(hop) = first(types::SecurityClass::Lowsec) {
+            warnings.push(RouteWarning::FirstLowsec(hop, systems[hop]));
+        }
+        if let Some(hop) = first(types::SecurityClass::Nullsec) {
+            warnings.push(RouteWarning::FirstNullsec(hop, systems[hop]));
+        }
+
+        let mut hop = 0;
+        for (is_nullsec, run) in &systems
+            .iter()
+            .chunk_by(|system| class(system) == types::SecurityClass::Nullsec)
+        {
+            let run = run.collect::<Vec<_>>();
+            if is_nullsec && run.len() > 1 {
+                warnings.push(RouteWarning::ConsecutiveNullsec {
+                    hop,
+                    system: run[0],
+                    count: run.len(),
+                });
+            }
+            hop += run.len();
+        }
+
+        if let Some(departure) = self.departure {
+            let downtime = rules::next_downtime(departure.time);
+            let mut arrival = departure.time;
+            for (i, hop) in self.hops().into_iter().enumerate() {
+                arrival += departure.times.time(&hop.connection);
+                if matches!(hop.connection, types::ConnectionType::Wormhole(_))
+                    && arrival > downtime
+                {
+                    warnings.push(RouteWarning::WormholeAfterDowntime(i + 1, hop.to));
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Returns an owned copy of the path, pinned to the version of the universe it
+    /// was computed in.
+    pub fn to_route(&self) -> Route {
+        Route {
+            version: self.universe.version(),
+            hops: self
+                .hops()
+                .into_iter()
+                .map(|hop| RouteHop {
+                    from: hop.from.id,
+                    to: hop.to.id,
+                    connection: hop.connection,
+                })
+                .collect(),
+        }
+    }
+
+    /// Total straight-line distance of all jumps of the path.
+    pub fn lightyears(&self) -> types::Lightyears {
+        self.hops()
+            .iter()
+            .fold(types::Lightyears(0.0), |total, hop| total + hop.lightyears)
+    }
+
+    /// Estimates the time needed to travel the whole path.
+    pub fn eta(&self, times: &TravelTimes) -> Duration {
+        self.path
+            .iter()
+            .filter_map(|element| match element {
+                PathElementInternal::Connection(type_) => Some(times.time(type_)),
+                _ => None,
+            })
+            .fold(Duration::zero(), |total, time| total + time)
+    }
+
+    /// Returns the number of jumps that can be made before the next downtime, using
+    /// the departure and travel times given to `PathBuilder::depart_at()`. Returns
+    /// `None` if the path has no departure or can be travelled before downtime.
+    pub fn downtime_split(&self) -> Option<usize> {
+        let departure = self.departure?;
+        rules::steps_before_downtime(
+            departure.time,
+            self.hops()
+                .iter()
+                .map(|hop| departure.times.time(&hop.connection)),
+        )
+    }
+}
+
+/// How to split a path into legs, see `Path::legs()`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LegSplit {
+    Security,
+    Region,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LegKey {
+    Security(types::SecurityClass),
+    Region(String),
+}
+
+impl std::fmt::Display for LegKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LegKey::Security(class) => write!(f, "{}", class),
+            LegKey::Region(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Consecutive systems of a path sharing the same security class or region.
+#[derive(Debug)]
+pub struct Leg<'a> {
+    pub key: LegKey,
+    pub systems: Vec<&'a types::System>,
+    /// The system the leg was entered from, `None` for the first leg.
+    pub entry: Option<&'a types::System>,
+    /// The system the leg is left to, `None` for the last leg.
+    pub exit: Option<&'a types::System>,
+}
+
+impl Leg<'_> {
+    /// Number of jumps made into the systems of the leg.
+    pub fn jumps(&self) -> usize {
+        match self.entry {
+            Some(_) => self.systems.len(),
+            None => self.systems.len() - 1,
+        }
+    }
+}
+
+impl std::fmt::Display for Leg<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}j {}", self.jumps(), self.key)
+    }
+}
+
+/// Security warnings for a path. `hop` is the index of the system in the path,
+/// starting with 0 for the origin.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteWarning<'a> {
+    FirstLowsec(usize, &'a types::System),
+    FirstNullsec(usize, &'a types::System),
+    ConsecutiveNullsec {
+        hop: usize,
+        system: &'a types::System,
+        count: usize,
+    },
+    /// A wormhole is jumped after the next downtime, which it may not survive. Only
+    /// reported for paths with a departure, see `PathBuilder::depart_at()`.
+    WormholeAfterDowntime(usize, &'a types::System),
+}
+
+impl std::fmt::Display for RouteWarning<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RouteWarning::FirstLowsec(hop, system) => {
+                write!(f, "first lowsec entry at hop {} ({})", hop, system.name)
+            }
+            RouteWarning::FirstNullsec(hop, system) => {
+                write!(f, "first nullsec entry at hop {} ({})", hop, system.name)
+            }
+            RouteWarning::ConsecutiveNullsec { hop, system, count } => write!(
+                f,
+                "passes {} consecutive nullsec systems from hop {} ({})",
+                count, hop, system.name
+            ),
+            RouteWarning::WormholeAfterDowntime(hop, system) => write!(
+                f,
+                "wormhole to {} at hop {} is jumped after downtime",
+                system.name, hop
+            ),
+        }
+    }
+}
+
+/// A jump of a `Route`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RouteHop {
+    pub from: types::SystemId,
+    pub to: types::SystemId,
+    pub connection: types::ConnectionType,
+}
+
+/// Problems found when revalidating a saved `Route`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StaleHop {
+    MissingSystem(types::SystemId),
+    MissingConnection {
+        from: types::SystemId,
+        to: types::SystemId,
+    },
+}
+
+/// An owned path that does not borrow the universe and can be stored, e.g. using
+/// serde with the `serde` feature. Routes remember the version of the universe they
+/// were computed against, so stale routes can be detected when loading them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Route {
+    pub version: u64,
+    pub hops: Vec<RouteHop>,
+}
+
+impl Route {
+    /// Returns true if the universe is unchanged since the route was computed.
+    pub fn is_current(&self, universe: &dyn types::Navigatable) -> bool {
+        self.version == universe.version()
+    }
+
+    /// Fuel needed to send a ship of the given mass in kilograms along the route.
+    /// Systems missing from the universe are treated as zero lightyears apart.
+    pub fn fuel(&self, universe: &dyn types::Navigatable, mass: u64) -> fuel::FuelCost {
+        self.hops
+            .iter()
+            .map(|hop| {
+                let ly = match (universe.get_system(&hop.from), universe.get_system(&hop.to)) {
+                    (Some(from), Some(to)) => from.distance(to).into(),
+                    _ => types::Lightyears(0.0),
+                };
+                fuel::connection_fuel(&hop.connection, ly, mass)
+            })
+            .sum()
+    }
+
+    /// Checks that every system and connection of the route still exists in the
+    /// universe. Returns an empty list if the route can still be travelled.
+    pub fn revalidate(&self, universe: &dyn types::Navigatable) -> Vec<StaleHop> {
+        let mut stale = Vec::new();
+        for hop in &self.hops {
+            let mut missing = false;
+            for id in [hop.from, hop.to] {
+                if universe.get_system(&id).is_none() {
+                    missing = true;
+                    if !stale.contains(&StaleHop::MissingSystem(id)) {
+                        stale.push(StaleHop::MissingSystem(id));
+                    }
+                }
+            }
+            if missing {
+                continue;
+            }
+            let connected = universe
+                .get_connections(&hop.from)
+                .unwrap_or_default()
+                .iter()
+                .any(|conn| {
+                    conn.to == hop.to
+                        && std::mem::discriminant(&conn.r#type)
+                            == std::mem::discriminant(&hop.connection)
+                });
+            if !connected {
+                stale.push(StaleHop::MissingConnection {
+                    from: hop.from,
+                    to: hop.to,
+                });
+            }
+        }
+        stale
+    }
+
+    /// Compares the route with a newer one, e.g. computed after a wormhole appeared.
+    /// Hops between the same systems are matched up in order, hops that only
+    /// changed their connection are reported as changed.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::navigation::{Route, RouteHop};
+    /// use neweden::{ConnectionType, StargateType};
+    ///
+    /// let hop = |from: u32, to: u32, connection| RouteHop {
+    ///     from: from.into(),
+    ///     to: to.into(),
+    ///     connection,
+    /// };
+    /// let gate = ConnectionType::Stargate(StargateType::Local);
+    /// let before = Route {
+    ///     version: 0,
+    ///     hops: vec![hop(1, 2, gate.clone()), hop(2, 3, gate.clone())],
+    /// };
+    /// let after = Route {
+    ///     version: 0,
+    ///     hops: vec![hop(1, 2, gate.clone()), hop(2, 3, ConnectionType::Ansiblex)],
+    /// };
+    /// let diff = before.diff(&after);
+    /// assert_eq!("= 1 hop, ~ 2 > 3 (Stargate (Local) -> Ansiblex)", diff.to_string());
+    /// ```
+    pub fn diff(&self, other: &Route) -> RouteDiff {
+        let key = |hop: &RouteHop| (hop.from, hop.to);
+        let (n, m) = (self.hops.len(), other.hops.len());
+        // longest common subsequence of hops by their systems
+        let mut lcs = vec![vec![0; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if key(&self.hops[i]) == key(&other.hops[j]) {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let mut changes = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && key(&self.hops[i]) == key(&other.hops[j]) {
+                let (before, after) = (&self.hops[i], &other.hops[j]);
+                changes.push(if before.connection == after.connection {
+                    HopChange::Kept(after.clone())
+                } else {
+                    HopChange::Changed {
+                        before: before.clone(),
+                        after: after.clone(),
+                    }
+                });
+                i += 1;
+                j += 1;
+            } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+                changes.push(HopChange::Added(other.hops[j].clone()));
+                j += 1;
+            } else {
+                changes.push(HopChange::Removed(self.hops[i].clone()));
+                i += 1;
+            }
+        }
+        RouteDiff { changes }
+    }
+}
+
+/// A hop of a `RouteDiff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HopChange {
+    Kept(RouteHop),
+    Added(RouteHop),
+    Removed(RouteHop),
+    /// Same systems, but a different connection, e.g. an Ansiblex instead of a stargate.
+    Changed {
+        before: RouteHop,
+        after: RouteHop,
+    },
+}
+
+/// Difference between two routes, see `Route::diff()`. The hops of the old route
+/// are in order, as are the hops of the new route.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteDiff {
+    pub changes: Vec<HopChange>,
+}
+
+impl RouteDiff {
+    /// Returns true if both routes use the same hops.
+    pub fn is_empty(&self) -> bool {
+        self.changes
+            .iter()
+            .all(|change| matches!(change, HopChange::Kept(_)))
+    }
+
+    pub fn added(&self) -> impl Iterator<Item = &RouteHop> {
+        self.changes.iter().filter_map(|change| match change {
+            HopChange::Added(hop) => Some(hop),
+            _ => None,
+        })
+    }
+
+    pub fn removed(&self) -> impl Iterator<Item = &RouteHop> {
+        self.changes.iter().filter_map(|change| match change {
+            HopChange::Removed(hop) => Some(hop),
+            _ => None,
+        })
+    }
+}
+
+/// Compact rendering, unchanged hops are collapsed into a count, e.g.
+/// `= 3 hops, - 1 > 2, + 1 > 5, ~ 5 > 6 (Stargate (Local) -> Ansiblex)`.
+impl std::fmt::Display for RouteDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        let mut kept = 0;
+        for change in &self.changes {
+            if let HopChange::Kept(_) = change {
+                kept += 1;
+                continue;
+            }
+            if kept > 0 {
+                parts.push(format!(
+                    "= {} hop{}",
+                    kept,
+                    if kept == 1 { "" } else { "s" }
+                ));
+                kept = 0;
+            }
+            parts.push(match change {
+                HopChange::Added(hop) => format!("+ {} > {}", hop.from.0, hop.to.0),
+                HopChange::Removed(hop) => format!("- {} > {}", hop.from.0, hop.to.0),
+                HopChange::Changed { before, after } => format!(
+                    "~ {} > {} ({} -> {})",
+                    after.from.0, after.to.0, before.connection, after.connection
+                ),
+                HopChange::Kept(_) => unreachable!(),
+            });
+        }
+        if kept > 0 {
+            parts.push(format!(
+                "= {} hop{}",
+                kept,
+                if kept == 1 { "" } else { "s" }
+            ));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
 }
 
 pub struct PathIterator<'a> {
@@ -141,19 +661,84 @@ impl<'a> Iterator for Path<'a> {
     }
 }
 
-type Cost = u32;
+type Cost = u64;
+
+/// Cost of a jump with `PathBuilder::autopilot()`. The tie-break added for the
+/// system entered is below `AUTOPILOT_TIE_BREAK`, so the tie-breaks of up to 256
+/// jumps never outweigh a jump, longer than any route in New Eden.
+const AUTOPILOT_JUMP: Cost = 1 << 24;
+
+/// Tie-breaks are the system id relative to the first k-space system.
+const AUTOPILOT_TIE_BREAK: Cost = 1 << 16;
+
+/// Estimated time spent per connection, used for `Path::eta()` and
+/// `Preference::Fastest`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TravelTimes {
+    /// Align, warp to the gate and jump, including the gate cloak.
+    pub stargate: Duration,
+    /// Ansiblex gates have no gate cloak and are usually placed close to each other.
+    pub ansiblex: Duration,
+    /// Time for the bridge itself once it is open.
+    pub bridge: Duration,
+    /// Waiting for the titan or black ops pilot to open the bridge.
+    pub bridge_wait: Duration,
+    /// Warp to the wormhole, jump and session change.
+    pub wormhole: Duration,
+}
+
+impl Default for TravelTimes {
+    fn default() -> Self {
+        Self {
+            stargate: Duration::seconds(60),
+            ansiblex: Duration::seconds(30),
+            bridge: Duration::seconds(10),
+            bridge_wait: Duration::minutes(2),
+            wormhole: Duration::seconds(45),
+        }
+    }
+}
+
+impl TravelTimes {
+    /// Returns the time needed to travel the given connection.
+    pub fn time(&self, connection: &types::ConnectionType) -> Duration {
+        match connection {
+            types::ConnectionType::Stargate(_) => self.stargate,
+            types::ConnectionType::Ansiblex => self.ansiblex,
+            types::ConnectionType::Bridge(_) => self.bridge + self.bridge_wait,
+            types::ConnectionType::Wormhole(_) => self.wormhole,
+        }
+    }
+}
 
 #[derive(Copy, Clone)]
 pub enum Preference {
     Shortest,
     Highsec,
     LowsecAndNullsec,
+    /// Minimize the travel time instead of the number of jumps.
+    Fastest(TravelTimes),
+    /// Minimize the total straight-line distance instead of the number of jumps.
+    Lightyears,
 }
 
 impl Preference {
-    fn cost(&self, universe: &dyn types::Navigatable, to: types::SystemId) -> Cost {
+    fn cost(&self, universe: &dyn types::Navigatable, connection: &types::Connection) -> Cost {
+        let to = connection.to;
         match self {
             Self::Shortest => 1, // all are equal distance
+            Self::Fastest(times) => times.time(&connection.r#type).num_seconds().max(1) as Cost,
+            Self::Lightyears => match (
+                universe.get_system(&connection.from),
+                universe.get_system(&to),
+            ) {
+                // in millilightyears, but every jump must cost something
+                (Some(from), Some(to)) => {
+                    let ly = types::Lightyears::from(from.distance(to));
+                    ((ly.0 * 1000.0).round() as Cost).max(1)
+                }
+                _ => 1,
+            },
             Self::Highsec => {
                 // we must have positive weights
                 // security can go from -1.0 to 1.0
@@ -168,6 +753,40 @@ impl Preference {
             },
         }
     }
+
+    /// Values identifying the preference, see `PathBuilder::cache_key()`.
+    fn key(&self) -> Vec<u32> {
+        match self {
+            Self::Shortest => vec![0],
+            Self::Highsec => vec![1],
+            Self::LowsecAndNullsec => vec![2],
+            Self::Fastest(times) => {
+                let mut key = vec![3];
+                for time in [
+                    times.stargate,
+                    times.ansiblex,
+                    times.bridge,
+                    times.bridge_wait,
+                    times.wormhole,
+                ] {
+                    let seconds = time.num_seconds() as u64;
+                    key.extend([seconds as u32, (seconds >> 32) as u32]);
+                }
+                key
+            }
+            Self::Lightyears => vec![4],
+        }
+    }
+
+    /// The cost of one ordinary jump, used to express penalties in jumps. Jumps
+    /// differ in length, so a jump for `Lightyears` is the connection itself.
+    fn jump_cost(&self, universe: &dyn types::Navigatable, connection: &types::Connection) -> Cost {
+        match self {
+            Self::Shortest | Self::Highsec | Self::LowsecAndNullsec => 1,
+            Self::Fastest(times) => times.stargate.num_seconds().max(1) as Cost,
+            Self::Lightyears => self.cost(universe, connection),
+        }
+    }
 }
 
 #[derive(Eq, Clone)]
@@ -188,11 +807,47 @@ impl std::cmp::PartialEq for Succ {
     }
 }
 
+/// Kinds of connections that can be switched off for a single path query, e.g. to
+/// compare a route with and without Ansiblex gates on the same `ExtendedUniverse`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Layer {
+    Stargates,
+    Bridges,
+    Wormholes,
+    Ansiblex,
+}
+
+impl From<&types::ConnectionType> for Layer {
+    fn from(type_: &types::ConnectionType) -> Self {
+        match type_ {
+            types::ConnectionType::Stargate(_) => Self::Stargates,
+            types::ConnectionType::Bridge(_) => Self::Bridges,
+            types::ConnectionType::Wormhole(_) => Self::Wormholes,
+            types::ConnectionType::Ansiblex => Self::Ansiblex,
+        }
+    }
+}
+
+pub(crate) type SystemFilter<'a> = Box<dyn Fn(&types::System) -> bool + 'a>;
+type ConnectionPenalty<'a> = Box<dyn Fn(&types::Connection, DateTime<Utc>) -> u32 + 'a>;
+
 pub struct PathBuilder<'a> {
     universe: &'a dyn types::Navigatable,
     waypoints: Vec<&'a types::System>,
     avoid: HashSet<&'a types::SystemId>,
+    avoid_where: Vec<SystemFilter<'a>>,
+    default_avoid: bool,
+    penalize_where: Vec<(SystemFilter<'a>, Cost)>,
+    penalize_connection: Vec<ConnectionPenalty<'a>>,
     preference: Preference,
+    ship: Option<rules::ShipProfile>,
+    restrictions: rules::SizeRestrictions,
+    pilot: Option<rules::PilotProfile>,
+    autopilot: bool,
+    disabled: HashSet<Layer>,
+    max_hop_lightyears: Option<types::Lightyears>,
+    departure: Option<Departure>,
+    created: DateTime<Utc>,
 }
 
 impl<'a> PathBuilder<'a> {
@@ -201,7 +856,32 @@ impl<'a> PathBuilder<'a> {
             universe,
             waypoints: Default::default(),
             avoid: Default::default(),
+            avoid_where: Default::default(),
+            default_avoid: true,
+            penalize_where: Default::default(),
+            penalize_connection: Default::default(),
             preference: Preference::Shortest,
+            ship: None,
+            restrictions: Default::default(),
+            pilot: None,
+            autopilot: false,
+            disabled: Default::default(),
+            max_hop_lightyears: None,
+            departure: None,
+            created: Utc::now(),
+        }
+    }
+
+    /// Routes like the in-game autopilot set to "prefer safer": stargates only and
+    /// never entering systems below 0.5. Ties between equally long routes are broken
+    /// in the cost of each jump, preferring the route whose systems have the lowest
+    /// ids in total, so the same route is returned no matter the order of the
+    /// connections. The client breaks ties its own way, so this approximates the
+    /// in-game route and may pick a different one of equal length.
+    pub fn autopilot(universe: &'a dyn types::Navigatable) -> Self {
+        Self {
+            autopilot: true,
+            ..Self::new(universe)
         }
     }
 
@@ -230,6 +910,263 @@ impl<'a> PathBuilder<'a> {
         self
     }
 
+    /// Avoid all systems matching the predicate, e.g. `rules::is_npc_nullsec`.
+    pub fn avoid_where(mut self, predicate: impl Fn(&types::System) -> bool + 'a) -> Self {
+        self.avoid_where.push(Box::new(predicate));
+        self
+    }
+
+    /// Whether to avoid the systems the universe avoids by default, see
+    /// `Navigatable::avoided_by_default()`, e.g. to route through abyssal or void
+    /// systems. Enabled by default.
+    pub fn default_avoid(mut self, enabled: bool) -> Self {
+        self.default_avoid = enabled;
+        self
+    }
+
+    /// Entering a system matching the predicate costs as much as `jumps` additional
+    /// jumps with the chosen preference, e.g. to prefer routes through friendly space
+    /// without avoiding other systems entirely.
+    pub fn penalize_where(
+        mut self,
+        predicate: impl Fn(&types::System) -> bool + 'a,
+        jumps: u32,
+    ) -> Self {
+        self.penalize_where
+            .push((Box::new(predicate), jumps.into()));
+        self
+    }
+
+    /// Using a connection costs as much as the returned number of additional jumps
+    /// with the chosen preference, e.g. for gates likely camped at the time, see
+    /// `camps::avoid_camps()`. The time is the departure given to `depart_at()`, or
+    /// the time the builder was created.
+    pub fn penalize_connection(
+        mut self,
+        penalty: impl Fn(&types::Connection, DateTime<Utc>) -> u32 + 'a,
+    ) -> Self {
+        self.penalize_connection.push(Box::new(penalty));
+        self
+    }
+
+    /// Only route through systems and connections the given ship can use.
+    pub fn ship(mut self, profile: rules::ShipProfile) -> Self {
+        self.ship = Some(profile);
+        self
+    }
+
+    /// Size limits of systems and connections, only used together with `ship()`.
+    pub fn size_restrictions(mut self, restrictions: rules::SizeRestrictions) -> Self {
+        self.restrictions = restrictions;
+        self
+    }
+
+    /// Only use connections available to the given pilot, e.g. Pochven stargates.
+    pub fn pilot(mut self, profile: rules::PilotProfile) -> Self {
+        self.pilot = Some(profile);
+        self
+    }
+
+    /// Plans for a departure at `time`, taking `times` per connection. Wormholes that
+    /// have expired by then are not used. The path warns about wormholes jumped
+    /// after the next downtime and tells where to stop for it, see
+    /// `Path::downtime_split()`.
+    pub fn depart_at(mut self, time: DateTime<Utc>, times: TravelTimes) -> Self {
+        self.departure = Some(Departure { time, times });
+        self
+    }
+
+    /// Ignore all connections of the given kind for this path.
+    pub fn disable(mut self, layer: Layer) -> Self {
+        self.disabled.insert(layer);
+        self
+    }
+
+    /// Use connections of the given kind again after they were disabled.
+    pub fn enable(mut self, layer: Layer) -> Self {
+        self.disabled.remove(&layer);
+        self
+    }
+
+    /// Never use connections between systems further apart than `ly`.
+    pub fn max_hop_lightyears(mut self, ly: types::Lightyears) -> Self {
+        self.max_hop_lightyears = Some(ly);
+        self
+    }
+
+    /// A key identifying the waypoints and all settings of the builder, e.g. to cache
+    /// the built route with `cache::RouteCache`. The key is stable across Rust
+    /// releases and can be persisted. Returns `None` if the builder uses predicates
+    /// like `avoid_where()`, which can't be compared.
+    pub fn cache_key(&self) -> Option<u64> {
+        if !self.avoid_where.is_empty()
+            || !self.penalize_where.is_empty()
+            || !self.penalize_connection.is_empty()
+        {
+            return None;
+        }
+        let mut key = vec![self.waypoints.len() as u32];
+        key.extend(self.waypoints.iter().map(|system| system.id.0));
+        let mut avoid = self.avoid.iter().map(|id| id.0).collect::<Vec<_>>();
+        avoid.sort_unstable();
+        key.push(avoid.len() as u32);
+        key.extend(avoid);
+        key.push(self.default_avoid as u32);
+        key.extend(self.preference.key());
+        match &self.ship {
+            Some(ship) => key.extend([1, ship.size() as u32, ship.can_enter_highsec() as u32]),
+            None => key.push(0),
+        }
+        key.extend(self.restrictions.key());
+        match &self.pilot {
+            Some(pilot) => {
+                key.push(1);
+                key.extend(pilot.key());
+            }
+            None => key.push(0),
+        }
+        key.push(self.autopilot as u32);
+        let mut disabled = self
+            .disabled
+            .iter()
+            .map(|layer| *layer as u32)
+            .collect::<Vec<_>>();
+        disabled.sort_unstable();
+        key.push(disabled.len() as u32);
+        key.extend(disabled);
+        match self.max_hop_lightyears {
+            Some(ly) => {
+                let bits = ly.0.to_bits();
+                key.extend([1, bits as u32, (bits >> 32) as u32]);
+            }
+            None => key.push(0),
+        }
+        match self.departure {
+            Some(departure) => {
+                let seconds = departure.time.timestamp() as u64;
+                key.extend([1, seconds as u32, (seconds >> 32) as u32]);
+                key.extend(Preference::Fastest(departure.times).key());
+            }
+            None => key.push(0),
+        }
+        Some(types::fingerprint(types::FINGERPRINT_SEED, key))
+    }
+
+    /// The universe and the first and last waypoint, see `cache::RouteCache`.
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn endpoints(
+        &self,
+    ) -> Option<(&'a dyn types::Navigatable, types::SystemId, types::SystemId)> {
+        Some((
+            self.universe,
+            self.waypoints.first()?.id,
+            self.waypoints.last()?.id,
+        ))
+    }
+
+    fn is_avoided(&self, id: &types::SystemId) -> bool {
+        if self.avoid.contains(id) || self.default_avoid && self.universe.avoided_by_default(id) {
+            return true;
+        }
+        match self.universe.get_system(id) {
+            Some(system) => {
+                self.avoid_where.iter().any(|predicate| predicate(system))
+                    || self
+                        .ship
+                        .as_ref()
+                        .is_some_and(|ship| !ship.can_enter(system))
+            }
+            None => false,
+        }
+    }
+
+    fn is_usable(&self, connection: &types::Connection) -> bool {
+        if self.disabled.contains(&Layer::from(&connection.r#type)) {
+            return false;
+        }
+        if let (Some(departure), types::ConnectionType::Wormhole(wormhole)) =
+            (self.departure, &connection.r#type)
+            && wormhole.expires <= departure.time
+        {
+            return false;
+        }
+        if let Some(max) = self.max_hop_lightyears {
+            let from = self.universe.get_system(&connection.from);
+            let to = self.universe.get_system(&connection.to);
+            if let (Some(from), Some(to)) = (from, to)
+                && types::Lightyears::from(from.distance(to)) > max
+            {
+                return false;
+            }
+        }
+        let usable = self.ship.as_ref().is_none_or(|ship| {
+            ship.can_use(connection) && self.restrictions.allows(ship, connection)
+        });
+        let usable = usable
+            && self.pilot.as_ref().is_none_or(|pilot| {
+                match (
+                    self.universe.get_system(&connection.from),
+                    self.universe.get_system(&connection.to),
+                ) {
+                    (Some(from), Some(to)) => pilot.can_use(connection, from, to),
+                    _ => true,
+                }
+            });
+        let usable = usable
+            && (!self.autopilot
+                || matches!(connection.r#type, types::ConnectionType::Stargate(_))
+                    && self.universe.get_system(&connection.to).is_some_and(|to| {
+                        types::SecurityClass::from(to.security) == types::SecurityClass::Highsec
+                    }));
+        usable && !self.is_avoided(&connection.to)
+    }
+
+    fn cost(&self, connection: &types::Connection) -> Cost {
+        let cost = self.preference.cost(self.universe, connection);
+        let jumps = match self.universe.get_system(&connection.to) {
+            Some(system) => self
+                .penalize_where
+                .iter()
+                .filter(|(predicate, _)| predicate(system))
+                .fold(0 as Cost, |jumps, (_, penalty)| {
+                    jumps.saturating_add(*penalty)
+                }),
+            None => 0,
+        };
+        let time = self
+            .departure
+            .map_or(self.created, |departure| departure.time);
+        let jumps = self
+            .penalize_connection
+            .iter()
+            .fold(jumps, |jumps, penalty| {
+                jumps.saturating_add(penalty(connection, time).into())
+            });
+        let jump = self.preference.jump_cost(self.universe, connection);
+        let cost = cost.saturating_add(jumps.saturating_mul(jump));
+        if self.autopilot {
+            let tie_break =
+                Cost::from(connection.to.0.saturating_sub(30_000_000)) % AUTOPILOT_TIE_BREAK;
+            return cost
+                .saturating_mul(AUTOPILOT_JUMP)
+                .saturating_add(tie_break);
+        }
+        cost
+    }
+
+    fn cheapest_connection(
+        &self,
+        from: &types::SystemId,
+        to: &types::SystemId,
+    ) -> Option<types::ConnectionType> {
+        self.universe
+            .get_connections(from)?
+            .iter()
+            .filter(|conn| conn.to == *to && self.is_usable(conn))
+            .min_by_key(|conn| self.cost(conn))
+            .map(|conn| conn.r#type.clone())
+    }
+
     // TODO: We need to include the Connection itself, otherwise connections can be
     // ambiguous in the rare case that a wormhole leads to the same system next door.
     // In practise it likely doesn't matter.
@@ -238,9 +1175,9 @@ impl<'a> PathBuilder<'a> {
             if let Some(connections) = self.universe.get_connections(&s.id) {
                 connections
                     .iter()
-                    .filter(|conn| !self.avoid.contains(&conn.to))
+                    .filter(|conn| self.is_usable(conn))
                     .map(|conn| {
-                        let cost = self.preference.cost(self.universe, conn.to);
+                        let cost = self.cost(conn);
                         let succ = Succ {
                             id: conn.to,
                             via: Some(conn.r#type.clone()),
@@ -267,11 +1204,18 @@ impl<'a> PathBuilder<'a> {
                 successor,
                 |s: &Succ| s.id == b.id,
             ) {
+                let mut previous: Option<types::SystemId> = None;
                 for succ in np {
-                    if let Some(via) = succ.via {
+                    // dijkstra keeps the first `Succ` seen for a system, whose `via`
+                    // may belong to a more expensive connection than the one taken
+                    let via = previous
+                        .and_then(|from| self.cheapest_connection(&from, &succ.id))
+                        .or(succ.via);
+                    if let Some(via) = via {
                         result.push(PathElementInternal::Connection(via));
                         jump_count += 1;
                     }
+                    previous = Some(succ.id);
                     if succ.id == a.id || succ.id == b.id {
                         result.push(PathElementInternal::Waypoint(succ.id));
                     } else {
@@ -284,7 +1228,836 @@ impl<'a> PathBuilder<'a> {
         }
 
         result.dedup();
-        Some(Path::new(self.universe, self.waypoints, result, jump_count))
+        Some(Path::new(
+            self.universe,
+            self.waypoints,
+            result,
+            jump_count,
+            self.departure,
+        ))
+    }
+}
+
+/// Number of systems per security class along a route, including both ends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecuritySummary {
+    pub highsec: usize,
+    pub lowsec: usize,
+    pub nullsec: usize,
+    pub min_security: types::Security,
+}
+
+impl SecuritySummary {
+    fn new<'a>(systems: impl IntoIterator<Item = &'a types::System>) -> Option<Self> {
+        let mut summary: Option<Self> = None;
+        for system in systems {
+            let summary = summary.get_or_insert(Self {
+                highsec: 0,
+                lowsec: 0,
+                nullsec: 0,
+                min_security: system.security,
+            });
+            match types::SecurityClass::from(system.security) {
+                types::SecurityClass::Highsec => summary.highsec += 1,
+                types::SecurityClass::Lowsec => summary.lowsec += 1,
+                types::SecurityClass::Nullsec => summary.nullsec += 1,
+            }
+            if system.security < summary.min_security {
+                summary.min_security = system.security;
+            }
+        }
+        summary
+    }
+}
+
+/// Distance between the origin of a `distance_report` and one of its targets.
+#[derive(Debug, Clone)]
+pub struct Distance<'a> {
+    pub target: &'a types::System,
+    /// Number of jumps on the shortest route, `None` if the target is unreachable.
+    pub jumps: Option<usize>,
+    /// Direct distance, as used by jump drives.
+    pub lightyears: types::Lightyears,
+    /// Security of the shortest route, `None` if the target is unreachable.
+    pub security: Option<SecuritySummary>,
+}
+
+/// Computes jump and lightyear distances from `origin` to each of the targets.
+/// All routes are computed in a single pass over the universe.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::navigation::distance_report;
+/// use neweden::{Connection, ConnectionType, Navigatable, StargateType, System};
+///
+/// let system = |id: u32, security: f32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: security.into(),
+///     region_name: "The Forge".to_string(),
+///     faction_id: None,
+/// };
+/// let gate = |from: u32, to: u32| Connection {
+///     from: from.into(),
+///     to: to.into(),
+///     r#type: ConnectionType::Stargate(StargateType::Local),
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(30000001, 0.9))
+///     .system(system(30000002, 0.3))
+///     .system(system(30000003, -0.2))
+///     .connection(gate(30000001, 30000002))
+///     .connection(gate(30000002, 30000003))
+///     .build();
+/// let origin = universe.get_system(&30000001.into()).unwrap();
+/// let target = universe.get_system(&30000003.into()).unwrap();
+/// let report = distance_report(&universe, origin, vec![target]);
+/// assert_eq!(Some(2), report[0].jumps);
+/// assert_eq!(1, report[0].security.as_ref().unwrap().nullsec);
+/// ```
+pub fn distance_report<'a>(
+    universe: &'a dyn types::Navigatable,
+    origin: &'a types::System,
+    targets: impl IntoIterator<Item = &'a types::System>,
+) -> Vec<Distance<'a>> {
+    let parents = dijkstra_all(&origin.id, |id| {
+        universe
+            .get_connections(id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|conn| (conn.to, 1 as Cost))
+    });
+
+    targets
+        .into_iter()
+        .map(|target| {
+            let lightyears = origin.distance(target).into();
+            let route = if target.id == origin.id {
+                Some(vec![origin.id])
+            } else if parents.contains_key(&target.id) {
+                Some(build_path(&target.id, &parents))
+            } else {
+                None
+            };
+            let jumps = route.as_ref().map(|route| route.len() - 1);
+            let security = route.and_then(|route| {
+                SecuritySummary::new(route.iter().filter_map(|id| universe.get_system(id)))
+            });
+            Distance {
+                target,
+                jumps,
+                lightyears,
+                security,
+            }
+        })
+        .collect()
+}
+
+/// Returns a closed route starting and ending in the first system that visits every
+/// given system at least once, e.g. for a scout patrolling a pocket or a ratting
+/// constellation. Systems are visited nearest first, the order is then improved by
+/// 2-opt. Returns `None` if no systems are given or any system is unreachable.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::navigation::patrol;
+/// use neweden::{Connection, ConnectionType, Navigatable, StargateType, System};
+///
+/// let system = |id: u32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: (-0.3).into(),
+///     region_name: "Pure Blind".to_string(),
+///     faction_id: None,
+/// };
+/// let mut builder = UniverseBuilder::default();
+/// for (from, to) in [(1, 2), (2, 3), (3, 1)] {
+///     builder = builder
+///         .system(system(from))
+///         .connection(Connection {
+///             from: from.into(),
+///             to: to.into(),
+///             r#type: ConnectionType::Stargate(StargateType::Local),
+///         });
+/// }
+/// let universe = builder.build();
+/// let systems = [1, 2, 3].map(|id| universe.get_system(&id.into()).unwrap());
+/// let path = patrol(&universe, &systems).unwrap();
+/// assert_eq!(3, path.jumps());
+/// ```
+pub fn patrol<'a>(
+    universe: &'a dyn types::Navigatable,
+    systems: &[&'a types::System],
+) -> Option<Path<'a>> {
+    let start = *systems.first()?;
+    let mut distances = HashMap::new();
+    for system in systems {
+        let parents = dijkstra_all(&system.id, |id| {
+            universe
+                .get_connections(id)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|conn| !universe.avoided_by_default(&conn.to))
+                .map(|conn| (conn.to, 1 as Cost))
+        });
+        for target in systems {
+            if target.id == system.id {
+                distances.insert((system.id, target.id), 0);
+            } else {
+                distances.insert((system.id, target.id), parents.get(&target.id)?.1);
+            }
+        }
+    }
+    let distance = |a: &types::System, b: &types::System| distances[&(a.id, b.id)];
+
+    let mut order = vec![start];
+    let mut remaining = systems[1..].to_vec();
+    while !remaining.is_empty() {
+        let last = order.last().unwrap();
+        let (next, _) = remaining
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, system)| distance(last, system))
+            .unwrap();
+        order.push(remaining.remove(next));
+    }
+
+    // reverse segments of the tour as long as that makes it shorter
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 1..order.len() {
+            for j in i + 1..order.len() {
+                let after = order.get(j + 1).unwrap_or(&start);
+                let before = distance(order[i - 1], order[i]) + distance(order[j], after);
+                let reversed = distance(order[i - 1], order[j]) + distance(order[i], after);
+                if reversed < before {
+                    order[i..=j].reverse();
+                    improved = true;
+                }
+            }
+        }
+    }
+
+    order.push(start);
+    PathBuilder::new(universe).waypoints(order).build()
+}
+
+/// Systems from which a target system can be reached within a number of jumps,
+/// e.g. to estimate how much warning intel channels give before hostiles arrive.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::navigation::Approaches;
+/// use neweden::{Connection, ConnectionType, SecurityClass, StargateType, System};
+///
+/// let system = |id: u32, security: f32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: security.into(),
+///     region_name: "Black Rise".to_string(),
+///     faction_id: None,
+/// };
+/// let gate = |from: u32, to: u32| Connection {
+///     from: from.into(),
+///     to: to.into(),
+///     r#type: ConnectionType::Stargate(StargateType::Local),
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(30000001, 0.4))
+///     .system(system(30000002, 0.3))
+///     .system(system(30000003, -0.2))
+///     .connection(gate(30000002, 30000001))
+///     .connection(gate(30000003, 30000002))
+///     .build();
+/// let approaches = Approaches::new(&universe, &30000001.into(), 5).unwrap();
+/// assert_eq!(2, approaches.len());
+/// assert_eq!(1, approaches.with_security(SecurityClass::Nullsec)[2].len());
+/// ```
+#[derive(Debug)]
+pub struct Approaches<'a> {
+    pub target: &'a types::System,
+    /// Systems by the number of jumps needed to reach the target. The first entry only
+    /// contains the target itself.
+    pub by_jumps: Vec<Vec<&'a types::System>>,
+}
+
+impl<'a> Approaches<'a> {
+    /// Searches backwards from `target` along incoming connections for up to `jumps`
+    /// jumps. Returns `None` if the target is not part of the universe.
+    pub fn new(
+        universe: &'a dyn types::Navigatable,
+        target: &types::SystemId,
+        jumps: usize,
+    ) -> Option<Self> {
+        let target = universe.get_system(target)?;
+        let mut incoming: HashMap<types::SystemId, Vec<types::SystemId>> = HashMap::new();
+        for system in universe.all_systems() {
+            for to in universe.neighbors(&system.id) {
+                incoming.entry(to).or_default().push(system.id);
+            }
+        }
+
+        let mut seen = HashSet::from([target.id]);
+        let mut by_jumps = vec![vec![target]];
+        while by_jumps.len() <= jumps {
+            let mut ring = by_jumps
+                .last()
+                .unwrap()
+                .iter()
+                .flat_map(|system| incoming.get(&system.id).into_iter().flatten())
+                .filter(|id| seen.insert(**id))
+                .filter_map(|id| universe.get_system(id))
+                .collect::<Vec<_>>();
+            if ring.is_empty() {
+                break;
+            }
+            ring.sort_by_key(|system| system.id);
+            by_jumps.push(ring);
+        }
+        Some(Self { target, by_jumps })
+    }
+
+    /// Number of systems the target can be reached from, excluding the target.
+    pub fn len(&self) -> usize {
+        self.by_jumps.iter().skip(1).map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Only the systems of the given security class, still grouped by jumps.
+    pub fn with_security(&self, class: types::SecurityClass) -> Vec<Vec<&'a types::System>> {
+        self.by_jumps
+            .iter()
+            .map(|ring| {
+                ring.iter()
+                    .filter(|system| types::SecurityClass::from(system.security) == class)
+                    .copied()
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Jump counts of the autopilot route and the shortest unrestricted route between
+/// two systems, see `compare_autopilot`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutopilotComparison {
+    /// Jumps of the autopilot route, `None` if there is no route staying in highsec.
+    pub autopilot: Option<usize>,
+    /// Jumps of the shortest route, `None` if the systems are not connected at all.
+    pub shortest: Option<usize>,
+}
+
+impl AutopilotComparison {
+    /// Number of jumps saved by taking the shortest route instead of the autopilot
+    /// route, `None` if either route does not exist.
+    pub fn saved_jumps(&self) -> Option<usize> {
+        Some(self.autopilot? - self.shortest?)
+    }
+}
+
+/// Compares the autopilot route from `from` to `to` with the shortest route through
+/// any security class, e.g. to decide whether hauling through lowsec is worth it.
+pub fn compare_autopilot<'a>(
+    universe: &'a dyn types::Navigatable,
+    from: &'a types::System,
+    to: &'a types::System,
+) -> AutopilotComparison {
+    let jumps = |builder: PathBuilder<'a>| {
+        builder
+            .waypoint(from)
+            .waypoint(to)
+            .build()
+            .map(|path| path.jumps())
+    };
+    AutopilotComparison {
+        autopilot: jumps(PathBuilder::autopilot(universe)),
+        shortest: jumps(PathBuilder::new(universe)),
+    }
+}
+
+#[cfg(test)]
+mod routing_tests {
+    use crate::builder::{ExtendedUniverseBuilder, UniverseBuilder};
+    use crate::types::Navigatable;
+
+    use super::*;
+    use crate::fixtures::system;
+
+    fn gate(from: u32, to: u32) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+        }
+    }
+
+    /// A line of systems 1 - 2 - 3 - 4 - 5 with the given security ratings.
+    fn line(securities: &[f32]) -> types::Universe {
+        let mut builder = UniverseBuilder::default();
+        for (i, security) in securities.iter().enumerate() {
+            let id = i as u32 + 1;
+            builder = builder.system(system(id).with_security(*security));
+            if i > 0 {
+                builder = builder
+                    .connection(gate(id - 1, id))
+                    .connection(gate(id, id - 1));
+            }
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn test_route_revalidate() {
+        let universe = line(&[0.9, 0.8, 0.7]);
+        let path = PathBuilder::new(&universe)
+            .waypoint(universe.get_system(&1.into()).unwrap())
+            .waypoint(universe.get_system(&3.into()).unwrap())
+            .build()
+            .unwrap();
+        let route = path.to_route();
+        assert!(route.is_current(&universe));
+        assert!(route.revalidate(&universe).is_empty());
+
+        let shorter = line(&[0.9, 0.8]);
+        assert!(!route.is_current(&shorter));
+        assert_eq!(
+            vec![StaleHop::MissingSystem(3.into())],
+            route.revalidate(&shorter)
+        );
+    }
+
+    #[test]
+    fn test_route_diff() {
+        // 1 - 2 - 3 - 4 by stargates, a wormhole from 2 to 4 appears
+        let universe = line(&[0.9, 0.8, 0.7, 0.6]);
+        let route = |universe: &dyn types::Navigatable| {
+            PathBuilder::new(universe)
+                .waypoint(universe.get_system(&1.into()).unwrap())
+                .waypoint(universe.get_system(&4.into()).unwrap())
+                .build()
+                .unwrap()
+                .to_route()
+        };
+        let before = route(&universe);
+        let extended = ExtendedUniverseBuilder::new(&universe)
+            .connection(types::Connection {
+                from: 2.into(),
+                to: 4.into(),
+                r#type: types::ConnectionType::Ansiblex,
+            })
+            .build();
+        let after = route(&extended);
+
+        let diff = before.diff(&after);
+        assert!(!diff.is_empty());
+        assert_eq!(1, diff.added().count());
+        assert_eq!(2, diff.removed().count());
+        assert_eq!("= 1 hop, + 2 > 4, - 2 > 3, - 3 > 4", diff.to_string());
+        assert!(before.diff(&before).is_empty());
+        assert_eq!("= 3 hops", before.diff(&before).to_string());
+    }
+
+    #[test]
+    fn test_legs() {
+        let universe = line(&[0.9, 0.8, 0.4, 0.3, -0.1, 0.6]);
+        let path = PathBuilder::new(&universe)
+            .waypoint(universe.get_system(&1.into()).unwrap())
+            .waypoint(universe.get_system(&6.into()).unwrap())
+            .build()
+            .unwrap();
+        let legs = path.legs(LegSplit::Security);
+        assert_eq!(
+            vec!["1j highsec", "2j lowsec", "1j nullsec", "1j highsec"],
+            legs.iter().map(|leg| leg.to_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(path.jumps(), legs.iter().map(Leg::jumps).sum::<usize>());
+        assert!(legs[0].entry.is_none());
+        assert_eq!(types::SystemId(3), legs[0].exit.unwrap().id);
+        assert_eq!(types::SystemId(2), legs[1].entry.unwrap().id);
+        assert_eq!(1, path.legs(LegSplit::Region).len());
+    }
+
+    #[test]
+    fn test_autopilot() {
+        // 1 - 2 - 3 - 4 through lowsec and 1 - 5 - 6 - 7 - 4 through highsec
+        let universe = UniverseBuilder::default()
+            .system(system(1).with_security(0.9))
+            .system(system(2).with_security(0.5))
+            .system(system(3).with_security(0.44))
+            .system(system(4).with_security(0.8))
+            .system(system(5).with_security(0.7))
+            .system(system(6).with_security(0.6))
+            .system(system(7).with_security(0.5))
+            .connection(gate(1, 2))
+            .connection(gate(2, 3))
+            .connection(gate(3, 4))
+            .connection(gate(1, 5))
+            .connection(gate(5, 6))
+            .connection(gate(6, 7))
+            .connection(gate(7, 4))
+            .build();
+        let from = universe.get_system(&1.into()).unwrap();
+        let to = universe.get_system(&4.into()).unwrap();
+        let path = PathBuilder::autopilot(&universe)
+            .waypoint(from)
+            .waypoint(to)
+            .build()
+            .unwrap();
+        assert!(path.systems().all(|s| s.security.0 >= 0.5));
+
+        let comparison = compare_autopilot(&universe, from, to);
+        assert_eq!(Some(4), comparison.autopilot);
+        assert_eq!(Some(3), comparison.shortest);
+        assert_eq!(Some(1), comparison.saved_jumps());
+
+        // two routes of two jumps, the one through the lower id wins no matter the
+        // order of the connections
+        for order in [[30000003, 30000002], [30000002, 30000003]] {
+            let mut builder = UniverseBuilder::default();
+            for id in [30000001, 30000002, 30000003, 30000004] {
+                builder = builder.system(system(id).with_security(0.9));
+            }
+            for id in order {
+                builder = builder
+                    .connection(gate(30000001, id))
+                    .connection(gate(id, 30000004));
+            }
+            let universe = builder.build();
+            let path = PathBuilder::autopilot(&universe)
+                .waypoint(universe.get_system(&30000001.into()).unwrap())
+                .waypoint(universe.get_system(&30000004.into()).unwrap())
+                .build()
+                .unwrap();
+            assert_eq!(
+                vec![30000001, 30000002, 30000004],
+                path.systems().map(|s| s.id.0).collect::<Vec<_>>()
+            );
+        }
+
+        // the costs of long routes don't overflow
+        let universe = line(&[0.9; 300]);
+        let path = PathBuilder::autopilot(&universe)
+            .waypoint(universe.get_system(&1.into()).unwrap())
+            .waypoint(universe.get_system(&300.into()).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(299, path.jumps());
+    }
+
+    #[test]
+    fn test_route_fuel() {
+        let universe = line(&[0.9, 0.8, 0.7, 0.6]);
+        let extended = ExtendedUniverseBuilder::new(&universe)
+            .connection(types::Connection {
+                from: 1.into(),
+                to: 3.into(),
+                r#type: types::ConnectionType::Ansiblex,
+            })
+            .build();
+        let route = PathBuilder::new(&extended)
+            .waypoint(universe.get_system(&1.into()).unwrap())
+            .waypoint(universe.get_system(&4.into()).unwrap())
+            .build()
+            .unwrap()
+            .to_route();
+        assert_eq!(
+            fuel::FuelCost::liquid_ozone(350),
+            route.fuel(&extended, 100_000_000)
+        );
+    }
+
+    #[test]
+    fn test_lightyears() {
+        let ly = |ly: f64| types::Meters::from(types::Lightyears(ly)).0;
+        // 1 - 2 - 3 - 4 along the x axis and a shortcut 1 - 5 - 4 far away
+        let universe = UniverseBuilder::default()
+            .system(system(1).with_security(-0.5).at(ly(0.0), 0.0, 0.0))
+            .system(system(2).with_security(-0.5).at(ly(1.0), 0.0, 0.0))
+            .system(system(3).with_security(-0.5).at(ly(1.5), 0.0, 0.0))
+            .system(system(4).with_security(-0.5).at(ly(2.0), 0.0, 0.0))
+            .system(system(5).with_security(-0.5).at(ly(5.0), 0.0, 0.0))
+            .connection(gate(1, 2))
+            .connection(gate(2, 3))
+            .connection(gate(3, 4))
+            .connection(gate(1, 5))
+            .connection(gate(5, 4))
+            .build();
+        let route = |builder: PathBuilder| {
+            let path = builder
+                .waypoint(universe.get_system(&1.into()).unwrap())
+                .waypoint(universe.get_system(&4.into()).unwrap())
+                .build()?;
+            let hops = path.hops().iter().map(|hop| hop.lightyears.0).collect_vec();
+            Some((path.systems().map(|s| s.id.0).collect_vec(), hops))
+        };
+
+        let (systems, hops) = route(PathBuilder::new(&universe)).unwrap();
+        assert_eq!(vec![1, 5, 4], systems);
+        assert!((hops.iter().sum::<f64>() - 8.0).abs() < 1e-9);
+
+        let (systems, hops) =
+            route(PathBuilder::new(&universe).prefer(Preference::Lightyears)).unwrap();
+        assert_eq!(vec![1, 2, 3, 4], systems);
+        assert!((hops[1] - 0.5).abs() < 1e-9);
+
+        let (systems, _) =
+            route(PathBuilder::new(&universe).max_hop_lightyears(types::Lightyears(2.0))).unwrap();
+        assert_eq!(vec![1, 2, 3, 4], systems);
+        assert!(
+            route(PathBuilder::new(&universe).max_hop_lightyears(types::Lightyears(0.7))).is_none()
+        );
+    }
+
+    #[test]
+    fn test_approaches() {
+        let universe = line(&[0.9, 0.4, -0.1, -0.3, -0.5]);
+        let approaches = Approaches::new(&universe, &1.into(), 3).unwrap();
+        assert_eq!(3, approaches.len());
+        assert_eq!(types::SystemId(4), approaches.by_jumps[3][0].id);
+        let nullsec = approaches.with_security(types::SecurityClass::Nullsec);
+        assert_eq!(
+            vec![0, 0, 1, 1],
+            nullsec.iter().map(Vec::len).collect::<Vec<_>>()
+        );
+        assert!(Approaches::new(&universe, &9.into(), 3).is_none());
+    }
+
+    #[test]
+    fn test_patrol() {
+        // a star around 1, patrolling the leaves has to return to 1 in between
+        let mut builder = UniverseBuilder::default().system(system(1).with_security(-0.5));
+        for id in 2..=4 {
+            builder = builder
+                .system(system(id).with_security(-0.5))
+                .connection(gate(1, id))
+                .connection(gate(id, 1));
+        }
+        let universe = builder.system(system(5).with_security(-0.5)).build();
+        let get = |id: u32| universe.get_system(&id.into()).unwrap();
+
+        let path = patrol(&universe, &[get(2), get(3), get(4)]).unwrap();
+        assert_eq!(6, path.jumps());
+        assert_eq!(Some(get(2)), path.from());
+        assert_eq!(Some(get(2)), path.to());
+        assert!(patrol(&universe, &[get(2), get(5)]).is_none());
+        assert!(patrol(&universe, &[]).is_none());
+    }
+
+    #[test]
+    fn test_size_restrictions() {
+        // 1 - 2 - 3 by stargates, 1 - 4 - 3 a shortcut through a shattered system
+        let universe = UniverseBuilder::default()
+            .system(system(1).with_security(-0.5))
+            .system(system(2).with_security(-0.5))
+            .system(system(3).with_security(-0.5))
+            .system(system(4).with_security(-0.5))
+            .system(system(5).with_security(-0.5))
+            .connection(gate(1, 2))
+            .connection(gate(2, 5))
+            .connection(gate(5, 3))
+            .connection(gate(1, 4))
+            .connection(gate(4, 3))
+            .build();
+        let restrictions = rules::SizeRestrictions::default()
+            .shattered(4.into())
+            .connection(5.into(), 3.into(), rules::ShipSize::Small);
+        let jumps = |size| {
+            PathBuilder::new(&universe)
+                .waypoint(universe.get_system(&1.into()).unwrap())
+                .waypoint(universe.get_system(&3.into()).unwrap())
+                .ship(rules::ShipProfile::new(size))
+                .size_restrictions(restrictions.clone())
+                .build()
+                .map(|path| path.jumps())
+        };
+
+        assert_eq!(Some(2), jumps(rules::ShipSize::Small));
+        assert_eq!(Some(2), jumps(rules::ShipSize::Freighter));
+        assert_eq!(None, jumps(rules::ShipSize::Capital));
+    }
+
+    #[test]
+    fn test_disable_layer() {
+        let universe = line(&[0.9, 0.8, 0.7, 0.6]);
+        let extended = ExtendedUniverseBuilder::new(&universe)
+            .connection(types::Connection {
+                from: 1.into(),
+                to: 4.into(),
+                r#type: types::ConnectionType::Ansiblex,
+            })
+            .build();
+        let jumps = |builder: PathBuilder| {
+            builder
+                .waypoint(universe.get_system(&1.into()).unwrap())
+                .waypoint(universe.get_system(&4.into()).unwrap())
+                .build()
+                .unwrap()
+                .jumps()
+        };
+        assert_eq!(1, jumps(PathBuilder::new(&extended)));
+        assert_eq!(
+            3,
+            jumps(PathBuilder::new(&extended).disable(Layer::Ansiblex))
+        );
+        assert_eq!(
+            1,
+            jumps(
+                PathBuilder::new(&extended)
+                    .disable(Layer::Ansiblex)
+                    .enable(Layer::Ansiblex)
+            )
+        );
+    }
+
+    #[test]
+    fn test_fastest() {
+        let universe = line(&[0.9, 0.8, 0.7, 0.6, 0.5]);
+        let extended = ExtendedUniverseBuilder::new(&universe)
+            .connection(types::Connection {
+                from: 1.into(),
+                to: 5.into(),
+                r#type: types::ConnectionType::Bridge(types::BridgeType::Titan(
+                    types::JumpdriveSkills::new(5, 5),
+                )),
+            })
+            .build();
+        let path = |preference| {
+            PathBuilder::new(&extended)
+                .prefer(preference)
+                .waypoint(universe.get_system(&1.into()).unwrap())
+                .waypoint(universe.get_system(&5.into()).unwrap())
+                .build()
+                .unwrap()
+        };
+        let times = TravelTimes::default();
+        let shortest = path(Preference::Shortest);
+        assert_eq!(1, shortest.jumps());
+        assert_eq!(Duration::seconds(130), shortest.eta(&times));
+
+        let fastest = path(Preference::Fastest(TravelTimes {
+            bridge_wait: Duration::minutes(10),
+            ..times
+        }));
+        assert_eq!(4, fastest.jumps());
+        assert_eq!(Duration::minutes(4), fastest.eta(&times));
+    }
+
+    #[test]
+    fn test_depart_at() {
+        use chrono::TimeZone;
+
+        let universe = line(&[0.9, 0.8, 0.7, 0.6, 0.5]);
+        let wormhole = |expires| types::Connection {
+            from: 1.into(),
+            to: 5.into(),
+            r#type: types::ConnectionType::Wormhole(types::Wormhole {
+                expires,
+                remaining_hours: 0,
+                signature: "ABC-123".to_string(),
+                exit_signature: None,
+                max_ship_size: types::WormholeMaxShipSize::Unknown,
+                shattered: None,
+            }),
+        };
+        let departure = Utc.with_ymd_and_hms(2024, 5, 4, 10, 58, 0).unwrap();
+        let extended = ExtendedUniverseBuilder::new(&universe)
+            .connection(wormhole(departure - Duration::hours(1)))
+            .build();
+        let path = |universe| {
+            PathBuilder::new(universe)
+                .depart_at(departure, TravelTimes::default())
+                .waypoint(universe.get_system(&1.into()).unwrap())
+                .waypoint(universe.get_system(&5.into()).unwrap())
+                .build()
+                .unwrap()
+        };
+        // the wormhole has collapsed, two of the four gates fit before downtime
+        let gates = path(&extended);
+        assert_eq!(4, gates.jumps());
+        assert_eq!(Some(2), gates.downtime_split());
+
+        let extended = ExtendedUniverseBuilder::new(&universe)
+            .connection(wormhole(departure + Duration::hours(16)))
+            .build();
+        let wormhole = path(&extended);
+        assert_eq!(1, wormhole.jumps());
+        assert_eq!(None, wormhole.downtime_split());
+        assert!(wormhole.warnings().is_empty());
+
+        let late = PathBuilder::new(&extended)
+            .depart_at(departure + Duration::seconds(90), TravelTimes::default())
+            .waypoint(universe.get_system(&1.into()).unwrap())
+            .waypoint(universe.get_system(&5.into()).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(Some(0), late.downtime_split());
+        assert_eq!(
+            vec!["wormhole to 5 at hop 1 is jumped after downtime"],
+            late.warnings()
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_warnings() {
+        let universe = line(&[0.9, 0.5, 0.4, -0.1, -0.5, 0.2, -0.3]);
+        let path = PathBuilder::new(&universe)
+            .waypoint(universe.get_system(&1.into()).unwrap())
+            .waypoint(universe.get_system(&7.into()).unwrap())
+            .build()
+            .unwrap();
+        let warnings = path
+            .warnings()
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "first lowsec entry at hop 2 (3)",
+                "first nullsec entry at hop 3 (4)",
+                "passes 2 consecutive nullsec systems from hop 3 (4)",
+            ],
+            warnings
+        );
+    }
+
+    #[test]
+    fn test_hops_with_annotations() {
+        let universe = line(&[0.9, 0.8, 0.7]);
+        let extended = ExtendedUniverseBuilder::new(&universe)
+            .annotate(
+                2.into(),
+                3.into(),
+                types::Annotation::Tag("camped".to_string()),
+            )
+            .build();
+        let path = PathBuilder::new(&extended)
+            .waypoint(extended.get_system(&1.into()).unwrap())
+            .waypoint(extended.get_system(&3.into()).unwrap())
+            .build()
+            .unwrap();
+
+        let hops = path.hops();
+        assert_eq!(2, hops.len());
+        assert_eq!(types::SystemId(1), hops[0].from.id);
+        assert!(hops[0].annotations.is_empty());
+        assert_eq!(types::SystemId(3), hops[1].to.id);
+        assert_eq!(
+            &[types::Annotation::Tag("camped".to_string())],
+            hops[1].annotations
+        );
     }
 }
 
@@ -470,7 +2243,9 @@ mod tests {
                     expires: Utc::now(),
                     remaining_hours: 0,
                     signature: "X".to_string(),
+                    exit_signature: None,
                     max_ship_size: types::WormholeMaxShipSize::Unknown,
+                    shattered: None,
                 }),
             }]
             .into();
@@ -0,0 +1,154 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Keyed stores for application data, such as intel notes or home system markers,
+//! that can be attached to an `ExtendedUniverse`.
+
+use std::collections::HashMap;
+use std::collections::hash_map;
+use std::hash::Hash;
+
+use crate::types;
+
+/// A mutable store mapping keys, usually `SystemId`s, to application data.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::{ExtendedUniverse, SystemId};
+///
+/// struct Note(String);
+///
+/// let universe = UniverseBuilder::default().build();
+/// let mut extended = ExtendedUniverse::new(&universe, Default::default());
+/// extended
+///     .overlay_mut::<Note>()
+///     .insert(30000142.into(), Note("staging".to_string()));
+///
+/// let notes = extended.overlay::<Note>().unwrap();
+/// assert_eq!("staging", notes.get(&SystemId(30000142)).unwrap().0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Overlay<K, T> {
+    entries: HashMap<K, T>,
+}
+
+impl<K, T> Default for Overlay<K, T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, T> Overlay<K, T> {
+    pub fn insert(&mut self, key: K, value: T) -> Option<T> {
+        self.entries.insert(key, value)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.entries.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.entries.get_mut(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        self.entries.remove(key)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> hash_map::Iter<'_, K, T> {
+        self.entries.iter()
+    }
+}
+
+impl<T> Overlay<types::SystemId, T> {
+    /// Returns the entries for the given systems in order, skipping systems without an
+    /// entry. Useful to look up data along a path, e.g. `overlay.along(path.systems())`.
+    pub fn along<'a, 's>(
+        &'a self,
+        systems: impl IntoIterator<Item = &'s types::System>,
+    ) -> Vec<(&'s types::System, &'a T)> {
+        systems
+            .into_iter()
+            .filter_map(|system| Some((system, self.entries.get(&system.id)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_insert_remove() {
+        let mut overlay = Overlay::default();
+        assert_eq!(None, overlay.insert(types::SystemId(1), "home"));
+        // a later value shadows the earlier one
+        assert_eq!(Some("home"), overlay.insert(types::SystemId(1), "staging"));
+        assert_eq!(Some(&"staging"), overlay.get(&types::SystemId(1)));
+        assert_eq!(1, overlay.len());
+
+        // removing takes the key out entirely, nothing earlier shows through
+        assert_eq!(Some("staging"), overlay.remove(&types::SystemId(1)));
+        assert_eq!(None, overlay.get(&types::SystemId(1)));
+        assert!(!overlay.contains(&types::SystemId(1)));
+        assert_eq!(None, overlay.remove(&types::SystemId(1)));
+        assert!(overlay.is_empty());
+    }
+
+    #[test]
+    fn test_extended_universe() {
+        struct Note(&'static str);
+        struct Home;
+
+        let universe = UniverseBuilder::default()
+            .system(system(1))
+            .system(system(2))
+            .system(system(3))
+            .build();
+        let mut extended = types::ExtendedUniverse::new(&universe, Default::default());
+        assert!(extended.overlay::<Note>().is_none());
+        extended
+            .overlay_mut::<Note>()
+            .insert(1.into(), Note("cyno"));
+        extended
+            .overlay_mut::<Note>()
+            .insert(3.into(), Note("bubbled"));
+        extended.overlay_mut::<Home>().insert(1.into(), Home);
+
+        // overlays of different types don't shadow each other
+        extended.overlay_mut::<Home>().remove(&1.into());
+        let notes = extended.overlay::<Note>().unwrap();
+        assert_eq!("cyno", notes.get(&1.into()).unwrap().0);
+        assert!(extended.overlay::<Home>().unwrap().is_empty());
+
+        let systems = [3, 2, 1].map(|id| extended.get_system(&id.into()).unwrap());
+        assert_eq!(
+            vec![(3, "bubbled"), (1, "cyno")],
+            notes
+                .along(systems)
+                .into_iter()
+                .map(|(system, note)| (system.id.0, note.0))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,225 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Placement of infrastructure, e.g. finding the system for a staging station that
+//! keeps every ratting constellation of an alliance within as few jumps as possible.
+//!
+//! Candidates are ranked by the number of jumps to the farthest served system, ties
+//! are broken by the total number of jumps to all served systems. Distances follow
+//! the connections of the universe and skip systems avoided by default.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::navigation::SystemFilter;
+use crate::types;
+
+/// A candidate system and its distances to the served systems.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement<'a> {
+    pub system: &'a types::System,
+    /// Jumps to the farthest served system.
+    pub max_jumps: usize,
+    /// Jumps to all served systems combined.
+    pub total_jumps: usize,
+}
+
+/// Suggests systems to place infrastructure in, serving a set of systems.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::placement::PlacementFinder;
+/// use neweden::{Connection, ConnectionType, Navigatable, StargateType, System};
+///
+/// let system = |id: u32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: (-0.4).into(),
+///     region_name: "Delve".to_string(),
+///     faction_id: None,
+/// };
+/// let gate = |from: u32, to: u32| Connection {
+///     from: from.into(),
+///     to: to.into(),
+///     r#type: ConnectionType::Stargate(StargateType::Local),
+/// };
+/// // a chain 1 - 2 - 3
+/// let universe = UniverseBuilder::default()
+///     .system(system(1))
+///     .system(system(2))
+///     .system(system(3))
+///     .connection(gate(1, 2))
+///     .connection(gate(2, 1))
+///     .connection(gate(2, 3))
+///     .connection(gate(3, 2))
+///     .build();
+/// let best = PlacementFinder::new(&universe)
+///     .serve(universe.get_system(&1.into()).unwrap())
+///     .serve(universe.get_system(&3.into()).unwrap())
+///     .best()
+///     .unwrap();
+/// assert_eq!((2.into(), 1), (best.system.id, best.max_jumps));
+/// ```
+pub struct PlacementFinder<'a> {
+    universe: &'a dyn types::Navigatable,
+    served: Vec<&'a types::System>,
+    filters: Vec<SystemFilter<'a>>,
+}
+
+impl<'a> PlacementFinder<'a> {
+    pub fn new(universe: &'a dyn types::Navigatable) -> Self {
+        Self {
+            universe,
+            served: Vec::new(),
+            filters: Vec::new(),
+        }
+    }
+
+    /// A system the infrastructure should be close to.
+    pub fn serve(mut self, system: &'a types::System) -> Self {
+        self.served.push(system);
+        self
+    }
+
+    pub fn serve_many(mut self, systems: impl IntoIterator<Item = &'a types::System>) -> Self {
+        self.served.extend(systems);
+        self
+    }
+
+    /// Only consider systems matching the predicate, e.g. systems with a station or
+    /// sovereignty of the alliance. Multiple filters must all match.
+    pub fn candidate_where(mut self, predicate: impl Fn(&types::System) -> bool + 'a) -> Self {
+        self.filters.push(Box::new(predicate));
+        self
+    }
+
+    /// Jumps from every system reachable from `from`.
+    fn distances_from(&self, from: types::SystemId) -> HashMap<types::SystemId, usize> {
+        let mut distances = HashMap::from([(from, 0)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(id) = queue.pop_front() {
+            let distance = distances[&id];
+            for conn in self.universe.get_connections(&id).unwrap_or_default() {
+                if self.universe.avoided_by_default(&conn.to) || distances.contains_key(&conn.to) {
+                    continue;
+                }
+                distances.insert(conn.to, distance + 1);
+                queue.push_back(conn.to);
+            }
+        }
+        distances
+    }
+
+    /// All candidates that can reach every served system, best first. Returns no
+    /// candidates if no systems are served.
+    pub fn build(self) -> Vec<Placement<'a>> {
+        if self.served.is_empty() {
+            return Vec::new();
+        }
+        // connections are usually symmetric, so the jumps from a served system to a
+        // candidate are the jumps from the candidate back
+        let distances = self
+            .served
+            .iter()
+            .map(|system| self.distances_from(system.id))
+            .collect::<Vec<_>>();
+
+        let mut placements = Vec::new();
+        for id in distances[0].keys() {
+            let Some(system) = self.universe.get_system(id) else {
+                continue;
+            };
+            if !self.filters.iter().all(|filter| filter(system)) {
+                continue;
+            }
+            let Some(jumps) = distances
+                .iter()
+                .map(|d| d.get(id).copied())
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            placements.push(Placement {
+                system,
+                max_jumps: jumps.iter().copied().max().unwrap_or_default(),
+                total_jumps: jumps.iter().sum(),
+            });
+        }
+        placements.sort_by_key(|p| (p.max_jumps, p.total_jumps, p.system.id));
+        placements
+    }
+
+    /// The best candidate, `None` if no candidate can reach all served systems.
+    pub fn best(self) -> Option<Placement<'a>> {
+        self.build().into_iter().next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::rules;
+    use crate::types::Navigatable;
+
+    fn gate(from: u32, to: u32) -> [types::Connection; 2] {
+        let r#type = types::ConnectionType::Stargate(types::StargateType::Local);
+        [
+            types::Connection {
+                from: from.into(),
+                to: to.into(),
+                r#type: r#type.clone(),
+            },
+            types::Connection {
+                from: to.into(),
+                to: from.into(),
+                r#type,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_placement() {
+        // a star around 1 with arms 1 - 2 - 3, 1 - 4 - 5 and 1 - 6, 7 is disconnected
+        let builder = (1..=7).fold(UniverseBuilder::default(), |builder, id| {
+            builder.system(system(id).with_security(if id == 1 { 0.5 } else { -0.4 }))
+        });
+        let universe = [gate(1, 2), gate(2, 3), gate(1, 4), gate(4, 5), gate(1, 6)]
+            .concat()
+            .into_iter()
+            .fold(builder, |builder, conn| builder.connection(conn))
+            .build();
+        let get = |id: u32| universe.get_system(&id.into()).unwrap();
+
+        let placements = PlacementFinder::new(&universe)
+            .serve_many([get(3), get(5), get(6)])
+            .build();
+        assert_eq!(7 - 1, placements.len());
+        assert_eq!(get(1), placements[0].system);
+        assert_eq!((2, 5), (placements[0].max_jumps, placements[0].total_jumps));
+
+        // the hub is highsec, the best sov system is one jump further out
+        let best = PlacementFinder::new(&universe)
+            .serve_many([get(3), get(5), get(6)])
+            .candidate_where(rules::is_sov_nullsec)
+            .candidate_where(|system| system.id != 4.into())
+            .best()
+            .unwrap();
+        assert_eq!(
+            (get(2), 3, 6),
+            (best.system, best.max_jumps, best.total_jumps)
+        );
+
+        assert!(
+            PlacementFinder::new(&universe)
+                .serve_many([get(3), get(7)])
+                .best()
+                .is_none()
+        );
+        assert!(PlacementFinder::new(&universe).build().is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Range rings around a staging system, e.g. for titan bridges or jump drives.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::format::csv_field;
+use crate::types;
+
+/// A group of in-range systems sharing the same region and security class.
+#[derive(Debug)]
+pub struct RangeGroup<'a> {
+    pub region_name: String,
+    pub security: types::SecurityClass,
+    pub systems: Vec<(&'a types::System, types::Lightyears)>,
+}
+
+/// All systems within range of an origin, grouped by region and security class.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::range::RangeRings;
+/// use neweden::{Lightyears, System};
+///
+/// let system = |id: u32, name: &str, x: f64| System {
+///     id: id.into(),
+///     name: name.to_string(),
+///     coordinate: (x, 0.0, 0.0).into(),
+///     security: (-0.3).into(),
+///     region_name: "Delve".to_string(),
+///     faction_id: None,
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(30004759, "1DQ1-A", 0.0))
+///     .system(system(30004760, "8QT-H4", 1e16))
+///     .build();
+/// let rings = RangeRings::new(&universe, &30004759.into(), Lightyears(6.0)).unwrap();
+/// assert!(rings.to_csv().contains("8QT-H4"));
+/// ```
+#[derive(Debug)]
+pub struct RangeRings<'a> {
+    pub origin: &'a types::System,
+    pub range: types::Lightyears,
+    pub groups: Vec<RangeGroup<'a>>,
+}
+
+impl<'a> RangeRings<'a> {
+    /// Collects all systems within `range` of `origin`. Returns `None` if the origin
+    /// is not part of the universe.
+    pub fn new(
+        universe: &'a dyn types::Navigatable,
+        origin: &types::SystemId,
+        range: types::Lightyears,
+    ) -> Option<Self> {
+        let origin = universe.get_system(origin)?;
+        let mut groups: BTreeMap<(String, types::SecurityClass), Vec<_>> = BTreeMap::new();
+        for system in universe.get_systems_by_range(&origin.id, range.into())? {
+            if system.id == origin.id {
+                continue;
+            }
+            let key = (system.region_name.clone(), system.security.into());
+            groups
+                .entry(key)
+                .or_default()
+                .push((system, origin.distance(system).into()));
+        }
+
+        let groups = groups
+            .into_iter()
+            .map(|((region_name, security), mut systems)| {
+                systems
+                    .sort_by(|a: &(&types::System, types::Lightyears), b| a.1.0.total_cmp(&b.1.0));
+                RangeGroup {
+                    region_name,
+                    security,
+                    systems,
+                }
+            })
+            .collect();
+
+        Some(Self {
+            origin,
+            range,
+            groups,
+        })
+    }
+
+    /// Exports the rings as CSV with one line per system.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("region,security,system_id,system,distance_ly\n");
+        for group in &self.groups {
+            for (system, ly) in &group.systems {
+                writeln!(
+                    out,
+                    "{},{},{},{},{:.3}",
+                    csv_field(&group.region_name),
+                    group.security,
+                    system.id.0,
+                    csv_field(&system.name),
+                    ly.0
+                )
+                .unwrap();
+            }
+        }
+        out
+    }
+
+    /// Exports the rings as a JSON object.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        let system = |system: &'a types::System, distance_ly| json::System {
+            id: system.id.0,
+            name: &system.name,
+            distance_ly,
+        };
+        let rings = json::Rings {
+            origin: system(self.origin, None),
+            range_ly: self.range.0,
+            groups: self
+                .groups
+                .iter()
+                .map(|group| json::Group {
+                    region: &group.region_name,
+                    security: group.security.to_string(),
+                    systems: group
+                        .systems
+                        .iter()
+                        .map(|(s, ly)| system(s, Some(ly.0)))
+                        .collect(),
+                })
+                .collect(),
+        };
+        serde_json::to_string(&rings).expect("range rings are valid JSON")
+    }
+}
+
+/// The shape of `RangeRings::to_json()`.
+#[cfg(feature = "json")]
+mod json {
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    pub(super) struct Rings<'a> {
+        pub(super) origin: System<'a>,
+        pub(super) range_ly: f64,
+        pub(super) groups: Vec<Group<'a>>,
+    }
+
+    #[derive(Serialize)]
+    pub(super) struct Group<'a> {
+        pub(super) region: &'a str,
+        pub(super) security: String,
+        pub(super) systems: Vec<System<'a>>,
+    }
+
+    #[derive(Serialize)]
+    pub(super) struct System<'a> {
+        pub(super) id: u32,
+        pub(super) name: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) distance_ly: Option<f64>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+
+    #[test]
+    fn test_range_rings() {
+        let ly = |ly: f64| types::Meters::from(types::Lightyears(ly)).0;
+        let universe = UniverseBuilder::default()
+            .system(
+                system(30000001)
+                    .named("Origin")
+                    .in_region("A")
+                    .with_security(-0.5),
+            )
+            .system(
+                system(30000002)
+                    .named("Near")
+                    .in_region("B")
+                    .with_security(-0.5)
+                    .at(ly(2.0), 0.0, 0.0),
+            )
+            .system(
+                system(30000003)
+                    .named("Low")
+                    .in_region("B")
+                    .with_security(0.3)
+                    .at(ly(3.0), 0.0, 0.0),
+            )
+            .system(
+                system(30000004)
+                    .named("High")
+                    .in_region("B")
+                    .with_security(0.9)
+                    .at(ly(3.0), 0.0, 0.0),
+            )
+            .system(
+                system(30000005)
+                    .named("Far")
+                    .in_region("B")
+                    .with_security(-0.5)
+                    .at(ly(9.0), 0.0, 0.0),
+            )
+            .build();
+        let rings = RangeRings::new(&universe, &30000001.into(), types::Lightyears(5.0)).unwrap();
+
+        assert_eq!(2, rings.groups.len());
+        assert_eq!(types::SecurityClass::Lowsec, rings.groups[0].security);
+        assert_eq!("Near", rings.groups[1].systems[0].0.name);
+        assert_eq!(
+            "region,security,system_id,system,distance_ly\n\
+             B,lowsec,30000003,Low,3.000\n\
+             B,nullsec,30000002,Near,2.000\n",
+            rings.to_csv()
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_range_rings_json() {
+        let ly = |ly: f64| types::Meters::from(types::Lightyears(ly)).0;
+        let universe = UniverseBuilder::default()
+            .system(
+                system(30000001)
+                    .named("Origin")
+                    .in_region("A")
+                    .with_security(-0.5),
+            )
+            .system(
+                system(30000002)
+                    .named("Ne\"ar")
+                    .in_region("B")
+                    .with_security(-0.5)
+                    .at(ly(2.0), 0.0, 0.0),
+            )
+            .build();
+        let rings = RangeRings::new(&universe, &30000001.into(), types::Lightyears(5.0)).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&rings.to_json()).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "origin": {"id": 30000001, "name": "Origin"},
+                "range_ly": 5.0,
+                "groups": [{
+                    "region": "B",
+                    "security": "nullsec",
+                    "systems": [{"id": 30000002, "name": "Ne\"ar", "distance_ly": 2.0}],
+                }],
+            }),
+            json
+        );
+    }
+}
@@ -0,0 +1,171 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Former names of systems, so lookups by a name from before a rename still resolve,
+//! e.g. names in old killmails, bookmarks or configuration files.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+
+use crate::names;
+use crate::types;
+
+/// A system that was known under `old_name` before the SDE released on `since`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    pub id: types::SystemId,
+    pub old_name: String,
+    pub since: NaiveDate,
+}
+
+/// A system found by name. `renamed` is set if the name is outdated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameLookup<'a> {
+    pub system: &'a types::System,
+    pub renamed: Option<&'a Rename>,
+}
+
+impl NameLookup<'_> {
+    /// A note for the user if the system was found by an old name.
+    pub fn deprecation(&self) -> Option<String> {
+        self.renamed.map(|rename| {
+            format!(
+                "{} was renamed to {} on {}",
+                rename.old_name, self.system.name, rename.since
+            )
+        })
+    }
+}
+
+/// Renames keyed by the SDE version, i.e. its release date, that introduced them.
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::renames::Renames;
+/// use neweden::System;
+///
+/// let universe = UniverseBuilder::default()
+///     .system(System {
+///         id: 30002737.into(),
+///         name: "Ahbazon".to_string(),
+///         coordinate: (0.0, 0.0, 0.0).into(),
+///         security: 0.4.into(),
+///         region_name: "Genesis".to_string(),
+///         faction_id: None,
+///     })
+///     .build();
+/// let mut renames = Renames::default();
+/// renames.insert(NaiveDate::from_ymd_opt(2021, 3, 9).unwrap(), 30002737.into(), "Old Ahbazon");
+///
+/// let found = renames.lookup(&universe, "old ahbazon").unwrap();
+/// assert_eq!("Ahbazon", found.system.name);
+/// assert_eq!(
+///     Some("Old Ahbazon was renamed to Ahbazon on 2021-03-09".to_string()),
+///     found.deprecation()
+/// );
+/// assert_eq!(None, renames.lookup(&universe, "Ahbazon").unwrap().deprecation());
+/// ```
+#[derive(Debug, Default)]
+pub struct Renames {
+    versions: BTreeMap<NaiveDate, Vec<Rename>>,
+}
+
+impl Renames {
+    pub fn insert(&mut self, since: NaiveDate, id: types::SystemId, old_name: &str) {
+        self.versions.entry(since).or_default().push(Rename {
+            id,
+            old_name: old_name.to_string(),
+            since,
+        });
+    }
+
+    /// Renames introduced by the SDE released on the given date.
+    pub fn version(&self, since: NaiveDate) -> &[Rename] {
+        self.versions
+            .get(&since)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// All former names of a system, oldest first.
+    pub fn history(&self, id: &types::SystemId) -> impl Iterator<Item = &Rename> {
+        self.versions
+            .values()
+            .flatten()
+            .filter(move |rename| rename.id == *id)
+    }
+
+    /// Finds a system by its current name or a former name, compared with
+    /// `names::matches`. Current names take precedence, then the most recent rename.
+    pub fn lookup<'a>(
+        &'a self,
+        universe: &'a dyn types::Navigatable,
+        name: &str,
+    ) -> Option<NameLookup<'a>> {
+        if let Some(system) = universe
+            .all_systems()
+            .into_iter()
+            .find(|system| names::matches(&system.name, name))
+        {
+            return Some(NameLookup {
+                system,
+                renamed: None,
+            });
+        }
+        self.versions
+            .values()
+            .rev()
+            .flatten()
+            .filter(|rename| names::matches(&rename.old_name, name))
+            .find_map(|rename| {
+                Some(NameLookup {
+                    system: universe.get_system(&rename.id)?,
+                    renamed: Some(rename),
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+
+    #[test]
+    fn test_lookup() {
+        let universe = UniverseBuilder::default()
+            .system(system(1).named("Current"))
+            .system(system(2).named("Taken"))
+            .build();
+        let first = NaiveDate::from_ymd_opt(2019, 1, 1).unwrap();
+        let second = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+        let mut renames = Renames::default();
+        renames.insert(first, 1.into(), "Original");
+        renames.insert(second, 1.into(), "Interim");
+        renames.insert(second, 2.into(), "Original");
+        renames.insert(second, 1.into(), "Taken");
+
+        let found = renames.lookup(&universe, "interim").unwrap();
+        assert_eq!(types::SystemId(1), found.system.id);
+        assert_eq!(Some(second), found.renamed.map(|rename| rename.since));
+        // the most recent rename wins
+        assert_eq!(
+            types::SystemId(2),
+            renames.lookup(&universe, "Original").unwrap().system.id
+        );
+        // current names win over former names
+        let taken = renames.lookup(&universe, "Taken").unwrap();
+        assert_eq!(types::SystemId(2), taken.system.id);
+        assert!(taken.renamed.is_none());
+        assert!(renames.lookup(&universe, "Unknown").is_none());
+
+        assert_eq!(3, renames.history(&1.into()).count());
+        assert_eq!(3, renames.version(second).len());
+    }
+}
@@ -0,0 +1,209 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Mining resources of systems, e.g. to find the nearest ice system.
+//!
+//! The SDE doesn't record which ores spawn where or which systems have ice belts.
+//! Ores are estimated from the security band the way belts spawn in game, ice
+//! systems have to be marked from an external list. Resources are kept in an
+//! `Overlay` keyed by `SystemId`.
+
+use crate::navigation::{Distance, distance_report};
+use crate::overlay::Overlay;
+use crate::types;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Ore {
+    Veldspar,
+    Scordite,
+    Pyroxeres,
+    Plagioclase,
+    Omber,
+    Kernite,
+    Jaspet,
+    Hemorphite,
+    Hedbergite,
+    Gneiss,
+    DarkOchre,
+    Spodumain,
+    Crokite,
+    Bistot,
+    Arkonor,
+    Mercoxit,
+}
+
+const HIGHSEC_ORES: &[Ore] = &[
+    Ore::Veldspar,
+    Ore::Scordite,
+    Ore::Pyroxeres,
+    Ore::Plagioclase,
+];
+const LOW_HIGHSEC_ORES: &[Ore] = &[
+    Ore::Veldspar,
+    Ore::Scordite,
+    Ore::Pyroxeres,
+    Ore::Plagioclase,
+    Ore::Omber,
+    Ore::Kernite,
+];
+const LOWSEC_ORES: &[Ore] = &[
+    Ore::Omber,
+    Ore::Kernite,
+    Ore::Jaspet,
+    Ore::Hemorphite,
+    Ore::Hedbergite,
+];
+const NULLSEC_ORES: &[Ore] = &[
+    Ore::Gneiss,
+    Ore::DarkOchre,
+    Ore::Spodumain,
+    Ore::Crokite,
+    Ore::Bistot,
+    Ore::Arkonor,
+    Ore::Mercoxit,
+];
+
+/// Ores usually found in the belts of a system with the given security. This is a
+/// heuristic, belts vary between systems of the same band.
+pub fn expected_ores(security: types::Security) -> &'static [Ore] {
+    match security.class() {
+        types::SecurityClass::Highsec if security.display_value() >= 0.7 => HIGHSEC_ORES,
+        types::SecurityClass::Highsec => LOW_HIGHSEC_ORES,
+        types::SecurityClass::Lowsec => LOWSEC_ORES,
+        types::SecurityClass::Nullsec => NULLSEC_ORES,
+    }
+}
+
+/// Resources of a system.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resources {
+    /// The system has an ice belt.
+    pub ice: bool,
+    pub ores: Vec<Ore>,
+}
+
+impl Resources {
+    /// Estimates the ores of a known space system. Wormhole systems get no ores as
+    /// their belts depend on the class, not the security.
+    pub fn estimate(system: &types::System) -> Self {
+        let ores = match types::SystemClass::from(&system.id) {
+            types::SystemClass::KSpace => expected_ores(system.security).to_vec(),
+            _ => Vec::new(),
+        };
+        Self { ice: false, ores }
+    }
+
+    pub fn has_ore(&self, ore: Ore) -> bool {
+        self.ores.contains(&ore)
+    }
+}
+
+/// Estimates the resources of all systems of a universe and marks the given
+/// systems as having ice belts.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::resources::{estimate, Ore};
+/// use neweden::{SystemId, System};
+///
+/// let universe = UniverseBuilder::default()
+///     .system(System {
+///         id: 30000142.into(),
+///         name: "Jita".to_string(),
+///         coordinate: (0.0, 0.0, 0.0).into(),
+///         security: 0.9459.into(),
+///         region_name: "The Forge".to_string(),
+///         faction_id: None,
+///     })
+///     .build();
+/// let resources = estimate(&universe, []);
+/// let jita = resources.get(&SystemId(30000142)).unwrap();
+/// assert!(jita.has_ore(Ore::Veldspar));
+/// assert!(!jita.ice);
+/// ```
+pub fn estimate(
+    universe: &dyn types::Navigatable,
+    ice: impl IntoIterator<Item = types::SystemId>,
+) -> Overlay<types::SystemId, Resources> {
+    let mut overlay = Overlay::default();
+    for system in universe.all_systems() {
+        overlay.insert(system.id, Resources::estimate(system));
+    }
+    for id in ice {
+        if let Some(resources) = overlay.get_mut(&id) {
+            resources.ice = true;
+        }
+    }
+    overlay
+}
+
+/// Systems with matching resources within `max_jumps` of `origin`, nearest first.
+pub fn nearest<'a>(
+    universe: &'a dyn types::Navigatable,
+    origin: &'a types::System,
+    resources: &Overlay<types::SystemId, Resources>,
+    max_jumps: usize,
+    predicate: impl Fn(&Resources) -> bool,
+) -> Vec<Distance<'a>> {
+    let targets = universe
+        .all_systems()
+        .into_iter()
+        .filter(|system| resources.get(&system.id).is_some_and(&predicate));
+    let mut distances = distance_report(universe, origin, targets)
+        .into_iter()
+        .filter(|distance| distance.jumps.is_some_and(|jumps| jumps <= max_jumps))
+        .collect::<Vec<_>>();
+    distances.sort_by(|a, b| {
+        a.jumps
+            .cmp(&b.jumps)
+            .then_with(|| a.target.name.cmp(&b.target.name))
+    });
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_nearest_ice() {
+        // 1 - 2 - 3 - 4 - 5, ice in 3 and 5
+        let mut builder = UniverseBuilder::default().system(system(1).with_security(0.9));
+        for (id, security) in [(2, 0.5), (3, 0.3), (4, -0.2), (5, 0.6)] {
+            builder = builder.system(system(id).with_security(security));
+            for (from, to) in [(id - 1, id), (id, id - 1)] {
+                builder = builder.connection(types::Connection {
+                    from: from.into(),
+                    to: to.into(),
+                    r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+                });
+            }
+        }
+        let universe = builder.build();
+        let resources = estimate(&universe, [3.into(), 5.into()]);
+        assert!(resources.get(&2.into()).unwrap().has_ore(Ore::Kernite));
+        assert!(resources.get(&4.into()).unwrap().has_ore(Ore::Arkonor));
+        assert!(!resources.get(&1.into()).unwrap().has_ore(Ore::Omber));
+
+        let origin = universe.get_system(&1.into()).unwrap();
+        let ice = nearest(&universe, origin, &resources, 6, |r| r.ice)
+            .iter()
+            .map(|distance| (distance.target.id.0, distance.jumps))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(3, Some(2)), (5, Some(4))], ice);
+        assert_eq!(
+            1,
+            nearest(&universe, origin, &resources, 3, |r| r.ice).len()
+        );
+        let highsec_ice = nearest(&universe, origin, &resources, 6, |r| {
+            r.ice && r.has_ore(Ore::Veldspar)
+        });
+        assert_eq!(types::SystemId(5), highsec_ice[0].target.id);
+    }
+}
@@ -3,8 +3,19 @@
  * All rights reserved.
  */
 
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+
+use crate::navigation;
 use crate::types;
 
+/// Daily server downtime starts at 11:00 UTC.
+pub const DOWNTIME_START: NaiveTime = NaiveTime::from_hms_opt(11, 0, 0).unwrap();
+
+/// Downtime usually takes less than 15 minutes.
+pub const DOWNTIME_DURATION: Duration = Duration::minutes(15);
+
 pub fn allows_cynos(system: &types::System) -> bool {
     let sec_class = types::SecurityClass::from(system.security);
     let sys_class = types::SystemClass::from(system);
@@ -12,6 +23,636 @@ pub fn allows_cynos(system: &types::System) -> bool {
         (types::SystemClass::KSpace, types::SecurityClass::Highsec) => false,
         (types::SystemClass::KSpace, types::SecurityClass::Lowsec) => true,
         (types::SystemClass::KSpace, types::SecurityClass::Nullsec) => true,
-        (types::SystemClass::WSpace, _) => false,
+        (
+            types::SystemClass::WSpace | types::SystemClass::Abyssal | types::SystemClass::Void,
+            _,
+        ) => false,
+    }
+}
+
+pub fn is_npc_nullsec(system: &types::System) -> bool {
+    system.nullsec_class() == Some(types::NullsecClass::Npc)
+}
+
+pub fn is_sov_nullsec(system: &types::System) -> bool {
+    system.nullsec_class() == Some(types::NullsecClass::Sovereign)
+}
+
+/// Faction ID of the Triglavian Collective, which owns all Pochven systems.
+pub const TRIGLAVIAN_FACTION_ID: u32 = 500026;
+
+pub fn is_pochven(system: &types::System) -> bool {
+    system.faction_id == Some(TRIGLAVIAN_FACTION_ID)
+}
+
+/// Pilot specific information that changes which connections are available.
+///
+/// Pochven stargates only let pilots pass that are in good standing with the
+/// Triglavian Collective. Pilots with bad standings can only travel through Pochven
+/// using filaments, which lead to random systems and can't be used for routing.
+///
+/// # Example
+/// ```
+/// use neweden::rules::PilotProfile;
+///
+/// let pilot = PilotProfile::new().triglavian_standing(-2.5);
+/// assert!(!pilot.can_use_pochven_gates());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PilotProfile {
+    triglavian_standing: f32,
+}
+
+impl Default for PilotProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PilotProfile {
+    /// Minimum Triglavian standing required to use Pochven stargates.
+    pub const MIN_POCHVEN_STANDING: f32 = 0.0;
+
+    /// Creates a profile for a pilot with neutral standings.
+    pub fn new() -> Self {
+        Self {
+            triglavian_standing: 0.0,
+        }
+    }
+
+    pub fn triglavian_standing(mut self, standing: f32) -> Self {
+        self.triglavian_standing = standing;
+        self
+    }
+
+    pub fn can_use_pochven_gates(&self) -> bool {
+        self.triglavian_standing >= Self::MIN_POCHVEN_STANDING
+    }
+
+    /// Values identifying the profile, see `PathBuilder::cache_key()`.
+    pub(crate) fn key(&self) -> Vec<u32> {
+        vec![self.triglavian_standing.to_bits()]
+    }
+
+    /// Returns whether the pilot can use the connection between the two systems.
+    pub fn can_use(
+        &self,
+        connection: &types::Connection,
+        from: &types::System,
+        to: &types::System,
+    ) -> bool {
+        match connection.r#type {
+            types::ConnectionType::Stargate(_) if is_pochven(from) || is_pochven(to) => {
+                self.can_use_pochven_gates()
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Size class of a hull, ordered from smallest to largest. Used to decide which
+/// wormholes a ship fits through.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShipSize {
+    Small,
+    Medium,
+    Large,
+    Freighter,
+    Capital,
+    Supercapital,
+}
+
+/// Restrictions that apply to a given hull. Pass a profile to
+/// `PathBuilder::ship()` to never route a ship through systems or connections it
+/// cannot use.
+///
+/// # Example
+/// ```
+/// use neweden::rules::{ShipProfile, ShipSize};
+///
+/// let carrier = ShipProfile::new(ShipSize::Capital);
+/// assert!(!carrier.can_enter_highsec());
+/// let orca = ShipProfile::new(ShipSize::Capital).highsec(true);
+/// assert!(orca.can_enter_highsec());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShipProfile {
+    size: ShipSize,
+    highsec: bool,
+}
+
+impl ShipProfile {
+    /// Creates a profile for the given size. Capitals and supercapitals are not
+    /// allowed in highsec unless overridden using `highsec()`.
+    pub fn new(size: ShipSize) -> Self {
+        Self {
+            size,
+            highsec: size < ShipSize::Capital,
+        }
+    }
+
+    /// Overrides whether the hull may enter highsec, e.g. for the Orca or jump freighters.
+    pub fn highsec(mut self, allowed: bool) -> Self {
+        self.highsec = allowed;
+        self
+    }
+
+    pub fn size(&self) -> ShipSize {
+        self.size
+    }
+
+    pub fn can_enter_highsec(&self) -> bool {
+        self.highsec
+    }
+
+    pub fn can_dock(&self) -> bool {
+        self.size < ShipSize::Supercapital
+    }
+
+    pub fn can_enter(&self, system: &types::System) -> bool {
+        self.highsec || types::SecurityClass::from(system.security) != types::SecurityClass::Highsec
+    }
+
+    pub fn can_use(&self, connection: &types::Connection) -> bool {
+        match &connection.r#type {
+            types::ConnectionType::Stargate(_) | types::ConnectionType::Ansiblex => true,
+            // Titans and black ops can only bridge subcapitals
+            types::ConnectionType::Bridge(_) => self.size < ShipSize::Capital,
+            types::ConnectionType::Wormhole(wh) => {
+                let max = match wh.max_ship_size {
+                    types::WormholeMaxShipSize::Small => ShipSize::Small,
+                    types::WormholeMaxShipSize::Medium => ShipSize::Medium,
+                    types::WormholeMaxShipSize::Large => ShipSize::Large,
+                    types::WormholeMaxShipSize::XLarge => ShipSize::Freighter,
+                    types::WormholeMaxShipSize::Capital => ShipSize::Capital,
+                    // be optimistic, most unknown holes are at least large
+                    types::WormholeMaxShipSize::Unknown => ShipSize::Capital,
+                };
+                self.size <= max
+                    && wh
+                        .shattered
+                        .is_none_or(|shattered| self.size <= shattered.max_ship_size())
+            }
+        }
+    }
+}
+
+/// Size limits of individual systems and connections that are not part of the
+/// connection itself, e.g. shattered wormhole systems capitals cannot enter or a
+/// frigate-only shortcut from a mapping tool. Limits only apply to paths built with
+/// a ship profile, see `PathBuilder::size_restrictions()`.
+///
+/// # Example
+/// ```
+/// use neweden::rules::{ShipProfile, ShipSize, SizeRestrictions};
+/// use neweden::{Connection, ConnectionType, StargateType};
+///
+/// let restrictions = SizeRestrictions::default().shattered(31000001.into());
+/// let connection = Connection {
+///     from: 31000002.into(),
+///     to: 31000001.into(),
+///     r#type: ConnectionType::Stargate(StargateType::Local),
+/// };
+/// assert!(restrictions.allows(&ShipProfile::new(ShipSize::Large), &connection));
+/// assert!(!restrictions.allows(&ShipProfile::new(ShipSize::Capital), &connection));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SizeRestrictions {
+    systems: HashMap<types::SystemId, ShipSize>,
+    connections: HashMap<(types::SystemId, types::SystemId), ShipSize>,
+}
+
+impl SizeRestrictions {
+    /// Only ships up to `max` may enter the system.
+    pub fn system(mut self, id: types::SystemId, max: ShipSize) -> Self {
+        self.systems.insert(id, max);
+        self
+    }
+
+    /// Shattered wormhole systems cannot be entered by capitals.
+    pub fn shattered(self, id: types::SystemId) -> Self {
+        self.shattered_kind(id, types::Shattered::Regular)
+    }
+
+    /// Restricts the system to the hulls that can enter the given kind of shattered
+    /// system, e.g. frigates for class 13 systems.
+    pub fn shattered_kind(self, id: types::SystemId, kind: types::Shattered) -> Self {
+        self.system(id, kind.max_ship_size())
+    }
+
+    /// Only ships up to `max` may use connections from `from` to `to`.
+    pub fn connection(mut self, from: types::SystemId, to: types::SystemId, max: ShipSize) -> Self {
+        self.connections.insert((from, to), max);
+        self
+    }
+
+    /// Returns true if the ship may use the connection and enter its destination.
+    pub fn allows(&self, ship: &ShipProfile, connection: &types::Connection) -> bool {
+        let fits = |max: Option<&ShipSize>| max.is_none_or(|max| ship.size() <= *max);
+        fits(self.systems.get(&connection.to))
+            && fits(self.connections.get(&(connection.from, connection.to)))
+    }
+
+    /// Values identifying the restrictions, see `PathBuilder::cache_key()`.
+    pub(crate) fn key(&self) -> Vec<u32> {
+        let mut systems = self
+            .systems
+            .iter()
+            .map(|(id, max)| [id.0, *max as u32])
+            .collect::<Vec<_>>();
+        systems.sort_unstable();
+        let mut connections = self
+            .connections
+            .iter()
+            .map(|((from, to), max)| [from.0, to.0, *max as u32])
+            .collect::<Vec<_>>();
+        connections.sort_unstable();
+        let mut key = vec![systems.len() as u32];
+        key.extend(systems.into_iter().flatten());
+        key.push(connections.len() as u32);
+        key.extend(connections.into_iter().flatten());
+        key
+    }
+}
+
+/// Rules a route must follow, e.g. the doctrine of an alliance for a given hull.
+/// Used to audit routes computed elsewhere with `check_route()`.
+///
+/// # Example
+/// ```
+/// use neweden::navigation::{Route, RouteHop};
+/// use neweden::rules::{self, RuleSet, ShipProfile, ShipSize, Violation};
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::{ConnectionType, SecurityClass, StargateType, System};
+///
+/// let system = |id: u32, security: f32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: security.into(),
+///     region_name: "The Forge".to_string(),
+///     faction_id: None,
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(30000142, 0.9))
+///     .system(system(30002813, 0.3))
+///     .build();
+/// let route = Route {
+///     version: 0,
+///     hops: vec![RouteHop {
+///         from: 30000142.into(),
+///         to: 30002813.into(),
+///         connection: ConnectionType::Stargate(StargateType::Local),
+///     }],
+/// };
+/// let rules = RuleSet::default().forbid(SecurityClass::Lowsec);
+/// let violations = rules::check_route(&universe, &route, &rules);
+/// assert_eq!(
+///     vec![Violation::ForbiddenSecurity { hop: 0, system: 30002813.into() }],
+///     violations
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleSet {
+    ship: Option<ShipProfile>,
+    pilot: Option<PilotProfile>,
+    restrictions: SizeRestrictions,
+    forbidden: Vec<types::SecurityClass>,
+    avoid: Vec<types::SystemId>,
+}
+
+impl RuleSet {
+    /// Checks that the ship can enter every system and use every connection.
+    pub fn ship(mut self, profile: ShipProfile) -> Self {
+        self.ship = Some(profile);
+        self
+    }
+
+    /// Checks that the pilot can use every stargate, e.g. into Pochven.
+    pub fn pilot(mut self, profile: PilotProfile) -> Self {
+        self.pilot = Some(profile);
+        self
+    }
+
+    /// Size limits of systems and connections, only checked together with a ship.
+    pub fn size_restrictions(mut self, restrictions: SizeRestrictions) -> Self {
+        self.restrictions = restrictions;
+        self
+    }
+
+    /// Systems of the given security class must not be entered.
+    pub fn forbid(mut self, class: types::SecurityClass) -> Self {
+        self.forbidden.push(class);
+        self
+    }
+
+    /// The system must not be entered, e.g. hostile staging.
+    pub fn avoid(mut self, id: types::SystemId) -> Self {
+        self.avoid.push(id);
+        self
+    }
+}
+
+/// A rule broken by a hop of a route. `hop` is the index of the hop in
+/// `Route::hops`, the system is the one entered by the hop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    UnknownSystem {
+        hop: usize,
+        system: types::SystemId,
+    },
+    ForbiddenSecurity {
+        hop: usize,
+        system: types::SystemId,
+    },
+    AvoidedSystem {
+        hop: usize,
+        system: types::SystemId,
+    },
+    /// The ship may not enter the system, e.g. a capital entering highsec.
+    ShipCannotEnter {
+        hop: usize,
+        system: types::SystemId,
+    },
+    /// The ship doesn't fit through the connection or its size restrictions.
+    ShipCannotUse {
+        hop: usize,
+        system: types::SystemId,
+    },
+    /// The pilot's standings don't allow using the stargate.
+    PilotCannotUse {
+        hop: usize,
+        system: types::SystemId,
+    },
+}
+
+impl Violation {
+    pub fn hop(&self) -> usize {
+        match self {
+            Violation::UnknownSystem { hop, .. }
+            | Violation::ForbiddenSecurity { hop, .. }
+            | Violation::AvoidedSystem { hop, .. }
+            | Violation::ShipCannotEnter { hop, .. }
+            | Violation::ShipCannotUse { hop, .. }
+            | Violation::PilotCannotUse { hop, .. } => *hop,
+        }
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::UnknownSystem { hop, system } => {
+                write!(f, "hop {hop}: unknown system {}", system.0)
+            }
+            Violation::ForbiddenSecurity { hop, system } => {
+                write!(
+                    f,
+                    "hop {hop}: enters forbidden security class in {}",
+                    system.0
+                )
+            }
+            Violation::AvoidedSystem { hop, system } => {
+                write!(f, "hop {hop}: enters avoided system {}", system.0)
+            }
+            Violation::ShipCannotEnter { hop, system } => {
+                write!(f, "hop {hop}: ship may not enter {}", system.0)
+            }
+            Violation::ShipCannotUse { hop, system } => {
+                write!(
+                    f,
+                    "hop {hop}: ship cannot use the connection to {}",
+                    system.0
+                )
+            }
+            Violation::PilotCannotUse { hop, system } => {
+                write!(
+                    f,
+                    "hop {hop}: pilot cannot use the stargate to {}",
+                    system.0
+                )
+            }
+        }
+    }
+}
+
+/// Checks every hop of a route, e.g. from ESI or a user, against the rules. Returns
+/// the violations in hop order, empty if the route is compliant. Whether the
+/// connections exist is not checked, see `Route::revalidate()`.
+pub fn check_route(
+    universe: &dyn types::Navigatable,
+    route: &navigation::Route,
+    rules: &RuleSet,
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (hop, step) in route.hops.iter().enumerate() {
+        let system = step.to;
+        let (Some(from), Some(to)) = (
+            universe.get_system(&step.from),
+            universe.get_system(&step.to),
+        ) else {
+            let unknown = if universe.get_system(&step.from).is_none() {
+                step.from
+            } else {
+                step.to
+            };
+            violations.push(Violation::UnknownSystem {
+                hop,
+                system: unknown,
+            });
+            continue;
+        };
+        let connection = types::Connection {
+            from: step.from,
+            to: step.to,
+            r#type: step.connection.clone(),
+        };
+        if rules
+            .forbidden
+            .contains(&types::SecurityClass::from(to.security))
+        {
+            violations.push(Violation::ForbiddenSecurity { hop, system });
+        }
+        if rules.avoid.contains(&system) {
+            violations.push(Violation::AvoidedSystem { hop, system });
+        }
+        if let Some(ship) = &rules.ship {
+            if !ship.can_enter(to) {
+                violations.push(Violation::ShipCannotEnter { hop, system });
+            }
+            if !ship.can_use(&connection) || !rules.restrictions.allows(ship, &connection) {
+                violations.push(Violation::ShipCannotUse { hop, system });
+            }
+        }
+        if let Some(pilot) = &rules.pilot
+            && !pilot.can_use(&connection, from, to)
+        {
+            violations.push(Violation::PilotCannotUse { hop, system });
+        }
+    }
+    violations
+}
+
+/// Returns the start of the next daily downtime at or after the given time.
+/// If `time` is within a downtime window, the start of that downtime is returned.
+pub fn next_downtime(time: DateTime<Utc>) -> DateTime<Utc> {
+    let today = time.date_naive().and_time(DOWNTIME_START).and_utc();
+    if time < today + DOWNTIME_DURATION {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+/// Returns the number of steps, each taking its duration after the previous one
+/// starting at `start`, that end before the next downtime. Returns `None` if all
+/// steps end before downtime.
+pub fn steps_before_downtime(
+    start: DateTime<Utc>,
+    durations: impl IntoIterator<Item = Duration>,
+) -> Option<usize> {
+    let downtime = next_downtime(start);
+    let mut end = start;
+    for (steps, duration) in durations.into_iter().enumerate() {
+        end += duration;
+        if end > downtime {
+            return Some(steps);
+        }
+    }
+    None
+}
+
+pub fn is_downtime(time: DateTime<Utc>) -> bool {
+    let start = next_downtime(time);
+    start <= time && time < start + DOWNTIME_DURATION
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::fixtures::system;
+
+    #[test]
+    fn test_next_downtime() {
+        let before = Utc.with_ymd_and_hms(2024, 5, 4, 10, 30, 0).unwrap();
+        let during = Utc.with_ymd_and_hms(2024, 5, 4, 11, 5, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2024, 5, 4, 12, 0, 0).unwrap();
+        let downtime = Utc.with_ymd_and_hms(2024, 5, 4, 11, 0, 0).unwrap();
+
+        assert_eq!(downtime, next_downtime(before));
+        assert_eq!(downtime, next_downtime(during));
+        assert_eq!(downtime + Duration::days(1), next_downtime(after));
+        assert!(!is_downtime(before));
+        assert!(is_downtime(during));
+    }
+
+    #[test]
+    fn test_shattered() {
+        let wormhole = |max_ship_size, shattered| types::Connection {
+            from: 30000142.into(),
+            to: 31000005.into(),
+            r#type: types::ConnectionType::Wormhole(types::Wormhole {
+                expires: Utc::now(),
+                remaining_hours: 16,
+                signature: "ABC-123".to_string(),
+                exit_signature: None,
+                max_ship_size,
+                shattered,
+            }),
+        };
+        let frigate = ShipProfile::new(ShipSize::Small);
+        let battleship = ShipProfile::new(ShipSize::Large);
+        let freighter = ShipProfile::new(ShipSize::Freighter);
+        let carrier = ShipProfile::new(ShipSize::Capital);
+
+        let regular = wormhole(
+            types::WormholeMaxShipSize::Capital,
+            Some(types::Shattered::Regular),
+        );
+        assert!(freighter.can_use(&regular));
+        assert!(!carrier.can_use(&regular));
+        let small = wormhole(
+            types::WormholeMaxShipSize::Large,
+            Some(types::Shattered::Frigate),
+        );
+        assert!(frigate.can_use(&small));
+        assert!(!battleship.can_use(&small));
+        // Thera is not shattered, the mass of its wormhole types limits the size
+        let f135 = types::WormholeTypeInfo::from_code("F135").unwrap();
+        let thera = types::Connection {
+            from: 30000142.into(),
+            to: 31000005.into(),
+            r#type: types::ConnectionType::Wormhole(f135.wormhole("ABC-123", chrono::Utc::now())),
+        };
+        assert!(battleship.can_use(&thera));
+        assert!(!freighter.can_use(&thera));
+
+        let restrictions =
+            SizeRestrictions::default().shattered_kind(31000005.into(), types::Shattered::Frigate);
+        let unflagged = wormhole(types::WormholeMaxShipSize::Large, None);
+        assert!(battleship.can_use(&unflagged));
+        assert!(!restrictions.allows(&battleship, &unflagged));
+        assert!(restrictions.allows(&frigate, &unflagged));
+    }
+
+    #[test]
+    fn test_check_route() {
+        let universe = crate::builder::UniverseBuilder::default()
+            .system(system(1).with_security(0.9))
+            .system(system(2).with_security(0.3))
+            .system(
+                system(3)
+                    .with_security(-1.0)
+                    .in_region("Pochven")
+                    .with_faction(Some(TRIGLAVIAN_FACTION_ID)),
+            )
+            .build();
+        let hop = |from: u32, to: u32| navigation::RouteHop {
+            from: from.into(),
+            to: to.into(),
+            connection: types::ConnectionType::Stargate(types::StargateType::Local),
+        };
+        let route = navigation::Route {
+            version: 0,
+            hops: vec![hop(2, 1), hop(1, 2), hop(2, 3), hop(3, 4)],
+        };
+
+        assert_eq!(1, check_route(&universe, &route, &RuleSet::default()).len());
+        let rules = RuleSet::default()
+            .ship(ShipProfile::new(ShipSize::Capital))
+            .pilot(PilotProfile::new().triglavian_standing(-1.0))
+            .forbid(types::SecurityClass::Lowsec)
+            .avoid(3.into());
+        let violations = check_route(&universe, &route, &rules);
+        assert_eq!(
+            vec![
+                Violation::ShipCannotEnter {
+                    hop: 0,
+                    system: 1.into()
+                },
+                Violation::ForbiddenSecurity {
+                    hop: 1,
+                    system: 2.into()
+                },
+                Violation::AvoidedSystem {
+                    hop: 2,
+                    system: 3.into()
+                },
+                Violation::PilotCannotUse {
+                    hop: 2,
+                    system: 3.into()
+                },
+                Violation::UnknownSystem {
+                    hop: 3,
+                    system: 4.into()
+                },
+            ],
+            violations
+        );
+        assert_eq!("hop 2: enters avoided system 3", violations[2].to_string());
     }
 }
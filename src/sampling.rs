@@ -0,0 +1,280 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Random sampling of plausible routes between two systems for Monte-Carlo risk
+//! analysis, e.g. estimating how likely hostiles travelling from their staging pass
+//! through a given system.
+//!
+//! Routes are random walks towards the destination. Each step prefers connections
+//! that don't make the route longer, and routes are never more than `slack` jumps
+//! longer than the shortest route. Sampling is deterministic for a given seed.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::navigation::{Route, RouteHop};
+use crate::types;
+
+/// SplitMix64, good enough for sampling and stable across platforms.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Number of attempts per sample before giving up on a walk that ran into a dead end.
+const MAX_ATTEMPTS: usize = 100;
+
+/// Samples random routes between two systems.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::sampling::RouteSampler;
+/// use neweden::{Connection, ConnectionType, Navigatable, StargateType, System};
+///
+/// let system = |id: u32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: (-0.4).into(),
+///     region_name: "Delve".to_string(),
+///     faction_id: None,
+/// };
+/// let gate = |from: u32, to: u32| Connection {
+///     from: from.into(),
+///     to: to.into(),
+///     r#type: ConnectionType::Stargate(StargateType::Local),
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(30004759))
+///     .system(system(30004760))
+///     .connection(gate(30004759, 30004760))
+///     .build();
+/// let routes = RouteSampler::new(&universe)
+///     .from(universe.get_system(&30004759.into()).unwrap())
+///     .to(universe.get_system(&30004760.into()).unwrap())
+///     .seed(42)
+///     .sample(10);
+/// assert_eq!(10, routes.len());
+/// assert!(routes.iter().all(|route| route.hops.len() == 1));
+/// ```
+pub struct RouteSampler<'a> {
+    universe: &'a dyn types::Navigatable,
+    from: Option<&'a types::System>,
+    to: Option<&'a types::System>,
+    slack: usize,
+    detour_weight: f64,
+    seed: u64,
+}
+
+impl<'a> RouteSampler<'a> {
+    pub fn new(universe: &'a dyn types::Navigatable) -> Self {
+        Self {
+            universe,
+            from: None,
+            to: None,
+            slack: 2,
+            detour_weight: 0.25,
+            seed: 0,
+        }
+    }
+
+    pub fn from(mut self, system: &'a types::System) -> Self {
+        self.from = Some(system);
+        self
+    }
+
+    pub fn to(mut self, system: &'a types::System) -> Self {
+        self.to = Some(system);
+        self
+    }
+
+    /// Maximum number of jumps a route may be longer than the shortest route.
+    /// Defaults to 2.
+    pub fn slack(mut self, jumps: usize) -> Self {
+        self.slack = jumps;
+        self
+    }
+
+    /// Relative weight of a jump that makes the route one jump longer than a jump
+    /// that keeps it on a shortest route, between 0.0 and 1.0. Defaults to 0.25.
+    pub fn detour_weight(mut self, weight: f64) -> Self {
+        self.detour_weight = weight;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Number of jumps from every system that can reach `to` through the systems
+    /// reachable from `from`.
+    fn distances_to(
+        &self,
+        from: types::SystemId,
+        to: types::SystemId,
+    ) -> HashMap<types::SystemId, usize> {
+        let mut incoming: HashMap<types::SystemId, Vec<types::SystemId>> = HashMap::new();
+        let mut seen = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(id) = queue.pop_front() {
+            for conn in self.universe.get_connections(&id).unwrap_or_default() {
+                if self.universe.avoided_by_default(&conn.to) {
+                    continue;
+                }
+                incoming.entry(conn.to).or_default().push(id);
+                if seen.insert(conn.to) {
+                    queue.push_back(conn.to);
+                }
+            }
+        }
+
+        let mut distances = HashMap::new();
+        if !seen.contains(&to) {
+            return distances;
+        }
+        distances.insert(to, 0);
+        let mut queue = VecDeque::from([to]);
+        while let Some(id) = queue.pop_front() {
+            let distance = distances[&id];
+            for prev in incoming.get(&id).into_iter().flatten() {
+                if !distances.contains_key(prev) {
+                    distances.insert(*prev, distance + 1);
+                    queue.push_back(*prev);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Samples `n` routes. Returns fewer routes if the destination cannot be reached
+    /// or random walks keep running into dead ends, and no routes if the start or the
+    /// destination was not set.
+    pub fn sample(self, n: usize) -> Vec<Route> {
+        let (Some(from), Some(to)) = (self.from, self.to) else {
+            return Vec::new();
+        };
+        let distances = self.distances_to(from.id, to.id);
+        let Some(shortest) = distances.get(&from.id) else {
+            return Vec::new();
+        };
+        let max_jumps = shortest + self.slack;
+
+        let mut rng = Rng(self.seed);
+        let mut routes = Vec::with_capacity(n);
+        for _ in 0..n {
+            let route = (0..MAX_ATTEMPTS).find_map(|_| {
+                let mut hops = Vec::new();
+                let mut visited = HashSet::from([from.id]);
+                let mut current = from.id;
+                while current != to.id {
+                    let candidates = self
+                        .universe
+                        .get_connections(&current)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|conn| !visited.contains(&conn.to))
+                        .filter_map(|conn| {
+                            let remaining = *distances.get(&conn.to)?;
+                            if hops.len() + 1 + remaining > max_jumps {
+                                return None;
+                            }
+                            let detour = (1 + remaining).saturating_sub(distances[&current]);
+                            Some((conn, self.detour_weight.powi(detour as i32)))
+                        })
+                        .collect::<Vec<_>>();
+                    let total = candidates.iter().map(|(_, weight)| weight).sum::<f64>();
+                    if candidates.is_empty() || total <= 0.0 {
+                        return None;
+                    }
+
+                    let mut pick = rng.next_f64() * total;
+                    let mut chosen = &candidates[candidates.len() - 1].0;
+                    for (conn, weight) in &candidates {
+                        if pick < *weight {
+                            chosen = conn;
+                            break;
+                        }
+                        pick -= weight;
+                    }
+                    visited.insert(chosen.to);
+                    hops.push(RouteHop {
+                        from: chosen.from,
+                        to: chosen.to,
+                        connection: chosen.r#type.clone(),
+                    });
+                    current = chosen.to;
+                }
+                Some(Route {
+                    version: self.universe.version(),
+                    hops,
+                })
+            });
+            routes.extend(route);
+        }
+        routes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_sample() {
+        // 1 - 2 - 4 is the shortest route, 1 - 3 - 5 - 4 is one jump longer
+        let mut builder = UniverseBuilder::default();
+        for id in 1..=6 {
+            builder = builder.system(system(id));
+        }
+        for (from, to) in [(1, 2), (2, 4), (1, 3), (3, 5), (5, 4), (1, 6)] {
+            for (from, to) in [(from, to), (to, from)] {
+                builder = builder.connection(types::Connection {
+                    from: from.into(),
+                    to: to.into(),
+                    r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+                });
+            }
+        }
+        let universe = builder.build();
+        let sampler = || {
+            RouteSampler::new(&universe)
+                .from(universe.get_system(&1.into()).unwrap())
+                .to(universe.get_system(&4.into()).unwrap())
+                .seed(7)
+        };
+
+        let routes = sampler().sample(200);
+        assert_eq!(200, routes.len());
+        let shortest = routes.iter().filter(|route| route.hops.len() == 2).count();
+        assert!(shortest > 100 && shortest < 200);
+        assert!(routes.iter().all(|route| route.hops.len() <= 4));
+        assert!(routes.iter().all(|route| route.hops[0].from == 1.into()));
+        assert!(
+            routes
+                .iter()
+                .all(|route| route.hops.last().unwrap().to == 4.into())
+        );
+        assert_eq!(routes, sampler().sample(200));
+
+        let strict = sampler().slack(0).sample(50);
+        assert!(strict.iter().all(|route| route.hops.len() == 2));
+    }
+}
@@ -0,0 +1,178 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Parser for signatures copied from the probe scanner and reconciliation of the
+//! scanned signatures against the wormholes mapped in an `ExtendedUniverse`.
+
+use thiserror::Error;
+
+use crate::types;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ScanParseError {
+    #[error("line {0}: expected at least 4 tab separated columns")]
+    MissingColumns(usize),
+    #[error("line {0}: invalid signature id {1:?}")]
+    InvalidSignature(usize, String),
+}
+
+/// A single line of the probe scanner. Lines are tab separated and contain the
+/// signature ID, scan group, group, name, signal strength and distance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannedSignature {
+    pub id: String,
+    /// Either `Cosmic Signature` or `Cosmic Anomaly`.
+    pub scan_group: String,
+    /// E.g. `Wormhole` or `Data Site`, `None` until scanned down far enough.
+    pub group: Option<String>,
+    pub name: Option<String>,
+}
+
+impl ScannedSignature {
+    pub fn is_wormhole(&self) -> bool {
+        self.group.as_deref() == Some("Wormhole")
+    }
+}
+
+/// Parses the clipboard content of the probe scanner. Empty lines are ignored.
+///
+/// # Example
+/// ```
+/// use neweden::scanner::parse;
+///
+/// let paste = "ABC-123\tCosmic Signature\tWormhole\tUnstable Wormhole\t100.0%\t4.32 AU\n\
+///              XYZ-987\tCosmic Signature\t\t\t0.0%\t12.1 AU";
+/// let signatures = parse(paste).unwrap();
+/// assert!(signatures[0].is_wormhole());
+/// assert_eq!(None, signatures[1].group);
+/// ```
+pub fn parse(paste: &str) -> Result<Vec<ScannedSignature>, ScanParseError> {
+    paste
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let columns = line.split('\t').map(str::trim).collect::<Vec<_>>();
+            if columns.len() < 4 {
+                return Err(ScanParseError::MissingColumns(i + 1));
+            }
+            let id = columns[0].to_uppercase();
+            if !is_signature_id(&id) {
+                return Err(ScanParseError::InvalidSignature(
+                    i + 1,
+                    columns[0].to_string(),
+                ));
+            }
+            let optional = |s: &str| (!s.is_empty()).then(|| s.to_string());
+            Ok(ScannedSignature {
+                id,
+                scan_group: columns[1].to_string(),
+                group: optional(columns[2]),
+                name: optional(columns[3]),
+            })
+        })
+        .collect()
+}
+
+fn is_signature_id(id: &str) -> bool {
+    let bytes = id.as_bytes();
+    bytes.len() == 7
+        && bytes[..3].iter().all(u8::is_ascii_alphabetic)
+        && bytes[3] == b'-'
+        && bytes[4..].iter().all(u8::is_ascii_digit)
+}
+
+/// Difference between a probe scanner paste and the mapped wormholes of a system.
+#[derive(Debug)]
+pub struct Reconciliation<'a, 's> {
+    /// Signatures that are mapped and still present.
+    pub matched: Vec<(&'s ScannedSignature, &'a types::Connection)>,
+    /// Signatures that are not mapped yet. Anomalies are never reported.
+    pub new: Vec<&'s ScannedSignature>,
+    /// Mapped wormholes whose signature is no longer present.
+    pub despawned: Vec<&'a types::Connection>,
+}
+
+/// Compares scanned signatures in `system` with the connections mapped in the universe.
+pub fn reconcile<'a, 's, U: types::Galaxy + types::Navigatable>(
+    universe: &'a types::ExtendedUniverse<'_, U>,
+    system: &types::SystemId,
+    scanned: &'s [ScannedSignature],
+) -> Reconciliation<'a, 's> {
+    let mut result = Reconciliation {
+        matched: Vec::new(),
+        new: Vec::new(),
+        despawned: Vec::new(),
+    };
+    for signature in scanned {
+        if signature.scan_group == "Cosmic Anomaly" {
+            continue;
+        }
+        match universe.connection_by_signature(system, &signature.id) {
+            Some(connection) => result.matched.push((signature, connection)),
+            None => result.new.push(signature),
+        }
+    }
+    for (id, connection) in universe.signatures_in(system) {
+        if !scanned.iter().any(|signature| signature.id == id) {
+            result.despawned.push(connection);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::builder::UniverseBuilder;
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Err(ScanParseError::MissingColumns(2)),
+            parse("ABC-123\tCosmic Signature\t\t\t0.0%\t1 AU\nfoo")
+        );
+        assert_eq!(
+            Err(ScanParseError::InvalidSignature(1, "ABC123".to_string())),
+            parse("ABC123\tCosmic Signature\t\t\t0.0%\t1 AU")
+        );
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let universe = UniverseBuilder::default().build();
+        let wormhole = |signature: &str, to: u32| types::Connection {
+            from: 31000001.into(),
+            to: to.into(),
+            r#type: types::ConnectionType::Wormhole(types::Wormhole {
+                expires: Utc::now(),
+                remaining_hours: 4,
+                signature: signature.to_string(),
+                exit_signature: None,
+                max_ship_size: types::WormholeMaxShipSize::Large,
+                shattered: None,
+            }),
+        };
+        let extended = types::ExtendedUniverse::new(
+            &universe,
+            vec![wormhole("ABC-123", 31000002), wormhole("DEF-456", 31000003)].into(),
+        );
+        let scanned = parse(
+            "ABC-123\tCosmic Signature\tWormhole\tUnstable Wormhole\t100.0%\t4.32 AU\n\
+             XYZ-987\tCosmic Signature\t\t\t0.0%\t12.1 AU\n\
+             QQQ-111\tCosmic Anomaly\tCombat Site\tBlood Hideaway\t100.0%\t3 AU",
+        )
+        .unwrap();
+
+        let result = reconcile(&extended, &31000001.into(), &scanned);
+        assert_eq!(1, result.matched.len());
+        assert_eq!(types::SystemId(31000002), result.matched[0].1.to);
+        assert_eq!(vec![&scanned[1]], result.new);
+        assert_eq!(1, result.despawned.len());
+        assert_eq!(types::SystemId(31000003), result.despawned[0].to);
+    }
+}
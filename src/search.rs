@@ -1,23 +1,176 @@
-use anyhow::{anyhow, Result};
+//! Full text search over system names, see `Universe::search()`.
+//!
+//! Results are ranked by how well the name matches the query, multiplied by the
+//! boost of the system. By default the trade hubs are boosted, so that short,
+//! ambiguous queries like "ji" find Jita first. Applications that keep track of
+//! the systems a user recently picked pass them to `Universe::search_recent()` to
+//! boost them as well. The index keeps no state between searches, so results never
+//! depend on other callers.
+//!
+//! Systems whose name is exactly the query, after `names::normalize()`, are
+//! returned first with the maximal score, no matter how the ngrams of other names
+//! score. The ngram matches follow.
+//!
+//! Queries are not parsed with tantivy's query syntax. They are split into the
+//! same ngrams as the names, so characters like `+`, `-` or `"` typed by users are
+//! matched literally and never fail to parse.
+//!
+//! Like players do in chat, a query can be qualified with a region or constellation
+//! after a comma, e.g. "Hek, Metropolis". Only systems in a region or constellation
+//! whose name starts with the qualifier are returned. The qualifier is part of the
+//! index query, so systems outside the area never take the place of matches inside.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Result, anyhow};
+use itertools::Itertools;
 use tantivy::{
+    Index, IndexWriter, Searcher, TantivyDocument, Term,
     collector::TopDocs,
     doc,
-    query::QueryParser,
+    indexer::UserOperation,
+    query::{BooleanQuery, ConstScoreQuery, Occur, Query, TermQuery, TermSetQuery},
     schema::{self, *},
     tokenizer::*,
-    Index, IndexWriter, Searcher, TantivyDocument,
 };
 
-use crate::types::System;
+use crate::types::{Region, System, SystemId};
+
+/// Tantivy never uses more than 8 indexing threads.
+pub(crate) const MAX_THREADS: usize = 8;
+
+/// Tantivy requires at least 15 MB of memory per indexing thread.
+const MEMORY_PER_THREAD: usize = 15_000_000;
+
+/// Documents handed to the writer at once. Each batch is indexed by one thread, so
+/// smaller batches spread the documents over more threads.
+const BATCH_SIZE: usize = 1_000;
+
+/// Candidates fetched from the index before boosting and truncating to `RESULTS`.
+/// Systems outside the qualified area are excluded by the index query, so they
+/// don't count towards the candidates.
+const CANDIDATES: usize = 50;
+
+/// Results returned by a search.
+const RESULTS: usize = 10;
+
+/// Factors the scores of systems are multiplied with.
+///
+/// # Example
+/// ```
+/// use neweden::search::SearchBoosts;
+/// use neweden::SystemId;
+///
+/// // prefer Amamake over other systems and don't boost the trade hubs
+/// let boosts = SearchBoosts::none().system(30002537.into(), 3.0);
+/// assert_eq!(3.0, boosts.get(&SystemId(30002537)));
+/// assert_eq!(1.0, boosts.get(&SystemId(30000142)));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchBoosts {
+    systems: HashMap<SystemId, f32>,
+    recent: f32,
+}
+
+impl Default for SearchBoosts {
+    /// Boosts the five trade hubs and recent systems.
+    fn default() -> Self {
+        Self::none()
+            .system(30000142.into(), 2.0) // Jita
+            .system(30002187.into(), 1.8) // Amarr
+            .system(30002659.into(), 1.5) // Dodixie
+            .system(30002510.into(), 1.5) // Rens
+            .system(30002053.into(), 1.5) // Hek
+            .recent(1.5)
+    }
+}
+
+impl SearchBoosts {
+    /// No boosts at all, results are ranked by their match only.
+    pub fn none() -> Self {
+        Self {
+            systems: HashMap::new(),
+            recent: 1.0,
+        }
+    }
+
+    /// Multiplies the score of the system with `factor`, e.g. for region capitals or
+    /// the home system of an alliance.
+    pub fn system(mut self, id: SystemId, factor: f32) -> Self {
+        self.systems.insert(id, factor);
+        self
+    }
+
+    /// Multiplies the score of the recent systems passed to
+    /// `Universe::search_recent()`.
+    pub fn recent(mut self, factor: f32) -> Self {
+        self.recent = factor;
+        self
+    }
+
+    pub fn get(&self, id: &SystemId) -> f32 {
+        self.systems.get(id).copied().unwrap_or(1.0)
+    }
+}
+
+/// One indexing thread per core, up to `MAX_THREADS`.
+pub(crate) fn default_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_THREADS)
+}
+
+/// A system found by a search.
+#[derive(Debug, Clone)]
+pub struct SearchHit<'a> {
+    pub system: &'a System,
+    /// The region of the system, `None` if the universe has no hierarchy.
+    pub region: Option<&'a Region>,
+    /// How well the name matched, multiplied by the boost of the system. Exact
+    /// matches have the maximal score.
+    pub score: f32,
+}
+
+/// Splits a query into the name and the qualifier after the last comma. Without a
+/// comma, or with an empty qualifier, the whole query is the name.
+pub(crate) fn split_qualifier(query: &str) -> (&str, Option<&str>) {
+    match query.rsplit_once(',') {
+        Some((name, qualifier)) if !qualifier.trim().is_empty() => {
+            (name.trim(), Some(qualifier.trim()))
+        }
+        _ => (query.trim_end_matches([',', ' ']), None),
+    }
+}
+
+/// Returns true if `name` starts with the qualifier, ignoring case.
+pub(crate) fn matches_qualifier(name: &str, qualifier: &str) -> bool {
+    name.to_lowercase().starts_with(&qualifier.to_lowercase())
+}
 
 pub(crate) struct SearchIndex {
     fields: Fields,
+    /// Systems by their normalized names and aliases.
+    exact: HashMap<String, Vec<SystemId>>,
+    /// Additional names of systems, kept for `reindex()`.
+    aliases: HashMap<SystemId, Vec<String>>,
     searcher: Searcher,
-    query_parser: QueryParser,
+    analyzer: TextAnalyzer,
+    pub(crate) boosts: SearchBoosts,
 }
 
 impl SearchIndex {
     pub(crate) fn new<'a>(systems: impl IntoIterator<Item = &'a System>) -> Result<Self> {
+        Self::with_names(systems, |_| Vec::new(), default_threads())
+    }
+
+    /// Indexes additional names of each system, e.g. translations, using `threads`
+    /// indexing threads between 1 and `MAX_THREADS`.
+    pub(crate) fn with_names<'a>(
+        systems: impl IntoIterator<Item = &'a System>,
+        names: impl Fn(&System) -> Vec<String>,
+        threads: usize,
+    ) -> Result<Self> {
         let mut builder = schema::Schema::builder();
 
         let tokenizer = TextAnalyzer::builder(NgramTokenizer::new(2, 3, false).unwrap())
@@ -38,34 +191,156 @@ impl SearchIndex {
         let schema = builder.build();
 
         let index = Index::create_in_ram(schema);
-        index.tokenizers().register("tok", tokenizer);
+        index.tokenizers().register("tok", tokenizer.clone());
 
-        let mut writer: IndexWriter = index.writer(15_000_000)?;
+        let threads = threads.clamp(1, MAX_THREADS);
+        let mut writer: IndexWriter =
+            index.writer_with_num_threads(threads, threads * MEMORY_PER_THREAD)?;
 
-        for system in systems {
-            writer.add_document(doc! {
-                name => system.name.clone(),
-                id => system.id.0 as i64,
-            })?;
+        let mut exact: HashMap<_, Vec<_>> = HashMap::new();
+        let mut aliases = HashMap::new();
+        for batch in &systems.into_iter().chunks(BATCH_SIZE) {
+            let operations = batch
+                .map(|system| {
+                    let mut document = doc! {
+                        name => system.name.clone(),
+                        id => system.id.0 as i64,
+                    };
+                    exact
+                        .entry(crate::names::normalize(&system.name))
+                        .or_default()
+                        .push(system.id);
+                    let names = names(system);
+                    for alias in &names {
+                        exact
+                            .entry(crate::names::normalize(alias))
+                            .or_default()
+                            .push(system.id);
+                        document.add_text(name, alias);
+                    }
+                    if !names.is_empty() {
+                        aliases.insert(system.id, names);
+                    }
+                    UserOperation::Add(document)
+                })
+                .collect::<Vec<_>>();
+            writer.run(operations)?;
         }
         writer.commit()?;
 
         let reader = index.reader()?;
         let searcher = reader.searcher();
-        let query_parser = QueryParser::for_index(&index, vec![name, id]);
 
         Ok(Self {
-            fields: Fields { id },
+            fields: Fields { name, id },
+            exact,
+            aliases,
             searcher,
-            query_parser,
+            analyzer: tokenizer,
+            boosts: SearchBoosts::default(),
         })
     }
 
-    pub(crate) fn search(&self, query: &str) -> Result<Vec<SearchResult>> {
+    /// Builds a new index with the same boosts.
+    pub(crate) fn rebuild<'a>(
+        &self,
+        systems: impl IntoIterator<Item = &'a System>,
+        names: impl Fn(&System) -> Vec<String>,
+        threads: usize,
+    ) -> Result<Self> {
+        let mut index = Self::with_names(systems, names, threads)?;
+        index.boosts = self.boosts.clone();
+        Ok(index)
+    }
+
+    /// Builds a new index with the same boosts and additional names.
+    pub(crate) fn reindex<'a>(
+        &self,
+        systems: impl IntoIterator<Item = &'a System>,
+        threads: usize,
+    ) -> Result<Self> {
+        self.rebuild(
+            systems,
+            |system| self.aliases.get(&system.id).cloned().unwrap_or_default(),
+            threads,
+        )
+    }
+
+    fn boost(&self, id: SystemId, recent: &[SystemId]) -> f32 {
+        let recent = if recent.contains(&id) {
+            self.boosts.recent
+        } else {
+            1.0
+        };
+        self.boosts.get(&id) * recent
+    }
+
+    /// Matches names sharing any ngram with the input, which also tolerates typos,
+    /// and a system id if the input is a number.
+    fn query(&self, input: &str) -> BooleanQuery {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        let mut analyzer = self.analyzer.clone();
+        analyzer.token_stream(input).process(&mut |token| {
+            let term = Term::from_field_text(self.fields.name, &token.text);
+            clauses.push((
+                Occur::Should,
+                Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs)),
+            ));
+        });
+        if let Ok(id) = input.trim().parse::<i64>() {
+            let term = Term::from_field_i64(self.fields.id, id);
+            clauses.push((
+                Occur::Should,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
+        BooleanQuery::new(clauses)
+    }
+
+    /// Restricts a query to the systems in `within`, without changing the scores.
+    fn within(&self, query: BooleanQuery, within: &HashSet<SystemId>) -> BooleanQuery {
+        let ids = within
+            .iter()
+            .map(|id| Term::from_field_i64(self.fields.id, i64::from(id.0)));
+        BooleanQuery::new(vec![
+            (Occur::Must, Box::new(query) as Box<dyn Query>),
+            (
+                Occur::Must,
+                Box::new(ConstScoreQuery::new(Box::new(TermSetQuery::new(ids)), 0.0)),
+            ),
+        ])
+    }
+
+    /// Searches systems, boosting the `recent` systems. With `within`, only these
+    /// systems are searched, the index never returns candidates outside of them.
+    pub(crate) fn search(
+        &self,
+        query: &str,
+        recent: &[SystemId],
+        within: Option<&HashSet<SystemId>>,
+    ) -> Result<Vec<SearchResult>> {
         use schema::document::Value;
-        let query = self.query_parser.parse_query(query)?;
-        let top_docs = self.searcher.search(&query, &TopDocs::with_limit(10))?;
-        top_docs
+        let mut exact = self
+            .exact
+            .get(&crate::names::normalize(query))
+            .into_iter()
+            .flatten()
+            .filter(|id| within.is_none_or(|within| within.contains(id)))
+            .map(|id| SearchResult {
+                id: id.0 as i64,
+                score: f32::MAX,
+            })
+            .collect::<Vec<_>>();
+        exact.dedup_by_key(|result| result.id);
+
+        let query = match within {
+            Some(within) => self.within(self.query(query), within),
+            None => self.query(query),
+        };
+        let top_docs = self
+            .searcher
+            .search(&query, &TopDocs::with_limit(CANDIDATES))?;
+        let mut results = top_docs
             .into_iter()
             .map(|(score, address)| {
                 let doc = self.searcher.doc::<TantivyDocument>(address)?;
@@ -74,17 +349,24 @@ impl SearchIndex {
                     .ok_or(anyhow!("missing id"))?
                     .as_i64()
                     .ok_or(anyhow!("error converting to i64"))?;
-                Ok(SearchResult { id, _score: score })
+                let score = score * self.boost(SystemId(id as u32), recent);
+                Ok(SearchResult { id, score })
             })
-            .collect::<Result<Vec<_>>>()
+            .collect::<Result<Vec<_>>>()?;
+        results.retain(|result| !exact.iter().any(|hit| hit.id == result.id));
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        exact.extend(results);
+        exact.truncate(RESULTS);
+        Ok(exact)
     }
 }
 
 struct Fields {
+    name: schema::Field,
     id: schema::Field,
 }
 
 pub(crate) struct SearchResult {
     pub(crate) id: i64,
-    pub(crate) _score: f32,
+    pub(crate) score: f32,
 }
@@ -0,0 +1,260 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! A compact binary file of a universe, so short-lived tools only load the
+//! database on the first run.
+//!
+//! The file starts with a magic number, the format version and the version of the
+//! universe, all little endian, followed by the systems, connections, hierarchy,
+//! stations, stargates, celestials and wormhole classes encoded with bincode. Files
+//! of other format versions are rejected, and the universe version is checked after
+//! loading to detect corrupt files.
+
+use std::path::Path;
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+use crate::celestials::Celestials;
+use crate::source::tables::{SideTables, wormhole_class_overlay};
+use crate::stargates::Stargates;
+use crate::stations::Stations;
+use crate::types::{self, Navigatable};
+
+const MAGIC: &[u8; 8] = b"NEWEDEN\0";
+const FORMAT_VERSION: u32 = 2;
+const HEADER_SIZE: usize = 20;
+
+#[derive(Serialize, Deserialize)]
+struct Payload {
+    systems: Vec<types::System>,
+    connections: Vec<types::Connection>,
+    tables: SideTables,
+}
+
+fn encode(universe: &types::Universe) -> anyhow::Result<Vec<u8>> {
+    let mut systems = universe
+        .systems
+        .systems()
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>();
+    systems.sort_by_key(|system| system.id);
+    let mut connections = universe
+        .connections
+        .0
+        .values()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+    connections.sort_by_key(|conn| (conn.from, conn.to));
+    let payload = Payload {
+        systems,
+        connections,
+        tables: SideTables::new(universe),
+    };
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&universe.version().to_le_bytes());
+    bincode::serde::encode_into_std_write(&payload, &mut out, bincode::config::standard())?;
+    Ok(out)
+}
+
+fn decode(data: &[u8]) -> anyhow::Result<types::Universe> {
+    if data.len() < HEADER_SIZE || &data[..8] != MAGIC {
+        bail!("not a universe file");
+    }
+    let format = u32::from_le_bytes(data[8..12].try_into()?);
+    if format != FORMAT_VERSION {
+        bail!("unsupported format version {format}, expected {FORMAT_VERSION}");
+    }
+    let version = u64::from_le_bytes(data[12..HEADER_SIZE].try_into()?);
+
+    let (payload, read): (Payload, _) =
+        bincode::serde::decode_from_slice(&data[HEADER_SIZE..], bincode::config::standard())?;
+    if HEADER_SIZE + read != data.len() {
+        bail!("trailing data");
+    }
+
+    let tables = payload.tables;
+    let universe = types::Universe::new(payload.systems.into(), payload.connections.into())
+        .with_wormhole_classes(wormhole_class_overlay(&tables.wormhole_classes))
+        .with_hierarchy(tables.hierarchy)
+        .with_stations(Stations::new(tables.stations))
+        .with_stargates(Stargates::new(tables.stargates))
+        .with_celestials(Celestials::new(tables.celestials));
+    if universe.version() != version {
+        bail!("universe version mismatch, the file is corrupt");
+    }
+    Ok(universe)
+}
+
+impl types::Universe {
+    /// Writes the universe and its hierarchy, stations, stargates, celestials and
+    /// wormhole classes to a binary file.
+    pub fn write_cache(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, encode(self)?)?;
+        std::fs::rename(partial, path)?;
+        Ok(())
+    }
+
+    /// Reads a universe written by `write_cache()`.
+    pub fn read_cache(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        decode(&std::fs::read(path)?)
+    }
+
+    /// Reads the universe from the cache file, or loads it and writes the cache file
+    /// if the file is missing or can't be read, e.g. after a format change.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use neweden::builder::UniverseBuilder;
+    /// use neweden::Universe;
+    ///
+    /// let universe = Universe::cached("universe.bin", || {
+    ///     // e.g. DatabaseBuilder::new("./sqlite-latest.sqlite").build()
+    ///     Ok(UniverseBuilder::default().build())
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn cached(
+        path: impl AsRef<Path>,
+        load: impl FnOnce() -> anyhow::Result<Self>,
+    ) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if let Ok(universe) = Self::read_cache(path) {
+            return Ok(universe);
+        }
+        let universe = load()?;
+        universe.write_cache(path)?;
+        Ok(universe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::celestials::Celestial;
+    use crate::fixtures::system;
+    use crate::hierarchy::Hierarchy;
+    use crate::overlay::Overlay;
+    use crate::stargates::Stargate;
+    use crate::stations::Station;
+
+    fn connection(from: u32, to: u32, r#type: types::ConnectionType) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type,
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let universe = UniverseBuilder::default()
+            .system(
+                system(1)
+                    .in_region("The Forge")
+                    .at(-1.2906e17, 6.07553e16, 1.0)
+                    .with_faction(Some(500001)),
+            )
+            .system(system(2).in_region("The Forge"))
+            .system(system(3).in_region("Lonetrek"))
+            .connection(connection(
+                1,
+                2,
+                types::ConnectionType::Stargate(types::StargateType::Constellation),
+            ))
+            .connection(connection(2, 3, types::ConnectionType::Ansiblex))
+            .build();
+        let mut hierarchy = Hierarchy::default();
+        hierarchy.insert_region(10000002.into(), "The Forge");
+        hierarchy.insert_constellation(20000020.into(), 10000002.into(), "Kimotoro");
+        hierarchy.insert_system(1.into(), 20000020.into());
+        let mut wormhole_classes = Overlay::default();
+        wormhole_classes.insert(3.into(), types::WormholeClass::Class(5));
+        let universe = universe
+            .with_hierarchy(hierarchy)
+            .with_stations(Stations::new(vec![Station {
+                id: 60003760,
+                name: "Jita IV - Moon 4".to_string(),
+                system: 1.into(),
+                services: Default::default(),
+            }]))
+            .with_stargates(Stargates::new(vec![Stargate {
+                id: 50001248,
+                system: 1.into(),
+                destination: 2.into(),
+                position: (1.0, 2.0, 3.0).into(),
+            }]))
+            .with_celestials(Celestials::new(vec![Celestial {
+                id: 40009077,
+                name: "Jita IV".to_string(),
+                system: 1.into(),
+                kind: crate::celestials::CelestialKind::Planet,
+                position: (4.0, 5.0, 6.0).into(),
+            }]))
+            .with_wormhole_classes(wormhole_classes);
+        let data = encode(&universe).unwrap();
+        let loaded = decode(&data).unwrap();
+
+        assert_eq!(universe.version(), loaded.version());
+        let first = loaded.get_system(&1.into()).unwrap();
+        assert_eq!("1", first.name);
+        assert_eq!(Some(500001), first.faction_id);
+        assert_eq!(-1.2906e17, first.coordinate.x);
+        assert_eq!(None, loaded.get_system(&2.into()).unwrap().faction_id);
+        assert_eq!(
+            "Lonetrek",
+            loaded.get_system(&3.into()).unwrap().region_name
+        );
+        assert_eq!(
+            types::ConnectionType::Ansiblex,
+            loaded.get_connections(&2.into()).unwrap()[0].r#type
+        );
+
+        assert_eq!(
+            Some(10000002.into()),
+            loaded.hierarchy().region_of(&1.into())
+        );
+        assert_eq!(
+            "Jita IV - Moon 4",
+            loaded.stations_in_system(&1.into())[0].name
+        );
+        assert_eq!(2, loaded.stargates_in_system(&1.into())[0].destination.0);
+        assert_eq!("Jita IV", loaded.celestials(&1.into())[0].name);
+        assert_eq!(
+            Some(types::WormholeClass::Class(5)),
+            loaded.wormhole_class(&3.into())
+        );
+
+        assert!(decode(&data[..data.len() - 1]).is_err());
+        let mut future = data.clone();
+        future[8] = 1;
+        assert!(decode(&future).is_err());
+        let mut corrupt = data.clone();
+        corrupt[12] ^= 1;
+        assert!(decode(&corrupt).is_err());
+    }
+
+    #[test]
+    fn test_cached() {
+        let path = std::env::temp_dir().join(format!("neweden-{}.bin", std::process::id()));
+        let load = || {
+            Ok(UniverseBuilder::default()
+                .system(system(1).in_region("Delve"))
+                .build())
+        };
+        let universe = types::Universe::cached(&path, load).unwrap();
+        let cached = types::Universe::cached(&path, || bail!("not loaded again")).unwrap();
+        assert_eq!(universe.version(), cached.version());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
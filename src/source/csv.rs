@@ -0,0 +1,371 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Loads a universe from the CSV exports of the dump published by Fuzzwork, for
+//! environments without SQLite bindings.
+//!
+//! Columns are looked up by the names in the header row, so the order of the
+//! columns and additional columns don't matter. Fuzzwork writes `None` for missing
+//! values. Region names are not part of `mapSolarSystems.csv`, pass
+//! `mapRegions.csv` to get them, otherwise the region id is used as name. If
+//! `mapSolarSystems.csv` has a `constellationID` column the universe knows the region
+//! and constellation of its systems, named by id as the CSV has no constellation names.
+//!
+//! Malformed rows fail the load unless `ParseMode::Lenient` is set, see
+//! `source::report`.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+use ::csv::{ReaderBuilder, StringRecord, Trim};
+use anyhow::{Context, anyhow, bail};
+
+use crate::hierarchy;
+use crate::source::report::{LoadReport, ParseMode};
+use crate::types;
+
+enum Input {
+    Path(PathBuf),
+    Reader(Box<dyn Read>),
+}
+
+impl Input {
+    fn read(self) -> anyhow::Result<Table> {
+        match self {
+            Input::Path(path) => {
+                let file =
+                    File::open(&path).with_context(|| format!("reading {}", path.display()))?;
+                Table::parse(file).with_context(|| format!("reading {}", path.display()))
+            }
+            Input::Reader(reader) => Table::parse(reader),
+        }
+    }
+}
+
+struct Table {
+    columns: HashMap<String, usize>,
+    rows: Vec<StringRecord>,
+}
+
+impl Table {
+    fn parse(data: impl Read) -> anyhow::Result<Self> {
+        let mut reader = ReaderBuilder::new()
+            .flexible(true)
+            .trim(Trim::All)
+            .from_reader(data);
+        let columns = reader
+            .headers()?
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.to_string(), i))
+            .collect::<HashMap<_, _>>();
+        if columns.keys().all(String::is_empty) {
+            bail!("missing header row");
+        }
+        Ok(Self {
+            columns,
+            rows: reader.into_records().collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn column(&self, name: &str) -> anyhow::Result<usize> {
+        self.columns
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("missing column {name}"))
+    }
+}
+
+/// A field of a row, `None` if empty or `None`.
+fn field(row: &StringRecord, column: usize) -> Option<&str> {
+    row.get(column)
+        .filter(|value| !value.is_empty() && *value != "None")
+}
+
+fn parse<T: std::str::FromStr>(row: &StringRecord, column: usize, name: &str) -> anyhow::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let value = field(row, column).ok_or_else(|| anyhow!("missing {name}"))?;
+    value
+        .parse()
+        .with_context(|| format!("invalid {name}: {value}"))
+}
+
+/// Loads a universe from `mapSolarSystems.csv` and `mapSolarSystemJumps.csv`.
+///
+/// # Example
+/// ```no_run
+/// use neweden::source::csv::CsvBuilder;
+///
+/// let universe = CsvBuilder::new("./mapSolarSystems.csv", "./mapSolarSystemJumps.csv")
+///     .regions("./mapRegions.csv")
+///     .build()
+///     .unwrap();
+/// ```
+pub struct CsvBuilder {
+    systems: Input,
+    jumps: Input,
+    regions: Option<Input>,
+    mode: ParseMode,
+}
+
+impl CsvBuilder {
+    pub fn new(systems: impl Into<PathBuf>, jumps: impl Into<PathBuf>) -> Self {
+        Self {
+            systems: Input::Path(systems.into()),
+            jumps: Input::Path(jumps.into()),
+            regions: None,
+            mode: ParseMode::Strict,
+        }
+    }
+
+    /// Reads the CSV data from readers instead of files, e.g. from an archive.
+    pub fn from_readers(systems: impl Read + 'static, jumps: impl Read + 'static) -> Self {
+        Self {
+            systems: Input::Reader(Box::new(systems)),
+            jumps: Input::Reader(Box::new(jumps)),
+            regions: None,
+            mode: ParseMode::Strict,
+        }
+    }
+
+    /// Takes the region names from `mapRegions.csv`.
+    pub fn regions(mut self, path: impl Into<PathBuf>) -> Self {
+        self.regions = Some(Input::Path(path.into()));
+        self
+    }
+
+    pub fn regions_reader(mut self, regions: impl Read + 'static) -> Self {
+        self.regions = Some(Input::Reader(Box::new(regions)));
+        self
+    }
+
+    /// Fails on malformed rows or skips them, defaults to `ParseMode::Strict`.
+    pub fn mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<types::Universe> {
+        Ok(self.build_with_report()?.0)
+    }
+
+    /// Builds the universe and reports the rows skipped in `ParseMode::Lenient`.
+    pub fn build_with_report(self) -> anyhow::Result<(types::Universe, LoadReport)> {
+        let mut report = LoadReport::default();
+        let mut regions = HashMap::new();
+        if let Some(input) = self.regions {
+            let table = input.read().context("reading regions")?;
+            let (id, name) = (table.column("regionID")?, table.column("regionName")?);
+            for (i, row) in table.rows.iter().enumerate() {
+                let region = (|| {
+                    Ok((
+                        parse::<u32>(row, id, "regionID")?,
+                        parse(row, name, "regionName")?,
+                    ))
+                })();
+                if let Some((region_id, name)) = report.row(self.mode, "regions", i + 1, region)? {
+                    regions.insert(region_id, name);
+                }
+            }
+        }
+
+        let table = self.systems.read().context("reading systems")?;
+        let (id, name) = (
+            table.column("solarSystemID")?,
+            table.column("solarSystemName")?,
+        );
+        let (x, y, z) = (table.column("x")?, table.column("y")?, table.column("z")?);
+        let security = table.column("security")?;
+        let (region, faction) = (table.column("regionID")?, table.column("factionID")?);
+        let constellation = table.column("constellationID").ok();
+        let mut hierarchy = hierarchy::Hierarchy::default();
+        let mut systems = Vec::with_capacity(table.rows.len());
+        for (i, row) in table.rows.iter().enumerate() {
+            let system = (|| {
+                let region_id: u32 = parse(row, region, "regionID")?;
+                let constellation_id = constellation
+                    .map(|column| parse::<u32>(row, column, "constellationID"))
+                    .transpose()?;
+                let system = types::System {
+                    id: parse::<u32>(row, id, "solarSystemID")?.into(),
+                    name: parse(row, name, "solarSystemName")?,
+                    coordinate: (
+                        parse(row, x, "x")?,
+                        parse(row, y, "y")?,
+                        parse(row, z, "z")?,
+                    )
+                        .into(),
+                    security: parse::<f32>(row, security, "security")?.into(),
+                    region_name: regions
+                        .get(&region_id)
+                        .cloned()
+                        .unwrap_or_else(|| region_id.to_string()),
+                    faction_id: field(row, faction)
+                        .map(|_| parse(row, faction, "factionID"))
+                        .transpose()?,
+                };
+                Ok((system, region_id, constellation_id))
+            })();
+            if let Some((system, region_id, constellation_id)) =
+                report.row(self.mode, "systems", i + 1, system)?
+            {
+                if let Some(constellation_id) = constellation_id {
+                    let name = constellation_id.to_string();
+                    hierarchy.insert_region(region_id.into(), &system.region_name);
+                    hierarchy.insert_constellation(
+                        constellation_id.into(),
+                        region_id.into(),
+                        &name,
+                    );
+                    hierarchy.insert_system(system.id, constellation_id.into());
+                }
+                systems.push(system);
+            }
+        }
+
+        let table = self.jumps.read().context("reading jumps")?;
+        let columns = [
+            "fromRegionID",
+            "fromConstellationID",
+            "fromSolarSystemID",
+            "toRegionID",
+            "toConstellationID",
+            "toSolarSystemID",
+        ]
+        .map(|name| table.column(name).map(|column| (column, name)));
+        let columns = columns.into_iter().collect::<anyhow::Result<Vec<_>>>()?;
+        let mut connections = Vec::with_capacity(table.rows.len());
+        for (i, row) in table.rows.iter().enumerate() {
+            let connection = (|| {
+                let values = columns
+                    .iter()
+                    .map(|(column, name)| parse::<u32>(row, *column, name))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let stargate_type = if values[0] != values[3] {
+                    types::StargateType::Regional
+                } else if values[1] != values[4] {
+                    types::StargateType::Constellation
+                } else {
+                    types::StargateType::Local
+                };
+                Ok(types::Connection {
+                    from: values[2].into(),
+                    to: values[5].into(),
+                    r#type: types::ConnectionType::Stargate(stargate_type),
+                })
+            })();
+            connections.extend(report.row(self.mode, "jumps", i + 1, connection)?);
+        }
+
+        Ok((
+            types::Universe::new(systems.into(), connections.into()).with_hierarchy(hierarchy),
+            report,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_table() {
+        let table = Table::parse("a,\"b, \"\"c\"\"\",\r\n1,2,3\n\n".as_bytes()).unwrap();
+        assert_eq!(1, table.column("b, \"c\"").unwrap());
+        assert_eq!(
+            vec![vec!["1", "2", "3"]],
+            table
+                .rows
+                .iter()
+                .map(|row| row.iter().collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        );
+        assert!(Table::parse("".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_build() {
+        let systems = "\
+regionID,constellationID,solarSystemID,solarSystemName,x,y,z,luminosity,security,factionID
+10000002,20000020,30000142,Jita,-1.29e+17,6.07e+16,1.17e+17,0.2,0.945913116664839,500001
+10000002,20000020,30000144,Perimeter,-1.29e+17,6.07e+16,1.17e+17,0.3,0.907184,None
+10000033,20000390,30002813,Tama,-1.2e+17,6.1e+16,1.1e+17,0.1,0.263255,None
+";
+        let jumps = "\
+fromRegionID,fromConstellationID,fromSolarSystemID,toSolarSystemID,toConstellationID,toRegionID
+10000002,20000020,30000142,30000144,20000020,10000002
+10000002,20000020,30000144,30002813,20000390,10000033
+";
+        let universe = CsvBuilder::from_readers(systems.as_bytes(), jumps.as_bytes())
+            .regions_reader("regionID,regionName\n10000002,\"The Forge\"\n".as_bytes())
+            .build()
+            .unwrap();
+
+        let jita = universe.get_system(&30000142.into()).unwrap();
+        assert_eq!(("Jita", "The Forge"), (&*jita.name, &*jita.region_name));
+        assert_eq!(Some(500001), jita.faction_id);
+        let tama = universe.get_system(&30002813.into()).unwrap();
+        assert_eq!((None, "10000033"), (tama.faction_id, &*tama.region_name));
+        assert_eq!(
+            Some(types::RegionId(10000033)),
+            universe.region_of(&tama.id).map(|region| region.id)
+        );
+        assert_eq!(2, universe.systems_in_region(&10000002.into()).len());
+        assert_eq!(
+            types::ConnectionType::Stargate(types::StargateType::Regional),
+            universe.get_connections(&30000144.into()).unwrap()[0].r#type
+        );
+
+        let broken = CsvBuilder::from_readers("solarSystemID\n1\n".as_bytes(), jumps.as_bytes());
+        assert!(broken.build().is_err());
+    }
+
+    #[test]
+    fn test_lenient() {
+        let systems = "\
+regionID,solarSystemID,solarSystemName,x,y,z,security,factionID
+10000002,30000142,Jita,0,0,0,0.945913116664839,500001
+10000002,30000144,Perimeter,0,0,0,,None
+10000033,30002813,Tama,0,0,0,0.263255,None
+";
+        let jumps = "\
+fromRegionID,fromConstellationID,fromSolarSystemID,toSolarSystemID,toConstellationID,toRegionID
+10000002,20000020,30000142,30000144,20000020,10000002
+10000002,20000020,30000144,3000281x,20000390,10000033
+";
+        let err = CsvBuilder::from_readers(systems.as_bytes(), jumps.as_bytes())
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!("systems row 2: missing security", format!("{err:#}"));
+
+        let (universe, report) = CsvBuilder::from_readers(systems.as_bytes(), jumps.as_bytes())
+            .mode(ParseMode::Lenient)
+            .build_with_report()
+            .unwrap();
+        assert!(universe.get_system(&30000144.into()).is_none());
+        assert!(universe.get_system(&30002813.into()).is_some());
+        assert_eq!(1, universe.get_connections(&30000142.into()).unwrap().len());
+        assert!(universe.get_connections(&30000144.into()).is_none());
+        assert_eq!(
+            vec![("systems", 2), ("jumps", 2)],
+            report
+                .skipped
+                .iter()
+                .map(|skipped| (skipped.table.as_str(), skipped.row))
+                .collect::<Vec<_>>()
+        );
+        assert!(
+            report.skipped[1]
+                .error
+                .starts_with("invalid toSolarSystemID")
+        );
+    }
+}
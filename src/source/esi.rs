@@ -0,0 +1,573 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Loads a universe from CCP's ESI instead of a static data dump.
+//!
+//! Requests go through a `Transport`, `HttpTransport` performs them with ureq.
+//! Paginated endpoints are followed using the `X-Pages` header, requests are spaced out and back off when
+//! the ESI error limit runs low, and responses can be cached on disk so restarts
+//! don't download all ~8000 systems and ~14000 stargates again.
+
+use std::cell::Cell;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+
+use crate::hierarchy;
+use crate::types;
+
+pub const ESI_URL: &str = "https://esi.evetech.net/latest";
+
+/// Back off once fewer errors than this remain in the current ESI error window.
+const ERROR_LIMIT_THRESHOLD: u32 = 10;
+
+/// A response of an ESI request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+    /// Value of the `X-Pages` header, 1 if missing.
+    pub pages: u32,
+    /// Value of the `X-ESI-Error-Limit-Remain` header.
+    pub error_limit_remain: Option<u32>,
+    /// Value of the `X-ESI-Error-Limit-Reset` header, in seconds.
+    pub error_limit_reset: Option<u64>,
+}
+
+/// Performs a GET request and returns the response, including error responses.
+pub trait Transport {
+    fn get(&self, url: &str) -> anyhow::Result<Response>;
+}
+
+impl<T: Transport + ?Sized> Transport for &T {
+    fn get(&self, url: &str) -> anyhow::Result<Response> {
+        (**self).get(url)
+    }
+}
+
+/// Performs requests with a ureq agent.
+pub struct HttpTransport {
+    agent: ureq::Agent,
+}
+
+impl HttpTransport {
+    pub fn new() -> Self {
+        Self::with_agent(
+            ureq::Agent::config_builder()
+                .http_status_as_error(false)
+                .build()
+                .new_agent(),
+        )
+    }
+
+    /// Uses an agent configured by the caller, e.g. with a proxy or timeouts. The
+    /// agent must not treat error statuses as errors, ESI's are handled by
+    /// `EsiBuilder`.
+    pub fn with_agent(agent: ureq::Agent) -> Self {
+        Self { agent }
+    }
+}
+
+impl Default for HttpTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for HttpTransport {
+    fn get(&self, url: &str) -> anyhow::Result<Response> {
+        let mut response = self.agent.get(url).call()?;
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+        };
+        let (pages, error_limit_remain, error_limit_reset) = (
+            header("x-pages"),
+            header("x-esi-error-limit-remain"),
+            header("x-esi-error-limit-reset"),
+        );
+        Ok(Response {
+            status: response.status().as_u16(),
+            pages: pages.map_or(1, |pages: u64| pages as u32),
+            error_limit_remain: error_limit_remain.map(|remain: u64| remain as u32),
+            error_limit_reset,
+            body: response.body_mut().read_to_string()?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct Position {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+#[derive(Deserialize)]
+struct SystemInfo {
+    system_id: u32,
+    name: String,
+    security_status: f32,
+    constellation_id: u32,
+    position: Position,
+    #[serde(default)]
+    stargates: Vec<u32>,
+}
+
+#[derive(Deserialize)]
+struct Destination {
+    system_id: u32,
+}
+
+#[derive(Deserialize)]
+struct Stargate {
+    stargate_id: u32,
+    system_id: u32,
+    position: Position,
+    destination: Destination,
+}
+
+#[derive(Deserialize)]
+struct Constellation {
+    region_id: u32,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Region {
+    name: String,
+}
+
+/// Systems held by an NPC faction carry its id in the sovereignty map, ESI has no
+/// faction on the system itself.
+#[derive(Deserialize)]
+struct Sovereignty {
+    system_id: u32,
+    faction_id: Option<u32>,
+}
+
+/// Loads a universe from ESI.
+///
+/// # Example
+/// ```no_run
+/// use neweden::source::esi::{EsiBuilder, HttpTransport};
+///
+/// let universe = EsiBuilder::new(HttpTransport::new())
+///     .cache_dir("/var/cache/neweden")
+///     .build()
+///     .unwrap();
+/// ```
+pub struct EsiBuilder<T> {
+    transport: T,
+    base_url: String,
+    cache_dir: Option<PathBuf>,
+    min_interval: Duration,
+    retries: usize,
+    last_request: Cell<Option<Instant>>,
+}
+
+impl<T: Transport> EsiBuilder<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            base_url: ESI_URL.to_string(),
+            cache_dir: None,
+            min_interval: Duration::from_millis(20),
+            retries: 3,
+            last_request: Cell::new(None),
+        }
+    }
+
+    /// Uses a different ESI host or version, e.g. a proxy.
+    pub fn base_url(mut self, url: &str) -> Self {
+        self.base_url = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Caches responses as files in the directory. Cached responses never expire,
+    /// remove the directory to fetch fresh data.
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Minimum time between two requests. Defaults to 20ms.
+    pub fn min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
+
+    /// Number of retries after server errors and rate limiting. Defaults to 3.
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    fn cache_path(&self, path: &str) -> Option<PathBuf> {
+        let name = path.trim_matches('/').replace(['/', '?', '=', '&'], "_");
+        Some(self.cache_dir.as_ref()?.join(format!("{name}.json")))
+    }
+
+    fn throttle(&self) {
+        if let Some(last) = self.last_request.get() {
+            let elapsed = last.elapsed();
+            if elapsed < self.min_interval {
+                std::thread::sleep(self.min_interval - elapsed);
+            }
+        }
+        self.last_request.set(Some(Instant::now()));
+    }
+
+    fn fetch(&self, path: &str) -> anyhow::Result<Response> {
+        let url = format!("{}{}", self.base_url, path);
+        let mut attempt = 0;
+        loop {
+            self.throttle();
+            let response = self.transport.get(&url)?;
+            let limited = response.status == 420 || response.status == 429;
+            if let Some(remain) = response.error_limit_remain
+                && (limited || remain < ERROR_LIMIT_THRESHOLD)
+            {
+                let reset = response.error_limit_reset.unwrap_or(60);
+                std::thread::sleep(Duration::from_secs(reset));
+            }
+            match response.status {
+                200..=299 => return Ok(response),
+                420 | 429 | 500..=599 if attempt < self.retries => attempt += 1,
+                status => return Err(anyhow!("GET {url} failed with status {status}")),
+            }
+        }
+    }
+
+    /// Fetches all pages of an endpoint, using the cache if enabled.
+    fn get<R: serde::de::DeserializeOwned>(&self, path: &str) -> anyhow::Result<Vec<R>> {
+        let cache = self.cache_path(path);
+        if let Some(cache) = &cache
+            && let Ok(body) = std::fs::read_to_string(cache)
+        {
+            return serde_json::from_str(&body).with_context(|| format!("cached {path}"));
+        }
+
+        let merged = self.get_pages(path)?;
+        if let Some(cache) = &cache {
+            if let Some(dir) = cache.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(cache, merged.to_string())?;
+        }
+        serde_json::from_value(merged).with_context(|| format!("GET {path}"))
+    }
+
+    /// Fetches all pages of an endpoint bypassing the cache, for data that changes,
+    /// such as `stats::SystemStats`.
+    #[cfg(feature = "stats")]
+    pub(crate) fn get_fresh<R: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> anyhow::Result<Vec<R>> {
+        serde_json::from_value(self.get_pages(path)?).with_context(|| format!("GET {path}"))
+    }
+
+    /// Fetches all pages of an endpoint and merges them into one array.
+    fn get_pages(&self, path: &str) -> anyhow::Result<serde_json::Value> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let first = self.fetch(path)?;
+        let mut items = vec![first.body];
+        for page in 2..=first.pages {
+            items.push(self.fetch(&format!("{path}{separator}page={page}"))?.body);
+        }
+        let values = items
+            .iter()
+            .map(|body| serde_json::from_str::<serde_json::Value>(body))
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("GET {path}"))?;
+        // paginated endpoints return arrays, merge them into one
+        Ok(match values.as_slice() {
+            [serde_json::Value::Array(_), ..] => serde_json::Value::Array(
+                values
+                    .into_iter()
+                    .flat_map(|value| match value {
+                        serde_json::Value::Array(items) => items,
+                        other => vec![other],
+                    })
+                    .collect(),
+            ),
+            _ => serde_json::Value::Array(values),
+        })
+    }
+
+    fn get_one<R: serde::de::DeserializeOwned>(&self, path: &str) -> anyhow::Result<R> {
+        self.get::<R>(path)?
+            .pop()
+            .ok_or_else(|| anyhow!("GET {path} returned nothing"))
+    }
+
+    /// Loads k-space and w-space systems and their stargates.
+    pub fn build(self) -> anyhow::Result<types::Universe> {
+        let ids = self.get::<u32>("/universe/systems/")?;
+        let systems = ids
+            .into_iter()
+            .filter(|id| *id < 32000000)
+            .map(|id| self.get_one::<SystemInfo>(&format!("/universe/systems/{id}/")))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut regions = HashMap::new();
+        let mut region_names = HashMap::new();
+        let mut hierarchy = hierarchy::Hierarchy::default();
+        let constellations = systems
+            .iter()
+            .map(|system| system.constellation_id)
+            .collect::<HashSet<_>>();
+        for id in constellations {
+            let constellation =
+                self.get_one::<Constellation>(&format!("/universe/constellations/{id}/"))?;
+            if let Entry::Vacant(entry) = region_names.entry(constellation.region_id) {
+                let region = self.get_one::<Region>(&format!(
+                    "/universe/regions/{}/",
+                    constellation.region_id
+                ))?;
+                hierarchy.insert_region(constellation.region_id.into(), &region.name);
+                entry.insert(region.name);
+            }
+            hierarchy.insert_constellation(
+                id.into(),
+                constellation.region_id.into(),
+                &constellation.name,
+            );
+            regions.insert(id, constellation.region_id);
+        }
+        for system in &systems {
+            hierarchy.insert_system(system.system_id.into(), system.constellation_id.into());
+        }
+
+        let location = systems
+            .iter()
+            .map(|system| {
+                (
+                    system.system_id,
+                    (system.constellation_id, regions[&system.constellation_id]),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let mut connections = Vec::new();
+        let mut stargates = Vec::new();
+        for system in &systems {
+            for id in &system.stargates {
+                let gate = self.get_one::<Stargate>(&format!("/universe/stargates/{id}/"))?;
+                let (Some(from), Some(to)) = (
+                    location.get(&gate.system_id),
+                    location.get(&gate.destination.system_id),
+                ) else {
+                    continue;
+                };
+                let stargate_type = if from.1 != to.1 {
+                    types::StargateType::Regional
+                } else if from.0 != to.0 {
+                    types::StargateType::Constellation
+                } else {
+                    types::StargateType::Local
+                };
+                connections.push(types::Connection {
+                    from: gate.system_id.into(),
+                    to: gate.destination.system_id.into(),
+                    r#type: types::ConnectionType::Stargate(stargate_type),
+                });
+                stargates.push(crate::stargates::Stargate {
+                    id: gate.stargate_id,
+                    system: gate.system_id.into(),
+                    destination: gate.destination.system_id.into(),
+                    position: (gate.position.x, gate.position.y, gate.position.z).into(),
+                });
+            }
+        }
+
+        let factions = self
+            .get::<Sovereignty>("/sovereignty/map/")?
+            .into_iter()
+            .filter_map(|sov| Some((sov.system_id, sov.faction_id?)))
+            .collect::<HashMap<_, _>>();
+        let systems = systems
+            .into_iter()
+            .map(|system| {
+                types::System::new(
+                    system.system_id.into(),
+                    system.name,
+                    (system.position.x, system.position.y, system.position.z).into(),
+                    system.security_status.into(),
+                    region_names[&regions[&system.constellation_id]].clone(),
+                    factions.get(&system.system_id).copied(),
+                )
+            })
+            .collect::<Vec<_>>();
+        Ok(types::Universe::new(systems.into(), connections.into())
+            .with_hierarchy(hierarchy)
+            .with_stargates(crate::stargates::Stargates::new(stargates)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::types::Navigatable;
+
+    /// Serves canned responses and records the requested URLs.
+    struct Fake {
+        responses: HashMap<String, Vec<Response>>,
+        requests: RefCell<Vec<String>>,
+    }
+
+    impl Transport for Fake {
+        fn get(&self, url: &str) -> anyhow::Result<Response> {
+            let count = self.requests.borrow().iter().filter(|u| *u == url).count();
+            self.requests.borrow_mut().push(url.to_string());
+            let responses = self
+                .responses
+                .get(url)
+                .ok_or_else(|| anyhow!("unexpected {url}"))?;
+            Ok(responses[count.min(responses.len() - 1)].clone())
+        }
+    }
+
+    fn ok(body: &str, pages: u32) -> Response {
+        Response {
+            status: 200,
+            body: body.to_string(),
+            pages,
+            error_limit_remain: Some(100),
+            error_limit_reset: Some(0),
+        }
+    }
+
+    fn system(id: u32, name: &str, constellation: u32, stargates: &[u32]) -> Response {
+        ok(
+            &format!(
+                r#"{{"system_id":{id},"name":"{name}","security_status":0.9,"constellation_id":{constellation},"position":{{"x":1.0,"y":2.0,"z":3.0}},"stargates":{stargates:?}}}"#
+            ),
+            1,
+        )
+    }
+
+    fn fake() -> Fake {
+        let mut responses = HashMap::new();
+        let mut add = |path: &str, response: Vec<Response>| {
+            responses.insert(format!("{ESI_URL}{path}"), response);
+        };
+        let unavailable = Response {
+            status: 503,
+            ..ok("", 1)
+        };
+        add("/universe/systems/", vec![ok("[30000001,30000002]", 2)]);
+        add(
+            "/universe/systems/?page=2",
+            vec![ok("[30000003,32000001]", 2)],
+        );
+        add(
+            "/universe/systems/30000001/",
+            vec![system(30000001, "A", 1, &[50000001])],
+        );
+        add(
+            "/universe/systems/30000002/",
+            vec![unavailable, system(30000002, "B", 1, &[50000002, 50000003])],
+        );
+        add(
+            "/universe/systems/30000003/",
+            vec![system(30000003, "C", 2, &[50000004])],
+        );
+        add(
+            "/universe/constellations/1/",
+            vec![ok(r#"{"region_id":10,"name":"Kimotoro"}"#, 1)],
+        );
+        add(
+            "/universe/constellations/2/",
+            vec![ok(r#"{"region_id":11,"name":"Ihilakken"}"#, 1)],
+        );
+        add(
+            "/universe/regions/10/",
+            vec![ok(r#"{"name":"The Forge"}"#, 1)],
+        );
+        add(
+            "/universe/regions/11/",
+            vec![ok(r#"{"name":"Lonetrek"}"#, 1)],
+        );
+        add(
+            "/sovereignty/map/",
+            vec![ok(
+                r#"[{"system_id":30000001,"faction_id":500001},{"system_id":30000003,"alliance_id":99000001}]"#,
+                1,
+            )],
+        );
+        for (gate, from, to) in [
+            (50000001, 30000001, 30000002),
+            (50000002, 30000002, 30000001),
+            (50000003, 30000002, 30000003),
+            (50000004, 30000003, 30000002),
+        ] {
+            add(
+                &format!("/universe/stargates/{gate}/"),
+                vec![ok(
+                    &format!(
+                        r#"{{"stargate_id":{gate},"system_id":{from},"position":{{"x":{gate}.0,"y":0.0,"z":0.0}},"destination":{{"system_id":{to}}}}}"#
+                    ),
+                    1,
+                )],
+            );
+        }
+        Fake {
+            responses,
+            requests: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let dir = std::env::temp_dir().join(format!("neweden-esi-{}", std::process::id()));
+        let transport = fake();
+        let builder = EsiBuilder::new(&transport)
+            .min_interval(Duration::ZERO)
+            .cache_dir(&dir);
+        let universe = builder.build().unwrap();
+
+        assert_eq!(3, universe.all_systems().len());
+        let b = universe.get_system(&30000002.into()).unwrap();
+        assert_eq!("B", b.name);
+        assert_eq!("The Forge", b.region_name);
+        assert_eq!(None, b.faction_id);
+        assert_eq!(
+            Some(500001),
+            universe.get_system(&30000001.into()).unwrap().faction_id
+        );
+        assert_eq!(
+            Some("Lonetrek".to_string()),
+            universe
+                .region_of(&30000003.into())
+                .map(|region| region.name)
+        );
+        let connections = universe.get_connections(&30000002.into()).unwrap();
+        assert_eq!(2, connections.len());
+        assert!(connections.iter().any(|conn| conn.to == 30000003.into()
+            && conn.r#type == types::ConnectionType::Stargate(types::StargateType::Regional)));
+        let gate = universe
+            .stargates()
+            .between(&30000002.into(), &30000003.into())
+            .unwrap();
+        assert_eq!((50000003, 50000003.0), (gate.id, gate.position.x));
+        let requests = transport.requests.borrow().len();
+
+        // everything is served from the cache now
+        let cached = EsiBuilder::new(&transport).cache_dir(&dir).build().unwrap();
+        assert_eq!(3, cached.all_systems().len());
+        assert_eq!(requests, transport.requests.borrow().len());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
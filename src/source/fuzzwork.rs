@@ -0,0 +1,220 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Keeps a copy of the latest Fuzzwork SQLite conversion of the SDE in a cache
+//! directory and loads the universe from it.
+//!
+//! The dump is only downloaded again when the checksum published next to it changes.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use md5::{Digest, Md5};
+
+use crate::source::sqlite::DatabaseBuilder;
+use crate::types;
+
+pub const FUZZWORK_URL: &str = "https://www.fuzzwork.co.uk/dump/sqlite-latest.sqlite.bz2";
+
+const DATABASE: &str = "sqlite-latest.sqlite";
+const CHECKSUM: &str = "sqlite-latest.sqlite.md5";
+
+type Fetch = Box<dyn Fn(&str) -> anyhow::Result<Vec<u8>>>;
+
+fn fetch_with(agent: ureq::Agent) -> Fetch {
+    Box::new(move |url| {
+        let mut response = agent.get(url).call()?;
+        // the dump is larger than ureq's default limit of 10MB
+        Ok(response
+            .body_mut()
+            .with_config()
+            .limit(u64::MAX)
+            .read_to_vec()?)
+    })
+}
+
+/// Downloads the dump if the cached copy is missing or outdated.
+///
+/// # Example
+/// ```no_run
+/// use neweden::source::fuzzwork::FuzzworkBuilder;
+///
+/// let universe = FuzzworkBuilder::new("/var/cache/neweden").build().unwrap();
+/// ```
+pub struct FuzzworkBuilder {
+    cache_dir: PathBuf,
+    url: String,
+    fetch: Fetch,
+}
+
+impl FuzzworkBuilder {
+    pub fn new(cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            cache_dir: cache_dir.into(),
+            url: FUZZWORK_URL.to_string(),
+            fetch: fetch_with(ureq::agent()),
+        }
+    }
+
+    /// Downloads with an agent configured by the caller, e.g. with a proxy.
+    pub fn agent(mut self, agent: ureq::Agent) -> Self {
+        self.fetch = fetch_with(agent);
+        self
+    }
+
+    /// Uses a mirror instead of fuzzwork.co.uk. The checksum is expected at the
+    /// same URL with `.md5` appended.
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    /// Path of the cached database, whether or not it exists yet.
+    pub fn path(&self) -> PathBuf {
+        self.cache_dir.join(DATABASE)
+    }
+
+    /// Makes sure the cached database is the latest dump and returns its path.
+    pub fn update(&self) -> anyhow::Result<PathBuf> {
+        let url = format!("{}.md5", self.url);
+        let checksum = String::from_utf8((self.fetch)(&url)?)
+            .ok()
+            .and_then(|body| body.split_whitespace().next().map(str::to_lowercase))
+            .ok_or_else(|| anyhow!("{url}: invalid checksum"))?;
+
+        let path = self.path();
+        let checksum_path = self.cache_dir.join(CHECKSUM);
+        if path.exists() && std::fs::read_to_string(&checksum_path).ok() == Some(checksum.clone()) {
+            return Ok(path);
+        }
+
+        let compressed = (self.fetch)(&self.url)?;
+        let actual = hex(&md5(&compressed));
+        if actual != checksum {
+            return Err(anyhow!(
+                "{}: checksum mismatch, expected {checksum}, got {actual}",
+                self.url
+            ));
+        }
+        let mut database = Vec::new();
+        bzip2::read::BzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut database)
+            .context("decompressing the dump")?;
+
+        std::fs::create_dir_all(&self.cache_dir)?;
+        // never leave a partially written database behind
+        let partial = self.cache_dir.join(format!("{DATABASE}.partial"));
+        std::fs::write(&partial, database)?;
+        std::fs::rename(&partial, &path)?;
+        std::fs::write(&checksum_path, checksum)?;
+        Ok(path)
+    }
+
+    pub fn build(self) -> anyhow::Result<types::Universe> {
+        let path = self.update()?;
+        DatabaseBuilder::new(path_str(&path)?).build()
+    }
+}
+
+fn path_str(path: &Path) -> anyhow::Result<&str> {
+    path.to_str()
+        .ok_or_else(|| anyhow!("{}: path is not valid UTF-8", path.display()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// MD5 as published by Fuzzwork. Only used to detect corrupt downloads.
+fn md5(data: &[u8]) -> Vec<u8> {
+    Md5::digest(data).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::io::Write;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_md5() {
+        assert_eq!("d41d8cd98f00b204e9800998ecf8427e", hex(&md5(b"")));
+        assert_eq!("900150983cd24fb0d6963f7d28e17f72", hex(&md5(b"abc")));
+        assert_eq!(
+            "57edf4a22be3c955ac49da2e2107b67a",
+            hex(&md5(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            ))
+        );
+    }
+
+    fn dump(dir: &Path) -> Vec<u8> {
+        let path = dir.join("source.sqlite");
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE mapRegions (regionID INTEGER, regionName TEXT);
+            CREATE TABLE mapSolarSystems (
+                solarSystemID INTEGER, solarSystemName TEXT, regionID INTEGER,
+                x REAL, y REAL, z REAL, security REAL, factionID INTEGER
+            );
+            CREATE TABLE mapSolarSystemJumps (
+                fromRegionID INTEGER, fromConstellationID INTEGER, fromSolarSystemID INTEGER,
+                toRegionID INTEGER, toConstellationID INTEGER, toSolarSystemID INTEGER
+            );
+            INSERT INTO mapRegions VALUES (10000002, 'The Forge');
+            INSERT INTO mapSolarSystems VALUES
+                (30000142, 'Jita', 10000002, 0, 0, 0, 0.9459, 500001),
+                (30000144, 'Perimeter', 10000002, 0, 0, 0, 0.9072, 500001);
+            INSERT INTO mapSolarSystemJumps VALUES
+                (10000002, 20000020, 30000142, 10000002, 20000020, 30000144);
+            ",
+        )
+        .unwrap();
+        drop(conn);
+        std::fs::read(&path).unwrap()
+    }
+
+    #[test]
+    fn test_build() {
+        let dir = std::env::temp_dir().join(format!("neweden-fuzzwork-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(&dump(&dir)).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let checksum = Rc::new(RefCell::new(hex(&md5(&compressed))));
+        let downloads = Rc::new(Cell::new(0));
+
+        let builder = || {
+            let (checksum, downloads, compressed) =
+                (checksum.clone(), downloads.clone(), compressed.clone());
+            let mut builder = FuzzworkBuilder::new(dir.join("cache"));
+            builder.fetch = Box::new(move |url| {
+                if url.ends_with(".md5") {
+                    Ok(format!("{}  sqlite-latest.sqlite.bz2\n", checksum.borrow()).into_bytes())
+                } else {
+                    downloads.set(downloads.get() + 1);
+                    Ok(compressed.clone())
+                }
+            });
+            builder
+        };
+
+        let universe = builder().build().unwrap();
+        assert_eq!("Jita", universe.get_system(&30000142.into()).unwrap().name);
+        assert_eq!(1, universe.get_connections(&30000142.into()).unwrap().len());
+        builder().build().unwrap();
+        assert_eq!(1, downloads.get());
+
+        *checksum.borrow_mut() = "0".repeat(32);
+        assert!(builder().update().is_err());
+        assert_eq!(2, downloads.get());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
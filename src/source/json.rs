@@ -0,0 +1,247 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! JSON snapshots of a universe, e.g. to ship a universe to a web service that has
+//! no database.
+//!
+//! A snapshot contains the systems and connections of the universe with its
+//! hierarchy, stations, stargates, celestials and wormhole classes and, if taken
+//! from an `ExtendedUniverse`, its additional connections and avoided systems.
+//! Annotations and overlays are application data and are not included.
+
+use serde::{Deserialize, Serialize};
+
+use crate::celestials::{Celestial, Celestials};
+use crate::hierarchy::Hierarchy;
+use crate::source::tables::{SideTables, wormhole_class_overlay};
+use crate::stargates::{Stargate, Stargates};
+use crate::stations::{Station, Stations};
+use crate::types;
+use crate::workspace::Extension;
+
+/// The serialized form of a universe. All entries are sorted by id, so snapshots of
+/// the same universe are identical.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub systems: Vec<types::System>,
+    pub connections: Vec<types::Connection>,
+    #[serde(default)]
+    pub hierarchy: Hierarchy,
+    #[serde(default)]
+    pub stations: Vec<Station>,
+    #[serde(default)]
+    pub stargates: Vec<Stargate>,
+    #[serde(default)]
+    pub celestials: Vec<Celestial>,
+    #[serde(default)]
+    pub wormhole_classes: Vec<(types::SystemId, types::WormholeClass)>,
+    /// Connections added by an `ExtendedUniverse`.
+    #[serde(default)]
+    pub extension: Vec<types::Connection>,
+    /// Systems avoided by an `ExtendedUniverse`.
+    #[serde(default)]
+    pub avoid: Vec<types::SystemId>,
+}
+
+fn sorted_connections(connections: &types::AdjacentMap) -> Vec<types::Connection> {
+    let mut connections = connections
+        .0
+        .values()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+    connections.sort_by_key(|conn| (conn.from, conn.to));
+    connections
+}
+
+impl Snapshot {
+    pub fn new(universe: &types::Universe) -> Self {
+        let mut systems = universe
+            .systems
+            .systems()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        systems.sort_by_key(|system| system.id);
+        let tables = SideTables::new(universe);
+        Self {
+            systems,
+            connections: sorted_connections(&universe.connections),
+            hierarchy: tables.hierarchy,
+            stations: tables.stations,
+            stargates: tables.stargates,
+            celestials: tables.celestials,
+            wormhole_classes: tables.wormhole_classes,
+            extension: Vec::new(),
+            avoid: Vec::new(),
+        }
+    }
+
+    /// Includes the connections and avoided systems added by the extended universe.
+    pub fn extended(universe: &types::ExtendedUniverse<'_, types::Universe>) -> Self {
+        let mut avoid = universe.avoid.iter().copied().collect::<Vec<_>>();
+        avoid.sort();
+        Self {
+            extension: sorted_connections(&universe.connections),
+            avoid,
+            ..Self::new(universe.universe)
+        }
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Builds the base universe.
+    pub fn universe(&self) -> types::Universe {
+        types::Universe::new(
+            self.systems.iter().cloned().into(),
+            self.connections.iter().cloned().into(),
+        )
+        .with_hierarchy(self.hierarchy.clone())
+        .with_stations(Stations::new(self.stations.clone()))
+        .with_stargates(Stargates::new(self.stargates.clone()))
+        .with_celestials(Celestials::new(self.celestials.clone()))
+        .with_wormhole_classes(wormhole_class_overlay(&self.wormhole_classes))
+    }
+
+    /// The additions of the extended universe, e.g. to add to a `Workspace` or pass
+    /// to `ExtendedUniverseBuilder`.
+    pub fn extension(&self) -> Extension {
+        Extension {
+            connections: self.extension.clone(),
+            avoid: self.avoid.clone(),
+        }
+    }
+}
+
+impl types::Universe {
+    /// Serializes the systems and connections of the universe together with its
+    /// hierarchy, stations, stargates, celestials and wormhole classes.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::builder::UniverseBuilder;
+    /// use neweden::{Navigatable, System, Universe};
+    ///
+    /// let universe = UniverseBuilder::default()
+    ///     .system(System {
+    ///         id: 30000142.into(),
+    ///         name: "Jita".to_string(),
+    ///         coordinate: (0.0, 0.0, 0.0).into(),
+    ///         security: 0.9459.into(),
+    ///         region_name: "The Forge".to_string(),
+    ///         faction_id: Some(500001),
+    ///     })
+    ///     .build();
+    /// let json = universe.to_json().unwrap();
+    /// let loaded = Universe::from_json(&json).unwrap();
+    /// assert_eq!(universe.version(), loaded.version());
+    /// ```
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Snapshot::new(self).to_json()
+    }
+
+    /// Loads a universe from `to_json()` output. Additions of an extended universe
+    /// in the snapshot are ignored, use `Snapshot::extension()` for those.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(Snapshot::from_json(json)?.universe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{ExtendedUniverseBuilder, UniverseBuilder};
+    use crate::fixtures::system;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_snapshot() {
+        let universe = UniverseBuilder::default()
+            .system(system(1))
+            .system(system(2).in_region("Delve").at(1.0, 2.0, 3.0))
+            .system(system(3))
+            .connection(types::Connection {
+                from: 1.into(),
+                to: 2.into(),
+                r#type: types::ConnectionType::Stargate(types::StargateType::Regional),
+            })
+            .build();
+        let mut hierarchy = Hierarchy::default();
+        hierarchy.insert_region(10000060.into(), "Delve");
+        hierarchy.insert_constellation(20000696.into(), 10000060.into(), "O-EIMK");
+        hierarchy.insert_system(1.into(), 20000696.into());
+        let mut wormhole_classes = crate::overlay::Overlay::default();
+        wormhole_classes.insert(3.into(), types::WormholeClass::Class(2));
+        let universe = universe
+            .with_hierarchy(hierarchy)
+            .with_stations(Stations::new(vec![Station {
+                id: 60013843,
+                name: "1DQ1-A - Station".to_string(),
+                system: 1.into(),
+                services: Default::default(),
+            }]))
+            .with_stargates(Stargates::new(vec![Stargate {
+                id: 50000001,
+                system: 1.into(),
+                destination: 2.into(),
+                position: (1.0, 2.0, 3.0).into(),
+            }]))
+            .with_celestials(Celestials::new(vec![Celestial {
+                id: 40000001,
+                name: "1DQ1-A I".to_string(),
+                system: 1.into(),
+                kind: crate::celestials::CelestialKind::Planet,
+                position: (4.0, 5.0, 6.0).into(),
+            }]))
+            .with_wormhole_classes(wormhole_classes);
+        let extended = ExtendedUniverseBuilder::new(&universe)
+            .connection(types::Connection {
+                from: 2.into(),
+                to: 3.into(),
+                r#type: types::ConnectionType::Ansiblex,
+            })
+            .avoid(3.into())
+            .build();
+
+        let json = Snapshot::extended(&extended).to_json().unwrap();
+        let snapshot = Snapshot::from_json(&json).unwrap();
+        let loaded = snapshot.universe();
+        assert_eq!(universe.version(), loaded.version());
+        assert_eq!("Delve", loaded.get_system(&2.into()).unwrap().region_name);
+        assert_eq!(json, Snapshot::extended(&extended).to_json().unwrap());
+        assert_eq!(
+            Some(20000696.into()),
+            loaded.hierarchy().constellation_of(&1.into())
+        );
+        assert_eq!(
+            "1DQ1-A - Station",
+            loaded.stations_in_system(&1.into())[0].name
+        );
+        assert_eq!(2, loaded.stargates_in_system(&1.into())[0].destination.0);
+        assert_eq!("1DQ1-A I", loaded.celestials(&1.into())[0].name);
+        assert_eq!(
+            Some(types::WormholeClass::Class(2)),
+            loaded.wormhole_class(&3.into())
+        );
+
+        let extension = snapshot.extension();
+        let reloaded = ExtendedUniverseBuilder::new(&loaded)
+            .connection(extension.connections[0].clone())
+            .avoid(extension.avoid[0])
+            .build();
+        assert_eq!(extended.version(), reloaded.version());
+        assert!(reloaded.avoided_by_default(&3.into()));
+
+        // the base universe ignores the extension
+        let base = types::Universe::from_json(&json).unwrap();
+        assert!(base.get_connections(&2.into()).is_none());
+    }
+}
@@ -0,0 +1,624 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! A universe file that is memory mapped instead of loaded, so several processes
+//! on one host share a single copy of the graph.
+//!
+//! The file consists of a header, a table of fixed size system records sorted by
+//! id, the offsets of the connections of each system, the connections and the
+//! names. All numbers are little endian. Systems are found by binary search and
+//! connections are read straight from the mapping. The hierarchy, stations,
+//! stargates, celestials and wormhole classes follow the names, encoded with bincode.
+//!
+//! `Navigatable` hands out references to `System`s, which own their names. A system
+//! is therefore decoded into process memory the first time it is looked up and
+//! kept. The tables after the names are decoded when the file is opened, the graph
+//! stays in the shared mapping.
+//!
+//! The file must not be modified while it is mapped. `write()` replaces the file
+//! atomically, processes that have the previous file open keep reading it.
+
+use std::cell::OnceCell;
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{anyhow, bail};
+use memmap2::Mmap;
+
+use crate::celestials::{Celestial, Celestials};
+use crate::hierarchy::Hierarchy;
+use crate::overlay::Overlay;
+use crate::source::tables::{SideTables, wormhole_class_overlay};
+use crate::stargates::{Stargate, Stargates};
+use crate::stations::{Station, Stations};
+use crate::types::{self, Navigatable};
+
+const MAGIC: &[u8; 8] = b"NEWEDMAP";
+const FORMAT_VERSION: u32 = 2;
+
+const HEADER_SIZE: usize = 48;
+const SYSTEM_SIZE: usize = 48;
+const CONNECTION_SIZE: usize = 8;
+
+const LOCAL: u8 = 0;
+const CONSTELLATION: u8 = 1;
+const REGIONAL: u8 = 2;
+const ANSIBLEX: u8 = 3;
+
+fn connection_tag(r#type: &types::ConnectionType) -> anyhow::Result<u8> {
+    Ok(match r#type {
+        types::ConnectionType::Stargate(types::StargateType::Local) => LOCAL,
+        types::ConnectionType::Stargate(types::StargateType::Constellation) => CONSTELLATION,
+        types::ConnectionType::Stargate(types::StargateType::Regional) => REGIONAL,
+        types::ConnectionType::Ansiblex => ANSIBLEX,
+        other => bail!("cannot map {other} connections, use an ExtendedUniverse"),
+    })
+}
+
+fn connection_type(tag: u8) -> anyhow::Result<types::ConnectionType> {
+    Ok(match tag {
+        LOCAL => types::ConnectionType::Stargate(types::StargateType::Local),
+        CONSTELLATION => types::ConnectionType::Stargate(types::StargateType::Constellation),
+        REGIONAL => types::ConnectionType::Stargate(types::StargateType::Regional),
+        ANSIBLEX => types::ConnectionType::Ansiblex,
+        tag => bail!("invalid connection type {tag}"),
+    })
+}
+
+fn u16_at(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes(data[at..at + 2].try_into().unwrap())
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(data[at..at + 8].try_into().unwrap())
+}
+
+fn f32_at(data: &[u8], at: usize) -> f32 {
+    f32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn f64_at(data: &[u8], at: usize) -> f64 {
+    f64::from_le_bytes(data[at..at + 8].try_into().unwrap())
+}
+
+/// Offsets of the sections of a file with `systems` systems, `connections`
+/// connections and `strings` bytes of names.
+struct Layout {
+    systems: usize,
+    connections: usize,
+    adjacency_at: usize,
+    connections_at: usize,
+    strings_at: usize,
+    tables_at: usize,
+}
+
+impl Layout {
+    fn new(systems: usize, connections: usize, strings: usize) -> Self {
+        let adjacency_at = HEADER_SIZE + systems * SYSTEM_SIZE;
+        let connections_at = adjacency_at + (systems + 1) * 4;
+        let strings_at = connections_at + connections * CONNECTION_SIZE;
+        Self {
+            systems,
+            connections,
+            adjacency_at,
+            connections_at,
+            strings_at,
+            tables_at: strings_at + strings,
+        }
+    }
+
+    fn system_at(&self, index: usize) -> usize {
+        HEADER_SIZE + index * SYSTEM_SIZE
+    }
+}
+
+fn encode(universe: &types::Universe) -> anyhow::Result<Vec<u8>> {
+    let mut systems = universe.systems.systems();
+    systems.sort_by_key(|system| system.id);
+    let mut connections = universe
+        .connections
+        .0
+        .values()
+        .flatten()
+        .collect::<Vec<_>>();
+    connections.sort_by_key(|conn| (conn.from, conn.to));
+    let layout = Layout::new(systems.len(), connections.len(), 0);
+
+    let mut strings = Vec::new();
+    let mut string = |value: &str| -> anyhow::Result<(u32, u16)> {
+        let len = u16::try_from(value.len()).map_err(|_| anyhow!("name too long: {value}"))?;
+        let offset = u32::try_from(strings.len())?;
+        strings.extend_from_slice(value.as_bytes());
+        Ok((offset, len))
+    };
+
+    let mut out = Vec::with_capacity(layout.strings_at);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&u32::try_from(systems.len())?.to_le_bytes());
+    out.extend_from_slice(&universe.version().to_le_bytes());
+    out.extend_from_slice(&u32::try_from(connections.len())?.to_le_bytes());
+    // the length of the names is only known once the systems are written
+    let strings_len_at = out.len();
+    out.resize(HEADER_SIZE, 0);
+
+    for system in &systems {
+        let (name, name_len) = string(&system.name)?;
+        let (region, region_len) = string(&system.region_name)?;
+        out.extend_from_slice(&system.id.0.to_le_bytes());
+        // 0 is not a faction id
+        out.extend_from_slice(&system.faction_id.unwrap_or(0).to_le_bytes());
+        for value in [
+            system.coordinate.x,
+            system.coordinate.y,
+            system.coordinate.z,
+        ] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&system.security.0.to_le_bytes());
+        out.extend_from_slice(&name.to_le_bytes());
+        out.extend_from_slice(&region.to_le_bytes());
+        out.extend_from_slice(&name_len.to_le_bytes());
+        out.extend_from_slice(&region_len.to_le_bytes());
+    }
+
+    // connections are sorted by origin, so each system's connections are a range
+    let mut next = 0;
+    for system in &systems {
+        out.extend_from_slice(&(next as u32).to_le_bytes());
+        while next < connections.len() && connections[next].from == system.id {
+            next += 1;
+        }
+    }
+    if next != connections.len() {
+        bail!(
+            "connection from unknown system {}",
+            connections[next].from.0
+        );
+    }
+    out.extend_from_slice(&(next as u32).to_le_bytes());
+
+    for conn in &connections {
+        out.extend_from_slice(&conn.to.0.to_le_bytes());
+        out.push(connection_tag(&conn.r#type)?);
+        out.extend_from_slice(&[0; 3]);
+    }
+    out[strings_len_at..strings_len_at + 4]
+        .copy_from_slice(&u32::try_from(strings.len())?.to_le_bytes());
+    out.extend_from_slice(&strings);
+    bincode::serde::encode_into_std_write(
+        SideTables::new(universe),
+        &mut out,
+        bincode::config::standard(),
+    )?;
+    Ok(out)
+}
+
+/// Writes the universe in the memory mapped format. Only stargates and Ansiblex
+/// gates can be written, other connections belong into an `ExtendedUniverse`.
+pub fn write(universe: &types::Universe, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, encode(universe)?)?;
+    std::fs::rename(partial, path)?;
+    Ok(())
+}
+
+/// A universe read from a memory mapped file written by `write()`.
+///
+/// # Example
+/// ```no_run
+/// use neweden::source::mmap::{self, MmapUniverse};
+/// use neweden::source::sqlite::DatabaseBuilder;
+/// use neweden::Navigatable;
+///
+/// // once, e.g. when deploying a new SDE
+/// let universe = DatabaseBuilder::new("./sqlite-latest.sqlite").build().unwrap();
+/// mmap::write(&universe, "/var/lib/neweden/universe.map").unwrap();
+///
+/// // in every process
+/// let universe = MmapUniverse::open("/var/lib/neweden/universe.map").unwrap();
+/// println!("{:?}", universe.get_system(&30000142.into()).unwrap().name);
+/// ```
+pub struct MmapUniverse {
+    data: Mmap,
+    layout: Layout,
+    systems: Vec<OnceCell<types::System>>,
+    version: u64,
+    hierarchy: Hierarchy,
+    stations: Stations,
+    stargates: Stargates,
+    celestials: Celestials,
+    wormhole_classes: Overlay<types::SystemId, types::WormholeClass>,
+}
+
+impl MmapUniverse {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the file is only read. It must not be modified while mapped, which
+        // `write()` guarantees by replacing the file instead of writing into it.
+        let data = unsafe { Mmap::map(&file)? };
+        Self::new(data)
+    }
+
+    /// The regions and constellations of the systems, empty if the universe that
+    /// was written didn't know them.
+    pub fn hierarchy(&self) -> &Hierarchy {
+        &self.hierarchy
+    }
+
+    pub fn stations(&self) -> &Stations {
+        &self.stations
+    }
+
+    pub fn stations_in_system(&self, id: &types::SystemId) -> Vec<&Station> {
+        self.stations.in_system(id).collect()
+    }
+
+    pub fn stargates(&self) -> &Stargates {
+        &self.stargates
+    }
+
+    pub fn stargates_in_system(&self, id: &types::SystemId) -> Vec<&Stargate> {
+        self.stargates.in_system(id).collect()
+    }
+
+    pub fn all_celestials(&self) -> &Celestials {
+        &self.celestials
+    }
+
+    pub fn celestials(&self, id: &types::SystemId) -> Vec<&Celestial> {
+        self.celestials.in_system(id).collect()
+    }
+
+    pub fn wormhole_class(&self, id: &types::SystemId) -> Option<types::WormholeClass> {
+        self.wormhole_classes.get(id).copied()
+    }
+
+    fn new(data: Mmap) -> anyhow::Result<Self> {
+        if data.len() < HEADER_SIZE || &data[..8] != MAGIC {
+            bail!("not a universe map");
+        }
+        let format = u32_at(&data, 8);
+        if format != FORMAT_VERSION {
+            bail!("unsupported format version {format}, expected {FORMAT_VERSION}");
+        }
+        let layout = Layout::new(
+            u32_at(&data, 12) as usize,
+            u32_at(&data, 24) as usize,
+            u32_at(&data, 28) as usize,
+        );
+        let version = u64_at(&data, 16);
+        if data.len() < layout.tables_at {
+            bail!("unexpected end of file");
+        }
+        let (tables, read): (SideTables, _) = bincode::serde::decode_from_slice(
+            &data[layout.tables_at..],
+            bincode::config::standard(),
+        )?;
+        if layout.tables_at + read != data.len() {
+            bail!("trailing data");
+        }
+
+        let universe = Self {
+            systems: (0..layout.systems).map(|_| OnceCell::new()).collect(),
+            layout,
+            data,
+            version,
+            wormhole_classes: wormhole_class_overlay(&tables.wormhole_classes),
+            hierarchy: tables.hierarchy,
+            stations: Stations::new(tables.stations),
+            stargates: Stargates::new(tables.stargates),
+            celestials: Celestials::new(tables.celestials),
+        };
+        universe.validate()?;
+        Ok(universe)
+    }
+
+    /// Checks the offsets, the connection types and the universe version, so
+    /// lookups can't read out of bounds and corrupt files are detected.
+    fn validate(&self) -> anyhow::Result<()> {
+        let strings = self.layout.tables_at - self.layout.strings_at;
+        let mut ids = Vec::with_capacity(self.layout.systems);
+        let mut previous = 0;
+        for index in 0..self.layout.systems {
+            let at = self.layout.system_at(index);
+            let id = u32_at(&self.data, at);
+            if !ids.is_empty() && id <= previous {
+                bail!("systems are not sorted");
+            }
+            previous = id;
+            ids.push(id);
+            for (offset, len) in [(at + 36, at + 44), (at + 40, at + 46)] {
+                let end = u32_at(&self.data, offset) as usize + u16_at(&self.data, len) as usize;
+                if end > strings {
+                    bail!("invalid name of system {id}");
+                }
+            }
+            let (start, end) = self.adjacency(index);
+            if start > end || end > self.layout.connections {
+                bail!("invalid connections of system {id}");
+            }
+        }
+        let last = self.layout.adjacency_at + self.layout.systems * 4;
+        if u32_at(&self.data, last) as usize != self.layout.connections {
+            bail!("invalid connection count");
+        }
+
+        let mut version = types::fingerprint(types::FINGERPRINT_SEED, ids.iter().copied());
+        for (index, id) in ids.iter().enumerate() {
+            let (start, end) = self.adjacency(index);
+            for conn in start..end {
+                let at = self.layout.connections_at + conn * CONNECTION_SIZE;
+                connection_type(self.data[at + 4])?;
+                version = types::fingerprint(version, [*id, u32_at(&self.data, at)]);
+            }
+        }
+        if version != self.version {
+            bail!("universe version mismatch, the file is corrupt");
+        }
+        Ok(())
+    }
+
+    fn adjacency(&self, index: usize) -> (usize, usize) {
+        let at = self.layout.adjacency_at + index * 4;
+        (
+            u32_at(&self.data, at) as usize,
+            u32_at(&self.data, at + 4) as usize,
+        )
+    }
+
+    fn index(&self, id: &types::SystemId) -> Option<usize> {
+        let (mut low, mut high) = (0, self.layout.systems);
+        while low < high {
+            let mid = (low + high) / 2;
+            let found = u32_at(&self.data, self.layout.system_at(mid));
+            match found.cmp(&id.0) {
+                std::cmp::Ordering::Equal => return Some(mid),
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+            }
+        }
+        None
+    }
+
+    fn string(&self, offset: usize, len: usize) -> String {
+        let at = self.layout.strings_at + offset;
+        String::from_utf8_lossy(&self.data[at..at + len]).into_owned()
+    }
+
+    fn coordinate(&self, index: usize) -> types::Coordinate {
+        let at = self.layout.system_at(index);
+        (
+            f64_at(&self.data, at + 8),
+            f64_at(&self.data, at + 16),
+            f64_at(&self.data, at + 24),
+        )
+            .into()
+    }
+
+    fn system(&self, index: usize) -> &types::System {
+        self.systems[index].get_or_init(|| {
+            let at = self.layout.system_at(index);
+            let faction_id = u32_at(&self.data, at + 4);
+            types::System {
+                id: u32_at(&self.data, at).into(),
+                name: self.string(
+                    u32_at(&self.data, at + 36) as usize,
+                    u16_at(&self.data, at + 44) as usize,
+                ),
+                coordinate: self.coordinate(index),
+                security: f32_at(&self.data, at + 32).into(),
+                region_name: self.string(
+                    u32_at(&self.data, at + 40) as usize,
+                    u16_at(&self.data, at + 46) as usize,
+                ),
+                faction_id: (faction_id != 0).then_some(faction_id),
+            }
+        })
+    }
+}
+
+impl types::Navigatable for MmapUniverse {
+    fn get_system(&self, id: &types::SystemId) -> Option<&types::System> {
+        Some(self.system(self.index(id)?))
+    }
+
+    fn get_connections(&self, from: &types::SystemId) -> Option<Vec<types::Connection>> {
+        let (start, end) = self.adjacency(self.index(from)?);
+        if start == end {
+            return None;
+        }
+        let connections = (start..end)
+            .map(|conn| {
+                let at = self.layout.connections_at + conn * CONNECTION_SIZE;
+                types::Connection {
+                    from: *from,
+                    to: u32_at(&self.data, at).into(),
+                    r#type: connection_type(self.data[at + 4])
+                        .expect("connection types are validated when the file is opened"),
+                }
+            })
+            .collect();
+        Some(connections)
+    }
+
+    fn get_systems_by_range(
+        &self,
+        from: &types::SystemId,
+        range: types::Meters,
+    ) -> Option<Vec<&types::System>> {
+        let origin = self.coordinate(self.index(from)?);
+        // scans the coordinates in the mapping and only decodes systems in range
+        let systems = (0..self.layout.systems)
+            .filter(|index| {
+                let c = self.coordinate(*index);
+                let (dx, dy, dz) = (c.x - origin.x, c.y - origin.y, c.z - origin.z);
+                (dx * dx + dy * dy + dz * dz).sqrt() <= range.0
+            })
+            .map(|index| self.system(index))
+            .filter(|system| system.is_navigable())
+            .filter(|system| {
+                types::SecurityClass::from(system.security) != types::SecurityClass::Highsec
+            })
+            .collect();
+        Some(systems)
+    }
+
+    fn all_systems(&self) -> Vec<&types::System> {
+        (0..self.layout.systems)
+            .map(|index| self.system(index))
+            .collect()
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn avoided_by_default(&self, id: &types::SystemId) -> bool {
+        !types::SystemClass::from(id).is_navigable()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::navigation::PathBuilder;
+
+    fn stargate(from: u32, to: u32) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+        }
+    }
+
+    #[test]
+    fn test_mmap() {
+        let universe = UniverseBuilder::default()
+            .system(
+                system(3)
+                    .named("Ouelletta")
+                    .with_security(0.3)
+                    .at(2e16, 0.0, 0.0),
+            )
+            .system(
+                system(1)
+                    .named("Vlillirier")
+                    .with_security(0.4)
+                    .in_region("Placid")
+                    .with_faction(Some(500004)),
+            )
+            .system(
+                system(2)
+                    .named("Intaki")
+                    .with_security(0.9)
+                    .at(1e16, 0.0, 0.0),
+            )
+            .connection(stargate(1, 2))
+            .connection(stargate(2, 1))
+            .connection(stargate(2, 3))
+            .connection(types::Connection {
+                from: 3.into(),
+                to: 1.into(),
+                r#type: types::ConnectionType::Ansiblex,
+            })
+            .build();
+        let mut hierarchy = Hierarchy::default();
+        hierarchy.insert_region(10000048.into(), "Placid");
+        hierarchy.insert_constellation(20000560.into(), 10000048.into(), "Amevync");
+        hierarchy.insert_system(1.into(), 20000560.into());
+        let mut wormhole_classes = Overlay::default();
+        wormhole_classes.insert(3.into(), types::WormholeClass::Thera);
+        let universe = universe
+            .with_hierarchy(hierarchy)
+            .with_stations(Stations::new(vec![Station {
+                id: 60010825,
+                name: "Vlillirier V - Moon 9".to_string(),
+                system: 1.into(),
+                services: Default::default(),
+            }]))
+            .with_stargates(Stargates::new(vec![Stargate {
+                id: 50000001,
+                system: 1.into(),
+                destination: 2.into(),
+                position: (1.0, 2.0, 3.0).into(),
+            }]))
+            .with_celestials(Celestials::new(vec![Celestial {
+                id: 40000001,
+                name: "Vlillirier I".to_string(),
+                system: 1.into(),
+                kind: crate::celestials::CelestialKind::Planet,
+                position: (4.0, 5.0, 6.0).into(),
+            }]))
+            .with_wormhole_classes(wormhole_classes);
+        let path = std::env::temp_dir().join(format!("neweden-{}.map", std::process::id()));
+        write(&universe, &path).unwrap();
+        let mapped = MmapUniverse::open(&path).unwrap();
+
+        assert_eq!(universe.version(), mapped.version());
+        let first = mapped.get_system(&1.into()).unwrap();
+        assert_eq!(
+            ("Vlillirier", "Placid"),
+            (&*first.name, &*first.region_name)
+        );
+        assert_eq!(Some(500004), first.faction_id);
+        assert!(mapped.get_system(&4.into()).is_none());
+        assert_eq!(2, mapped.get_connections(&2.into()).unwrap().len());
+        assert_eq!(
+            types::ConnectionType::Ansiblex,
+            mapped.get_connections(&3.into()).unwrap()[0].r#type
+        );
+
+        let path_found = PathBuilder::new(&mapped)
+            .waypoint(mapped.get_system(&1.into()).unwrap())
+            .waypoint(mapped.get_system(&3.into()).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(2, path_found.jumps());
+        let in_range = mapped
+            .get_systems_by_range(&1.into(), types::Meters(2.5e16))
+            .unwrap()
+            .iter()
+            .map(|system| system.id.0)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![1, 3], in_range);
+
+        assert_eq!(
+            Some(20000560.into()),
+            mapped.hierarchy().constellation_of(&1.into())
+        );
+        assert_eq!(
+            "Vlillirier V - Moon 9",
+            mapped.stations_in_system(&1.into())[0].name
+        );
+        assert_eq!(2, mapped.stargates_in_system(&1.into())[0].destination.0);
+        assert_eq!("Vlillirier I", mapped.celestials(&1.into())[0].name);
+        assert_eq!(
+            Some(types::WormholeClass::Thera),
+            mapped.wormhole_class(&3.into())
+        );
+        drop(mapped);
+
+        // a flipped byte in a connection and an invalid connection type are detected
+        let data = std::fs::read(&path).unwrap();
+        let at = Layout::new(3, 4, 0).connections_at;
+        let mut corrupt = data.clone();
+        corrupt[at] ^= 1;
+        std::fs::write(&path, &corrupt).unwrap();
+        assert!(MmapUniverse::open(&path).is_err());
+        let mut corrupt = data.clone();
+        corrupt[at + 4] = 9;
+        std::fs::write(&path, &corrupt).unwrap();
+        assert!(MmapUniverse::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -3,11 +3,47 @@
  * All rights reserved.
  */
 
+pub mod pool;
+pub mod remote;
+
+#[cfg(any(feature = "sqlite", feature = "postgres", feature = "mysql"))]
+pub mod progress;
+
+#[cfg(any(feature = "sqlite", feature = "csv"))]
+pub mod report;
+
 #[cfg(feature = "postgres")]
 pub mod postgres;
 
 #[cfg(feature = "sqlite")]
 pub mod sqlite;
 
-#[cfg(feature = "rpc")]
-pub mod rpc;
+#[cfg(feature = "cache")]
+pub mod binary;
+
+#[cfg(any(feature = "cache", feature = "mmap", feature = "json"))]
+mod tables;
+
+#[cfg(feature = "mmap")]
+pub mod mmap;
+
+#[cfg(feature = "csv")]
+pub mod csv;
+
+#[cfg(feature = "mysql")]
+pub mod mysql;
+
+#[cfg(feature = "parquet")]
+pub mod parquet;
+
+#[cfg(feature = "fuzzwork")]
+pub mod fuzzwork;
+
+#[cfg(feature = "sde")]
+pub mod sde;
+
+#[cfg(feature = "json")]
+pub mod json;
+
+#[cfg(feature = "esi")]
+pub mod esi;
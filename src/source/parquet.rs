@@ -0,0 +1,464 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Loads a universe from Parquet files of the SDE tables, e.g. written by a data
+//! pipeline for analytics, without converting them to SQLite first.
+//!
+//! # Schema
+//!
+//! Columns are looked up by name, additional columns are ignored. Integer columns
+//! may be any Parquet integer type, float columns any float or integer type.
+//!
+//! Systems (`mapSolarSystems`):
+//!
+//! | Column            | Parquet type      |
+//! |-------------------|-------------------|
+//! | `solarSystemID`   | INT32             |
+//! | `solarSystemName` | BYTE_ARRAY (UTF8) |
+//! | `regionID`        | INT32             |
+//! | `x`, `y`, `z`     | DOUBLE, meters    |
+//! | `security`        | DOUBLE            |
+//! | `factionID`       | INT32, optional   |
+//!
+//! Jumps (`mapSolarSystemJumps`): `fromRegionID`, `fromConstellationID`,
+//! `fromSolarSystemID`, `toRegionID`, `toConstellationID` and `toSolarSystemID`, all
+//! INT32.
+//!
+//! Regions (`mapRegions`, optional): `regionID` as INT32 and `regionName` as UTF8.
+//! Without regions the region id is used as region name.
+//!
+//! If the systems have a `constellationID` INT32 column the universe knows the region
+//! and constellation of each system. Constellations (`mapConstellations`, optional)
+//! name them with `constellationID` as INT32 and `constellationName` as UTF8,
+//! otherwise they are named by id.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow, bail};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::Field;
+use parquet::schema::types::Type;
+
+use crate::hierarchy;
+use crate::types;
+
+pub const SYSTEMS_COLUMNS: [&str; 8] = [
+    "solarSystemID",
+    "solarSystemName",
+    "regionID",
+    "x",
+    "y",
+    "z",
+    "security",
+    "factionID",
+];
+
+pub const JUMPS_COLUMNS: [&str; 6] = [
+    "fromRegionID",
+    "fromConstellationID",
+    "fromSolarSystemID",
+    "toRegionID",
+    "toConstellationID",
+    "toSolarSystemID",
+];
+
+pub const REGIONS_COLUMNS: [&str; 2] = ["regionID", "regionName"];
+
+pub const CONSTELLATIONS_COLUMNS: [&str; 2] = ["constellationID", "constellationName"];
+
+/// The values of a column.
+struct Column(Vec<Field>);
+
+impl Column {
+    fn int(&self, row: usize) -> anyhow::Result<Option<i64>> {
+        Ok(Some(match &self.0[row] {
+            Field::Null => return Ok(None),
+            Field::Byte(value) => (*value).into(),
+            Field::Short(value) => (*value).into(),
+            Field::Int(value) => (*value).into(),
+            Field::Long(value) => *value,
+            Field::UByte(value) => (*value).into(),
+            Field::UShort(value) => (*value).into(),
+            Field::UInt(value) => (*value).into(),
+            Field::ULong(value) => i64::try_from(*value)?,
+            other => bail!("expected an integer, got {other}"),
+        }))
+    }
+
+    fn id(&self, row: usize) -> anyhow::Result<Option<u32>> {
+        Ok(self.int(row)?.map(u32::try_from).transpose()?)
+    }
+
+    fn float(&self, row: usize) -> anyhow::Result<Option<f64>> {
+        match &self.0[row] {
+            Field::Float(value) => Ok(Some((*value).into())),
+            Field::Double(value) => Ok(Some(*value)),
+            _ => Ok(self.int(row)?.map(|value| value as f64)),
+        }
+    }
+
+    fn text(&self, row: usize) -> anyhow::Result<Option<String>> {
+        match &self.0[row] {
+            Field::Null => Ok(None),
+            Field::Str(value) => Ok(Some(value.clone())),
+            other => bail!("expected text, got {other}"),
+        }
+    }
+}
+
+fn has_column(path: &Path, name: &str) -> anyhow::Result<bool> {
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let schema = reader.metadata().file_metadata().schema();
+    Ok(schema.get_fields().iter().any(|field| field.name() == name))
+}
+
+/// Reads the named columns of a Parquet file in the given order.
+fn read(path: &Path, columns: &[&str]) -> anyhow::Result<Vec<Column>> {
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let schema = reader.metadata().file_metadata().schema();
+    let fields = columns
+        .iter()
+        .map(|name| {
+            schema
+                .get_fields()
+                .iter()
+                .find(|field| field.name() == *name)
+                .cloned()
+                .ok_or_else(|| anyhow!("missing column {name}"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let projection = Type::group_type_builder(schema.name())
+        .with_fields(fields)
+        .build()?;
+
+    let mut values = columns.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+    for row in reader.get_row_iter(Some(projection))? {
+        for (column, (_, field)) in values.iter_mut().zip(row?.into_columns()) {
+            column.push(field);
+        }
+    }
+    Ok(values.into_iter().map(Column).collect())
+}
+
+/// Columns of one file.
+struct Table {
+    name: &'static str,
+    columns: Vec<Column>,
+    rows: usize,
+}
+
+impl Table {
+    fn read(name: &'static str, path: &Path, columns: &[&str]) -> anyhow::Result<Self> {
+        let columns = read(path, columns)
+            .with_context(|| format!("reading {name} from {}", path.display()))?;
+        let rows = columns.first().map_or(0, |column| column.0.len());
+        Ok(Self {
+            name,
+            columns,
+            rows,
+        })
+    }
+
+    /// The value of a cell, failing if it is null.
+    fn required<T>(
+        &self,
+        column: usize,
+        row: usize,
+        value: impl Fn(&Column, usize) -> anyhow::Result<Option<T>>,
+    ) -> anyhow::Result<T> {
+        value(&self.columns[column], row)
+            .and_then(|value| value.ok_or_else(|| anyhow!("null value")))
+            .with_context(|| format!("{} row {}, column {}", self.name, row + 1, column + 1))
+    }
+}
+
+/// Loads a universe from Parquet files.
+///
+/// # Example
+/// ```no_run
+/// use neweden::source::parquet::ParquetBuilder;
+/// use neweden::Navigatable;
+///
+/// let universe = ParquetBuilder::new("systems.parquet", "jumps.parquet")
+///     .regions("regions.parquet")
+///     .build()
+///     .unwrap();
+/// println!("{:?}", universe.get_system(&30000142.into()).unwrap().name); // Jita
+/// ```
+pub struct ParquetBuilder {
+    systems: PathBuf,
+    jumps: PathBuf,
+    regions: Option<PathBuf>,
+    constellations: Option<PathBuf>,
+}
+
+impl ParquetBuilder {
+    pub fn new(systems: impl Into<PathBuf>, jumps: impl Into<PathBuf>) -> Self {
+        Self {
+            systems: systems.into(),
+            jumps: jumps.into(),
+            regions: None,
+            constellations: None,
+        }
+    }
+
+    /// Takes the region names from the regions file.
+    pub fn regions(mut self, path: impl Into<PathBuf>) -> Self {
+        self.regions = Some(path.into());
+        self
+    }
+
+    /// Takes the constellation names from the constellations file.
+    pub fn constellations(mut self, path: impl Into<PathBuf>) -> Self {
+        self.constellations = Some(path.into());
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<types::Universe> {
+        let mut regions = HashMap::new();
+        if let Some(path) = &self.regions {
+            let table = Table::read("regions", path, &REGIONS_COLUMNS)?;
+            for row in 0..table.rows {
+                regions.insert(
+                    table.required(0, row, Column::id)?,
+                    table.required(1, row, Column::text)?,
+                );
+            }
+        }
+
+        let mut constellations = HashMap::new();
+        if let Some(path) = &self.constellations {
+            let table = Table::read("constellations", path, &CONSTELLATIONS_COLUMNS)?;
+            for row in 0..table.rows {
+                constellations.insert(
+                    table.required(0, row, Column::id)?,
+                    table.required(1, row, Column::text)?,
+                );
+            }
+        }
+
+        let table = Table::read("systems", &self.systems, &SYSTEMS_COLUMNS)?;
+        let constellation = if has_column(&self.systems, "constellationID")
+            .with_context(|| format!("reading systems from {}", self.systems.display()))?
+        {
+            Some(Table::read("systems", &self.systems, &["constellationID"])?)
+        } else {
+            None
+        };
+        let mut hierarchy = hierarchy::Hierarchy::default();
+        let systems = (0..table.rows)
+            .map(|row| {
+                let region_id = table.required(2, row, Column::id)?;
+                let system = types::System {
+                    id: table.required(0, row, Column::id)?.into(),
+                    name: table.required(1, row, Column::text)?,
+                    coordinate: (
+                        table.required(3, row, Column::float)?,
+                        table.required(4, row, Column::float)?,
+                        table.required(5, row, Column::float)?,
+                    )
+                        .into(),
+                    security: (table.required(6, row, Column::float)? as f32).into(),
+                    region_name: regions
+                        .get(&region_id)
+                        .cloned()
+                        .unwrap_or_else(|| region_id.to_string()),
+                    faction_id: table.columns[7]
+                        .id(row)
+                        .with_context(|| format!("systems row {}, column 8", row + 1))?,
+                };
+                if let Some(constellation) = &constellation {
+                    let constellation_id = constellation.required(0, row, Column::id)?;
+                    let name = constellations
+                        .get(&constellation_id)
+                        .cloned()
+                        .unwrap_or_else(|| constellation_id.to_string());
+                    hierarchy.insert_region(region_id.into(), &system.region_name);
+                    hierarchy.insert_constellation(
+                        constellation_id.into(),
+                        region_id.into(),
+                        &name,
+                    );
+                    hierarchy.insert_system(system.id, constellation_id.into());
+                }
+                Ok(system)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let table = Table::read("jumps", &self.jumps, &JUMPS_COLUMNS)?;
+        let connections = (0..table.rows)
+            .map(|row| {
+                let values = (0..JUMPS_COLUMNS.len())
+                    .map(|column| table.required(column, row, Column::id))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let stargate_type = if values[0] != values[3] {
+                    types::StargateType::Regional
+                } else if values[1] != values[4] {
+                    types::StargateType::Constellation
+                } else {
+                    types::StargateType::Local
+                };
+                Ok(types::Connection {
+                    from: values[2].into(),
+                    to: values[5].into(),
+                    r#type: types::ConnectionType::Stargate(stargate_type),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(types::Universe::new(systems.into(), connections.into()).with_hierarchy(hierarchy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parquet::data_type::{ByteArray, ByteArrayType, DataType, DoubleType, Int32Type};
+    use parquet::file::writer::{SerializedFileWriter, SerializedRowGroupWriter};
+    use parquet::schema::parser::parse_message_type;
+
+    use super::*;
+    use crate::types::Navigatable;
+
+    type RowGroup<'a> = SerializedRowGroupWriter<'a, File>;
+
+    fn column<T: DataType>(group: &mut RowGroup<'_>, values: &[Option<T::T>]) {
+        let mut column = group.next_column().unwrap().unwrap();
+        let levels = values
+            .iter()
+            .map(|value| i16::from(value.is_some()))
+            .collect::<Vec<_>>();
+        let values = values.iter().flatten().cloned().collect::<Vec<_>>();
+        column
+            .typed::<T>()
+            .write_batch(&values, Some(&levels), None)
+            .unwrap();
+        column.close().unwrap();
+    }
+
+    fn ints(group: &mut RowGroup<'_>, values: &[i32]) {
+        column::<Int32Type>(group, &values.iter().copied().map(Some).collect::<Vec<_>>());
+    }
+
+    fn texts(group: &mut RowGroup<'_>, values: &[&str]) {
+        let values = values
+            .iter()
+            .map(|value| Some(ByteArray::from(*value)))
+            .collect::<Vec<_>>();
+        column::<ByteArrayType>(group, &values);
+    }
+
+    /// Writes a file with one row group, all columns are optional.
+    fn write(path: &Path, columns: &str, write: impl FnOnce(&mut RowGroup<'_>)) {
+        let schema =
+            Arc::new(parse_message_type(&format!("message schema {{ {columns} }}")).unwrap());
+        let mut writer =
+            SerializedFileWriter::new(File::create(path).unwrap(), schema, Default::default())
+                .unwrap();
+        let mut group = writer.next_row_group().unwrap();
+        write(&mut group);
+        group.close().unwrap();
+        writer.close().unwrap();
+    }
+
+    fn systems(path: &Path, security: &[Option<f64>]) {
+        write(
+            path,
+            "OPTIONAL INT32 solarSystemID; OPTIONAL BYTE_ARRAY solarSystemName (UTF8);
+            OPTIONAL INT32 regionID; OPTIONAL DOUBLE x; OPTIONAL INT32 y; OPTIONAL DOUBLE z;
+            OPTIONAL DOUBLE security; OPTIONAL INT32 factionID; OPTIONAL INT32 constellationID;",
+            |group| {
+                ints(group, &[30000142, 30002813]);
+                texts(group, &["Jita", "Tama"]);
+                ints(group, &[10000002, 10000033]);
+                column::<DoubleType>(group, &[Some(-1.29e17), Some(-1.2e17)]);
+                ints(group, &[0, 0]);
+                column::<DoubleType>(group, &[Some(1.17e17), Some(1.1e17)]);
+                column::<DoubleType>(group, security);
+                column::<Int32Type>(group, &[Some(500001), None]);
+                ints(group, &[20000020, 20000390]);
+            },
+        );
+    }
+
+    #[test]
+    fn test_build() {
+        let dir = std::env::temp_dir().join(format!("neweden-parquet-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        systems(&dir.join("systems"), &[Some(0.9459), Some(0.2633)]);
+        let jumps = JUMPS_COLUMNS
+            .iter()
+            .map(|name| format!("OPTIONAL INT32 {name};"))
+            .collect::<String>();
+        write(&dir.join("jumps"), &jumps, |group| {
+            for values in [
+                [10000002, 10000033],
+                [20000020, 20000390],
+                [30000142, 30002813],
+                [10000033, 10000002],
+                [20000390, 20000020],
+                [30002813, 30000142],
+            ] {
+                ints(group, &values);
+            }
+        });
+        write(
+            &dir.join("regions"),
+            "OPTIONAL INT32 regionID; OPTIONAL BYTE_ARRAY regionName (UTF8);",
+            |group| {
+                ints(group, &[10000002]);
+                texts(group, &["The Forge"]);
+            },
+        );
+        write(
+            &dir.join("constellations"),
+            "OPTIONAL INT32 constellationID; OPTIONAL BYTE_ARRAY constellationName (UTF8);",
+            |group| {
+                ints(group, &[20000020]);
+                texts(group, &["Kimotoro"]);
+            },
+        );
+
+        let universe = ParquetBuilder::new(dir.join("systems"), dir.join("jumps"))
+            .regions(dir.join("regions"))
+            .constellations(dir.join("constellations"))
+            .build()
+            .unwrap();
+        let jita = universe.get_system(&30000142.into()).unwrap();
+        assert_eq!(("Jita", "The Forge"), (&*jita.name, &*jita.region_name));
+        assert_eq!((Some(500001), 0.9459), (jita.faction_id, jita.security.0));
+        let tama = universe.get_system(&30002813.into()).unwrap();
+        assert_eq!((None, "10000033"), (tama.faction_id, &*tama.region_name));
+        assert_eq!(
+            Some("Kimotoro".to_string()),
+            universe.constellation_of(&jita.id).map(|c| c.name)
+        );
+        assert_eq!(
+            Some("20000390".to_string()),
+            universe.constellation_of(&tama.id).map(|c| c.name)
+        );
+        assert_eq!(
+            types::ConnectionType::Stargate(types::StargateType::Regional),
+            universe.get_connections(&30002813.into()).unwrap()[0].r#type
+        );
+
+        let err = ParquetBuilder::new(dir.join("systems"), dir.join("regions"))
+            .build()
+            .err()
+            .unwrap();
+        assert!(format!("{err:#}").contains("missing column fromRegionID"));
+
+        systems(&dir.join("systems"), &[Some(0.9), None]);
+        let err = ParquetBuilder::new(dir.join("systems"), dir.join("jumps"))
+            .build()
+            .err()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!("systems row 2, column 7: null value", format!("{err:#}"));
+    }
+}
@@ -0,0 +1,177 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! A small connection pool for sources that are queried repeatedly, e.g. to refresh
+//! overlays, so that refreshes reuse connections instead of opening a new one.
+//!
+//! Applications that already use r2d2 or deadpool can pass connections from their
+//! pool instead, see `source::postgres::DatabaseBuilder::build_pooled()`.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Condvar, Mutex};
+
+type Connect<C> = Box<dyn Fn() -> anyhow::Result<C> + Send + Sync>;
+
+/// Keeps up to `size` connections. Connections are opened when needed and `get()`
+/// waits for a connection to be returned when all of them are in use.
+///
+/// # Example
+/// ```
+/// use neweden::source::pool::Pool;
+///
+/// let pool = Pool::new(2, || Ok(String::from("connection")));
+/// {
+///     let conn = pool.get().unwrap();
+///     assert_eq!("connection", *conn);
+/// } // returned to the pool
+/// assert_eq!(1, pool.idle());
+/// ```
+pub struct Pool<C> {
+    connect: Connect<C>,
+    size: usize,
+    state: Mutex<State<C>>,
+    returned: Condvar,
+}
+
+struct State<C> {
+    idle: Vec<C>,
+    open: usize,
+}
+
+impl<C> Pool<C> {
+    pub fn new(
+        size: usize,
+        connect: impl Fn() -> anyhow::Result<C> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            connect: Box::new(connect),
+            size: size.max(1),
+            state: Mutex::new(State {
+                idle: Vec::new(),
+                open: 0,
+            }),
+            returned: Condvar::new(),
+        }
+    }
+
+    /// Takes an idle connection, opens a new one if fewer than `size` are open, or
+    /// waits until one is returned.
+    pub fn get(&self) -> anyhow::Result<Pooled<'_, C>> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(conn) = state.idle.pop() {
+                return Ok(Pooled {
+                    pool: self,
+                    conn: Some(conn),
+                });
+            }
+            if state.open < self.size {
+                state.open += 1;
+                drop(state);
+                return match (self.connect)() {
+                    Ok(conn) => Ok(Pooled {
+                        pool: self,
+                        conn: Some(conn),
+                    }),
+                    Err(err) => {
+                        self.state.lock().unwrap().open -= 1;
+                        self.returned.notify_one();
+                        Err(err)
+                    }
+                };
+            }
+            state = self.returned.wait(state).unwrap();
+        }
+    }
+
+    /// The maximum number of connections.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The number of open connections that are not in use.
+    pub fn idle(&self) -> usize {
+        self.state.lock().unwrap().idle.len()
+    }
+}
+
+/// A connection taken from a `Pool`, returned when dropped.
+pub struct Pooled<'a, C> {
+    pool: &'a Pool<C>,
+    conn: Option<C>,
+}
+
+impl<C> Pooled<'_, C> {
+    /// Closes the connection instead of returning it, e.g. after it broke.
+    pub fn discard(mut self) {
+        self.conn = None;
+    }
+}
+
+impl<C> Deref for Pooled<'_, C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl<C> DerefMut for Pooled<'_, C> {
+    fn deref_mut(&mut self) -> &mut C {
+        self.conn.as_mut().unwrap()
+    }
+}
+
+impl<C> Drop for Pooled<'_, C> {
+    fn drop(&mut self) {
+        let mut state = self.pool.state.lock().unwrap();
+        match self.conn.take() {
+            Some(conn) => state.idle.push(conn),
+            None => state.open -= 1,
+        }
+        self.pool.returned.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_pool() {
+        let opened = Arc::new(AtomicUsize::new(0));
+        let counter = opened.clone();
+        let pool = Pool::new(2, move || Ok(counter.fetch_add(1, Ordering::SeqCst)));
+
+        let first = pool.get().unwrap();
+        let second = pool.get().unwrap();
+        assert_eq!((0, 1), (*first, *second));
+        drop(first);
+        assert_eq!(0, *pool.get().unwrap());
+        second.discard();
+        assert_eq!(1, pool.idle());
+
+        // blocks until a connection is returned
+        std::thread::scope(|scope| {
+            let a = pool.get().unwrap();
+            let b = pool.get().unwrap();
+            assert_eq!(3, opened.load(Ordering::SeqCst));
+            let waiting = scope.spawn(|| *pool.get().unwrap());
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            let returned = *a;
+            drop(a);
+            assert_eq!(returned, waiting.join().unwrap());
+            drop(b);
+        });
+        assert_eq!(3, opened.load(Ordering::SeqCst));
+
+        let failing = Pool::<u32>::new(1, || anyhow::bail!("unreachable"));
+        assert!(failing.get().is_err());
+        assert!(failing.get().is_err());
+    }
+}
@@ -6,13 +6,18 @@
 #[allow(non_snake_case)]
 mod schema;
 
-use anyhow;
-use diesel::pg::PgConnection;
+use std::collections::HashMap;
+use std::ops::DerefMut;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use diesel::pg::{PgConnection, PgRowByRowLoadingMode};
 use diesel::prelude::*;
 
+use crate::hierarchy;
+use crate::source::pool::Pool;
+use crate::source::progress::{BuildProgress, Phase, Reporter};
 use crate::types;
-use schema::mapSolarSystemJumps::dsl::*;
-use schema::mapSolarSystems::dsl::*;
 
 type DB = diesel::pg::Pg;
 
@@ -25,6 +30,10 @@ type DB = diesel::pg::Pg;
 /// such as dynamic wormhole connections during pathfinding, construct an `ExtendedUniverse`
 /// from a universe by calling `.extend()` or `ExtendedUniverse::new()`.
 ///
+/// The SDE tables are expected in the connection's search path under their SDE
+/// names. Use `.schema()`, `.table_prefix()` and `.table_name()` if they live
+/// elsewhere, and `.lowercase_identifiers()` for dumps with lowercase names.
+///
 /// # Example
 /// ```
 /// use std::env;
@@ -38,36 +47,665 @@ type DB = diesel::pg::Pg;
 /// ```
 pub struct DatabaseBuilder {
     uri: String,
+    schema: Option<String>,
+    table_prefix: String,
+    table_names: HashMap<String, String>,
+    lowercase: bool,
+    connections: usize,
+    progress: Option<Mutex<BuildProgress>>,
 }
 
 impl DatabaseBuilder {
     pub fn new(uri: &str) -> Self {
         Self {
             uri: uri.to_string(),
+            schema: None,
+            table_prefix: String::new(),
+            table_names: HashMap::new(),
+            lowercase: false,
+            connections: 1,
+            progress: None,
         }
     }
 
+    /// Reads the SDE tables from the schema, e.g. `sde` when they are kept apart
+    /// from the application's tables.
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema = Some(schema.to_string());
+        self
+    }
+
+    /// Prefix of the SDE table names, e.g. `sde_` for `sde_mapSolarSystems`.
+    pub fn table_prefix(mut self, prefix: &str) -> Self {
+        self.table_prefix = prefix.to_string();
+        self
+    }
+
+    /// Reads the SDE table `sde_name`, e.g. `mapSolarSystems`, from `table`
+    /// instead. The name is used as is, without prefix or case folding.
+    pub fn table_name(mut self, sde_name: &str, table: &str) -> Self {
+        self.table_names
+            .insert(sde_name.to_string(), table.to_string());
+        self
+    }
+
+    /// Uses lowercase table and column names, e.g. `mapsolarsystems.solarsystemid`.
+    /// Dumps imported without quoting the identifiers end up lowercase as Postgres
+    /// folds unquoted names.
+    pub fn lowercase_identifiers(mut self) -> Self {
+        self.lowercase = true;
+        self
+    }
+
+    /// Loads the tables in chunks over `connections` connections in parallel. On
+    /// remote databases this hides the round trip and transfer times, two
+    /// connections load systems and stargates concurrently. On a local database
+    /// opening the connections costs more than it saves. The chunks are ranges of
+    /// system ids with about the same number of rows, see `benches/postgres.rs`.
+    pub fn connections(mut self, connections: usize) -> Self {
+        self.connections = connections.max(1);
+        self
+    }
+
+    /// Reports the progress of the build, see `source::progress`. The totals
+    /// are counted with an additional query per table.
+    pub fn on_progress(
+        mut self,
+        progress: impl FnMut(usize, Option<usize>, Phase) -> bool + Send + 'static,
+    ) -> Self {
+        self.progress = Some(Mutex::new(Box::new(progress)));
+        self
+    }
+
+    /// A pool of up to `size` connections to the database, e.g. for `build_pooled()`
+    /// or for application queries that refresh overlays.
+    pub fn pool(&self, size: usize) -> Pool<PgConnection> {
+        let uri = self.uri.clone();
+        Pool::new(size, move || Ok(PgConnection::establish(&uri)?))
+    }
+
     pub fn build(self) -> anyhow::Result<types::Universe> {
+        if self.connections > 1 {
+            return self.build_pooled(|| Ok(Box::new(PgConnection::establish(&self.uri)?)));
+        }
         let mut conn = PgConnection::establish(&self.uri)?;
-        Self::from_connection(&mut conn)
+        self.build_with(&mut conn)
     }
 
-    pub(self) fn from_connection(conn: &mut PgConnection) -> anyhow::Result<types::Universe> {
-        let systems = mapSolarSystems
-            // this is k-space and w-space
-            .filter(solarSystemID.lt(32000000))
-            .load::<types::System>(conn)?;
+    /// Loads the universe using a connection owned by the caller, e.g. one checked
+    /// out of an r2d2 or deadpool pool. The URI passed to `new()` is not used.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use diesel::r2d2::{ConnectionManager, Pool};
+    /// use neweden::source::postgres::DatabaseBuilder;
+    ///
+    /// let pool = Pool::new(ConnectionManager::<PgConnection>::new(uri))?;
+    /// let universe = DatabaseBuilder::new("")
+    ///     .schema("sde")
+    ///     .build_with(&mut pool.get()?)?;
+    /// ```
+    pub fn build_with(&self, conn: &mut PgConnection) -> anyhow::Result<types::Universe> {
+        let mut reporter = Reporter::new(self.progress.as_ref());
+        let mut systems = Vec::new();
+        reporter.start(Phase::Systems, self.count_systems(conn)?)?;
+        self.for_each_system(conn, Chunk::ALL, |system| {
+            systems.push(system);
+            reporter.add(Phase::Systems, 1)
+        })?;
+        reporter.finish(Phase::Systems)?;
 
-        let jumps = mapSolarSystemJumps
-            .filter(
-                // only query k-space since w-space has no connections
-                fromSolarSystemID
-                    .lt(31000000)
-                    .and(toSolarSystemID.lt(31000000)),
-            )
-            .load::<types::Connection>(conn)?;
+        let mut jumps = Vec::new();
+        reporter.start(Phase::Connections, self.count_jumps(conn)?)?;
+        self.for_each_jump(conn, Chunk::ALL, |jump| {
+            jumps.push(jump);
+            reporter.add(Phase::Connections, 1)
+        })?;
+        reporter.finish(Phase::Connections)?;
+        let universe = types::Universe::new(systems.into(), jumps.into());
+        Ok(match self.hierarchy(conn)? {
+            Some(hierarchy) => universe.with_hierarchy(hierarchy),
+            None => universe,
+        })
+    }
+
+    /// Calls `f` for every system as it is received, without building a universe.
+    pub fn stream_systems(
+        &self,
+        conn: &mut PgConnection,
+        f: impl FnMut(types::System) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        self.for_each_system(conn, Chunk::ALL, f)
+    }
+
+    /// Calls `f` for every stargate as it is received, without building a universe.
+    pub fn stream_connections(
+        &self,
+        conn: &mut PgConnection,
+        f: impl FnMut(types::Connection) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        self.for_each_jump(conn, Chunk::ALL, f)
+    }
+
+    /// Loads the universe in parallel like `build()` with `.connections()`, taking
+    /// the connections from `connect`, e.g. `|| pool.get()`.
+    pub fn build_pooled<C, F>(&self, connect: F) -> anyhow::Result<types::Universe>
+    where
+        C: DerefMut<Target = PgConnection>,
+        F: Fn() -> anyhow::Result<C> + Sync,
+    {
+        let count = self.connections;
+        let mut reporter = Reporter::new(self.progress.as_ref());
+        let tasks = {
+            let mut conn = connect()?;
+            if self.progress.is_some() {
+                reporter.start(Phase::Systems, self.count_systems(&mut conn)?)?;
+                reporter.start(Phase::Connections, self.count_jumps(&mut conn)?)?;
+            }
+            let systems = self.system_chunks(&mut conn, count)?;
+            let jumps = self.jump_chunks(&mut conn, count)?;
+            // interleaved so that both tables are loaded at the same time
+            (0..systems.len().max(jumps.len()))
+                .flat_map(|i| {
+                    [
+                        systems.get(i).copied().map(Task::Systems),
+                        jumps.get(i).copied().map(Task::Jumps),
+                    ]
+                })
+                .flatten()
+                .collect::<Vec<_>>()
+        };
+        let reporter = Mutex::new(reporter);
+        let next = AtomicUsize::new(0);
+        let systems = Mutex::new(Vec::new());
+        let jumps = Mutex::new(Vec::new());
+        std::thread::scope(|scope| {
+            let workers = (0..count)
+                .map(|_| {
+                    scope.spawn(|| -> anyhow::Result<()> {
+                        let mut conn = connect()?;
+                        while let Some(task) = tasks.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            match *task {
+                                Task::Systems(chunk) => {
+                                    let mut loaded = Vec::new();
+                                    self.for_each_system(&mut conn, chunk, |system| {
+                                        loaded.push(system);
+                                        Ok(())
+                                    })?;
+                                    reporter.lock().unwrap().add(Phase::Systems, loaded.len())?;
+                                    systems.lock().unwrap().extend(loaded);
+                                }
+                                Task::Jumps(chunk) => {
+                                    let mut loaded = Vec::new();
+                                    self.for_each_jump(&mut conn, chunk, |jump| {
+                                        loaded.push(jump);
+                                        Ok(())
+                                    })?;
+                                    reporter
+                                        .lock()
+                                        .unwrap()
+                                        .add(Phase::Connections, loaded.len())?;
+                                    jumps.lock().unwrap().extend(loaded);
+                                }
+                            }
+                        }
+                        Ok(())
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .try_for_each(|worker| worker.join().expect("loader thread panicked"))
+        })?;
+        let mut reporter = reporter.into_inner().unwrap();
+        reporter.finish(Phase::Systems)?;
+        reporter.finish(Phase::Connections)?;
+
+        let universe = types::Universe::new(
+            systems.into_inner().unwrap().into(),
+            jumps.into_inner().unwrap().into(),
+        );
+        Ok(match self.hierarchy(&mut *connect()?)? {
+            Some(hierarchy) => universe.with_hierarchy(hierarchy),
+            None => universe,
+        })
+    }
+
+    /// Loads the regions and constellations from `mapRegions` and `mapConstellations`
+    /// together with the constellation of each system, `None` for dumps without
+    /// `mapConstellations`.
+    fn hierarchy(&self, conn: &mut PgConnection) -> anyhow::Result<Option<hierarchy::Hierarchy>> {
+        let constellations = self.table("mapConstellations");
+        let exists = diesel::sql_query("SELECT to_regclass($1) IS NOT NULL AS exists")
+            .bind::<diesel::sql_types::Text, _>(&constellations)
+            .get_result::<ExistsRow>(conn)?;
+        if !exists.exists {
+            return Ok(None);
+        }
+
+        let region_id = self.ident("regionID");
+        let constellation_id = self.ident("constellationID");
+        let mut hierarchy = hierarchy::Hierarchy::default();
+        let regions = diesel::sql_query(format!(
+            "SELECT {region_id} AS id, {name} AS name FROM {regions}",
+            name = self.ident("regionName"),
+            regions = self.table("mapRegions"),
+        ))
+        .load::<RegionRow>(conn)?;
+        for region in regions {
+            hierarchy.insert_region((region.id as u32).into(), &region.name.unwrap_or_default());
+        }
+
+        let rows = diesel::sql_query(format!(
+            "
+            SELECT {constellation_id} AS id, {region_id} AS region_id, {name} AS name
+            FROM {constellations}
+            ",
+            name = self.ident("constellationName"),
+        ))
+        .load::<ConstellationRow>(conn)?;
+        for constellation in rows {
+            hierarchy.insert_constellation(
+                (constellation.id as u32).into(),
+                (constellation.region_id as u32).into(),
+                &constellation.name.unwrap_or_default(),
+            );
+        }
+
+        let rows = diesel::sql_query(format!(
+            "
+            SELECT {id} AS id, {constellation_id} AS constellation_id
+            FROM {systems}
+            WHERE {id} < 32000000
+            ",
+            id = self.ident("solarSystemID"),
+            systems = self.table("mapSolarSystems"),
+        ))
+        .load::<SystemConstellationRow>(conn)?;
+        for row in rows {
+            if !hierarchy
+                .insert_system((row.id as u32).into(), (row.constellation_id as u32).into())
+            {
+                anyhow::bail!(
+                    "system {} is in unknown constellation {}",
+                    row.id,
+                    row.constellation_id
+                );
+            }
+        }
+        Ok(Some(hierarchy))
+    }
+
+    /// The number of systems to load, `None` unless progress is reported.
+    fn count_systems(&self, conn: &mut PgConnection) -> anyhow::Result<Option<usize>> {
+        if self.progress.is_none() {
+            return Ok(None);
+        }
+        self.count(
+            conn,
+            &self.table("mapSolarSystems"),
+            &format!("{} < 32000000", self.ident("solarSystemID")),
+        )
+        .map(Some)
+    }
+
+    /// The number of stargates to load, `None` unless progress is reported.
+    fn count_jumps(&self, conn: &mut PgConnection) -> anyhow::Result<Option<usize>> {
+        if self.progress.is_none() {
+            return Ok(None);
+        }
+        self.count(
+            conn,
+            &self.table("mapSolarSystemJumps"),
+            &format!(
+                "{} < 31000000 AND {} < 31000000",
+                self.ident("fromSolarSystemID"),
+                self.ident("toSolarSystemID")
+            ),
+        )
+        .map(Some)
+    }
+
+    /// Splits the systems into `count` ranges of ids with about the same number of
+    /// systems.
+    fn system_chunks(&self, conn: &mut PgConnection, count: usize) -> anyhow::Result<Vec<Chunk>> {
+        let id = self.ident("solarSystemID");
+        self.chunks(
+            conn,
+            &self.table("mapSolarSystems"),
+            &id,
+            &format!("{id} < 32000000"),
+            count,
+        )
+    }
+
+    /// Splits the stargates into `count` ranges of origin system ids with about the
+    /// same number of stargates.
+    fn jump_chunks(&self, conn: &mut PgConnection, count: usize) -> anyhow::Result<Vec<Chunk>> {
+        let from_system = self.ident("fromSolarSystemID");
+        self.chunks(
+            conn,
+            &self.table("mapSolarSystemJumps"),
+            &from_system,
+            &format!(
+                "{from_system} < 31000000 AND {} < 31000000",
+                self.ident("toSolarSystemID")
+            ),
+            count,
+        )
+    }
+
+    /// Finds the first id of each of `count` ranges of `column` with about the same
+    /// number of rows, so every chunk is read with an index range scan instead of
+    /// each chunk scanning the whole table.
+    fn chunks(
+        &self,
+        conn: &mut PgConnection,
+        table: &str,
+        column: &str,
+        filter: &str,
+        count: usize,
+    ) -> anyhow::Result<Vec<Chunk>> {
+        let starts = diesel::sql_query(format!(
+            "
+            SELECT MIN(id) AS start
+            FROM (
+                SELECT {column} AS id, ntile({count}) OVER (ORDER BY {column}) AS chunk
+                FROM {table}
+                WHERE {filter}
+            ) ids
+            GROUP BY chunk
+            ORDER BY chunk
+            "
+        ))
+        .load::<StartRow>(conn)?;
+        Ok(Chunk::split(starts.iter().map(|row| row.start)))
+    }
+
+    fn count(&self, conn: &mut PgConnection, table: &str, filter: &str) -> anyhow::Result<usize> {
+        let row = diesel::sql_query(format!(
+            "SELECT COUNT(*) AS count FROM {table} WHERE {filter}"
+        ))
+        .get_result::<CountRow>(conn)?;
+        Ok(row.count as usize)
+    }
+
+    fn for_each_system(
+        &self,
+        conn: &mut PgConnection,
+        chunk: Chunk,
+        mut f: impl FnMut(types::System) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let id = self.ident("solarSystemID");
+        let region_id = self.ident("regionID");
+        let query = diesel::sql_query(format!(
+            "
+            SELECT
+                s.{id} AS id,
+                s.{name} AS name,
+                s.{x} AS x, s.{y} AS y, s.{z} AS z,
+                s.{security} AS security,
+                s.{faction_id} AS faction_id,
+                r.{region_name} AS region_name
+            FROM {systems} s
+            LEFT JOIN {regions} r ON r.{region_id} = s.{region_id}
+            -- this is k-space and w-space
+            WHERE s.{id} < 32000000 {chunk}
+            ",
+            name = self.ident("solarSystemName"),
+            x = self.ident("x"),
+            y = self.ident("y"),
+            z = self.ident("z"),
+            security = self.ident("security"),
+            faction_id = self.ident("factionID"),
+            region_name = self.ident("regionName"),
+            systems = self.table("mapSolarSystems"),
+            regions = self.table("mapRegions"),
+            chunk = chunk.filter(&format!("s.{id}")),
+        ));
+        for row in query.load_iter::<SystemRow, PgRowByRowLoadingMode>(conn)? {
+            f(row?.into())?;
+        }
+        Ok(())
+    }
+
+    fn for_each_jump(
+        &self,
+        conn: &mut PgConnection,
+        chunk: Chunk,
+        mut f: impl FnMut(types::Connection) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let from_system = self.ident("fromSolarSystemID");
+        let to_system = self.ident("toSolarSystemID");
+        let query = diesel::sql_query(format!(
+            "
+            SELECT
+                {from_region} AS from_region,
+                {from_constellation} AS from_constellation,
+                {from_system} AS from_system,
+                {to_system} AS to_system,
+                {to_constellation} AS to_constellation,
+                {to_region} AS to_region
+            FROM {jumps}
+            -- only query k-space since w-space has no connections
+            WHERE {from_system} < 31000000 AND {to_system} < 31000000 {chunk}
+            ",
+            from_region = self.ident("fromRegionID"),
+            from_constellation = self.ident("fromConstellationID"),
+            to_constellation = self.ident("toConstellationID"),
+            to_region = self.ident("toRegionID"),
+            jumps = self.table("mapSolarSystemJumps"),
+            chunk = chunk.filter(&from_system),
+        ));
+        for row in query.load_iter::<JumpRow, PgRowByRowLoadingMode>(conn)? {
+            f(row?.into())?;
+        }
+        Ok(())
+    }
 
-        Ok(types::Universe::new(systems.into(), jumps.into()))
+    /// The quoted, schema qualified name of a SDE table.
+    fn table(&self, name: &str) -> String {
+        let table = match self.table_names.get(name) {
+            Some(table) => quote(table),
+            None => self.ident(&format!("{}{}", self.table_prefix, name)),
+        };
+        match &self.schema {
+            Some(schema) => format!("{}.{}", quote(schema), table),
+            None => table,
+        }
+    }
+
+    /// The quoted name of a SDE column or table, lowercased if the dump was imported
+    /// with Postgres' default case folding.
+    fn ident(&self, name: &str) -> String {
+        if self.lowercase {
+            quote(&name.to_lowercase())
+        } else {
+            quote(name)
+        }
+    }
+}
+
+/// A range of system ids of a table, unbounded if `start` or `end` is `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Chunk {
+    start: Option<i32>,
+    end: Option<i32>,
+}
+
+impl Chunk {
+    const ALL: Chunk = Chunk {
+        start: None,
+        end: None,
+    };
+
+    /// Splits all ids into ranges starting at `starts`, sorted ascending. The first
+    /// range is open towards lower ids, so no row is missed.
+    fn split(starts: impl IntoIterator<Item = i32>) -> Vec<Chunk> {
+        let mut starts = starts.into_iter().collect::<Vec<_>>();
+        starts.dedup();
+        let mut ends = starts.iter().skip(1).copied().map(Some).collect::<Vec<_>>();
+        ends.push(None);
+        let chunks = starts
+            .iter()
+            .enumerate()
+            .map(|(i, start)| Chunk {
+                start: (i > 0).then_some(*start),
+                end: ends[i],
+            })
+            .collect::<Vec<_>>();
+        if chunks.is_empty() {
+            vec![Chunk::ALL]
+        } else {
+            chunks
+        }
+    }
+
+    /// The condition selecting the range of `column`, to be appended to a `WHERE`.
+    fn filter(&self, column: &str) -> String {
+        let mut filter = String::new();
+        if let Some(start) = self.start {
+            filter.push_str(&format!("AND {column} >= {start} "));
+        }
+        if let Some(end) = self.end {
+            filter.push_str(&format!("AND {column} < {end}"));
+        }
+        filter
+    }
+}
+
+/// A chunk of a table loaded by `build_pooled()`.
+#[derive(Clone, Copy)]
+enum Task {
+    Systems(Chunk),
+    Jumps(Chunk),
+}
+
+/// Quotes an identifier, keeping its case.
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[derive(QueryableByName)]
+struct CountRow {
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    count: i64,
+}
+
+#[derive(QueryableByName)]
+struct StartRow {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    start: i32,
+}
+
+#[derive(QueryableByName)]
+struct ExistsRow {
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    exists: bool,
+}
+
+#[derive(QueryableByName)]
+struct RegionRow {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    id: i32,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+    name: Option<String>,
+}
+
+#[derive(QueryableByName)]
+struct ConstellationRow {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    id: i32,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    region_id: i32,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+    name: Option<String>,
+}
+
+#[derive(QueryableByName)]
+struct SystemConstellationRow {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    id: i32,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    constellation_id: i32,
+}
+
+#[derive(QueryableByName)]
+struct SystemRow {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    id: i32,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+    name: Option<String>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Double>)]
+    x: Option<f64>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Double>)]
+    y: Option<f64>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Double>)]
+    z: Option<f64>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Double>)]
+    security: Option<f64>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Integer>)]
+    faction_id: Option<i32>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+    region_name: Option<String>,
+}
+
+impl From<SystemRow> for types::System {
+    fn from(row: SystemRow) -> Self {
+        types::System {
+            id: types::SystemId(row.id as u32),
+            name: row.name.unwrap_or_default(),
+            coordinate: types::Coordinate {
+                x: row.x.unwrap_or_default(),
+                y: row.y.unwrap_or_default(),
+                z: row.z.unwrap_or_default(),
+            },
+            security: types::Security(row.security.unwrap_or_default() as f32),
+            region_name: row.region_name.unwrap_or_default(),
+            faction_id: row.faction_id.map(|id| id as u32),
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct JumpRow {
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Integer>)]
+    from_region: Option<i32>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Integer>)]
+    from_constellation: Option<i32>,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    from_system: i32,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    to_system: i32,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Integer>)]
+    to_constellation: Option<i32>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Integer>)]
+    to_region: Option<i32>,
+}
+
+impl From<JumpRow> for types::Connection {
+    fn from(row: JumpRow) -> Self {
+        types::Connection {
+            from: types::SystemId(row.from_system as u32),
+            to: types::SystemId(row.to_system as u32),
+            r#type: types::ConnectionType::Stargate(stargate_type(
+                (row.from_region, row.from_constellation),
+                (row.to_region, row.to_constellation),
+            )),
+        }
+    }
+}
+
+fn stargate_type(
+    (from_region, from_constellation): (Option<i32>, Option<i32>),
+    (to_region, to_constellation): (Option<i32>, Option<i32>),
+) -> types::StargateType {
+    if from_region != to_region {
+        types::StargateType::Regional
+    } else if from_constellation != to_constellation {
+        types::StargateType::Constellation
+    } else {
+        types::StargateType::Local
     }
 }
 
@@ -81,6 +719,7 @@ impl Queryable<schema::mapSolarSystems::SqlType, DB> for types::System {
         Option<f64>,    // z
         Option<f64>,    // luminosity
         Option<f64>,    // security
+        Option<i32>,    // factionID
     );
 
     fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
@@ -94,6 +733,7 @@ impl Queryable<schema::mapSolarSystems::SqlType, DB> for types::System {
             },
             security: types::Security(row.7.unwrap() as f32),
             region_name: "[TODO]".to_string(), // TODO: join region name
+            faction_id: row.8.map(|id| id as u32),
         })
     }
 }
@@ -109,16 +749,10 @@ impl Queryable<schema::mapSolarSystemJumps::SqlType, DB> for types::Connection {
     );
 
     fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
-        let stargate_type = match (row.0, row.1, row.4, row.5) {
-            (a, _, _, b) if a != b => types::StargateType::Regional,
-            (_, a, b, _) if a != b => types::StargateType::Constellation,
-            _ => types::StargateType::Local,
-        };
-
         Ok(types::Connection {
             from: types::SystemId(row.2 as u32),
             to: types::SystemId(row.3 as u32),
-            r#type: types::ConnectionType::Stargate(stargate_type),
+            r#type: types::ConnectionType::Stargate(stargate_type((row.0, row.1), (row.5, row.4))),
         })
     }
 }
@@ -128,6 +762,77 @@ mod tests {
     use std::env;
 
     use super::*;
+    use crate::types::Navigatable;
+    use schema::mapSolarSystemJumps::dsl::*;
+    use schema::mapSolarSystems::dsl::*;
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            r#""mapSolarSystems""#,
+            DatabaseBuilder::new("").table("mapSolarSystems")
+        );
+        assert_eq!(
+            r#""sde"."evesde_mapRegions""#,
+            DatabaseBuilder::new("")
+                .schema("sde")
+                .table_prefix("evesde_")
+                .table("mapRegions")
+        );
+        let builder = DatabaseBuilder::new("")
+            .schema("public")
+            .lowercase_identifiers()
+            .table_name("mapRegions", "Regions");
+        assert_eq!(
+            r#""public"."mapsolarsystems""#,
+            builder.table("mapSolarSystems")
+        );
+        assert_eq!(r#""public"."Regions""#, builder.table("mapRegions"));
+        assert_eq!(r#""solarsystemid""#, builder.ident("solarSystemID"));
+        assert_eq!(r#""a""b""#, quote(r#"a"b"#));
+    }
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(vec![Chunk::ALL], Chunk::split([]));
+        assert_eq!(vec![Chunk::ALL], Chunk::split([30000001]));
+        let chunks = Chunk::split([30000001, 30002000, 30002000, 31000001]);
+        assert_eq!(
+            vec![
+                Chunk {
+                    start: None,
+                    end: Some(30002000)
+                },
+                Chunk {
+                    start: Some(30002000),
+                    end: Some(31000001)
+                },
+                Chunk {
+                    start: Some(31000001),
+                    end: None
+                },
+            ],
+            chunks
+        );
+        assert_eq!(r#"AND "id" < 30002000"#, chunks[0].filter(r#""id""#));
+        assert_eq!(
+            r#"AND "id" >= 30002000 AND "id" < 31000001"#,
+            chunks[1].filter(r#""id""#)
+        );
+        assert_eq!("", Chunk::ALL.filter(r#""id""#));
+    }
+
+    #[test]
+    fn test_parallel() {
+        let uri = env::var("DATABASE_URL").expect("expected env variable DATABASE_URL set");
+        let universe = DatabaseBuilder::new(&uri).build().unwrap();
+        let parallel = DatabaseBuilder::new(&uri).connections(3).build().unwrap();
+        assert_eq!(universe.version(), parallel.version());
+        assert_eq!(
+            universe.region_of(&30000142.into()).map(|region| region.id),
+            parallel.region_of(&30000142.into()).map(|region| region.id)
+        );
+    }
 
     #[test]
     fn test_simple_system_query() {
@@ -95,7 +95,7 @@ table! {
         // regional -> Nullable<Bool>,
         // constellation -> Nullable<Bool>,
         security -> Nullable<Float8>,
-        // factionID -> Nullable<Int4>,
+        factionID -> Nullable<Int4>,
         // radius -> Nullable<Float8>,
         // sunTypeID -> Nullable<Int4>,
         // securityClass -> Nullable<Varchar>,
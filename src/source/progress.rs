@@ -0,0 +1,146 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Progress reports while loading a universe from a database, e.g. to show a
+//! progress bar in a GUI tool loading over a slow connection, and to abort the load.
+//!
+//! # Example
+//! ```no_run
+//! use neweden::source::progress::{Aborted, Phase};
+//! use neweden::source::sqlite::DatabaseBuilder;
+//!
+//! let result = DatabaseBuilder::new("./sqlite-latest.sqlite")
+//!     .on_progress(|loaded, total, phase| {
+//!         println!("{phase:?}: {loaded}/{}", total.unwrap_or(0));
+//!         // return false to abort
+//!         true
+//!     })
+//!     .build();
+//! if let Err(err) = result {
+//!     assert!(err.is::<Aborted>());
+//! }
+//! ```
+
+use std::sync::Mutex;
+
+use thiserror::Error;
+
+/// What is being loaded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Phase {
+    Systems,
+    Connections,
+    Stations,
+    Stargates,
+    Celestials,
+    WormholeClasses,
+}
+
+/// Number of phases.
+const PHASES: usize = 6;
+
+/// Returned by `build()` when the progress callback asked to stop.
+#[derive(Debug, Error)]
+#[error("loading the universe was aborted")]
+pub struct Aborted;
+
+/// Called with the number of rows loaded in the phase, the total if it is known
+/// and the phase. Returning false aborts the load.
+pub type BuildProgress = Box<dyn FnMut(usize, Option<usize>, Phase) -> bool + Send>;
+
+/// Rows loaded between two reports.
+const INTERVAL: usize = 1000;
+
+/// Counts the loaded rows and calls the progress callback, if any.
+pub(crate) struct Reporter<'a> {
+    callback: Option<&'a Mutex<BuildProgress>>,
+    // indexed by phase
+    loaded: [usize; PHASES],
+    reported: [usize; PHASES],
+    total: [Option<usize>; PHASES],
+}
+
+impl<'a> Reporter<'a> {
+    pub(crate) fn new(callback: Option<&'a Mutex<BuildProgress>>) -> Self {
+        Self {
+            callback,
+            loaded: [0; PHASES],
+            reported: [0; PHASES],
+            total: [None; PHASES],
+        }
+    }
+
+    /// Starts a phase with the expected number of rows.
+    pub(crate) fn start(&mut self, phase: Phase, total: Option<usize>) -> anyhow::Result<()> {
+        self.total[phase as usize] = total;
+        self.report(phase)
+    }
+
+    /// Adds loaded rows, reporting at most every `INTERVAL` rows.
+    pub(crate) fn add(&mut self, phase: Phase, rows: usize) -> anyhow::Result<()> {
+        self.loaded[phase as usize] += rows;
+        if self.loaded[phase as usize] - self.reported[phase as usize] >= INTERVAL {
+            self.report(phase)?;
+        }
+        Ok(())
+    }
+
+    /// Reports the final number of rows of a phase.
+    pub(crate) fn finish(&mut self, phase: Phase) -> anyhow::Result<()> {
+        self.report(phase)
+    }
+
+    fn report(&mut self, phase: Phase) -> anyhow::Result<()> {
+        let Some(callback) = self.callback else {
+            return Ok(());
+        };
+        let i = phase as usize;
+        self.reported[i] = self.loaded[i];
+        let mut callback = callback.lock().unwrap();
+        if !callback(self.loaded[i], self.total[i], phase) {
+            return Err(Aborted.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reporter() {
+        let reports = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let seen = reports.clone();
+        let callback: Mutex<BuildProgress> = Mutex::new(Box::new(move |loaded, total, phase| {
+            seen.lock().unwrap().push((loaded, total, phase));
+            loaded < 2000
+        }));
+        let mut reporter = Reporter::new(Some(&callback));
+
+        reporter.start(Phase::Systems, Some(1500)).unwrap();
+        for _ in 0..1500 {
+            reporter.add(Phase::Systems, 1).unwrap();
+        }
+        reporter.finish(Phase::Systems).unwrap();
+        reporter.start(Phase::Connections, None).unwrap();
+        reporter.add(Phase::Connections, 1999).unwrap();
+        reporter.add(Phase::Connections, 1).unwrap();
+        let err = reporter.finish(Phase::Connections).unwrap_err();
+        assert!(err.is::<Aborted>());
+
+        assert_eq!(
+            vec![
+                (0, Some(1500), Phase::Systems),
+                (1000, Some(1500), Phase::Systems),
+                (1500, Some(1500), Phase::Systems),
+                (0, None, Phase::Connections),
+                (1999, None, Phase::Connections),
+                (2000, None, Phase::Connections),
+            ],
+            *reports.lock().unwrap()
+        );
+    }
+}
@@ -0,0 +1,323 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Loads a universe from a network service, such as an internal universe server.
+//!
+//! Applications implement `RemoteSource` for their protocol and load the universe
+//! with `RemoteBuilder`. With the `rpc` feature, `HttpSource` loads it from a JSON
+//! HTTP service. Sources that can tell what changed since a version let
+//! `RemoteBuilder::refresh()` update a universe without downloading it again.
+
+use crate::hierarchy;
+use crate::types::{self, Navigatable};
+
+/// Changes between two versions of the universe.
+pub type Delta = crate::diff::UniverseDelta;
+
+/// A network backend a universe can be loaded from.
+pub trait RemoteSource {
+    fn fetch_systems(&mut self) -> anyhow::Result<Vec<types::System>>;
+
+    fn fetch_connections(&mut self) -> anyhow::Result<Vec<types::Connection>>;
+
+    /// The regions and constellations of the systems, `None` if the source doesn't
+    /// know them.
+    fn fetch_hierarchy(&mut self) -> anyhow::Result<Option<hierarchy::Hierarchy>> {
+        Ok(None)
+    }
+
+    /// Changes since the universe with the given `Navigatable::version()`. Returns
+    /// `None` if the source can't tell, and the universe is loaded again.
+    fn fetch_delta(&mut self, _version: u64) -> anyhow::Result<Option<Delta>> {
+        Ok(None)
+    }
+}
+
+impl<T: RemoteSource + ?Sized> RemoteSource for &mut T {
+    fn fetch_systems(&mut self) -> anyhow::Result<Vec<types::System>> {
+        (**self).fetch_systems()
+    }
+
+    fn fetch_connections(&mut self) -> anyhow::Result<Vec<types::Connection>> {
+        (**self).fetch_connections()
+    }
+
+    fn fetch_hierarchy(&mut self) -> anyhow::Result<Option<hierarchy::Hierarchy>> {
+        (**self).fetch_hierarchy()
+    }
+
+    fn fetch_delta(&mut self, version: u64) -> anyhow::Result<Option<Delta>> {
+        (**self).fetch_delta(version)
+    }
+}
+
+/// Loads the universe from a JSON HTTP service.
+///
+/// The service serves the systems as a JSON array at `<url>/systems` and the
+/// connections at `<url>/connections`, in the `serde` representation of `System`
+/// and `Connection`. `<url>/hierarchy` serves the `Hierarchy`, or 404 if the
+/// service doesn't know it. `<url>/delta/<version>` returns the `UniverseDelta` since a
+/// version, or 404 if the service can't tell.
+#[cfg(feature = "rpc")]
+pub struct HttpSource {
+    agent: ureq::Agent,
+    url: String,
+}
+
+#[cfg(feature = "rpc")]
+impl HttpSource {
+    pub fn new(url: &str) -> Self {
+        Self::with_agent(url, ureq::agent())
+    }
+
+    /// Uses an agent configured by the caller, e.g. with timeouts or a proxy.
+    pub fn with_agent(url: &str, agent: ureq::Agent) -> Self {
+        Self {
+            agent,
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> anyhow::Result<Option<T>> {
+        let url = format!("{}{path}", self.url);
+        let body = match self.agent.get(&url).call() {
+            Ok(mut response) => response.body_mut().read_to_string()?,
+            Err(ureq::Error::StatusCode(404)) => return Ok(None),
+            Err(err) => return Err(anyhow::Error::new(err).context(format!("GET {url}"))),
+        };
+        Ok(Some(serde_json::from_str(&body).map_err(|err| {
+            anyhow::Error::new(err).context(format!("GET {url}"))
+        })?))
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl RemoteSource for HttpSource {
+    fn fetch_systems(&mut self) -> anyhow::Result<Vec<types::System>> {
+        self.get("/systems")?
+            .ok_or_else(|| anyhow::anyhow!("{}/systems not found", self.url))
+    }
+
+    fn fetch_connections(&mut self) -> anyhow::Result<Vec<types::Connection>> {
+        self.get("/connections")?
+            .ok_or_else(|| anyhow::anyhow!("{}/connections not found", self.url))
+    }
+
+    fn fetch_hierarchy(&mut self) -> anyhow::Result<Option<hierarchy::Hierarchy>> {
+        self.get("/hierarchy")
+    }
+
+    fn fetch_delta(&mut self, version: u64) -> anyhow::Result<Option<Delta>> {
+        self.get(&format!("/delta/{version}"))
+    }
+}
+
+/// Loads a universe from a `RemoteSource`.
+///
+/// # Example
+/// ```
+/// use neweden::source::remote::{RemoteBuilder, RemoteSource};
+/// use neweden::{Connection, Navigatable, System};
+///
+/// struct Client; // e.g. wrapping a gRPC client
+///
+/// impl RemoteSource for Client {
+///     fn fetch_systems(&mut self) -> anyhow::Result<Vec<System>> {
+///         Ok(vec![System {
+///             id: 30000142.into(),
+///             name: "Jita".to_string(),
+///             coordinate: (0.0, 0.0, 0.0).into(),
+///             security: 0.9459.into(),
+///             region_name: "The Forge".to_string(),
+///             faction_id: None,
+///         }])
+///     }
+///
+///     fn fetch_connections(&mut self) -> anyhow::Result<Vec<Connection>> {
+///         Ok(vec![])
+///     }
+/// }
+///
+/// let mut builder = RemoteBuilder::new(Client);
+/// let universe = builder.build().unwrap();
+/// println!("{:?}", universe.get_system(&30000142.into()).unwrap().name); // Jita
+/// let universe = builder.refresh(&universe).unwrap();
+/// ```
+pub struct RemoteBuilder<S> {
+    source: S,
+}
+
+impl<S: RemoteSource> RemoteBuilder<S> {
+    pub fn new(source: S) -> Self {
+        Self { source }
+    }
+
+    pub fn build(&mut self) -> anyhow::Result<types::Universe> {
+        let systems = self.source.fetch_systems()?;
+        let connections = self.source.fetch_connections()?;
+        let universe = types::Universe::new(systems.into(), connections.into());
+        Ok(match self.source.fetch_hierarchy()? {
+            Some(hierarchy) => universe.with_hierarchy(hierarchy),
+            None => universe,
+        })
+    }
+
+    /// Returns the current universe, applying the changes since `universe` if the
+    /// source provides them and loading it again otherwise.
+    pub fn refresh(&mut self, universe: &types::Universe) -> anyhow::Result<types::Universe> {
+        let Some(delta) = self.source.fetch_delta(universe.version())? else {
+            return self.build();
+        };
+        Ok(universe.apply(delta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::system;
+
+    fn stargate(from: u32, to: u32) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+        }
+    }
+
+    fn hierarchy() -> hierarchy::Hierarchy {
+        let mut hierarchy = hierarchy::Hierarchy::default();
+        hierarchy.insert_region(10000002.into(), "The Forge");
+        hierarchy.insert_constellation(20000020.into(), 10000002.into(), "Kimotoro");
+        for id in 1..=4 {
+            hierarchy.insert_system(id.into(), 20000020.into());
+        }
+        hierarchy
+    }
+
+    #[derive(Default)]
+    struct Fake {
+        delta: Option<Delta>,
+        loads: usize,
+    }
+
+    impl RemoteSource for Fake {
+        fn fetch_systems(&mut self) -> anyhow::Result<Vec<types::System>> {
+            self.loads += 1;
+            Ok(vec![system(1), system(2), system(3)])
+        }
+
+        fn fetch_connections(&mut self) -> anyhow::Result<Vec<types::Connection>> {
+            Ok(vec![stargate(1, 2), stargate(2, 3), stargate(3, 1)])
+        }
+
+        fn fetch_hierarchy(&mut self) -> anyhow::Result<Option<hierarchy::Hierarchy>> {
+            Ok(Some(hierarchy()))
+        }
+
+        fn fetch_delta(&mut self, _version: u64) -> anyhow::Result<Option<Delta>> {
+            Ok(self.delta.take())
+        }
+    }
+
+    /// Serves one canned HTTP response per path.
+    #[cfg(feature = "rpc")]
+    fn serve(responses: Vec<(&'static str, u16, String)>) -> String {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(responses.len()) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                // skip the headers
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let (_, status, body) = responses
+                    .iter()
+                    .find(|(path, _, _)| request.split(' ').nth(1) == Some(*path))
+                    .unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    #[cfg(feature = "rpc")]
+    #[test]
+    fn test_http_source() {
+        let url = serve(vec![
+            (
+                "/systems",
+                200,
+                serde_json::to_string(&vec![system(1), system(2)]).unwrap(),
+            ),
+            (
+                "/connections",
+                200,
+                serde_json::to_string(&vec![stargate(1, 2)]).unwrap(),
+            ),
+            (
+                "/hierarchy",
+                200,
+                serde_json::to_string(&hierarchy()).unwrap(),
+            ),
+            ("/delta/1", 404, String::new()),
+        ]);
+        let mut source = HttpSource::new(&format!("{url}/"));
+        let universe = RemoteBuilder::new(&mut source).build().unwrap();
+        assert_eq!("2", universe.get_system(&2.into()).unwrap().name);
+        assert_eq!(1, universe.get_connections(&1.into()).unwrap().len());
+        assert_eq!(
+            Some("Kimotoro".to_string()),
+            universe.constellation_of(&2.into()).map(|c| c.name)
+        );
+        assert!(source.fetch_delta(1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut source = Fake::default();
+        let mut builder = RemoteBuilder::new(&mut source);
+        let universe = builder.build().unwrap();
+
+        let reloaded = builder.refresh(&universe).unwrap();
+        assert_eq!(universe.version(), reloaded.version());
+
+        builder.source.delta = Some(Delta {
+            systems: vec![system(1).named("Renamed"), system(4)],
+            removed_systems: vec![3.into()],
+            connections: vec![stargate(2, 4)],
+            removed_connections: vec![(1.into(), 2.into())],
+        });
+        let updated = builder.refresh(&universe).unwrap();
+        assert_eq!(2, source.loads);
+
+        assert_eq!("Renamed", updated.get_system(&1.into()).unwrap().name);
+        assert_eq!(
+            Some(types::RegionId(10000002)),
+            updated.region_of(&4.into()).map(|region| region.id)
+        );
+        assert!(updated.get_system(&3.into()).is_none());
+        assert!(updated.get_connections(&1.into()).is_none());
+        let from_two = updated
+            .get_connections(&2.into())
+            .unwrap()
+            .iter()
+            .map(|c| c.to.0)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![4], from_two);
+    }
+}
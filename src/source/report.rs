@@ -0,0 +1,112 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Handling of malformed rows while loading a universe. Community-converted dumps
+//! frequently contain a few bad rows, in `ParseMode::Lenient` these rows are skipped
+//! and listed in a `LoadReport` instead of aborting the whole load.
+//!
+//! # Example
+//! ```no_run
+//! use neweden::source::csv::CsvBuilder;
+//! use neweden::source::report::ParseMode;
+//!
+//! let (universe, report) = CsvBuilder::new("./mapSolarSystems.csv", "./mapSolarSystemJumps.csv")
+//!     .mode(ParseMode::Lenient)
+//!     .build_with_report()
+//!     .unwrap();
+//! for skipped in &report.skipped {
+//!     eprintln!("skipped {} row {}: {}", skipped.table, skipped.row, skipped.error);
+//! }
+//! ```
+
+/// What to do with a row that can't be parsed.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Fail the load on the first malformed row.
+    #[default]
+    Strict,
+    /// Skip malformed rows and report them.
+    Lenient,
+}
+
+/// A row that was skipped in `ParseMode::Lenient`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedRow {
+    pub table: String,
+    /// The number of the row within the table, starting at 1 and not counting a
+    /// header row.
+    pub row: usize,
+    pub error: String,
+}
+
+/// The rows skipped while loading a universe.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadReport {
+    pub skipped: Vec<SkippedRow>,
+}
+
+impl LoadReport {
+    /// Returns true if no row was skipped.
+    pub fn is_clean(&self) -> bool {
+        self.skipped.is_empty()
+    }
+
+    /// Returns the parsed row, or `None` if it was malformed and skipped. Fails in
+    /// `ParseMode::Strict`.
+    pub(crate) fn row<T>(
+        &mut self,
+        mode: ParseMode,
+        table: &str,
+        row: usize,
+        result: anyhow::Result<T>,
+    ) -> anyhow::Result<Option<T>> {
+        match (result, mode) {
+            (Ok(value), _) => Ok(Some(value)),
+            (Err(err), ParseMode::Strict) => Err(err.context(format!("{table} row {row}"))),
+            (Err(err), ParseMode::Lenient) => {
+                self.skipped.push(SkippedRow {
+                    table: table.to_string(),
+                    row,
+                    error: format!("{err:#}"),
+                });
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row() {
+        let mut report = LoadReport::default();
+        let bad = || anyhow::Result::<u32>::Err(anyhow::anyhow!("invalid security"));
+
+        assert_eq!(
+            Some(1),
+            report.row(ParseMode::Strict, "systems", 1, Ok(1)).unwrap()
+        );
+        let err = report
+            .row(ParseMode::Strict, "systems", 2, bad())
+            .unwrap_err();
+        assert_eq!("systems row 2: invalid security", format!("{err:#}"));
+        assert!(report.is_clean());
+
+        assert_eq!(
+            None,
+            report.row(ParseMode::Lenient, "jumps", 3, bad()).unwrap()
+        );
+        assert_eq!(
+            vec![SkippedRow {
+                table: "jumps".to_string(),
+                row: 3,
+                error: "invalid security".to_string()
+            }],
+            report.skipped
+        );
+    }
+}
@@ -0,0 +1,369 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Loads a universe from an unpacked copy of CCP's official static data export,
+//! without going through a Fuzzwork conversion.
+//!
+//! The export keeps the map in `fsd/universe/<space>/<region>/<constellation>/<system>/`
+//! with a `region.staticdata`, `constellation.staticdata` and `solarsystem.staticdata`
+//! file on each level. Names are taken from `bsd/invNames.yaml` if present and from
+//! the directory names otherwise.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use crate::hierarchy;
+use crate::types;
+
+#[derive(Debug, Deserialize)]
+struct Region {
+    #[serde(rename = "regionID")]
+    id: u32,
+    #[serde(rename = "factionID")]
+    faction_id: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Constellation {
+    #[serde(rename = "constellationID")]
+    id: u32,
+    #[serde(rename = "factionID")]
+    faction_id: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SolarSystem {
+    #[serde(rename = "solarSystemID")]
+    id: u32,
+    center: [f64; 3],
+    security: f32,
+    #[serde(rename = "factionID")]
+    faction_id: Option<u32>,
+    #[serde(default)]
+    stargates: BTreeMap<u32, Stargate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Stargate {
+    destination: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct Name {
+    #[serde(rename = "itemID")]
+    id: u32,
+    #[serde(rename = "itemName")]
+    name: String,
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let input = std::fs::read_to_string(path).with_context(|| path.display().to_string())?;
+    serde_yaml::from_str(&input).with_context(|| path.display().to_string())
+}
+
+fn subdirs(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in std::fs::read_dir(path).with_context(|| path.display().to_string())? {
+        let path = entry?.path();
+        if path.is_dir() {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+struct SdeSystem {
+    id: u32,
+    name: String,
+    coordinate: (f64, f64, f64),
+    security: f32,
+    region_id: u32,
+    region_name: String,
+    constellation_id: u32,
+    constellation_name: String,
+    faction_id: Option<u32>,
+    /// Stargates of the system and the stargate each one leads to.
+    stargates: Vec<(u32, u32)>,
+}
+
+/// Loads a universe from an unpacked SDE.
+///
+/// # Example
+/// ```no_run
+/// use neweden::source::sde::SdeBuilder;
+/// use neweden::Navigatable;
+///
+/// let universe = SdeBuilder::new("./sde").build().unwrap();
+/// println!("{:?}", universe.get_system(&30000142.into()).unwrap().name); // Jita
+/// ```
+pub struct SdeBuilder {
+    root: PathBuf,
+    spaces: Vec<String>,
+}
+
+impl SdeBuilder {
+    /// `root` is the `sde` directory containing `fsd` and `bsd`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            spaces: vec!["eve".to_string(), "wormhole".to_string()],
+        }
+    }
+
+    /// Loads the given directories of `fsd/universe`. Defaults to `eve` and `wormhole`,
+    /// use this to include `abyssal` and `void` or to only load known space.
+    pub fn spaces(mut self, spaces: &[&str]) -> Self {
+        self.spaces = spaces.iter().map(|space| space.to_string()).collect();
+        self
+    }
+
+    fn names(&self) -> anyhow::Result<HashMap<u32, String>> {
+        let path = self.root.join("bsd").join("invNames.yaml");
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+        let names: Vec<Name> = read(&path)?;
+        Ok(names.into_iter().map(|name| (name.id, name.name)).collect())
+    }
+
+    fn systems(&self, names: &HashMap<u32, String>) -> anyhow::Result<Vec<SdeSystem>> {
+        let name = |id: u32, path: &Path| names.get(&id).cloned().unwrap_or_else(|| dir_name(path));
+        let universe = self.root.join("fsd").join("universe");
+        let mut systems = Vec::new();
+        for space in &self.spaces {
+            let space = universe.join(space);
+            if !space.is_dir() {
+                continue;
+            }
+            for region_dir in subdirs(&space)? {
+                let region: Region = read(&region_dir.join("region.staticdata"))?;
+                let region_name = name(region.id, &region_dir);
+                for constellation_dir in subdirs(&region_dir)? {
+                    let constellation: Constellation =
+                        read(&constellation_dir.join("constellation.staticdata"))?;
+                    let constellation_name = name(constellation.id, &constellation_dir);
+                    let faction_id = constellation.faction_id.or(region.faction_id);
+                    for system_dir in subdirs(&constellation_dir)? {
+                        let system: SolarSystem = read(&system_dir.join("solarsystem.staticdata"))?;
+                        let [x, y, z] = system.center;
+                        systems.push(SdeSystem {
+                            id: system.id,
+                            name: name(system.id, &system_dir),
+                            coordinate: (x, y, z),
+                            security: system.security,
+                            region_id: region.id,
+                            region_name: region_name.clone(),
+                            constellation_id: constellation.id,
+                            constellation_name: constellation_name.clone(),
+                            faction_id: system.faction_id.or(faction_id),
+                            stargates: system
+                                .stargates
+                                .into_iter()
+                                .map(|(gate, stargate)| (gate, stargate.destination))
+                                .collect(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(systems)
+    }
+
+    pub fn build(self) -> anyhow::Result<types::Universe> {
+        let names = self.names()?;
+        let systems = self.systems(&names)?;
+
+        let gates = systems
+            .iter()
+            .flat_map(|system| {
+                system
+                    .stargates
+                    .iter()
+                    .map(move |(gate, _)| (*gate, system))
+            })
+            .collect::<HashMap<_, _>>();
+        let connections = systems
+            .iter()
+            .flat_map(|from| {
+                from.stargates.iter().filter_map(|(_, destination)| {
+                    // destinations outside of the loaded spaces are skipped
+                    let to = gates.get(destination)?;
+                    let stargate_type = if from.region_id != to.region_id {
+                        types::StargateType::Regional
+                    } else if from.constellation_id != to.constellation_id {
+                        types::StargateType::Constellation
+                    } else {
+                        types::StargateType::Local
+                    };
+                    Some(types::Connection {
+                        from: from.id.into(),
+                        to: to.id.into(),
+                        r#type: types::ConnectionType::Stargate(stargate_type),
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut hierarchy = hierarchy::Hierarchy::default();
+        for system in &systems {
+            hierarchy.insert_region(system.region_id.into(), &system.region_name);
+            hierarchy.insert_constellation(
+                system.constellation_id.into(),
+                system.region_id.into(),
+                &system.constellation_name,
+            );
+            hierarchy.insert_system(system.id.into(), system.constellation_id.into());
+        }
+
+        let systems = systems
+            .into_iter()
+            .map(|system| types::System {
+                id: system.id.into(),
+                name: system.name,
+                coordinate: system.coordinate.into(),
+                security: system.security.into(),
+                region_name: system.region_name,
+                faction_id: system.faction_id,
+            })
+            .collect::<Vec<_>>();
+        Ok(types::Universe::new(systems.into(), connections.into()).with_hierarchy(hierarchy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_parse() {
+        let system: SolarSystem = serde_yaml::from_str(
+            "
+center:
+- -1.29e+17
+- 6.07e+16
+- 1.17e+17
+security: 0.9459131360054016
+stargates:
+    50000056:
+        destination: 50000057
+        position:
+        - 1.0
+        - 2.0
+        - 3.0
+        typeID: 16
+solarSystemID: 30000142
+solarSystemNameID: 'Jita'
+",
+        )
+        .unwrap();
+        assert_eq!(30000142, system.id);
+        assert_eq!(0.94591314, system.security);
+        assert_eq!(-1.29e17, system.center[0]);
+        assert_eq!(50000057, system.stargates[&50000056].destination);
+        assert_eq!(None, system.faction_id);
+
+        let names: Vec<Name> =
+            serde_yaml::from_str("- itemID: 1\n  itemName: A\n- itemID: 2\n  itemName: B C\n")
+                .unwrap();
+        assert_eq!(
+            vec![(1, "A"), (2, "B C")],
+            names
+                .iter()
+                .map(|name| (name.id, name.name.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert!(serde_yaml::from_str::<Region>("a: 1\n  b: 2\n").is_err());
+    }
+
+    fn write(path: &Path, contents: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    fn system(id: u32, gates: &[(u32, u32)]) -> String {
+        let mut data =
+            format!("solarSystemID: {id}\nsecurity: 0.5\ncenter:\n- 1.0\n- 2.0\n- 3.0\n");
+        if !gates.is_empty() {
+            data.push_str("stargates:\n");
+            for (gate, destination) in gates {
+                data.push_str(&format!("  {gate}:\n    destination: {destination}\n"));
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_build() {
+        let root = std::env::temp_dir().join(format!("neweden-sde-{}", std::process::id()));
+        let eve = root.join("fsd/universe/eve");
+        write(
+            &eve.join("TheForge/region.staticdata"),
+            "regionID: 10000002\nfactionID: 500001\n",
+        );
+        write(
+            &eve.join("TheForge/Kimotoro/constellation.staticdata"),
+            "constellationID: 20000020\n",
+        );
+        write(
+            &eve.join("TheForge/Kimotoro/Jita/solarsystem.staticdata"),
+            &system(30000142, &[(1, 2), (3, 4)]),
+        );
+        write(
+            &eve.join("TheForge/Kimotoro/Perimeter/solarsystem.staticdata"),
+            &system(30000144, &[(2, 1)]),
+        );
+        write(
+            &eve.join("Lonetrek/region.staticdata"),
+            "regionID: 10000016\n",
+        );
+        write(
+            &eve.join("Lonetrek/Ihilakken/constellation.staticdata"),
+            "constellationID: 20000180\n",
+        );
+        write(
+            &eve.join("Lonetrek/Ihilakken/Nomaa/solarsystem.staticdata"),
+            &system(30001363, &[(4, 3)]),
+        );
+        write(
+            &root.join("bsd/invNames.yaml"),
+            "- itemID: 10000002\n  itemName: The Forge\n- itemID: 30000142\n  itemName: Jita\n",
+        );
+
+        let universe = SdeBuilder::new(&root).build().unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let jita = universe.get_system(&30000142.into()).unwrap();
+        assert_eq!("Jita", jita.name);
+        assert_eq!("The Forge", jita.region_name);
+        assert_eq!(Some(500001), jita.faction_id);
+        let kimotoro = universe.constellation_of(&30000142.into()).unwrap();
+        assert_eq!(
+            (10000002.into(), "Kimotoro"),
+            (kimotoro.region_id, kimotoro.name.as_str())
+        );
+        // names missing from invNames fall back to the directory
+        assert_eq!(
+            "Lonetrek",
+            universe.get_system(&30001363.into()).unwrap().region_name
+        );
+        let connections = universe.get_connections(&30000142.into()).unwrap();
+        assert_eq!(2, connections.len());
+        assert!(connections.iter().any(|conn| conn.to == 30001363.into()
+            && conn.r#type == types::ConnectionType::Stargate(types::StargateType::Regional)));
+        assert_eq!(1, universe.get_connections(&30000144.into()).unwrap().len());
+    }
+}
@@ -0,0 +1,359 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! A universe that queries the database on demand instead of loading all systems
+//! and stargates up front.
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+use lru::LruCache;
+
+use super::{DatabaseBuilder, SYSTEMS_QUERY, stargate_type, system_from_row};
+use crate::hierarchy;
+use crate::source::progress::Reporter;
+use crate::source::report::{LoadReport, ParseMode};
+use crate::types;
+
+/// Implements `Navigatable` by querying the database, for services that can't
+/// afford to keep the whole universe in memory.
+///
+/// Only the system ids are kept when opening the database, the systems and stargates
+/// are read once to check them and compute the version. `Navigatable` hands out
+/// references to systems, so a system stays in memory once it has been looked up.
+/// Connections are kept in a LRU cache of the configured size. `all_systems()`
+/// loads every system, and database errors are reported as missing systems. The
+/// regions and constellations are small and loaded up front.
+///
+/// # Example
+/// ```no_run
+/// use neweden::source::sqlite::DatabaseBuilder;
+/// use neweden::Navigatable;
+///
+/// let universe = DatabaseBuilder::new("./sqlite-latest.sqlite")
+///     .build_lazy(256)
+///     .unwrap();
+/// println!("{:?}", universe.get_connections(&30000142.into()));
+/// ```
+pub struct LazyUniverse {
+    conn: rusqlite::Connection,
+    index: HashMap<types::SystemId, usize>,
+    systems: Vec<OnceCell<types::System>>,
+    connections: RefCell<LruCache<types::SystemId, Vec<types::Connection>>>,
+    hierarchy: hierarchy::Hierarchy,
+    version: u64,
+}
+
+impl LazyUniverse {
+    /// Reads all systems and stargates once for the ids and the version, keeping
+    /// nothing else. Malformed rows fail or are skipped according to `mode`.
+    pub(super) fn new(
+        conn: rusqlite::Connection,
+        cache_size: usize,
+        reporter: &mut Reporter<'_>,
+        mode: ParseMode,
+    ) -> anyhow::Result<Self> {
+        let mut report = LoadReport::default();
+        let mut ids = Vec::new();
+        DatabaseBuilder::systems_from(&conn, reporter, mode, &mut report, |system| {
+            ids.push(system.id.0);
+            Ok(())
+        })?;
+        ids.sort_unstable();
+        let mut pairs = Vec::new();
+        DatabaseBuilder::connections_from(&conn, reporter, mode, &mut report, |connection| {
+            pairs.push((connection.from.0, connection.to.0));
+            Ok(())
+        })?;
+        pairs.sort_unstable();
+
+        // the same fingerprint as `Universe`, without keeping the stargates
+        let version = types::fingerprint(
+            types::fingerprint(types::FINGERPRINT_SEED, ids.iter().copied()),
+            pairs.into_iter().flat_map(|(from, to)| [from, to]),
+        );
+
+        // files written by `DatabaseWriter` have no constellations
+        let hierarchy = if DatabaseBuilder::has_table(&conn, "mapConstellations")? {
+            DatabaseBuilder::hierarchy_from(&conn)?
+        } else {
+            hierarchy::Hierarchy::default()
+        };
+
+        let cache_size = NonZeroUsize::new(cache_size).unwrap_or(NonZeroUsize::MIN);
+        Ok(Self {
+            conn,
+            index: ids
+                .iter()
+                .enumerate()
+                .map(|(i, id)| (types::SystemId(*id), i))
+                .collect(),
+            systems: ids.iter().map(|_| OnceCell::new()).collect(),
+            connections: RefCell::new(LruCache::new(cache_size)),
+            hierarchy,
+            version,
+        })
+    }
+
+    fn load_system(&self, id: &types::SystemId) -> rusqlite::Result<Option<types::System>> {
+        let mut stm = self
+            .conn
+            .prepare_cached(&format!("{SYSTEMS_QUERY} WHERE solarSystemID = ?"))?;
+        let mut rows = stm.query([id.0])?;
+        rows.next()?.map(system_from_row).transpose()
+    }
+
+    fn load_connections(&self, from: &types::SystemId) -> rusqlite::Result<Vec<types::Connection>> {
+        let mut stm = self.conn.prepare_cached(
+            "
+            SELECT
+                fromRegionID,
+                fromConstellationID,
+                toRegionID,
+                toConstellationID,
+                toSolarSystemID
+            FROM mapSolarSystemJumps
+            WHERE fromSolarSystemID = ?
+            ",
+        )?;
+        stm.query([from.0])?
+            .mapped(|row| {
+                Ok(types::Connection {
+                    from: *from,
+                    to: row.get::<_, u32>(4)?.into(),
+                    r#type: types::ConnectionType::Stargate(stargate_type(
+                        (row.get(0)?, row.get(1)?),
+                        (row.get(2)?, row.get(3)?),
+                    )),
+                })
+            })
+            .collect()
+    }
+
+    /// The regions and constellations of the systems, see `Universe::hierarchy()`.
+    pub fn hierarchy(&self) -> &hierarchy::Hierarchy {
+        &self.hierarchy
+    }
+
+    pub fn region_of(&self, id: &types::SystemId) -> Option<types::Region> {
+        self.hierarchy.region(&self.hierarchy.region_of(id)?)
+    }
+
+    pub fn constellation_of(&self, id: &types::SystemId) -> Option<types::Constellation> {
+        self.hierarchy
+            .constellation(&self.hierarchy.constellation_of(id)?)
+    }
+
+    /// Number of systems whose connections are currently cached.
+    pub fn cached_connections(&self) -> usize {
+        self.connections.borrow().len()
+    }
+}
+
+impl types::Navigatable for LazyUniverse {
+    fn get_system(&self, id: &types::SystemId) -> Option<&types::System> {
+        let slot = &self.systems[*self.index.get(id)?];
+        if let Some(system) = slot.get() {
+            return Some(system);
+        }
+        let system = self.load_system(id).ok()??;
+        Some(slot.get_or_init(|| system))
+    }
+
+    fn get_connections(&self, from: &types::SystemId) -> Option<Vec<types::Connection>> {
+        if let Some(connections) = self.connections.borrow_mut().get(from) {
+            return Some(connections.clone());
+        }
+        let connections = self.load_connections(from).ok()?;
+        if connections.is_empty() {
+            return None;
+        }
+        self.connections
+            .borrow_mut()
+            .put(*from, connections.clone());
+        Some(connections)
+    }
+
+    fn get_systems_by_range(
+        &self,
+        from: &types::SystemId,
+        range: types::Meters,
+    ) -> Option<Vec<&types::System>> {
+        let origin = self.get_system(from)?;
+        let c = &origin.coordinate;
+        let r = range.0;
+        let mut stm = self
+            .conn
+            .prepare_cached(
+                "
+                SELECT solarSystemID FROM mapSolarSystems
+                WHERE x BETWEEN ?1 AND ?2 AND y BETWEEN ?3 AND ?4 AND z BETWEEN ?5 AND ?6
+                ",
+            )
+            .ok()?;
+        let ids = stm
+            .query([c.x - r, c.x + r, c.y - r, c.y + r, c.z - r, c.z + r])
+            .ok()?
+            .mapped(|row| row.get::<_, u32>(0))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        // the same systems `Universe` returns: jump drive destinations in range
+        let systems = ids
+            .into_iter()
+            .filter_map(|id| self.get_system(&id.into()))
+            .filter(|system| system.is_navigable())
+            .filter(|system| {
+                types::SecurityClass::from(system.security) != types::SecurityClass::Highsec
+            })
+            .filter(|system| system.distance(origin).0 <= r)
+            .collect();
+        Some(systems)
+    }
+
+    fn all_systems(&self) -> Vec<&types::System> {
+        self.index
+            .keys()
+            .filter_map(|id| self.get_system(id))
+            .collect()
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn avoided_by_default(&self, id: &types::SystemId) -> bool {
+        !types::SystemClass::from(id).is_navigable()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::PathBuilder;
+    use crate::source::sqlite::DatabaseBuilder;
+    use crate::types::Navigatable;
+
+    fn database() -> rusqlite::Connection {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE mapRegions (regionID INTEGER, regionName TEXT);
+            CREATE TABLE mapConstellations (
+                constellationID INTEGER, regionID INTEGER, constellationName TEXT
+            );
+            CREATE TABLE mapSolarSystems (
+                solarSystemID INTEGER, solarSystemName TEXT, regionID INTEGER,
+                constellationID INTEGER, x REAL, y REAL, z REAL, security REAL,
+                factionID INTEGER
+            );
+            CREATE TABLE mapSolarSystemJumps (
+                fromRegionID INTEGER, fromConstellationID INTEGER, fromSolarSystemID INTEGER,
+                toRegionID INTEGER, toConstellationID INTEGER, toSolarSystemID INTEGER
+            );
+            INSERT INTO mapRegions VALUES (10000002, 'The Forge'), (10000033, 'The Citadel');
+            INSERT INTO mapConstellations VALUES
+                (20000020, 10000002, 'Kimotoro'), (20000390, 10000033, 'Okkamon');
+            INSERT INTO mapSolarSystems VALUES
+                (30000142, 'Jita', 10000002, 20000020, 0, 0, 0, 0.9459, 500001),
+                (30000144, 'Perimeter', 10000002, 20000020, 1e16, 0, 0, 0.9072, 500001),
+                (30002813, 'Tama', 10000033, 20000390, 2e16, 0, 0, 0.2632, NULL),
+                (30002812, 'Nourvukaiken', 10000033, 20000390, 9e16, 0, 0, 0.6, NULL);
+            INSERT INTO mapSolarSystemJumps VALUES
+                (10000002, 20000020, 30000142, 10000002, 20000020, 30000144),
+                (10000002, 20000020, 30000144, 10000002, 20000020, 30000142),
+                (10000002, 20000020, 30000144, 10000033, 20000390, 30002813),
+                (10000033, 20000390, 30002813, 10000002, 20000020, 30000144);
+            ",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_lazy() {
+        let universe =
+            LazyUniverse::new(database(), 1, &mut Reporter::new(None), ParseMode::Strict).unwrap();
+        let loaded = DatabaseBuilder::from_connection(database()).unwrap();
+        assert_eq!(loaded.version(), universe.version());
+
+        assert_eq!("Tama", universe.get_system(&30002813.into()).unwrap().name);
+        assert!(universe.get_system(&30000001.into()).is_none());
+        assert!(universe.get_connections(&30002812.into()).is_none());
+        assert_eq!(
+            Some("The Citadel".to_string()),
+            universe
+                .region_of(&30002813.into())
+                .map(|region| region.name)
+        );
+        assert_eq!(
+            Some("Kimotoro".to_string()),
+            universe.constellation_of(&30000142.into()).map(|c| c.name)
+        );
+
+        let connections = universe.get_connections(&30000144.into()).unwrap();
+        assert_eq!(2, connections.len());
+        universe.get_connections(&30000142.into()).unwrap();
+        assert_eq!(1, universe.cached_connections());
+
+        let path = PathBuilder::new(&universe)
+            .waypoint(universe.get_system(&30000142.into()).unwrap())
+            .waypoint(universe.get_system(&30002813.into()).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(2, path.jumps());
+
+        let in_range = universe
+            .get_systems_by_range(&30000142.into(), types::Meters(3e16))
+            .unwrap();
+        assert_eq!(
+            vec!["Tama"],
+            in_range.iter().map(|s| s.name.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(4, universe.all_systems().len());
+    }
+
+    #[test]
+    fn test_build_lazy() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::source::progress::Phase;
+
+        let path = std::env::temp_dir().join(format!("neweden-lazy-{}.sqlite", std::process::id()));
+        let conn = database();
+        conn.execute(
+            "INSERT INTO mapSolarSystems VALUES (30000001, 'Broken', 10000002, 20000020, 0, 0, 0, 'high', NULL)",
+            [],
+        )
+        .unwrap();
+        conn.execute("VACUUM INTO ?", [path.to_str().unwrap()])
+            .unwrap();
+        let builder = || DatabaseBuilder::new(path.to_str().unwrap());
+
+        assert!(builder().pool_size(2).build_lazy(16).is_err());
+        assert!(builder().load_stargates(true).build_lazy(16).is_err());
+        // strict by default, like `build()`
+        let err = builder().build_lazy(16).err().unwrap();
+        assert!(format!("{err:#}").starts_with("mapSolarSystems row 5"));
+
+        let phases = Arc::new(Mutex::new(Vec::new()));
+        let seen = phases.clone();
+        let universe = builder()
+            .mode(ParseMode::Lenient)
+            .on_progress(move |loaded, total, phase| {
+                seen.lock().unwrap().push((loaded, total, phase));
+                true
+            })
+            .build_lazy(16)
+            .unwrap();
+        // the malformed system is skipped like by `build()`
+        assert_eq!(4, universe.all_systems().len());
+        assert!(universe.get_system(&30000001.into()).is_none());
+        let phases = phases.lock().unwrap();
+        assert!(phases.contains(&(5, Some(5), Phase::Systems)));
+        assert!(phases.contains(&(4, Some(4), Phase::Connections)));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
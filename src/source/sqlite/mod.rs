@@ -1,10 +1,75 @@
-use anyhow;
+use std::sync::Mutex;
+
+use anyhow::{self, bail};
 use rusqlite;
 
+use crate::agents;
+use crate::celestials;
+use crate::hierarchy;
+use crate::locale;
+use crate::overlay::Overlay;
+use crate::source::pool::Pool;
+use crate::source::progress::{BuildProgress, Phase, Reporter};
+use crate::source::report::{LoadReport, ParseMode};
+use crate::stargates;
+use crate::stations;
 use crate::types;
+use crate::workspace;
+
+mod lazy;
+mod writer;
+
+pub use lazy::LazyUniverse;
+pub use writer::DatabaseWriter;
+
+const SYSTEMS_QUERY: &str = "
+    SELECT solarSystemID, solarSystemName, s.x, s.y, s.z, security, regionName, s.factionID
+    FROM mapSolarSystems s
+    JOIN mapRegions r USING (regionID)
+";
+
+/// Reads a system from the columns of `SYSTEMS_QUERY`.
+fn system_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<types::System> {
+    Ok(types::System {
+        id: row.get::<_, u32>(0)?.into(),
+        name: row.get(1)?,
+        coordinate: (row.get(2)?, row.get(3)?, row.get(4)?).into(),
+        security: row.get::<_, f32>(5)?.into(),
+        region_name: row.get(6)?,
+        faction_id: row.get(7)?,
+    })
+}
+
+/// The type of a stargate from the `(region, constellation)` of both ends.
+fn stargate_type(from: (i32, i32), to: (i32, i32)) -> types::StargateType {
+    if from.0 != to.0 {
+        types::StargateType::Regional
+    } else if from.1 != to.1 {
+        types::StargateType::Constellation
+    } else {
+        types::StargateType::Local
+    }
+}
+
+/// Where the database is read from.
+#[derive(Clone)]
+enum Database {
+    Uri(String),
+    #[cfg(feature = "sqlite-memory")]
+    Static(&'static [u8]),
+    #[cfg(feature = "sqlite-memory")]
+    Bytes(Vec<u8>),
+}
 
 pub struct DatabaseBuilder {
-    uri: String,
+    database: Database,
+    pool: Pool<rusqlite::Connection>,
+    progress: Option<Mutex<BuildProgress>>,
+    mode: ParseMode,
+    stations: bool,
+    stargates: bool,
+    celestials: bool,
+    wormhole_classes: bool,
 }
 
 /// Loads a universe from a database.
@@ -29,82 +94,1227 @@ pub struct DatabaseBuilder {
 /// ```
 impl DatabaseBuilder {
     pub fn new(uri: &str) -> Self {
+        Self::with_database(Database::Uri(uri.to_string()))
+    }
+
+    /// Reads the database from memory instead of a file, e.g. a dump embedded with
+    /// `include_bytes!()`. The data is used in place.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use neweden::source::sqlite::DatabaseBuilder;
+    ///
+    /// static DUMP: &[u8] = include_bytes!("../sqlite-latest.sqlite");
+    /// let universe = DatabaseBuilder::from_static(DUMP).build().unwrap();
+    /// ```
+    #[cfg(feature = "sqlite-memory")]
+    pub fn from_static(data: &'static [u8]) -> Self {
+        Self::with_database(Database::Static(data))
+    }
+
+    /// Reads the database from memory instead of a file, e.g. a dump downloaded
+    /// over HTTP. SQLite works on a copy of the data for every query method.
+    #[cfg(feature = "sqlite-memory")]
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self::with_database(Database::Bytes(data))
+    }
+
+    fn with_database(database: Database) -> Self {
         Self {
-            uri: uri.to_string(),
+            pool: Self::pool(&database, 1),
+            database,
+            progress: None,
+            mode: ParseMode::Strict,
+            stations: false,
+            stargates: false,
+            celestials: false,
+            wormhole_classes: false,
+        }
+    }
+
+    fn pool(database: &Database, size: usize) -> Pool<rusqlite::Connection> {
+        let database = database.clone();
+        Pool::new(size, move || Ok(Self::open(&database)?))
+    }
+
+    /// Keeps up to `size` connections open for the methods loading overlays, such
+    /// as `agents()` or `hierarchy()`, instead of opening the database on every
+    /// call. Defaults to a single connection.
+    pub fn pool_size(mut self, size: usize) -> Self {
+        self.pool = Self::pool(&self.database, size);
+        self
+    }
+
+    /// Opens the database read only.
+    fn open(database: &Database) -> rusqlite::Result<rusqlite::Connection> {
+        match database {
+            Database::Uri(uri) => rusqlite::Connection::open_with_flags(
+                uri,
+                rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_URI,
+            ),
+            #[cfg(feature = "sqlite-memory")]
+            Database::Static(data) => {
+                let mut conn = rusqlite::Connection::open_in_memory()?;
+                conn.deserialize_bytes(rusqlite::MAIN_DB, data)?;
+                Ok(conn)
+            }
+            #[cfg(feature = "sqlite-memory")]
+            Database::Bytes(data) => {
+                let mut conn = rusqlite::Connection::open_in_memory()?;
+                conn.deserialize_read_exact(rusqlite::MAIN_DB, &data[..], data.len(), true)?;
+                Ok(conn)
+            }
         }
     }
 
+    /// Reports the progress of `build()`, see `source::progress`.
+    pub fn on_progress(
+        mut self,
+        progress: impl FnMut(usize, Option<usize>, Phase) -> bool + Send + 'static,
+    ) -> Self {
+        self.progress = Some(Mutex::new(Box::new(progress)));
+        self
+    }
+
+    /// Fails on malformed rows or skips them, defaults to `ParseMode::Strict`. See
+    /// `source::report`.
+    pub fn mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Attaches the NPC stations from `staStations` to the built universe, see
+    /// `Universe::stations_in_system()`. Off by default.
+    pub fn load_stations(mut self, load: bool) -> Self {
+        self.stations = load;
+        self
+    }
+
+    /// Attaches the stargates from `mapJumps` and `mapDenormalize` to the built
+    /// universe, see `Universe::stargates_in_system()`. Off by default.
+    pub fn load_stargates(mut self, load: bool) -> Self {
+        self.stargates = load;
+        self
+    }
+
+    /// Attaches the planets, moons and belts from `mapDenormalize` to the built
+    /// universe, see `Universe::celestials()`. Off by default.
+    pub fn load_celestials(mut self, load: bool) -> Self {
+        self.celestials = load;
+        self
+    }
+
+    /// Attaches the classes of wormhole systems from `mapLocationWormholeClasses`
+    /// to the built universe, see `Universe::wormhole_class()`. Off by default.
+    pub fn load_wormhole_classes(mut self, load: bool) -> Self {
+        self.wormhole_classes = load;
+        self
+    }
+
     pub fn build(self) -> anyhow::Result<types::Universe> {
-        Self::from_connection(rusqlite::Connection::open_with_flags(
-            self.uri,
-            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_URI,
-        )?)
+        Ok(self.build_with_report()?.0)
     }
 
-    pub(self) fn from_connection(conn: rusqlite::Connection) -> anyhow::Result<types::Universe> {
-        let systems = {
-            let mut stm = conn.prepare(
-                "
-                SELECT solarSystemID, solarSystemName, s.x, s.y, s.z, security, regionName
-                FROM mapSolarSystems s
-                JOIN mapRegions r USING (regionID)
-                ",
-            )?;
-
-            stm.query([])?
-                .mapped(|row| {
-                    Ok(types::System {
-                        id: row.get::<_, u32>(0)?.into(),
-                        name: row.get(1)?,
-                        coordinate: (row.get(2)?, row.get(3)?, row.get(4)?).into(),
-                        security: row.get::<_, f32>(5)?.into(),
-                        region_name: row.get(6)?,
-                    })
+    /// Builds the universe and reports the rows skipped in `ParseMode::Lenient`.
+    pub fn build_with_report(self) -> anyhow::Result<(types::Universe, LoadReport)> {
+        let conn = self.pool.get()?;
+        let mut reporter = Reporter::new(self.progress.as_ref());
+        let mut report = LoadReport::default();
+        let mut universe = Self::load(&conn, &mut reporter, self.mode, &mut report)?;
+        // files written by `DatabaseWriter` may have no constellations
+        if Self::has_table(&conn, "mapConstellations")? {
+            universe = universe.with_hierarchy(Self::hierarchy_from(&conn)?);
+        }
+        if self.stations {
+            let stations = Self::stations_from(&conn, &mut reporter, self.mode, &mut report)?;
+            universe = universe.with_stations(stations);
+        }
+        if self.stargates {
+            let stargates = Self::stargates_from(&conn, &mut reporter, self.mode, &mut report)?;
+            universe = universe.with_stargates(stargates);
+        }
+        if self.celestials {
+            let celestials = Self::celestials_from(&conn, &mut reporter, self.mode, &mut report)?;
+            universe = universe.with_celestials(celestials);
+        }
+        if self.wormhole_classes {
+            let classes =
+                Self::wormhole_classes_from(&conn, &mut reporter, self.mode, &mut report)?;
+            universe = universe.with_wormhole_classes(classes);
+        }
+        Ok((universe, report))
+    }
+
+    /// Calls `f` for every system as it is read, without building a universe.
+    /// Malformed rows are skipped silently in `ParseMode::Lenient`.
+    pub fn stream_systems(
+        &self,
+        f: impl FnMut(types::System) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        Self::systems_from(
+            &*self.pool.get()?,
+            &mut Reporter::new(None),
+            self.mode,
+            &mut LoadReport::default(),
+            f,
+        )
+    }
+
+    /// Calls `f` for every stargate as it is read, without building a universe.
+    /// Malformed rows are skipped silently in `ParseMode::Lenient`.
+    pub fn stream_connections(
+        &self,
+        f: impl FnMut(types::Connection) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        Self::connections_from(
+            &*self.pool.get()?,
+            &mut Reporter::new(None),
+            self.mode,
+            &mut LoadReport::default(),
+            f,
+        )
+    }
+
+    /// Opens the database as a `LazyUniverse` that loads systems and stargates on
+    /// demand, caching the stargates of up to `cache_size` systems. The mode and
+    /// progress apply to reading the system ids and the version when opening. Fails
+    /// if a pool size or any of the side tables was set, the lazy universe queries
+    /// through a single connection and has no side tables.
+    pub fn build_lazy(self, cache_size: usize) -> anyhow::Result<LazyUniverse> {
+        if self.pool.size() > 1 {
+            bail!("build_lazy() uses a single connection, pool_size() has no effect");
+        }
+        if self.stations || self.stargates || self.celestials || self.wormhole_classes {
+            bail!("build_lazy() loads no stations, stargates, celestials or wormhole classes");
+        }
+        LazyUniverse::new(
+            Self::open(&self.database)?,
+            cache_size,
+            &mut Reporter::new(self.progress.as_ref()),
+            self.mode,
+        )
+    }
+
+    /// Loads the number of planets, moons and asteroid belts of each system from
+    /// `mapDenormalize`. Systems without celestials are omitted.
+    pub fn celestial_counts(
+        &self,
+    ) -> anyhow::Result<Overlay<types::SystemId, celestials::CelestialCounts>> {
+        Ok(self.celestials()?.counts())
+    }
+
+    /// Loads the planets, moons and asteroid belts with their positions from
+    /// `mapDenormalize`. Malformed rows are skipped silently in `ParseMode::Lenient`.
+    pub fn celestials(&self) -> anyhow::Result<celestials::Celestials> {
+        Self::celestials_from(
+            &*self.pool.get()?,
+            &mut Reporter::new(None),
+            self.mode,
+            &mut LoadReport::default(),
+        )
+    }
+
+    pub(self) fn celestials_from(
+        conn: &rusqlite::Connection,
+        reporter: &mut Reporter<'_>,
+        mode: ParseMode,
+        report: &mut LoadReport,
+    ) -> anyhow::Result<celestials::Celestials> {
+        let celestials = Self::rows_from(
+            conn,
+            reporter,
+            mode,
+            report,
+            (Phase::Celestials, "mapDenormalize"),
+            "
+            SELECT itemID, COALESCE(itemName, ''), solarSystemID, groupID, x, y, z
+            FROM mapDenormalize
+            WHERE groupID IN (7, 8, 9) AND solarSystemID IS NOT NULL
+            ",
+            |row| {
+                Ok(celestials::Celestial {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    system: row.get::<_, u32>(2)?.into(),
+                    kind: celestials::CelestialKind::from_group_id(row.get(3)?)
+                        .expect("filtered by group"),
+                    position: (row.get(4)?, row.get(5)?, row.get(6)?).into(),
                 })
-                .collect::<Result<Vec<_>, _>>()?
-        };
+            },
+        )?;
+        Ok(celestials::Celestials::new(celestials))
+    }
+
+    /// Loads the classes of wormhole systems from `mapLocationWormholeClasses`. The
+    /// class of a system defaults to the class of its constellation or region.
+    /// Malformed rows are skipped silently in `ParseMode::Lenient`.
+    pub fn wormhole_classes(
+        &self,
+    ) -> anyhow::Result<Overlay<types::SystemId, types::WormholeClass>> {
+        Self::wormhole_classes_from(
+            &*self.pool.get()?,
+            &mut Reporter::new(None),
+            self.mode,
+            &mut LoadReport::default(),
+        )
+    }
+
+    pub(self) fn wormhole_classes_from(
+        conn: &rusqlite::Connection,
+        reporter: &mut Reporter<'_>,
+        mode: ParseMode,
+        report: &mut LoadReport,
+    ) -> anyhow::Result<Overlay<types::SystemId, types::WormholeClass>> {
+        let classes = Self::rows_from(
+            conn,
+            reporter,
+            mode,
+            report,
+            (Phase::WormholeClasses, "mapLocationWormholeClasses"),
+            "
+            SELECT
+                s.solarSystemID,
+                COALESCE(sc.wormholeClassID, cc.wormholeClassID, rc.wormholeClassID)
+            FROM mapSolarSystems s
+            LEFT JOIN mapLocationWormholeClasses sc ON sc.locationID = s.solarSystemID
+            LEFT JOIN mapLocationWormholeClasses cc ON cc.locationID = s.constellationID
+            LEFT JOIN mapLocationWormholeClasses rc ON rc.locationID = s.regionID
+            ",
+            |row| Ok((row.get::<_, u32>(0)?, row.get::<_, Option<u32>>(1)?)),
+        )?;
+
+        let mut overlay = Overlay::default();
+        for (id, class) in classes {
+            if let Some(class) = class.and_then(types::WormholeClass::from_id) {
+                overlay.insert(id.into(), class);
+            }
+        }
+        Ok(overlay)
+    }
+
+    /// Loads all agents stationed in NPC stations from `agtAgents`.
+    pub fn agents(&self) -> anyhow::Result<agents::Agents> {
+        Self::agents_from(&*self.pool.get()?)
+    }
 
-        let connections = {
-            let mut stm = conn.prepare(
-                "
-                SELECT
-                    fromRegionID,
-                    fromConstellationID,
-                    fromSolarSystemID,
-                    toRegionID,
-                    toConstellationID,
-                    toSolarSystemID
-                FROM mapSolarSystemJumps
-                ",
-            )?;
-
-            stm.query([])?
-                .mapped(|row| {
-                    let from_region: i32 = row.get(0)?;
-                    let from_constellation: i32 = row.get(1)?;
-                    let from_system: i32 = row.get(2)?;
-
-                    let to_region: i32 = row.get(3)?;
-                    let to_constellation: i32 = row.get(4)?;
-                    let to_system: i32 = row.get(5)?;
-
-                    let stargate_type = if from_region != to_region {
-                        types::StargateType::Regional
-                    } else if from_constellation != to_constellation {
-                        types::StargateType::Constellation
-                    } else {
-                        types::StargateType::Local
-                    };
-
-                    Ok(types::Connection {
-                        from: from_system.into(),
-                        to: to_system.into(),
-                        r#type: types::ConnectionType::Stargate(stargate_type),
-                    })
+    pub(self) fn agents_from(conn: &rusqlite::Connection) -> anyhow::Result<agents::Agents> {
+        let mut stm = conn.prepare(
+            "
+            SELECT
+                a.agentID,
+                COALESCE(n.itemName, ''),
+                a.level,
+                a.divisionID,
+                a.corporationID,
+                COALESCE(c.itemName, ''),
+                a.locationID,
+                s.solarSystemID
+            FROM agtAgents a
+            JOIN staStations s ON s.stationID = a.locationID
+            LEFT JOIN invNames n ON n.itemID = a.agentID
+            LEFT JOIN invNames c ON c.itemID = a.corporationID
+            ",
+        )?;
+
+        let agents = stm
+            .query([])?
+            .mapped(|row| {
+                Ok(agents::Agent {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    level: row.get(2)?,
+                    division: row.get::<_, u32>(3)?.into(),
+                    corporation_id: row.get(4)?,
+                    corporation_name: row.get(5)?,
+                    station_id: row.get(6)?,
+                    system: row.get::<_, u32>(7)?.into(),
                 })
-                .collect::<Result<Vec<_>, _>>()?
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(agents::Agents::new(agents))
+    }
+
+    /// Loads the NPC stations from `staStations` with the services of their operation
+    /// from `staOperationServices`. Malformed rows are skipped silently in
+    /// `ParseMode::Lenient`.
+    pub fn stations(&self) -> anyhow::Result<stations::Stations> {
+        Self::stations_from(
+            &*self.pool.get()?,
+            &mut Reporter::new(None),
+            self.mode,
+            &mut LoadReport::default(),
+        )
+    }
+
+    pub(self) fn stations_from(
+        conn: &rusqlite::Connection,
+        reporter: &mut Reporter<'_>,
+        mode: ParseMode,
+        report: &mut LoadReport,
+    ) -> anyhow::Result<stations::Stations> {
+        let services = if Self::has_table(conn, "staOperationServices")? {
+            "SELECT COALESCE(SUM(DISTINCT o.serviceID), 0) FROM staOperationServices o
+            WHERE o.operationID = s.operationID"
+        } else {
+            "0"
         };
+        let stations = Self::rows_from(
+            conn,
+            reporter,
+            mode,
+            report,
+            (Phase::Stations, "staStations"),
+            &format!(
+                "SELECT s.stationID, s.stationName, s.solarSystemID, ({services})
+                FROM staStations s"
+            ),
+            |row| {
+                Ok(stations::Station {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    system: row.get::<_, u32>(2)?.into(),
+                    services: stations::Services(row.get(3)?),
+                })
+            },
+        )?;
+        Ok(stations::Stations::new(stations))
+    }
 
+    /// Loads the stargates and their positions from `mapDenormalize`, with the system
+    /// of the destination gate from `mapJumps`. Malformed rows are skipped silently
+    /// in `ParseMode::Lenient`.
+    pub fn stargates(&self) -> anyhow::Result<stargates::Stargates> {
+        Self::stargates_from(
+            &*self.pool.get()?,
+            &mut Reporter::new(None),
+            self.mode,
+            &mut LoadReport::default(),
+        )
+    }
+
+    pub(self) fn stargates_from(
+        conn: &rusqlite::Connection,
+        reporter: &mut Reporter<'_>,
+        mode: ParseMode,
+        report: &mut LoadReport,
+    ) -> anyhow::Result<stargates::Stargates> {
+        let stargates = Self::rows_from(
+            conn,
+            reporter,
+            mode,
+            report,
+            (Phase::Stargates, "mapJumps"),
+            "
+            SELECT g.itemID, g.solarSystemID, d.solarSystemID, g.x, g.y, g.z
+            FROM mapJumps j
+            JOIN mapDenormalize g ON g.itemID = j.stargateID
+            JOIN mapDenormalize d ON d.itemID = j.destinationID
+            ",
+            |row| {
+                Ok(stargates::Stargate {
+                    id: row.get(0)?,
+                    system: row.get::<_, u32>(1)?.into(),
+                    destination: row.get::<_, u32>(2)?.into(),
+                    position: (row.get(3)?, row.get(4)?, row.get(5)?).into(),
+                })
+            },
+        )?;
+        Ok(stargates::Stargates::new(stargates))
+    }
+
+    /// Reads the rows of `query` as `phase`, skipping malformed rows of `table` in
+    /// `ParseMode::Lenient`.
+    fn rows_from<T>(
+        conn: &rusqlite::Connection,
+        reporter: &mut Reporter<'_>,
+        mode: ParseMode,
+        report: &mut LoadReport,
+        (phase, table): (Phase, &str),
+        query: &str,
+        parse: impl Fn(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
+    ) -> anyhow::Result<Vec<T>> {
+        let total = conn.query_row(&format!("SELECT COUNT(*) FROM ({query})"), [], |row| {
+            row.get::<_, usize>(0)
+        })?;
+        reporter.start(phase, Some(total))?;
+        let mut stm = conn.prepare(query)?;
+        let mut rows = stm.query([])?;
+        let mut values = Vec::with_capacity(total);
+        let mut i = 0;
+        while let Some(row) = rows.next()? {
+            i += 1;
+            let value = parse(row).map_err(anyhow::Error::from);
+            if let Some(value) = report.row(mode, table, i, value)? {
+                values.push(value);
+            }
+            reporter.add(phase, 1)?;
+        }
+        reporter.finish(phase)?;
+        Ok(values)
+    }
+
+    fn has_table(conn: &rusqlite::Connection, name: &str) -> anyhow::Result<bool> {
+        let tables: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+            [name],
+            |row| row.get(0),
+        )?;
+        Ok(tables > 0)
+    }
+
+    /// Loads the regions and constellations from `mapRegions` and `mapConstellations`
+    /// together with the constellation of each system.
+    pub fn hierarchy(&self) -> anyhow::Result<hierarchy::Hierarchy> {
+        Self::hierarchy_from(&*self.pool.get()?)
+    }
+
+    pub(self) fn hierarchy_from(
+        conn: &rusqlite::Connection,
+    ) -> anyhow::Result<hierarchy::Hierarchy> {
+        let mut hierarchy = hierarchy::Hierarchy::default();
+
+        let mut stm = conn.prepare("SELECT regionID, regionName FROM mapRegions")?;
+        for row in stm
+            .query([])?
+            .mapped(|row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?)))
+        {
+            let (id, name) = row?;
+            hierarchy.insert_region(id.into(), &name);
+        }
+
+        let mut stm = conn.prepare(
+            "SELECT constellationID, regionID, constellationName FROM mapConstellations",
+        )?;
+        for row in stm.query([])?.mapped(|row| {
+            Ok((
+                row.get::<_, u32>(0)?,
+                row.get::<_, u32>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        }) {
+            let (id, region_id, name) = row?;
+            hierarchy.insert_constellation(id.into(), region_id.into(), &name);
+        }
+
+        let mut stm = conn.prepare(
+            "SELECT solarSystemID, constellationID FROM mapSolarSystems
+            WHERE constellationID IS NOT NULL",
+        )?;
+        for row in stm
+            .query([])?
+            .mapped(|row| Ok((row.get::<_, u32>(0)?, row.get::<_, u32>(1)?)))
+        {
+            let (id, constellation_id) = row?;
+            if !hierarchy.insert_system(id.into(), constellation_id.into()) {
+                anyhow::bail!("system {id} is in unknown constellation {constellation_id}");
+            }
+        }
+        Ok(hierarchy)
+    }
+
+    /// Loads the translated system names of the given languages, e.g. `["de", "ja"]`,
+    /// from `trnTranslations`. All languages are loaded if `languages` is empty.
+    pub fn localized_names(&self, languages: &[&str]) -> anyhow::Result<locale::LocalizedNames> {
+        Self::localized_names_from(&*self.pool.get()?, languages)
+    }
+
+    pub(self) fn localized_names_from(
+        conn: &rusqlite::Connection,
+        languages: &[&str],
+    ) -> anyhow::Result<locale::LocalizedNames> {
+        let mut stm = conn.prepare(
+            "
+            SELECT t.keyID, t.languageID, t.text
+            FROM trnTranslations t
+            JOIN trnTranslationColumns c ON c.tcID = t.tcID
+            WHERE c.columnName = 'solarSystemName'
+            ",
+        )?;
+
+        let mut names = locale::LocalizedNames::default();
+        for row in stm.query([])?.mapped(|row| {
+            Ok((
+                row.get::<_, u32>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        }) {
+            let (id, language, name) = row?;
+            // the SDE uses e.g. "en-us" for English
+            let language = language.to_lowercase();
+            if languages.is_empty() || languages.contains(&language.as_str()) {
+                names.insert(id.into(), &language, &name);
+            }
+        }
+        Ok(names)
+    }
+
+    /// Loads the connections and avoided systems of an extended universe written by
+    /// `DatabaseWriter::write_extended()`. Returns an empty extension for dumps.
+    pub fn extension(&self) -> anyhow::Result<workspace::Extension> {
+        Self::extension_from(&*self.pool.get()?)
+    }
+
+    pub(self) fn extension_from(
+        conn: &rusqlite::Connection,
+    ) -> anyhow::Result<workspace::Extension> {
+        let tables: i64 = conn.query_row(
+            "
+            SELECT COUNT(*) FROM sqlite_master
+            WHERE type = 'table' AND name IN ('newedenConnections', 'newedenAvoid')
+            ",
+            [],
+            |row| row.get(0),
+        )?;
+        if tables == 0 {
+            return Ok(workspace::Extension::default());
+        }
+
+        let mut stm = conn
+            .prepare("SELECT fromSolarSystemID, toSolarSystemID, type FROM newedenConnections")?;
+        let connections = stm
+            .query([])?
+            .mapped(|row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map(|row| {
+                let (from, to, name) = row?;
+                let r#type = match name.as_str() {
+                    "local" => types::ConnectionType::Stargate(types::StargateType::Local),
+                    "constellation" => {
+                        types::ConnectionType::Stargate(types::StargateType::Constellation)
+                    }
+                    "regional" => types::ConnectionType::Stargate(types::StargateType::Regional),
+                    "ansiblex" => types::ConnectionType::Ansiblex,
+                    other => anyhow::bail!("unknown connection type {other}"),
+                };
+                Ok(types::Connection {
+                    from: from.into(),
+                    to: to.into(),
+                    r#type,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut stm =
+            conn.prepare("SELECT solarSystemID FROM newedenAvoid ORDER BY solarSystemID")?;
+        let avoid = stm
+            .query([])?
+            .mapped(|row| Ok(types::SystemId(row.get(0)?)))
+            .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+
+        Ok(workspace::Extension { connections, avoid })
+    }
+
+    #[cfg(test)]
+    pub(self) fn from_connection(conn: rusqlite::Connection) -> anyhow::Result<types::Universe> {
+        Self::load(
+            &conn,
+            &mut Reporter::new(None),
+            ParseMode::Strict,
+            &mut LoadReport::default(),
+        )
+    }
+
+    /// Calls a loader of a side table in `ParseMode::Strict` without progress.
+    #[cfg(test)]
+    pub(self) fn strict<T>(
+        conn: &rusqlite::Connection,
+        load: fn(
+            &rusqlite::Connection,
+            &mut Reporter<'_>,
+            ParseMode,
+            &mut LoadReport,
+        ) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        load(
+            conn,
+            &mut Reporter::new(None),
+            ParseMode::Strict,
+            &mut LoadReport::default(),
+        )
+    }
+
+    fn load(
+        conn: &rusqlite::Connection,
+        reporter: &mut Reporter<'_>,
+        mode: ParseMode,
+        report: &mut LoadReport,
+    ) -> anyhow::Result<types::Universe> {
+        let mut systems = Vec::new();
+        Self::systems_from(conn, reporter, mode, report, |system| {
+            systems.push(system);
+            Ok(())
+        })?;
+        let mut connections = Vec::new();
+        Self::connections_from(conn, reporter, mode, report, |connection| {
+            connections.push(connection);
+            Ok(())
+        })?;
         Ok(types::Universe::new(systems.into(), connections.into()))
     }
+
+    fn systems_from(
+        conn: &rusqlite::Connection,
+        reporter: &mut Reporter<'_>,
+        mode: ParseMode,
+        report: &mut LoadReport,
+        mut f: impl FnMut(types::System) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let total = conn.query_row(
+            &format!("SELECT COUNT(*) FROM ({SYSTEMS_QUERY})"),
+            [],
+            |row| row.get::<_, usize>(0),
+        )?;
+        reporter.start(Phase::Systems, Some(total))?;
+        let mut stm = conn.prepare(SYSTEMS_QUERY)?;
+        let mut rows = stm.query([])?;
+        let mut i = 0;
+        while let Some(row) = rows.next()? {
+            i += 1;
+            let system = system_from_row(row).map_err(anyhow::Error::from);
+            if let Some(system) = report.row(mode, "mapSolarSystems", i, system)? {
+                f(system)?;
+            }
+            reporter.add(Phase::Systems, 1)?;
+        }
+        reporter.finish(Phase::Systems)
+    }
+
+    fn connections_from(
+        conn: &rusqlite::Connection,
+        reporter: &mut Reporter<'_>,
+        mode: ParseMode,
+        report: &mut LoadReport,
+        mut f: impl FnMut(types::Connection) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let total = conn.query_row("SELECT COUNT(*) FROM mapSolarSystemJumps", [], |row| {
+            row.get::<_, usize>(0)
+        })?;
+        reporter.start(Phase::Connections, Some(total))?;
+        let mut stm = conn.prepare(
+            "
+            SELECT
+                fromRegionID,
+                fromConstellationID,
+                fromSolarSystemID,
+                toRegionID,
+                toConstellationID,
+                toSolarSystemID
+            FROM mapSolarSystemJumps
+            ",
+        )?;
+        let mut rows = stm.query([])?;
+        let mut i = 0;
+        while let Some(row) = rows.next()? {
+            i += 1;
+            let connection = (|| {
+                let from_system: i32 = row.get(2)?;
+                let to_system: i32 = row.get(5)?;
+                let stargate_type =
+                    stargate_type((row.get(0)?, row.get(1)?), (row.get(3)?, row.get(4)?));
+                Ok(types::Connection {
+                    from: from_system.into(),
+                    to: to_system.into(),
+                    r#type: types::ConnectionType::Stargate(stargate_type),
+                })
+            })();
+            if let Some(connection) = report.row(mode, "mapSolarSystemJumps", i, connection)? {
+                f(connection)?;
+            }
+            reporter.add(Phase::Connections, 1)?;
+        }
+        reporter.finish(Phase::Connections)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::system;
+
+    #[test]
+    fn test_celestial_counts() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE mapDenormalize (
+                itemID INTEGER, itemName TEXT, groupID INTEGER, solarSystemID INTEGER,
+                x REAL, y REAL, z REAL
+            );
+            INSERT INTO mapDenormalize VALUES
+                (1, NULL, 7, 30000142, 0, 0, 0), (2, NULL, 8, 30000142, 0, 0, 0),
+                (3, NULL, 8, 30000142, 0, 0, 0), (4, NULL, 9, 30000142, 0, 0, 0),
+                (5, NULL, 10, 30000142, 0, 0, 0), (6, NULL, 7, 30000144, 0, 0, 0),
+                (7, NULL, 4, NULL, 0, 0, 0);
+            ",
+        )
+        .unwrap();
+
+        let counts = DatabaseBuilder::strict(&conn, DatabaseBuilder::celestials_from)
+            .unwrap()
+            .counts();
+        assert_eq!(2, counts.len());
+        let jita = counts.get(&30000142.into()).unwrap();
+        assert_eq!((1, 2, 1), (jita.planets, jita.moons, jita.belts));
+        assert_eq!(1, counts.get(&30000144.into()).unwrap().hiding_spots());
+    }
+
+    #[test]
+    fn test_celestials() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE mapDenormalize (
+                itemID INTEGER, groupID INTEGER, solarSystemID INTEGER, itemName TEXT,
+                x REAL, y REAL, z REAL
+            );
+            INSERT INTO mapDenormalize VALUES
+                (40009077, 7, 30000142, 'Jita I', 1.0e11, 0.0, 0.0),
+                (40009082, 8, 30000142, 'Jita IV - Moon 4', 2.0e11, 0.0, 0.0),
+                (40009100, 9, 30000142, NULL, 3.0e11, 0.0, 0.0),
+                (50001248, 10, 30000142, 'Stargate (Perimeter)', 0.0, 0.0, 0.0);
+            ",
+        )
+        .unwrap();
+
+        let celestials = DatabaseBuilder::strict(&conn, DatabaseBuilder::celestials_from).unwrap();
+        assert_eq!(3, celestials.len());
+        let moon = celestials.get(40009082).unwrap();
+        assert_eq!(
+            (celestials::CelestialKind::Moon, "Jita IV - Moon 4"),
+            (moon.kind, moon.name.as_str())
+        );
+        assert_eq!("", celestials.get(40009100).unwrap().name);
+    }
+
+    #[test]
+    fn test_wormhole_classes() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE mapSolarSystems (
+                solarSystemID INTEGER, constellationID INTEGER, regionID INTEGER
+            );
+            CREATE TABLE mapLocationWormholeClasses (locationID INTEGER, wormholeClassID INTEGER);
+            INSERT INTO mapSolarSystems VALUES
+                (31000005, 21000324, 11000031),
+                (31000001, 21000001, 11000001),
+                (31000002, 21000001, 11000001),
+                (30000142, 20000020, 10000002);
+            INSERT INTO mapLocationWormholeClasses VALUES
+                (11000031, 12), (11000001, 1), (21000001, 2), (31000002, 13), (10000002, 7);
+            ",
+        )
+        .unwrap();
+
+        let classes =
+            DatabaseBuilder::strict(&conn, DatabaseBuilder::wormhole_classes_from).unwrap();
+        assert_eq!(3, classes.len());
+        assert_eq!(
+            Some(&types::WormholeClass::Thera),
+            classes.get(&31000005.into())
+        );
+        // the constellation overrides the region, the system the constellation
+        assert_eq!(
+            Some(&types::WormholeClass::Class(2)),
+            classes.get(&31000001.into())
+        );
+        assert_eq!(
+            Some(&types::WormholeClass::Shattered),
+            classes.get(&31000002.into())
+        );
+    }
+
+    #[test]
+    fn test_agents() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE agtAgents (
+                agentID INTEGER, divisionID INTEGER, corporationID INTEGER,
+                locationID INTEGER, level INTEGER
+            );
+            CREATE TABLE staStations (stationID INTEGER, solarSystemID INTEGER);
+            CREATE TABLE invNames (itemID INTEGER, itemName TEXT);
+            INSERT INTO agtAgents VALUES
+                (3008416, 24, 1000035, 60003760, 4),
+                (3008417, 22, 1000035, 40000000, 2);
+            INSERT INTO staStations VALUES (60003760, 30000142);
+            INSERT INTO invNames VALUES (3008416, 'Antaken Kamola'), (1000035, 'Caldari Navy');
+            ",
+        )
+        .unwrap();
+
+        let agents = DatabaseBuilder::agents_from(&conn).unwrap();
+        assert_eq!(1, agents.len());
+        let agent = agents.in_system(&30000142.into()).next().unwrap();
+        assert_eq!("Antaken Kamola", agent.name);
+        assert_eq!("Caldari Navy", agent.corporation_name);
+        assert_eq!(agents::Division::Security, agent.division);
+    }
+
+    #[test]
+    fn test_stations() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE staStations (
+                stationID INTEGER, stationName TEXT, solarSystemID INTEGER, operationID INTEGER
+            );
+            INSERT INTO staStations VALUES
+                (60003760, 'Jita IV - Moon 4 - Caldari Navy Assembly Plant', 30000142, 26);
+            ",
+        )
+        .unwrap();
+        let stations = DatabaseBuilder::strict(&conn, DatabaseBuilder::stations_from).unwrap();
+        assert_eq!(
+            stations::Services(0),
+            stations.get(60003760).unwrap().services
+        );
+
+        conn.execute_batch(
+            "
+            CREATE TABLE staOperationServices (operationID INTEGER, serviceID INTEGER);
+            INSERT INTO staOperationServices VALUES (26, 64), (26, 4096), (27, 512);
+            ",
+        )
+        .unwrap();
+        let stations = DatabaseBuilder::strict(&conn, DatabaseBuilder::stations_from).unwrap();
+        let station = stations.in_system(&30000142.into()).next().unwrap();
+        assert!(station.services.contains(stations::Services::MARKET));
+        assert!(station.services.contains(stations::Services::REPAIR));
+        assert!(!station.services.contains(stations::Services::CLONING));
+
+        conn.execute(
+            "INSERT INTO staStations VALUES (60003761, 'Jita IV', NULL, 26)",
+            [],
+        )
+        .unwrap();
+        assert!(DatabaseBuilder::strict(&conn, DatabaseBuilder::stations_from).is_err());
+        let mut report = LoadReport::default();
+        let stations = DatabaseBuilder::stations_from(
+            &conn,
+            &mut Reporter::new(None),
+            ParseMode::Lenient,
+            &mut report,
+        )
+        .unwrap();
+        assert_eq!(1, stations.len());
+        assert_eq!(
+            vec![("staStations", 2)],
+            report
+                .skipped
+                .iter()
+                .map(|skipped| (skipped.table.as_str(), skipped.row))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_stargates() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE mapDenormalize (
+                itemID INTEGER, solarSystemID INTEGER, x REAL, y REAL, z REAL
+            );
+            CREATE TABLE mapJumps (stargateID INTEGER, destinationID INTEGER);
+            INSERT INTO mapDenormalize VALUES
+                (50001248, 30000142, 1.5e12, 0.0, -2.0e11),
+                (50001247, 30000144, 0.0, 0.0, 0.0),
+                (40009077, 30000142, 1.0, 2.0, 3.0);
+            INSERT INTO mapJumps VALUES (50001248, 50001247), (50001247, 50001248);
+            ",
+        )
+        .unwrap();
+
+        let stargates = DatabaseBuilder::strict(&conn, DatabaseBuilder::stargates_from).unwrap();
+        assert_eq!(2, stargates.len());
+        let gate = stargates.get(50001248).unwrap();
+        assert_eq!(
+            (30000142.into(), 30000144.into()),
+            (gate.system, gate.destination)
+        );
+        assert_eq!(1.5e12, gate.position.x);
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE mapRegions (regionID INTEGER, regionName TEXT);
+            CREATE TABLE mapConstellations (
+                constellationID INTEGER, regionID INTEGER, constellationName TEXT
+            );
+            CREATE TABLE mapSolarSystems (solarSystemID INTEGER, constellationID INTEGER);
+            INSERT INTO mapRegions VALUES (10000002, 'The Forge');
+            INSERT INTO mapConstellations VALUES (20000020, 10000002, 'Kimotoro');
+            INSERT INTO mapSolarSystems VALUES (30000142, 20000020), (30000144, 20000020);
+            ",
+        )
+        .unwrap();
+
+        let hierarchy = DatabaseBuilder::hierarchy_from(&conn).unwrap();
+        assert_eq!(2, hierarchy.len());
+        assert_eq!(
+            Some(types::RegionId(10000002)),
+            hierarchy.region_of(&30000142.into())
+        );
+        assert_eq!(
+            Some("Kimotoro"),
+            hierarchy.constellation_name(&20000020.into())
+        );
+
+        conn.execute(
+            "INSERT INTO mapSolarSystems VALUES (30000001, 20000001)",
+            [],
+        )
+        .unwrap();
+        assert!(DatabaseBuilder::hierarchy_from(&conn).is_err());
+    }
+
+    #[test]
+    fn test_progress() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE mapRegions (regionID INTEGER, regionName TEXT);
+            CREATE TABLE mapSolarSystems (
+                solarSystemID INTEGER, solarSystemName TEXT, regionID INTEGER,
+                x REAL, y REAL, z REAL, security REAL, factionID INTEGER
+            );
+            CREATE TABLE mapSolarSystemJumps (
+                fromRegionID INTEGER, fromConstellationID INTEGER, fromSolarSystemID INTEGER,
+                toRegionID INTEGER, toConstellationID INTEGER, toSolarSystemID INTEGER
+            );
+            INSERT INTO mapRegions VALUES (10000002, 'The Forge');
+            INSERT INTO mapSolarSystems VALUES
+                (30000142, 'Jita', 10000002, 0, 0, 0, 0.9459, 500001),
+                (30000144, 'Perimeter', 10000002, 1e16, 0, 0, 0.9072, 500001);
+            INSERT INTO mapSolarSystemJumps VALUES
+                (10000002, 20000020, 30000142, 10000002, 20000020, 30000144);
+            ",
+        )
+        .unwrap();
+
+        let reports = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let seen = reports.clone();
+        let progress: Mutex<BuildProgress> = Mutex::new(Box::new(move |loaded, total, phase| {
+            seen.lock().unwrap().push((loaded, total, phase));
+            true
+        }));
+        let universe = DatabaseBuilder::load(
+            &conn,
+            &mut Reporter::new(Some(&progress)),
+            ParseMode::Strict,
+            &mut LoadReport::default(),
+        )
+        .unwrap();
+        assert_eq!(2, universe.systems.systems().len());
+        assert_eq!(
+            vec![
+                (0, Some(2), Phase::Systems),
+                (2, Some(2), Phase::Systems),
+                (0, Some(1), Phase::Connections),
+                (1, Some(1), Phase::Connections),
+            ],
+            *reports.lock().unwrap()
+        );
+
+        let progress: Mutex<BuildProgress> =
+            Mutex::new(Box::new(|_, _, phase| phase == Phase::Systems));
+        let err = DatabaseBuilder::load(
+            &conn,
+            &mut Reporter::new(Some(&progress)),
+            ParseMode::Strict,
+            &mut LoadReport::default(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.is::<crate::source::progress::Aborted>());
+    }
+
+    #[test]
+    fn test_lenient() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE mapRegions (regionID INTEGER, regionName TEXT);
+            CREATE TABLE mapSolarSystems (
+                solarSystemID INTEGER, solarSystemName TEXT, regionID INTEGER,
+                x REAL, y REAL, z REAL, security REAL, factionID INTEGER
+            );
+            CREATE TABLE mapSolarSystemJumps (
+                fromRegionID INTEGER, fromConstellationID INTEGER, fromSolarSystemID INTEGER,
+                toRegionID INTEGER, toConstellationID INTEGER, toSolarSystemID INTEGER
+            );
+            INSERT INTO mapRegions VALUES (10000002, 'The Forge');
+            INSERT INTO mapSolarSystems VALUES
+                (30000142, 'Jita', 10000002, 0, 0, 0, 0.9459, 500001),
+                (30000144, 'Perimeter', 10000002, 1e16, 0, 0, 'high', 500001);
+            INSERT INTO mapSolarSystemJumps VALUES
+                (10000002, 20000020, 30000142, 10000002, 20000020, 30000144),
+                (10000002, 20000020, NULL, 10000002, 20000020, 30000142);
+            ",
+        )
+        .unwrap();
+
+        let load = |mode, report: &mut LoadReport| {
+            DatabaseBuilder::load(&conn, &mut Reporter::new(None), mode, report)
+        };
+        let err = load(ParseMode::Strict, &mut LoadReport::default())
+            .err()
+            .unwrap();
+        assert!(format!("{err:#}").starts_with("mapSolarSystems row 2"));
+
+        let mut report = LoadReport::default();
+        let universe = load(ParseMode::Lenient, &mut report).unwrap();
+        assert_eq!(1, universe.systems.systems().len());
+        assert_eq!(1, universe.connections.0.values().flatten().count());
+        assert_eq!(
+            vec![("mapSolarSystems", 2), ("mapSolarSystemJumps", 2)],
+            report
+                .skipped
+                .iter()
+                .map(|skipped| (skipped.table.as_str(), skipped.row))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "sqlite-memory")]
+    #[test]
+    fn test_from_bytes() {
+        use crate::types::Navigatable;
+
+        let path =
+            std::env::temp_dir().join(format!("neweden-bytes-{}.sqlite", std::process::id()));
+        let universe = crate::builder::UniverseBuilder::default()
+            .system(
+                system(30000142)
+                    .named("Jita")
+                    .with_security(0.9459)
+                    .in_region("The Forge"),
+            )
+            .build();
+        DatabaseWriter::new(path.to_str().unwrap())
+            .write(&universe)
+            .unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let loaded = DatabaseBuilder::from_bytes(data.clone()).build().unwrap();
+        assert_eq!(universe.version(), loaded.version());
+        let loaded = DatabaseBuilder::from_static(data.leak()).build().unwrap();
+        assert_eq!("Jita", loaded.get_system(&30000142.into()).unwrap().name);
+        assert!(
+            DatabaseBuilder::from_bytes(b"not a database".to_vec())
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_pool() {
+        let path = std::env::temp_dir().join(format!("neweden-pool-{}.sqlite", std::process::id()));
+        DatabaseWriter::new(path.to_str().unwrap())
+            .write(&crate::builder::UniverseBuilder::default().build())
+            .unwrap();
+        let builder = DatabaseBuilder::new(path.to_str().unwrap()).pool_size(2);
+        assert_eq!(0, builder.pool.idle());
+        builder.extension().unwrap();
+        builder.extension().unwrap();
+        // the connection was reused
+        assert_eq!(1, builder.pool.idle());
+        builder.build().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_side_tables() {
+        let path =
+            std::env::temp_dir().join(format!("neweden-tables-{}.sqlite", std::process::id()));
+        let mut classes = Overlay::default();
+        classes.insert(30000142.into(), types::WormholeClass::Thera);
+        let universe = crate::builder::UniverseBuilder::default()
+            .system(
+                system(30000142)
+                    .named("Jita")
+                    .with_security(0.9459)
+                    .in_region("The Forge"),
+            )
+            .build()
+            .with_stations(stations::Stations::new(vec![stations::Station {
+                id: 60003760,
+                name: "Jita IV - Moon 4".to_string(),
+                system: 30000142.into(),
+                services: stations::Services::MARKET,
+            }]))
+            .with_wormhole_classes(classes);
+        DatabaseWriter::new(path.to_str().unwrap())
+            .write(&universe)
+            .unwrap();
+
+        let loaded = DatabaseBuilder::new(path.to_str().unwrap())
+            .build()
+            .unwrap();
+        assert!(loaded.stations().is_empty());
+        assert_eq!(None, loaded.wormhole_class(&30000142.into()));
+
+        let phases = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let seen = phases.clone();
+        let loaded = DatabaseBuilder::new(path.to_str().unwrap())
+            .load_stations(true)
+            .load_wormhole_classes(true)
+            .on_progress(move |_, _, phase| {
+                seen.lock().unwrap().push(phase);
+                true
+            })
+            .build()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(1, loaded.stations_in_system(&30000142.into()).len());
+        assert_eq!(
+            Some(types::WormholeClass::Thera),
+            loaded.wormhole_class(&30000142.into())
+        );
+        let mut phases = phases.lock().unwrap().clone();
+        phases.dedup();
+        assert_eq!(
+            vec![
+                Phase::Systems,
+                Phase::Connections,
+                Phase::Stations,
+                Phase::WormholeClasses
+            ],
+            phases
+        );
+    }
+
+    #[test]
+    fn test_localized_names() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE trnTranslationColumns (tcID INTEGER, tableName TEXT, columnName TEXT);
+            CREATE TABLE trnTranslations (tcID INTEGER, keyID INTEGER, languageID TEXT, text TEXT);
+            INSERT INTO trnTranslationColumns VALUES
+                (40, 'dbo.mapSolarSystems', 'solarSystemName'), (8, 'dbo.invTypes', 'typeName');
+            INSERT INTO trnTranslations VALUES
+                (40, 30000142, 'RU', 'Джита'), (40, 30000142, 'JA', 'ジタ'), (8, 30000142, 'RU', 'x');
+            ",
+        )
+        .unwrap();
+
+        let names = DatabaseBuilder::localized_names_from(&conn, &["ru"]).unwrap();
+        assert_eq!(Some("Джита"), names.get(&30000142.into(), "ru"));
+        assert_eq!(None, names.get(&30000142.into(), "ja"));
+        let all = DatabaseBuilder::localized_names_from(&conn, &[]).unwrap();
+        assert_eq!(vec!["ja", "ru"], all.languages());
+    }
 }
@@ -0,0 +1,520 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Writes universes into new SQLite files that `DatabaseBuilder` can read, e.g. to
+//! create small fixture databases or to save a merged universe.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use anyhow::bail;
+
+use crate::types;
+
+const SCHEMA: &str = "
+    CREATE TABLE mapRegions (regionID INTEGER PRIMARY KEY, regionName TEXT);
+    CREATE TABLE mapSolarSystems (
+        solarSystemID INTEGER PRIMARY KEY, solarSystemName TEXT, regionID INTEGER,
+        constellationID INTEGER, x REAL, y REAL, z REAL, security REAL, factionID INTEGER
+    );
+    CREATE TABLE mapSolarSystemJumps (
+        fromRegionID INTEGER, fromConstellationID INTEGER, fromSolarSystemID INTEGER,
+        toRegionID INTEGER, toConstellationID INTEGER, toSolarSystemID INTEGER
+    );
+";
+
+/// Tables for `Universe::hierarchy()`, written if the universe has constellations.
+const HIERARCHY_SCHEMA: &str = "
+    CREATE TABLE mapConstellations (
+        constellationID INTEGER PRIMARY KEY, regionID INTEGER, constellationName TEXT
+    );
+";
+
+/// Tables for the stations, stargates, celestials and wormhole classes of a universe,
+/// written if it has any of them.
+const STATIONS_SCHEMA: &str = "
+    CREATE TABLE staStations (
+        stationID INTEGER PRIMARY KEY, stationName TEXT, solarSystemID INTEGER,
+        operationID INTEGER
+    );
+    CREATE TABLE staOperationServices (operationID INTEGER, serviceID INTEGER);
+";
+const DENORMALIZE_SCHEMA: &str = "
+    CREATE TABLE mapDenormalize (
+        itemID INTEGER PRIMARY KEY, itemName TEXT, groupID INTEGER, solarSystemID INTEGER,
+        x REAL, y REAL, z REAL
+    );
+";
+const JUMPS_SCHEMA: &str = "
+    CREATE TABLE mapJumps (stargateID INTEGER PRIMARY KEY, destinationID INTEGER);
+";
+const WORMHOLE_CLASSES_SCHEMA: &str = "
+    CREATE TABLE mapLocationWormholeClasses (
+        locationID INTEGER PRIMARY KEY, wormholeClassID INTEGER
+    );
+";
+
+/// The `groupID` of stargates in `mapDenormalize`.
+const STARGATE_GROUP_ID: u32 = 10;
+
+/// Tables for the additions of an `ExtendedUniverse`, read by
+/// `DatabaseBuilder::extension()`. They are not part of the SDE.
+const EXTENSION_SCHEMA: &str = "
+    CREATE TABLE newedenConnections (
+        fromSolarSystemID INTEGER, toSolarSystemID INTEGER, type TEXT
+    );
+    CREATE TABLE newedenAvoid (solarSystemID INTEGER PRIMARY KEY);
+";
+
+/// The first region id of the SDE. Regions missing from the hierarchy of a universe
+/// are numbered in name order after the known ones.
+const FIRST_REGION_ID: u32 = 10000001;
+
+fn connection_type_name(r#type: &types::ConnectionType) -> anyhow::Result<&'static str> {
+    Ok(match r#type {
+        types::ConnectionType::Stargate(types::StargateType::Local) => "local",
+        types::ConnectionType::Stargate(types::StargateType::Constellation) => "constellation",
+        types::ConnectionType::Stargate(types::StargateType::Regional) => "regional",
+        types::ConnectionType::Ansiblex => "ansiblex",
+        other => bail!("cannot write {other} connections"),
+    })
+}
+
+/// Writes a universe into a new SQLite file.
+///
+/// The hierarchy, stations, stargates, celestials and wormhole classes of the
+/// universe are written into their SDE tables. The region and constellation ids
+/// written for stargate jumps are made up so that `DatabaseBuilder` reads back the
+/// same stargate types. Bridges and wormholes of an extended universe can't be
+/// written as they depend on the pilot's skills or expire.
+///
+/// # Example
+/// ```no_run
+/// use neweden::source::sqlite::{DatabaseBuilder, DatabaseWriter};
+///
+/// let universe = DatabaseBuilder::new("./sqlite-latest.sqlite").build().unwrap();
+/// let highsec = universe.filter(|system| system.security.0 >= 0.45);
+/// DatabaseWriter::new("./highsec.sqlite").write(&highsec).unwrap();
+/// ```
+pub struct DatabaseWriter {
+    path: String,
+}
+
+impl DatabaseWriter {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+
+    /// Writes the systems, stargates and side tables of the universe. Fails if the
+    /// file exists.
+    pub fn write(&self, universe: &types::Universe) -> anyhow::Result<()> {
+        self.create(|conn| Self::write_to(conn, universe, None))
+    }
+
+    /// Writes the base universe together with the Ansiblex gates, stargates and
+    /// avoided systems added by the extended universe.
+    pub fn write_extended(
+        &self,
+        universe: &types::ExtendedUniverse<'_, types::Universe>,
+    ) -> anyhow::Result<()> {
+        self.create(|conn| Self::write_to(conn, universe.universe, Some(universe)))
+    }
+
+    /// Creates the file and removes it again if writing fails.
+    fn create(
+        &self,
+        write: impl FnOnce(&mut rusqlite::Connection) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        if Path::new(&self.path).exists() {
+            bail!("{} already exists", self.path);
+        }
+        let result = rusqlite::Connection::open(&self.path)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut conn| write(&mut conn));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+        result
+    }
+
+    fn write_to(
+        conn: &mut rusqlite::Connection,
+        universe: &types::Universe,
+        extended: Option<&types::ExtendedUniverse<'_, types::Universe>>,
+    ) -> anyhow::Result<()> {
+        let tx = conn.transaction()?;
+        tx.execute_batch(SCHEMA)?;
+
+        let systems = universe.systems.systems();
+        let hierarchy = &universe.hierarchy;
+        let mut regions = BTreeMap::new();
+        for (id, name) in hierarchy.regions() {
+            regions.entry(name).or_insert(id.0);
+        }
+        let mut next_region_id = regions
+            .values()
+            .map(|id| id + 1)
+            .max()
+            .unwrap_or_default()
+            .max(FIRST_REGION_ID);
+        for name in systems
+            .iter()
+            .map(|system| system.region_name.as_str())
+            .collect::<BTreeSet<_>>()
+        {
+            regions.entry(name).or_insert_with(|| {
+                next_region_id += 1;
+                next_region_id - 1
+            });
+        }
+        let region_id = |name: &str| regions[name];
+        {
+            let mut stm = tx.prepare("INSERT INTO mapRegions VALUES (?, ?)")?;
+            for (name, id) in &regions {
+                stm.execute(rusqlite::params![id, name])?;
+            }
+            let mut stm =
+                tx.prepare("INSERT INTO mapSolarSystems VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+            for system in &systems {
+                stm.execute(rusqlite::params![
+                    system.id.0,
+                    system.name,
+                    region_id(&system.region_name),
+                    hierarchy.constellation_of(&system.id).map(|id| id.0),
+                    system.coordinate.x,
+                    system.coordinate.y,
+                    system.coordinate.z,
+                    system.security.0,
+                    system.faction_id,
+                ])?;
+            }
+
+            let mut stm =
+                tx.prepare("INSERT INTO mapSolarSystemJumps VALUES (?, ?, ?, ?, ?, ?)")?;
+            for conn in universe.connections.0.values().flatten() {
+                let types::ConnectionType::Stargate(stargate) = &conn.r#type else {
+                    bail!("cannot write {} connections", conn.r#type);
+                };
+                let (Some(from), Some(to)) = (
+                    universe.systems.get(conn.from),
+                    universe.systems.get(conn.to),
+                ) else {
+                    continue;
+                };
+                let from_region = region_id(&from.region_name);
+                let to_region = match stargate {
+                    // keep the type even if both systems report the same region
+                    types::StargateType::Regional if from.region_name == to.region_name => {
+                        from_region + 1
+                    }
+                    _ => region_id(&to.region_name),
+                };
+                let from_constellation = from_region + 10000000;
+                let to_constellation = match stargate {
+                    types::StargateType::Local => from_constellation,
+                    _ => from_constellation + 1,
+                };
+                stm.execute(rusqlite::params![
+                    from_region,
+                    from_constellation,
+                    conn.from.0,
+                    to_region,
+                    to_constellation,
+                    conn.to.0,
+                ])?;
+            }
+        }
+
+        Self::write_side_tables(&tx, universe)?;
+
+        if let Some(extended) = extended {
+            tx.execute_batch(EXTENSION_SCHEMA)?;
+            let mut stm = tx.prepare("INSERT INTO newedenConnections VALUES (?, ?, ?)")?;
+            for conn in extended.connections.0.values().flatten() {
+                stm.execute(rusqlite::params![
+                    conn.from.0,
+                    conn.to.0,
+                    connection_type_name(&conn.r#type)?,
+                ])?;
+            }
+            let mut stm = tx.prepare("INSERT INTO newedenAvoid VALUES (?)")?;
+            for id in &extended.avoid {
+                stm.execute([id.0])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn write_side_tables(
+        tx: &rusqlite::Transaction<'_>,
+        universe: &types::Universe,
+    ) -> anyhow::Result<()> {
+        let constellations = universe.hierarchy.constellations();
+        if !constellations.is_empty() {
+            tx.execute_batch(HIERARCHY_SCHEMA)?;
+            let mut stm = tx.prepare("INSERT INTO mapConstellations VALUES (?, ?, ?)")?;
+            for constellation in constellations {
+                stm.execute(rusqlite::params![
+                    constellation.id.0,
+                    constellation.region_id.0,
+                    constellation.name,
+                ])?;
+            }
+        }
+
+        if !universe.stations.is_empty() {
+            tx.execute_batch(STATIONS_SCHEMA)?;
+            let mut stm = tx.prepare("INSERT INTO staStations VALUES (?, ?, ?, ?)")?;
+            let mut services = tx.prepare("INSERT INTO staOperationServices VALUES (?, ?)")?;
+            for station in universe.stations.iter() {
+                // every station gets an operation of its own, holding one row per
+                // service bit, which `DatabaseBuilder` sums up again
+                stm.execute(rusqlite::params![
+                    station.id,
+                    station.name,
+                    station.system.0,
+                    station.id,
+                ])?;
+                for bit in (0..32).map(|bit| 1u32 << bit) {
+                    if station.services.0 & bit != 0 {
+                        services.execute([station.id, bit])?;
+                    }
+                }
+            }
+        }
+
+        if !universe.stargates.is_empty() || !universe.celestials.is_empty() {
+            tx.execute_batch(DENORMALIZE_SCHEMA)?;
+            let mut stm = tx.prepare("INSERT INTO mapDenormalize VALUES (?, ?, ?, ?, ?, ?, ?)")?;
+            for celestial in universe.celestials.iter() {
+                stm.execute(rusqlite::params![
+                    celestial.id,
+                    celestial.name,
+                    celestial.kind.group_id(),
+                    celestial.system.0,
+                    celestial.position.x,
+                    celestial.position.y,
+                    celestial.position.z,
+                ])?;
+            }
+            for stargate in universe.stargates.iter() {
+                stm.execute(rusqlite::params![
+                    stargate.id,
+                    None::<String>,
+                    STARGATE_GROUP_ID,
+                    stargate.system.0,
+                    stargate.position.x,
+                    stargate.position.y,
+                    stargate.position.z,
+                ])?;
+            }
+
+            tx.execute_batch(JUMPS_SCHEMA)?;
+            let mut jumps = tx.prepare("INSERT INTO mapJumps VALUES (?, ?)")?;
+            for stargate in universe.stargates.iter() {
+                let destination = match universe
+                    .stargates
+                    .between(&stargate.destination, &stargate.system)
+                {
+                    Some(destination) => i64::from(destination.id),
+                    None => {
+                        // the destination gate is unknown, a placeholder with a
+                        // negative id keeps the system the gate jumps to
+                        let placeholder = -i64::from(stargate.id);
+                        stm.execute(rusqlite::params![
+                            placeholder,
+                            None::<String>,
+                            STARGATE_GROUP_ID,
+                            stargate.destination.0,
+                            0.0,
+                            0.0,
+                            0.0,
+                        ])?;
+                        placeholder
+                    }
+                };
+                jumps.execute(rusqlite::params![stargate.id, destination])?;
+            }
+        }
+
+        if !universe.wormhole_classes.is_empty() {
+            tx.execute_batch(WORMHOLE_CLASSES_SCHEMA)?;
+            let mut stm = tx.prepare("INSERT INTO mapLocationWormholeClasses VALUES (?, ?)")?;
+            for (id, class) in universe.wormhole_classes.iter() {
+                stm.execute([id.0, class.id()])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{ExtendedUniverseBuilder, UniverseBuilder};
+    use crate::fixtures::system;
+    use crate::source::sqlite::DatabaseBuilder;
+    use crate::types::Navigatable;
+
+    fn stargate(from: u32, to: u32, r#type: types::StargateType) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: types::ConnectionType::Stargate(r#type),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let universe = UniverseBuilder::default()
+            .system(
+                system(1)
+                    .in_region("The Forge")
+                    .at(1.0, 2.0, 3.0)
+                    .with_faction(Some(500001)),
+            )
+            .system(system(2).in_region("The Forge"))
+            .system(system(3).in_region("The Forge"))
+            .system(system(4).in_region("Lonetrek"))
+            .connection(stargate(1, 2, types::StargateType::Local))
+            .connection(stargate(2, 3, types::StargateType::Constellation))
+            .connection(stargate(3, 4, types::StargateType::Regional))
+            .build();
+        let mut hierarchy = crate::hierarchy::Hierarchy::default();
+        hierarchy.insert_region(10000002.into(), "The Forge");
+        hierarchy.insert_constellation(20000020.into(), 10000002.into(), "Kimotoro");
+        hierarchy.insert_system(1.into(), 20000020.into());
+        let mut wormhole_classes = crate::overlay::Overlay::default();
+        wormhole_classes.insert(4.into(), types::WormholeClass::Shattered);
+        let universe = universe
+            .with_hierarchy(hierarchy)
+            .with_stations(crate::stations::Stations::new(vec![
+                crate::stations::Station {
+                    id: 60003760,
+                    name: "Jita IV - Moon 4".to_string(),
+                    system: 1.into(),
+                    services: crate::stations::Services(
+                        crate::stations::Services::MARKET.0 | crate::stations::Services::REPAIR.0,
+                    ),
+                },
+            ]))
+            .with_stargates(crate::stargates::Stargates::new(vec![
+                crate::stargates::Stargate {
+                    id: 50000001,
+                    system: 1.into(),
+                    destination: 2.into(),
+                    position: (1.0, 2.0, 3.0).into(),
+                },
+                crate::stargates::Stargate {
+                    id: 50000002,
+                    system: 2.into(),
+                    destination: 1.into(),
+                    position: (4.0, 5.0, 6.0).into(),
+                },
+                crate::stargates::Stargate {
+                    id: 50000003,
+                    system: 2.into(),
+                    destination: 3.into(),
+                    position: (7.0, 8.0, 9.0).into(),
+                },
+            ]))
+            .with_celestials(crate::celestials::Celestials::new(vec![
+                crate::celestials::Celestial {
+                    id: 40009077,
+                    name: "Jita IV".to_string(),
+                    system: 1.into(),
+                    kind: crate::celestials::CelestialKind::Planet,
+                    position: (1.0, 1.0, 1.0).into(),
+                },
+            ]))
+            .with_wormhole_classes(wormhole_classes);
+        let extended = ExtendedUniverseBuilder::new(&universe)
+            .connection(types::Connection {
+                from: 1.into(),
+                to: 4.into(),
+                r#type: types::ConnectionType::Ansiblex,
+            })
+            .avoid(3.into())
+            .build();
+
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        DatabaseWriter::write_to(&mut conn, &universe, Some(&extended)).unwrap();
+        let extension = DatabaseBuilder::extension_from(&conn).unwrap();
+        let hierarchy = DatabaseBuilder::hierarchy_from(&conn).unwrap();
+        let stations = DatabaseBuilder::strict(&conn, DatabaseBuilder::stations_from).unwrap();
+        let stargates = DatabaseBuilder::strict(&conn, DatabaseBuilder::stargates_from).unwrap();
+        let celestials = DatabaseBuilder::strict(&conn, DatabaseBuilder::celestials_from).unwrap();
+        let wormhole_classes =
+            DatabaseBuilder::strict(&conn, DatabaseBuilder::wormhole_classes_from).unwrap();
+        let loaded = DatabaseBuilder::from_connection(conn).unwrap();
+
+        assert_eq!(universe.version(), loaded.version());
+        let first = loaded.get_system(&1.into()).unwrap();
+        assert_eq!(("1", "The Forge"), (&*first.name, &*first.region_name));
+        assert_eq!(Some(500001), first.faction_id);
+        for (from, r#type) in [
+            (1, types::StargateType::Local),
+            (2, types::StargateType::Constellation),
+            (3, types::StargateType::Regional),
+        ] {
+            assert_eq!(
+                types::ConnectionType::Stargate(r#type),
+                loaded.get_connections(&from.into()).unwrap()[0].r#type
+            );
+        }
+
+        assert_eq!(
+            types::ConnectionType::Ansiblex,
+            extension.connections[0].r#type
+        );
+        assert_eq!(vec![types::SystemId(3)], extension.avoid);
+
+        assert_eq!(
+            Some("Kimotoro"),
+            hierarchy.constellation_name(&20000020.into())
+        );
+        assert_eq!(Some(10000002.into()), hierarchy.region_of(&1.into()));
+        assert_eq!(None, hierarchy.region_of(&2.into()));
+        assert_eq!(universe.stations.get(60003760), stations.get(60003760));
+        assert_eq!(3, stargates.len());
+        assert_eq!(
+            Some(50000002),
+            stargates.between(&2.into(), &1.into()).map(|gate| gate.id)
+        );
+        assert_eq!(
+            Some(50000003),
+            stargates.between(&2.into(), &3.into()).map(|gate| gate.id)
+        );
+        assert_eq!(
+            "Jita IV",
+            celestials.in_system(&1.into()).next().unwrap().name
+        );
+        assert_eq!(
+            Some(&types::WormholeClass::Shattered),
+            wormhole_classes.get(&4.into())
+        );
+    }
+
+    #[test]
+    fn test_existing_file() {
+        let path =
+            std::env::temp_dir().join(format!("neweden-writer-{}.sqlite", std::process::id()));
+        let writer = DatabaseWriter::new(path.to_str().unwrap());
+        let universe = UniverseBuilder::default()
+            .system(system(1).in_region("Delve"))
+            .build();
+        writer.write(&universe).unwrap();
+        assert!(writer.write(&universe).is_err());
+        let loaded = DatabaseBuilder::new(path.to_str().unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(universe.version(), loaded.version());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
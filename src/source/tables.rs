@@ -0,0 +1,62 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! The tables a universe carries besides its graph, in the form the file formats
+//! encode them.
+
+use serde::{Deserialize, Serialize};
+
+use crate::celestials::Celestial;
+use crate::hierarchy::Hierarchy;
+use crate::overlay::Overlay;
+use crate::stargates::Stargate;
+use crate::stations::Station;
+use crate::types;
+
+/// The hierarchy, stations, stargates, celestials and wormhole classes of a
+/// universe. Entries are sorted by id, so files of the same universe are identical.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct SideTables {
+    pub(crate) hierarchy: Hierarchy,
+    pub(crate) stations: Vec<Station>,
+    pub(crate) stargates: Vec<Stargate>,
+    pub(crate) celestials: Vec<Celestial>,
+    pub(crate) wormhole_classes: Vec<(types::SystemId, types::WormholeClass)>,
+}
+
+impl SideTables {
+    pub(crate) fn new(universe: &types::Universe) -> Self {
+        let mut stations = universe.stations.iter().cloned().collect::<Vec<_>>();
+        stations.sort_by_key(|station| station.id);
+        let mut stargates = universe.stargates.iter().cloned().collect::<Vec<_>>();
+        stargates.sort_by_key(|stargate| stargate.id);
+        let mut celestials = universe.celestials.iter().cloned().collect::<Vec<_>>();
+        celestials.sort_by_key(|celestial| celestial.id);
+        let mut wormhole_classes = universe
+            .wormhole_classes
+            .iter()
+            .map(|(id, class)| (*id, *class))
+            .collect::<Vec<_>>();
+        wormhole_classes.sort_by_key(|(id, _)| *id);
+        Self {
+            hierarchy: universe.hierarchy.clone(),
+            stations,
+            stargates,
+            celestials,
+            wormhole_classes,
+        }
+    }
+}
+
+/// Indexes the classes of wormhole systems for `Universe::with_wormhole_classes()`.
+pub(crate) fn wormhole_class_overlay(
+    classes: &[(types::SystemId, types::WormholeClass)],
+) -> Overlay<types::SystemId, types::WormholeClass> {
+    let mut overlay = Overlay::default();
+    for (id, class) in classes {
+        overlay.insert(*id, *class);
+    }
+    overlay
+}
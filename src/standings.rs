@@ -0,0 +1,207 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Parser for exported contact and standings lists, e.g. from the ESI contacts
+//! endpoints or third party tools, and conversion into the structures used by the
+//! routing rules.
+//!
+//! Exports are comma or tab separated with the columns contact id, contact type,
+//! standing and an optional name. A header line starting with `contact_id` is skipped.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::rules;
+use crate::types;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum StandingsParseError {
+    #[error("line {0}: expected at least 3 columns")]
+    MissingColumns(usize),
+    #[error("line {0}: invalid contact id {1:?}")]
+    InvalidId(usize, String),
+    #[error("line {0}: unknown contact type {1:?}")]
+    UnknownContactType(usize, String),
+    #[error("line {0}: invalid standing {1:?}")]
+    InvalidStanding(usize, String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ContactType {
+    Character,
+    Corporation,
+    Alliance,
+    Faction,
+}
+
+impl std::str::FromStr for ContactType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "character" => Ok(Self::Character),
+            "corporation" => Ok(Self::Corporation),
+            "alliance" => Ok(Self::Alliance),
+            "faction" => Ok(Self::Faction),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contact {
+    pub id: u32,
+    pub r#type: ContactType,
+    /// Standing between -10.0 and 10.0.
+    pub standing: f32,
+    pub name: Option<String>,
+}
+
+/// Parses an exported standings list. Empty lines are ignored.
+///
+/// # Example
+/// ```
+/// use neweden::standings::{parse, ContactType};
+///
+/// let export = "contact_id,contact_type,standing,name\n\
+///               500026,faction,-10.0,Triglavian Collective";
+/// let contacts = parse(export).unwrap();
+/// assert_eq!(ContactType::Faction, contacts[0].r#type);
+/// assert_eq!(-10.0, contacts[0].standing);
+/// ```
+pub fn parse(export: &str) -> Result<Vec<Contact>, StandingsParseError> {
+    export
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with("contact_id"))
+        .map(|(i, line)| {
+            let separator = if line.contains('\t') { '\t' } else { ',' };
+            let columns = line.split(separator).map(str::trim).collect::<Vec<_>>();
+            if columns.len() < 3 {
+                return Err(StandingsParseError::MissingColumns(i + 1));
+            }
+            let id = columns[0]
+                .parse()
+                .map_err(|_| StandingsParseError::InvalidId(i + 1, columns[0].to_string()))?;
+            let r#type = columns[1].parse().map_err(|_| {
+                StandingsParseError::UnknownContactType(i + 1, columns[1].to_string())
+            })?;
+            let standing = columns[2]
+                .parse()
+                .ok()
+                .filter(|standing: &f32| (-10.0..=10.0).contains(standing))
+                .ok_or_else(|| {
+                    StandingsParseError::InvalidStanding(i + 1, columns[2].to_string())
+                })?;
+            let name = columns
+                .get(3)
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string());
+            Ok(Contact {
+                id,
+                r#type,
+                standing,
+                name,
+            })
+        })
+        .collect()
+}
+
+/// Standings towards characters, corporations, alliances and factions. Contacts
+/// without a standing are neutral.
+///
+/// # Example
+/// ```
+/// use neweden::rules::PilotProfile;
+/// use neweden::standings::{parse, Standings};
+///
+/// let standings = Standings::new(parse("500026,faction,-10.0").unwrap());
+/// let pilot = PilotProfile::from(&standings);
+/// assert!(!pilot.can_use_pochven_gates());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Standings {
+    standings: HashMap<(ContactType, u32), f32>,
+}
+
+impl Standings {
+    pub fn new(contacts: impl IntoIterator<Item = Contact>) -> Self {
+        Self {
+            standings: contacts
+                .into_iter()
+                .map(|contact| ((contact.r#type, contact.id), contact.standing))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, r#type: ContactType, id: u32) -> f32 {
+        self.standings.get(&(r#type, id)).copied().unwrap_or(0.0)
+    }
+
+    /// Returns the standing towards the faction owning the system, neutral for
+    /// systems without a faction.
+    pub fn system(&self, system: &types::System) -> f32 {
+        system
+            .faction_id
+            .map_or(0.0, |faction| self.get(ContactType::Faction, faction))
+    }
+
+    /// Returns a predicate for `PathBuilder::avoid_where` matching systems owned by a
+    /// faction we have a standing of `threshold` or below with.
+    pub fn avoid_below(&self, threshold: f32) -> impl Fn(&types::System) -> bool + '_ {
+        move |system| {
+            system.faction_id.is_some_and(|faction| {
+                self.standings
+                    .get(&(ContactType::Faction, faction))
+                    .is_some_and(|standing| *standing <= threshold)
+            })
+        }
+    }
+}
+
+impl From<&Standings> for rules::PilotProfile {
+    fn from(standings: &Standings) -> Self {
+        Self::new()
+            .triglavian_standing(standings.get(ContactType::Faction, rules::TRIGLAVIAN_FACTION_ID))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::system;
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Err(StandingsParseError::MissingColumns(2)),
+            parse("500026,faction,-10\n99000001,alliance")
+        );
+        assert_eq!(
+            Err(StandingsParseError::UnknownContactType(
+                1,
+                "npc".to_string()
+            )),
+            parse("500026\tnpc\t5.0")
+        );
+        assert_eq!(
+            Err(StandingsParseError::InvalidStanding(1, "-11".to_string())),
+            parse("500026,faction,-11")
+        );
+    }
+
+    #[test]
+    fn test_avoid_below() {
+        let standings = Standings::new(parse("500001,faction,-10\n500002,faction,5").unwrap());
+        let tanoo = |faction_id| system(30000001).named("Tanoo").with_faction(faction_id);
+        let avoid = standings.avoid_below(-5.0);
+        assert!(avoid(&tanoo(Some(500001))));
+        assert!(!avoid(&tanoo(Some(500002))));
+        assert!(!avoid(&tanoo(Some(500003))));
+        assert!(!avoid(&tanoo(None)));
+        assert_eq!(5.0, standings.system(&tanoo(Some(500002))));
+    }
+}
@@ -0,0 +1,145 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Individual stargates and their position within a system, e.g. for warp-in
+//! bookmarks or the distance a ship travels between the gates of a system. Data
+//! sources that know the gates attach them to the universe, see
+//! `Universe::stargates_in_system()`.
+
+use std::collections::HashMap;
+
+use crate::types;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stargate {
+    pub id: u32,
+    pub system: types::SystemId,
+    /// The system the gate jumps to.
+    pub destination: types::SystemId,
+    /// The position within the system in meters.
+    pub position: types::Coordinate,
+}
+
+/// All stargates, indexed by their system.
+#[derive(Debug, Clone, Default)]
+pub struct Stargates {
+    stargates: Vec<Stargate>,
+    by_system: HashMap<types::SystemId, Vec<usize>>,
+}
+
+impl Stargates {
+    pub fn new(stargates: Vec<Stargate>) -> Self {
+        let mut by_system: HashMap<_, Vec<_>> = HashMap::new();
+        for (i, stargate) in stargates.iter().enumerate() {
+            by_system.entry(stargate.system).or_default().push(i);
+        }
+        Self {
+            stargates,
+            by_system,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.stargates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stargates.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Stargate> {
+        self.stargates.iter()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Stargate> {
+        self.stargates.iter().find(|stargate| stargate.id == id)
+    }
+
+    pub fn in_system(&self, id: &types::SystemId) -> impl Iterator<Item = &Stargate> {
+        self.by_system
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|i| &self.stargates[*i])
+    }
+
+    /// The gate in `from` that jumps to `to`.
+    pub fn between(&self, from: &types::SystemId, to: &types::SystemId) -> Option<&Stargate> {
+        self.in_system(from)
+            .find(|stargate| stargate.destination == *to)
+    }
+
+    /// The distance travelled within `system` when arriving from `previous` and
+    /// leaving to `next`, i.e. between the two gates.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::stargates::{Stargate, Stargates};
+    /// use neweden::Meters;
+    ///
+    /// let gate = |id: u32, destination: u32, x: f64| Stargate {
+    ///     id,
+    ///     system: 30000142.into(),
+    ///     destination: destination.into(),
+    ///     position: (x, 0.0, 0.0).into(),
+    /// };
+    /// let stargates = Stargates::new(vec![
+    ///     gate(50001248, 30000138, -1.0e12),
+    ///     gate(50001249, 30000144, 2.0e12),
+    /// ]);
+    /// let leg = stargates.leg(&30000142.into(), &30000138.into(), &30000144.into());
+    /// assert_eq!(Some(Meters(3.0e12)), leg);
+    /// ```
+    pub fn leg(
+        &self,
+        system: &types::SystemId,
+        previous: &types::SystemId,
+        next: &types::SystemId,
+    ) -> Option<types::Meters> {
+        let arrival = self.between(system, previous)?;
+        let departure = self.between(system, next)?;
+        Some(arrival.position.distance(&departure.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+
+    #[test]
+    fn test_stargates() {
+        let gate = |id: u32, system: u32, destination: u32| Stargate {
+            id,
+            system: system.into(),
+            destination: destination.into(),
+            position: (id as f64, 0.0, 0.0).into(),
+        };
+        let universe = UniverseBuilder::default()
+            .build()
+            .with_stargates(Stargates::new(vec![
+                gate(50001248, 30000142, 30000138),
+                gate(50001249, 30000142, 30000144),
+                gate(50001250, 30000144, 30000142),
+            ]));
+
+        assert_eq!(2, universe.stargates_in_system(&30000142.into()).len());
+        assert!(universe.stargates_in_system(&30000138.into()).is_empty());
+        assert_eq!(
+            Some(50001250),
+            universe
+                .stargates()
+                .between(&30000144.into(), &30000142.into())
+                .map(|stargate| stargate.id)
+        );
+        assert!(
+            universe
+                .stargates()
+                .leg(&30000142.into(), &30000138.into(), &30000143.into())
+                .is_none()
+        );
+    }
+}
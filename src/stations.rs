@@ -0,0 +1,132 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! NPC stations and the services they offer, e.g. to find docking options along a
+//! route. With the `sqlite` feature stations are loaded from `staStations` of a dump
+//! and attached to the universe if enabled with `DatabaseBuilder::load_stations()`,
+//! see `Universe::stations_in_system()`.
+
+use std::collections::HashMap;
+
+use crate::types;
+
+/// Services of a station as the bit mask used by the SDE's `staServices`.
+///
+/// # Example
+/// ```
+/// use neweden::stations::Services;
+///
+/// let services = Services(Services::MARKET.0 | Services::REPAIR.0);
+/// assert!(services.contains(Services::MARKET));
+/// assert!(!services.contains(Services::CLONING));
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Services(pub u32);
+
+impl Services {
+    pub const REPROCESSING: Services = Services(16);
+    pub const MARKET: Services = Services(64);
+    pub const CLONING: Services = Services(512);
+    pub const REPAIR: Services = Services(4096);
+    pub const FACTORY: Services = Services(8192);
+    pub const FITTING: Services = Services(65536);
+    pub const INSURANCE: Services = Services(1048576);
+    pub const JUMP_CLONE: Services = Services(8388608);
+    pub const LOYALTY_STORE: Services = Services(16777216);
+
+    /// Returns true if all services of `other` are offered.
+    pub fn contains(&self, other: Services) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Station {
+    pub id: u32,
+    pub name: String,
+    pub system: types::SystemId,
+    pub services: Services,
+}
+
+/// All stations, indexed by their system.
+#[derive(Debug, Clone, Default)]
+pub struct Stations {
+    stations: Vec<Station>,
+    by_system: HashMap<types::SystemId, Vec<usize>>,
+}
+
+impl Stations {
+    pub fn new(stations: Vec<Station>) -> Self {
+        let mut by_system: HashMap<_, Vec<_>> = HashMap::new();
+        for (i, station) in stations.iter().enumerate() {
+            by_system.entry(station.system).or_default().push(i);
+        }
+        Self {
+            stations,
+            by_system,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.stations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Station> {
+        self.stations.iter()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Station> {
+        self.stations.iter().find(|station| station.id == id)
+    }
+
+    pub fn in_system(&self, id: &types::SystemId) -> impl Iterator<Item = &Station> {
+        self.by_system
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|i| &self.stations[*i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+
+    #[test]
+    fn test_stations() {
+        let station = |id: u32, system: u32, services: u32| Station {
+            id,
+            name: format!("Station {id}"),
+            system: system.into(),
+            services: Services(services),
+        };
+        let universe = UniverseBuilder::default()
+            .build()
+            .with_stations(Stations::new(vec![
+                station(60003760, 30000142, Services::MARKET.0 | Services::FITTING.0),
+                station(60003757, 30000142, Services::REPAIR.0),
+                station(60008494, 30002187, Services::MARKET.0),
+            ]));
+
+        let jita = universe.stations_in_system(&30000142.into());
+        assert_eq!(2, jita.len());
+        assert!(jita[0].services.contains(Services::FITTING));
+        assert!(universe.stations_in_system(&30000144.into()).is_empty());
+        assert_eq!(
+            Some(30002187.into()),
+            universe
+                .stations()
+                .get(60008494)
+                .map(|station| station.system)
+        );
+    }
+}
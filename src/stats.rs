@@ -0,0 +1,191 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Live activity of systems from ESI, e.g. to avoid systems with recent kills
+//! when planning a route.
+//!
+//! ESI updates `/universe/system_kills/` and `/universe/system_jumps/` about once
+//! an hour, with the numbers of the last hour. Systems without activity are not
+//! listed.
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::overlay::Overlay;
+use crate::source::esi::{EsiBuilder, Transport};
+use crate::types;
+
+#[derive(Deserialize)]
+struct Kills {
+    system_id: u32,
+    ship_kills: u32,
+    npc_kills: u32,
+    pod_kills: u32,
+}
+
+#[derive(Deserialize)]
+struct Jumps {
+    system_id: u32,
+    ship_jumps: u32,
+}
+
+/// Activity in a system during the last hour.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Activity {
+    pub ship_kills: u32,
+    pub npc_kills: u32,
+    pub pod_kills: u32,
+    pub ship_jumps: u32,
+}
+
+/// Kills and jumps per system, refreshed from ESI.
+///
+/// # Example
+/// ```no_run
+/// use neweden::source::esi::{EsiBuilder, HttpTransport};
+/// use neweden::stats::SystemStats;
+///
+/// let esi = EsiBuilder::new(HttpTransport::new());
+/// let mut stats = SystemStats::default();
+/// stats.refresh(&esi).unwrap();
+/// let jita = stats.get(&30000142.into());
+/// println!("{} jumps", jita.ship_jumps);
+/// ```
+#[derive(Debug, Default)]
+pub struct SystemStats {
+    systems: Overlay<types::SystemId, Activity>,
+    updated: Option<DateTime<Utc>>,
+}
+
+impl SystemStats {
+    /// Replaces the statistics with the current numbers from ESI. The statistics
+    /// are unchanged if a request fails.
+    pub fn refresh<T: Transport>(&mut self, esi: &EsiBuilder<T>) -> anyhow::Result<()> {
+        let kills = esi.get_fresh::<Kills>("/universe/system_kills/")?;
+        let jumps = esi.get_fresh::<Jumps>("/universe/system_jumps/")?;
+
+        let mut systems = Overlay::default();
+        for kill in kills {
+            systems.insert(
+                kill.system_id.into(),
+                Activity {
+                    ship_kills: kill.ship_kills,
+                    npc_kills: kill.npc_kills,
+                    pod_kills: kill.pod_kills,
+                    ship_jumps: 0,
+                },
+            );
+        }
+        for jump in jumps {
+            let id = jump.system_id.into();
+            match systems.get_mut(&id) {
+                Some(activity) => activity.ship_jumps = jump.ship_jumps,
+                None => {
+                    systems.insert(
+                        id,
+                        Activity {
+                            ship_jumps: jump.ship_jumps,
+                            ..Default::default()
+                        },
+                    );
+                }
+            }
+        }
+        self.systems = systems;
+        self.updated = Some(Utc::now());
+        Ok(())
+    }
+
+    /// The activity of a system, all zero if ESI didn't list the system.
+    pub fn get(&self, id: &types::SystemId) -> Activity {
+        self.systems.get(id).copied().unwrap_or_default()
+    }
+
+    /// Systems with activity, e.g. to look up the activity along a route with
+    /// `overlay().along(path.systems())`.
+    pub fn overlay(&self) -> &Overlay<types::SystemId, Activity> {
+        &self.systems
+    }
+
+    /// When the statistics were last refreshed, `None` if never.
+    pub fn updated(&self) -> Option<DateTime<Utc>> {
+        self.updated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::source::esi::{ESI_URL, Response};
+
+    /// Serves the next canned response for each URL.
+    struct Fake(RefCell<Vec<(String, u16, String)>>);
+
+    impl Transport for Fake {
+        fn get(&self, url: &str) -> anyhow::Result<Response> {
+            let mut responses = self.0.borrow_mut();
+            let i = responses
+                .iter()
+                .position(|(u, _, _)| format!("{ESI_URL}{u}") == url)
+                .ok_or_else(|| anyhow::anyhow!("unexpected {url}"))?;
+            let (_, status, body) = responses.remove(i);
+            Ok(Response {
+                status,
+                body,
+                pages: 1,
+                error_limit_remain: Some(100),
+                error_limit_reset: Some(0),
+            })
+        }
+    }
+
+    #[test]
+    fn test_refresh() {
+        let kills = "/universe/system_kills/".to_string();
+        let jumps = "/universe/system_jumps/".to_string();
+        let transport = Fake(RefCell::new(vec![
+            (
+                kills.clone(),
+                200,
+                r#"[{"system_id":30002813,"ship_kills":5,"npc_kills":12,"pod_kills":3}]"#
+                    .to_string(),
+            ),
+            (
+                jumps.clone(),
+                200,
+                r#"[{"system_id":30000142,"ship_jumps":2000},{"system_id":30002813,"ship_jumps":40}]"#
+                    .to_string(),
+            ),
+            (kills.clone(), 200, "[]".to_string()),
+            (jumps.clone(), 404, String::new()),
+        ]));
+        let esi = EsiBuilder::new(&transport).min_interval(Duration::ZERO);
+
+        let mut stats = SystemStats::default();
+        assert!(stats.updated().is_none());
+        stats.refresh(&esi).unwrap();
+        assert!(stats.updated().is_some());
+        assert_eq!(
+            Activity {
+                ship_kills: 5,
+                npc_kills: 12,
+                pod_kills: 3,
+                ship_jumps: 40
+            },
+            stats.get(&30002813.into())
+        );
+        assert_eq!(2000, stats.get(&30000142.into()).ship_jumps);
+        assert_eq!(Activity::default(), stats.get(&30000144.into()));
+        assert_eq!(2, stats.overlay().len());
+
+        // a failed refresh keeps the previous numbers
+        assert!(stats.refresh(&esi).is_err());
+        assert_eq!(5, stats.get(&30002813.into()).ship_kills);
+    }
+}
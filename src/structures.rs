@@ -0,0 +1,112 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Player-owned Upwell structures, e.g. citadels to dock at or Ansiblex jump gates.
+//! Structures are not part of the SDE. Applications fetch them from ESI or their own
+//! tools and register them on an `ExtendedUniverse` using `add_structure()`.
+
+use crate::types;
+
+/// The kind of an Upwell structure.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StructureKind {
+    /// Astrahus, Fortizar or Keepstar.
+    Citadel,
+    /// Raitaru, Azbel or Sotiyo.
+    EngineeringComplex,
+    /// Athanor or Tatara.
+    Refinery,
+    /// An Ansiblex jump gate to the system of its partner gate.
+    Ansiblex { destination: types::SystemId },
+    /// Pharolux cyno beacon.
+    CynoBeacon,
+    /// Tenebrex cyno jammer.
+    CynoJammer,
+}
+
+impl StructureKind {
+    /// The kind of a structure by its type id, e.g. from ESI. Ansiblex gates need
+    /// their destination and are not recognized.
+    pub fn from_type_id(type_id: u32) -> Option<Self> {
+        Some(match type_id {
+            35832 | 35833 | 35834 | 40340 | 47512 | 47513 | 47514 | 47515 | 47516 => Self::Citadel,
+            35825..=35827 => Self::EngineeringComplex,
+            35835 | 35836 => Self::Refinery,
+            35840 => Self::CynoBeacon,
+            37534 => Self::CynoJammer,
+            _ => return None,
+        })
+    }
+
+    /// Returns true if ships can dock at structures of this kind.
+    pub fn is_dockable(&self) -> bool {
+        matches!(
+            self,
+            Self::Citadel | Self::EngineeringComplex | Self::Refinery
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Structure {
+    pub id: u64,
+    pub name: String,
+    pub system: types::SystemId,
+    pub kind: StructureKind,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{ExtendedUniverseBuilder, UniverseBuilder};
+    use crate::fixtures::system;
+    use crate::types::Navigatable;
+
+    #[test]
+    fn test_structures() {
+        let universe = UniverseBuilder::default()
+            .system(system(30004759))
+            .system(system(30004760))
+            .build();
+        let keepstar = Structure {
+            id: 1022734985679,
+            name: "1DQ1-A - Keepstar".to_string(),
+            system: 30004759.into(),
+            kind: StructureKind::from_type_id(35834).unwrap(),
+        };
+        let gate = Structure {
+            id: 1030000000000,
+            name: "1DQ1-A » 8QT-H4".to_string(),
+            system: 30004759.into(),
+            kind: StructureKind::Ansiblex {
+                destination: 30004760.into(),
+            },
+        };
+        let mut extended = ExtendedUniverseBuilder::new(&universe)
+            .structure(keepstar.clone())
+            .build();
+        assert!(extended.get_connections(&30004759.into()).is_none());
+        extended.add_structure(gate);
+
+        let structures = extended.structures_in(&30004759.into());
+        assert_eq!(2, structures.len());
+        assert_eq!(
+            vec![&keepstar],
+            structures
+                .into_iter()
+                .filter(|structure| structure.kind.is_dockable())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(&keepstar), extended.structure(1022734985679));
+        let connections = extended.get_connections(&30004759.into()).unwrap();
+        assert_eq!(
+            (types::SystemId(30004760), types::ConnectionType::Ansiblex),
+            (connections[0].to, connections[0].r#type.clone())
+        );
+        assert!(extended.structures_in(&30004760.into()).is_empty());
+    }
+}
@@ -4,7 +4,10 @@
  */
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
-use std::collections::HashMap;
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+
+use crate::overlay::Overlay;
 
 /// Describes the ID of a solar system. Can be casted to from i32 or u32 using .into()
 ///
@@ -15,7 +18,8 @@ use std::collections::HashMap;
 /// let system_id: SystemId = 30000142.into(); // returns a SystemId
 /// assert_eq!(system_id, SystemId(30000142));
 /// ```
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SystemId(pub u32);
 
 impl From<u32> for SystemId {
@@ -36,8 +40,46 @@ impl From<i64> for SystemId {
     }
 }
 
+/// Describes the ID of a region, e.g. 10000002 for The Forge.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegionId(pub u32);
+
+impl From<u32> for RegionId {
+    fn from(other: u32) -> Self {
+        RegionId(other)
+    }
+}
+
+/// Describes the ID of a constellation, e.g. 20000020 for Kimotoro.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConstellationId(pub u32);
+
+impl From<u32> for ConstellationId {
+    fn from(other: u32) -> Self {
+        ConstellationId(other)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    pub id: RegionId,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Constellation {
+    pub id: ConstellationId,
+    pub region_id: RegionId,
+    pub name: String,
+}
+
 /// Describes a security rating. A security rating is between -1.0 and 1.0.
 #[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Security(pub f32); // TODO Bound check
 
 impl From<f32> for Security {
@@ -46,9 +88,140 @@ impl From<f32> for Security {
     }
 }
 
+impl Security {
+    /// Security as shown in the client. Positive true security is rounded half up to
+    /// one decimal, except that systems between 0.0 and 0.05 are shown as 0.1, so
+    /// 0.45 systems show as 0.5 and are highsec. Negative security is truncated.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::Security;
+    /// assert_eq!(0.5, Security(0.45).display_value());
+    /// assert_eq!(0.4, Security(0.4499).display_value());
+    /// assert_eq!(0.1, Security(0.01).display_value());
+    /// assert_eq!(-0.4, Security(-0.45).display_value());
+    /// assert_eq!(-0.9, Security(-0.99).display_value());
+    /// ```
+    pub fn display_value(&self) -> f32 {
+        if self.0 > 0.0 && self.0 < 0.05 {
+            return 0.1;
+        }
+        // true security is stored as f32, so 0.45 might be just below the half
+        // and -0.4 just above it
+        let tenths = if self.0 < 0.0 {
+            (self.0 * 10.0 - 1e-4).trunc()
+        } else {
+            (self.0 * 10.0 + 1e-4).round()
+        };
+        (tenths / 10.0).clamp(-1.0, 1.0)
+    }
+
+    /// The security class as used by the client. Any system with a true security of
+    /// 0.0 or below is nullsec, even if it is shown as 0.0.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::{Security, SecurityClass};
+    /// assert_eq!(SecurityClass::Highsec, Security(0.45).class());
+    /// assert_eq!(SecurityClass::Lowsec, Security(0.01).class());
+    /// assert_eq!(SecurityClass::Nullsec, Security(-0.04).class());
+    /// assert_eq!(SecurityClass::Nullsec, Security(0.0).class());
+    /// ```
+    pub fn class(&self) -> SecurityClass {
+        if self.0 <= 0.0 {
+            SecurityClass::Nullsec
+        } else if self.display_value() < 0.5 {
+            SecurityClass::Lowsec
+        } else {
+            SecurityClass::Highsec
+        }
+    }
+
+    /// Color of the security in the standard in-game palette.
+    pub fn color(&self) -> Rgb {
+        SecurityColor::default().color(self)
+    }
+
+    /// Color of the security in the standard in-game palette as `#rrggbb`.
+    pub fn hex(&self) -> String {
+        self.color().hex()
+    }
+}
+
+impl std::fmt::Display for Security {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1}", self.display_value())
+    }
+}
+
+/// A color with 8 bits per channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// The color as `#rrggbb`, e.g. for HTML and SVG output.
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// Maps the displayed security, in steps of 0.1 from 0.0 and below to 1.0, to colors.
+/// Defaults to the gradient used by the client.
+///
+/// # Example
+/// ```
+/// use neweden::{Rgb, Security, SecurityColor};
+///
+/// assert_eq!("#2fefef", Security(0.95).hex());
+/// assert_eq!("#f00000", Security(-0.4).hex());
+///
+/// let colorblind = SecurityColor::default().step(5, Rgb(0, 0, 255));
+/// assert_eq!(Rgb(0, 0, 255), colorblind.color(&Security(0.45)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityColor {
+    steps: [Rgb; 11],
+}
+
+impl Default for SecurityColor {
+    fn default() -> Self {
+        Self {
+            steps: [
+                Rgb(0xf0, 0x00, 0x00), // 0.0
+                Rgb(0xd7, 0x30, 0x00),
+                Rgb(0xf0, 0x48, 0x00),
+                Rgb(0xf0, 0x60, 0x00),
+                Rgb(0xd7, 0x77, 0x00),
+                Rgb(0xef, 0xef, 0x00), // 0.5
+                Rgb(0x8f, 0xef, 0x2f),
+                Rgb(0x00, 0xf0, 0x00),
+                Rgb(0x00, 0xef, 0x47),
+                Rgb(0x48, 0xf0, 0xc0),
+                Rgb(0x2f, 0xef, 0xef), // 1.0
+            ],
+        }
+    }
+}
+
+impl SecurityColor {
+    /// Replaces the color for a displayed security of `step / 10`. Step 0 is used for
+    /// all nullsec systems. Steps above 10 are ignored.
+    pub fn step(mut self, step: usize, color: Rgb) -> Self {
+        if let Some(entry) = self.steps.get_mut(step) {
+            *entry = color;
+        }
+        self
+    }
+
+    pub fn color(&self, security: &Security) -> Rgb {
+        let step = (security.display_value() * 10.0).round().clamp(0.0, 10.0) as usize;
+        self.steps[step]
+    }
+}
+
 /// Describes if a system's security rating is considered Highsec, Lowsec or Nullsec.
-/// In Eve Online, 1.0 t 0.45 is considered highsec. 0.0 to 0.45 is considered lowsec,
-/// and below 0.0 is considered nullsec.
+/// In Eve Online, 1.0 to 0.45 is considered highsec. Above 0.0 to 0.45 is considered
+/// lowsec, and 0.0 and below is considered nullsec. See `Security::class()`.
 ///
 /// A security instance can be converted into a SecurityClass.
 ///
@@ -63,7 +236,7 @@ impl From<f32> for Security {
 /// let s3 = Security(0.74);
 /// assert_eq!(SecurityClass::from(s3), SecurityClass::Highsec);
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum SecurityClass {
     Highsec,
     Lowsec,
@@ -72,32 +245,52 @@ pub enum SecurityClass {
 
 impl From<&Security> for SecurityClass {
     fn from(other: &Security) -> Self {
-        let sec = (other.0 * 10.0).round() / 10.0;
-        if sec < 0.0 {
-            Self::Nullsec
-        } else if sec < 0.5 {
-            Self::Lowsec
-        } else {
-            Self::Highsec
-        }
+        other.class()
     }
 }
 
 impl From<Security> for SecurityClass {
     fn from(other: Security) -> Self {
-        let sec = (other.0 * 10.0).round() / 10.0;
-        if sec < 0.0 {
-            Self::Nullsec
-        } else if sec < 0.5 {
-            Self::Lowsec
-        } else {
-            Self::Highsec
+        other.class()
+    }
+}
+
+impl std::fmt::Display for SecurityClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecurityClass::Highsec => write!(f, "highsec"),
+            SecurityClass::Lowsec => write!(f, "lowsec"),
+            SecurityClass::Nullsec => write!(f, "nullsec"),
         }
     }
 }
 
+/// Describes who controls a nullsec system. NPC nullsec (e.g. Curse, Venal, Stain or
+/// Syndicate) is owned by a NPC faction and has NPC stations, while sovereign nullsec
+/// can be claimed by player alliances.
+///
+/// # Example
+/// ```
+/// use neweden::{System, NullsecClass};
+/// let sakht = System {
+///     id: 30003585.into(),
+///     name: "Sakht".to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: (-0.05).into(),
+///     region_name: "Stain".to_string(),
+///     faction_id: Some(500019),
+/// };
+/// assert_eq!(sakht.nullsec_class(), Some(NullsecClass::Npc));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NullsecClass {
+    Npc,
+    Sovereign,
+}
+
 /// Defines a connection between two systems.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Connection {
     pub from: SystemId,
     pub to: SystemId,
@@ -107,6 +300,7 @@ pub struct Connection {
 /// The type of connection between two systems.
 /// Can be a bridge, a stargate or a wormhole.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ConnectionType {
     Stargate(StargateType),
     Bridge(BridgeType),
@@ -146,6 +340,7 @@ impl std::fmt::Display for ConnectionType {
 /// println!("titan's bridge range with JDC4 is {:?}", ly);
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BridgeType {
     // TODO: introduce a type JumpDrive
     Titan(JumpdriveSkills), // jump drive calibration, jump fuel conservation
@@ -153,9 +348,14 @@ pub enum BridgeType {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JumpdriveSkills {
     jump_drive_calibration: u8,
     fuel_conversation: u8,
+    #[cfg_attr(feature = "serde", serde(default))]
+    jump_freighters: u8,
+    #[cfg_attr(feature = "serde", serde(default))]
+    fuel_implant: u8,
 }
 
 impl JumpdriveSkills {
@@ -163,6 +363,8 @@ impl JumpdriveSkills {
         Self {
             jump_drive_calibration,
             fuel_conversation,
+            jump_freighters: 0,
+            fuel_implant: 0,
         }
     }
 
@@ -170,6 +372,142 @@ impl JumpdriveSkills {
         let jdc = f64::from(self.jump_drive_calibration);
         ly + (ly * 0.2 * jdc)
     }
+
+    /// Jump Fuel Conservation reduces fuel usage by 10% per level, implants by their
+    /// bonus in percent on top of it.
+    pub fn fuel_from_base(&self, fuel: f64) -> f64 {
+        let jfc = f64::from(self.fuel_conversation);
+        let implant = f64::from(self.fuel_implant);
+        (fuel - (fuel * 0.1 * jfc)) * (1.0 - implant / 100.0)
+    }
+}
+
+impl TryFrom<&PilotSkills> for JumpdriveSkills {
+    type Error = SkillError;
+
+    fn try_from(skills: &PilotSkills) -> Result<Self, Self::Error> {
+        skills.validate()?;
+        Ok(Self {
+            jump_drive_calibration: skills.jump_drive_calibration,
+            fuel_conversation: skills.jump_fuel_conservation,
+            jump_freighters: skills.jump_freighters,
+            fuel_implant: skills.fuel_implant,
+        })
+    }
+}
+
+/// Skills or implants out of the range the game allows, which would result in a
+/// negative fuel usage.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum SkillError {
+    #[error("{skill} at level {level}, skills go up to level 5")]
+    Level { skill: &'static str, level: u8 },
+    #[error("fuel implant with a bonus of {0}%, at most 100% are possible")]
+    Implant(u8),
+}
+
+/// Skills and implants of a capital pilot that change the range and fuel usage of
+/// jump drives and jump portals. Defaults to all skills at level 5 without implants.
+///
+/// # Example
+/// ```
+/// use neweden::{JumpdriveShip, JumpdriveSkills, Lightyears, PilotSkills};
+///
+/// let skills = PilotSkills::default().jump_drive_calibration(4);
+/// let carrier = JumpdriveShip::Carrier(JumpdriveSkills::new(5, 5))
+///     .with_skills(&skills)
+///     .unwrap();
+/// let ly: Lightyears = carrier.into();
+/// assert!((ly.0 - 6.3).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PilotSkills {
+    pub jump_drive_calibration: u8,
+    pub jump_fuel_conservation: u8,
+    /// Reduces the fuel usage of jump freighters by 10% per level.
+    pub jump_freighters: u8,
+    /// Fuel reduction of jump fuel conservation implants in percent.
+    pub fuel_implant: u8,
+}
+
+impl Default for PilotSkills {
+    fn default() -> Self {
+        Self {
+            jump_drive_calibration: 5,
+            jump_fuel_conservation: 5,
+            jump_freighters: 5,
+            fuel_implant: 0,
+        }
+    }
+}
+
+impl PilotSkills {
+    pub fn jump_drive_calibration(mut self, level: u8) -> Self {
+        self.jump_drive_calibration = level;
+        self
+    }
+
+    pub fn jump_fuel_conservation(mut self, level: u8) -> Self {
+        self.jump_fuel_conservation = level;
+        self
+    }
+
+    pub fn jump_freighters(mut self, level: u8) -> Self {
+        self.jump_freighters = level;
+        self
+    }
+
+    pub fn fuel_implant(mut self, percent: u8) -> Self {
+        self.fuel_implant = percent;
+        self
+    }
+
+    /// Checks that all skills are at most level 5 and the implant bonus at most 100%.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::{PilotSkills, SkillError};
+    ///
+    /// assert_eq!(Ok(()), PilotSkills::default().validate());
+    /// assert_eq!(
+    ///     Err(SkillError::Implant(120)),
+    ///     PilotSkills::default().fuel_implant(120).validate()
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), SkillError> {
+        for (skill, level) in [
+            ("Jump Drive Calibration", self.jump_drive_calibration),
+            ("Jump Fuel Conservation", self.jump_fuel_conservation),
+            ("Jump Freighters", self.jump_freighters),
+        ] {
+            if level > 5 {
+                return Err(SkillError::Level { skill, level });
+            }
+        }
+        if self.fuel_implant > 100 {
+            return Err(SkillError::Implant(self.fuel_implant));
+        }
+        Ok(())
+    }
+}
+
+impl BridgeType {
+    /// Isotopes used by the jump portal per lightyear and kilogram of bridged mass.
+    pub fn fuel_per_ly_kg(&self) -> f64 {
+        match self {
+            BridgeType::Titan(skills) => skills.fuel_from_base(0.000_005),
+            BridgeType::BlackOps(skills) => skills.fuel_from_base(0.000_002),
+        }
+    }
+
+    /// Returns the same bridge operated by a pilot with the given skills.
+    pub fn with_skills(self, skills: &PilotSkills) -> Result<Self, SkillError> {
+        let skills = skills.try_into()?;
+        Ok(match self {
+            BridgeType::Titan(_) => BridgeType::Titan(skills),
+            BridgeType::BlackOps(_) => BridgeType::BlackOps(skills),
+        })
+    }
 }
 
 /// Conversion for jumpdrive capable ships.
@@ -195,25 +533,128 @@ pub enum JumpdriveShip {
     Titan(JumpdriveSkills),
 }
 
+impl JumpdriveShip {
+    /// Isotopes used by the jump drive per lightyear.
+    pub fn fuel_per_ly(&self) -> f64 {
+        match self {
+            JumpdriveShip::BlackOps(skills) => skills.fuel_from_base(700.0),
+            JumpdriveShip::CapitalIndustrial(skills) => skills.fuel_from_base(4000.0),
+            JumpdriveShip::Carrier(skills) => skills.fuel_from_base(3000.0),
+            JumpdriveShip::Dreadnought(skills) => skills.fuel_from_base(3000.0),
+            JumpdriveShip::ForceAuxiliary(skills) => skills.fuel_from_base(3000.0),
+            JumpdriveShip::Jumpfreighter(skills) => {
+                let jf = f64::from(skills.jump_freighters);
+                skills.fuel_from_base(10000.0) * (1.0 - 0.1 * jf)
+            }
+            JumpdriveShip::Supercarrier(skills) => skills.fuel_from_base(3000.0),
+            JumpdriveShip::Titan(skills) => skills.fuel_from_base(3000.0),
+        }
+    }
+
+    /// Returns the same hull flown by a pilot with the given skills.
+    pub fn with_skills(self, skills: &PilotSkills) -> Result<Self, SkillError> {
+        let skills = skills.try_into()?;
+        Ok(match self {
+            JumpdriveShip::BlackOps(_) => JumpdriveShip::BlackOps(skills),
+            JumpdriveShip::CapitalIndustrial(_) => JumpdriveShip::CapitalIndustrial(skills),
+            JumpdriveShip::Carrier(_) => JumpdriveShip::Carrier(skills),
+            JumpdriveShip::Dreadnought(_) => JumpdriveShip::Dreadnought(skills),
+            JumpdriveShip::ForceAuxiliary(_) => JumpdriveShip::ForceAuxiliary(skills),
+            JumpdriveShip::Jumpfreighter(_) => JumpdriveShip::Jumpfreighter(skills),
+            JumpdriveShip::Supercarrier(_) => JumpdriveShip::Supercarrier(skills),
+            JumpdriveShip::Titan(_) => JumpdriveShip::Titan(skills),
+        })
+    }
+}
+
 /// Information about a stargate.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StargateType {
     Local,
     Constellation,
     Regional,
 }
 
+/// Information about a wormhole.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WormholeType {
+    VeryLarge, // everything, except supers+
+    Large,     // battleships
+    Medium,    // battlecruisers, etc
+    Small,     // frigates, etc
+    Unknown,
+}
+
 /// Wormhole definititions, a subset of fields used by EVE Scout
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Wormhole {
     pub expires: DateTime<Utc>,
     pub remaining_hours: u16,
+    /// Signature ID (e.g. `ABC-123`) in the system the wormhole starts in.
     pub signature: String,
+    /// Signature ID in the system the wormhole leads to, if known.
+    pub exit_signature: Option<String>,
     pub max_ship_size: WormholeMaxShipSize,
+    /// Set if the wormhole leads into a shattered system.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub shattered: Option<Shattered>,
+}
+
+/// Kind of a shattered wormhole system. Shattered systems have no planets and
+/// cannot be entered by capitals, which matters when routing through wormhole
+/// chains. Thera is a wormhole system of its own class, see `WormholeClass::Thera`;
+/// the size of ships entering it is limited by the mass of its wormhole types.
+///
+/// # Example
+/// ```
+/// use neweden::rules::ShipSize;
+/// use neweden::Shattered;
+///
+/// assert_eq!(ShipSize::Small, Shattered::Frigate.max_ship_size());
+/// assert_eq!(ShipSize::Freighter, Shattered::Regular.max_ship_size());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Shattered {
+    /// Shattered systems of class 1 to 6.
+    Regular,
+    /// The small shattered systems (class 13), which only frigate sized hulls can enter.
+    Frigate,
+}
+
+impl Shattered {
+    /// The largest hull that can enter a system of this kind.
+    pub fn max_ship_size(&self) -> crate::rules::ShipSize {
+        match self {
+            Self::Regular => crate::rules::ShipSize::Freighter,
+            Self::Frigate => crate::rules::ShipSize::Small,
+        }
+    }
+}
+
+/// User data attached to a connection in an `ExtendedUniverse`, e.g. by a mapping tool.
+/// Annotations are returned with each hop of a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Annotation {
+    /// The pilot that scouted the connection.
+    Scout(String),
+    /// Name of the bookmark for the connection in the given system.
+    Bookmark { system: SystemId, name: String },
+    /// The connection is expected to disappear at the given time, e.g. an EOL wormhole.
+    Expires(DateTime<Utc>),
+    /// Free-form tag.
+    Tag(String),
+}
+
+fn normalize_signature(signature: &str) -> String {
+    signature.trim().to_uppercase()
 }
 
 /// Wormhole max ship size, as defined by EVE Scout
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WormholeMaxShipSize {
     Small,
     Medium,
@@ -236,19 +677,278 @@ impl std::fmt::Display for WormholeMaxShipSize {
     }
 }
 
-/// Information about a wormhole.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum WormholeType {
-    VeryLarge, // everything, except supers+
-    Large,     // battleships
-    Medium,    // battlecruisers, etc
-    Small,     // frigates, etc
+/// The class of space a wormhole type leads to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WormholeDestination {
+    /// Wormhole space class 1 to 6.
+    Class(u8),
+    Highsec,
+    Lowsec,
+    Nullsec,
+    Thera,
+    /// One of the drifter hive systems, reached through unidentified wormholes.
+    Drifter(DrifterHive),
+    /// The exit side of a wormhole (K162), which can lead anywhere.
     Unknown,
 }
 
+/// The wormhole systems of the drifters. Each hive is only connected to the rest of
+/// the universe through unidentified wormholes of its own type, which appear in
+/// k-space and wormhole space and have to be found by scouts.
+///
+/// # Example
+/// ```
+/// use neweden::{DrifterHive, WormholeDestination, WormholeTypeInfo};
+///
+/// let wh = WormholeTypeInfo::from_code("C414").unwrap();
+/// assert_eq!(WormholeDestination::Drifter(DrifterHive::Conflux), wh.leads_to);
+/// assert_eq!(wh, DrifterHive::Conflux.wormhole_type());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrifterHive {
+    Sentinel,
+    Barbican,
+    Vidette,
+    Conflux,
+    Redoubt,
+}
+
+impl DrifterHive {
+    pub const ALL: [DrifterHive; 5] = [
+        Self::Sentinel,
+        Self::Barbican,
+        Self::Vidette,
+        Self::Conflux,
+        Self::Redoubt,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sentinel => "Sentinel",
+            Self::Barbican => "Barbican",
+            Self::Vidette => "Vidette",
+            Self::Conflux => "Conflux",
+            Self::Redoubt => "Redoubt",
+        }
+    }
+
+    /// Code of the unidentified wormhole leading into the hive.
+    pub fn wormhole_code(&self) -> &'static str {
+        match self {
+            Self::Sentinel => "S877",
+            Self::Barbican => "B735",
+            Self::Vidette => "V928",
+            Self::Conflux => "C414",
+            Self::Redoubt => "R259",
+        }
+    }
+
+    pub fn wormhole_type(&self) -> &'static WormholeTypeInfo {
+        WormholeTypeInfo::from_code(self.wormhole_code()).expect("drifter wormhole types are known")
+    }
+}
+
+/// The class of a wormhole system as defined by `mapLocationWormholeClasses` in the
+/// SDE, see `Universe::wormhole_class()`.
+///
+/// # Example
+/// ```
+/// use neweden::{DrifterHive, WormholeClass};
+///
+/// assert_eq!(Some(WormholeClass::Class(5)), WormholeClass::from_id(5));
+/// assert_eq!(Some(WormholeClass::Drifter(DrifterHive::Vidette)), WormholeClass::from_id(16));
+/// assert_eq!(None, WormholeClass::from_id(7)); // highsec
+/// assert_eq!("C13", WormholeClass::Shattered.to_string());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WormholeClass {
+    /// Class 1 to 6.
+    Class(u8),
+    /// The small shattered systems of class 13, see `Shattered::Frigate`.
+    Shattered,
+    Thera,
+    Drifter(DrifterHive),
+}
+
+impl WormholeClass {
+    /// The class by its `wormholeClassID`. Classes of known space, abyssal space and
+    /// Pochven are not wormhole classes.
+    pub fn from_id(id: u32) -> Option<Self> {
+        Some(match id {
+            1..=6 => Self::Class(id as u8),
+            12 => Self::Thera,
+            13 => Self::Shattered,
+            14 => Self::Drifter(DrifterHive::Sentinel),
+            15 => Self::Drifter(DrifterHive::Barbican),
+            16 => Self::Drifter(DrifterHive::Vidette),
+            17 => Self::Drifter(DrifterHive::Conflux),
+            18 => Self::Drifter(DrifterHive::Redoubt),
+            _ => return None,
+        })
+    }
+
+    /// The `wormholeClassID` of the class.
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::Class(class) => u32::from(*class),
+            Self::Thera => 12,
+            Self::Shattered => 13,
+            Self::Drifter(DrifterHive::Sentinel) => 14,
+            Self::Drifter(DrifterHive::Barbican) => 15,
+            Self::Drifter(DrifterHive::Vidette) => 16,
+            Self::Drifter(DrifterHive::Conflux) => 17,
+            Self::Drifter(DrifterHive::Redoubt) => 18,
+        }
+    }
+}
+
+impl std::fmt::Display for WormholeClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Class(class) => write!(f, "C{class}"),
+            Self::Shattered => write!(f, "C13"),
+            Self::Thera => write!(f, "Thera"),
+            Self::Drifter(hive) => write!(f, "{}", hive.name()),
+        }
+    }
+}
+
+/// Static information about a wormhole type as shown when scanning a wormhole,
+/// e.g. `B274`. Masses are in kilograms.
+///
+/// # Example
+/// ```
+/// use neweden::{WormholeDestination, WormholeMaxShipSize, WormholeTypeInfo};
+///
+/// let b274 = WormholeTypeInfo::from_code("B274").unwrap();
+/// assert_eq!(WormholeDestination::Highsec, b274.leads_to);
+/// assert_eq!(WormholeMaxShipSize::Large, b274.max_ship_size());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WormholeTypeInfo {
+    pub code: &'static str,
+    pub leads_to: WormholeDestination,
+    pub lifetime_hours: u16,
+    /// Total mass that can pass before the wormhole collapses, `None` for K162.
+    pub total_mass: Option<u64>,
+    /// Maximum mass of a single ship, `None` for K162.
+    pub max_jump_mass: Option<u64>,
+}
+
+const fn wormhole_type(
+    code: &'static str,
+    leads_to: WormholeDestination,
+    lifetime_hours: u16,
+    total_mass: u64,
+    max_jump_mass: u64,
+) -> WormholeTypeInfo {
+    WormholeTypeInfo {
+        code,
+        leads_to,
+        lifetime_hours,
+        total_mass: Some(total_mass),
+        max_jump_mass: Some(max_jump_mass),
+    }
+}
+
+/// Commonly seen wormhole types. Exits (K162) take their properties from the
+/// wormhole type on the other side.
+#[rustfmt::skip]
+const WORMHOLE_TYPES: &[WormholeTypeInfo] = &[
+    WormholeTypeInfo {
+        code: "K162",
+        leads_to: WormholeDestination::Unknown,
+        lifetime_hours: 24,
+        total_mass: None,
+        max_jump_mass: None,
+    },
+    wormhole_type("A239", WormholeDestination::Lowsec, 24, 2_000_000_000, 375_000_000),
+    wormhole_type("B274", WormholeDestination::Highsec, 24, 2_000_000_000, 375_000_000),
+    wormhole_type("B449", WormholeDestination::Highsec, 16, 2_000_000_000, 1_000_000_000),
+    wormhole_type("C247", WormholeDestination::Class(3), 16, 2_000_000_000, 375_000_000),
+    wormhole_type("D845", WormholeDestination::Highsec, 24, 5_000_000_000, 375_000_000),
+    wormhole_type("E545", WormholeDestination::Nullsec, 24, 2_000_000_000, 375_000_000),
+    wormhole_type("H121", WormholeDestination::Class(1), 16, 500_000_000, 62_000_000),
+    wormhole_type("H296", WormholeDestination::Class(5), 24, 3_300_000_000, 1_350_000_000),
+    wormhole_type("K346", WormholeDestination::Nullsec, 24, 3_000_000_000, 375_000_000),
+    wormhole_type("N110", WormholeDestination::Highsec, 24, 1_000_000_000, 62_000_000),
+    wormhole_type("N944", WormholeDestination::Lowsec, 24, 3_000_000_000, 1_350_000_000),
+    wormhole_type("O477", WormholeDestination::Class(3), 16, 2_000_000_000, 375_000_000),
+    wormhole_type("Q063", WormholeDestination::Highsec, 16, 500_000_000, 5_000_000),
+    wormhole_type("U210", WormholeDestination::Lowsec, 24, 3_000_000_000, 375_000_000),
+    wormhole_type("V753", WormholeDestination::Class(6), 24, 3_300_000_000, 1_350_000_000),
+    wormhole_type("X877", WormholeDestination::Class(4), 16, 2_000_000_000, 375_000_000),
+    wormhole_type("Y683", WormholeDestination::Class(4), 16, 2_000_000_000, 375_000_000),
+    wormhole_type("Z060", WormholeDestination::Nullsec, 24, 1_000_000_000, 62_000_000),
+    wormhole_type("Z142", WormholeDestination::Nullsec, 24, 3_000_000_000, 1_350_000_000),
+    wormhole_type("Z647", WormholeDestination::Class(1), 16, 500_000_000, 62_000_000),
+    wormhole_type("F135", WormholeDestination::Thera, 16, 750_000_000, 300_000_000),
+    wormhole_type("S877", WormholeDestination::Drifter(DrifterHive::Sentinel), 16, 750_000_000, 375_000_000),
+    wormhole_type("B735", WormholeDestination::Drifter(DrifterHive::Barbican), 16, 750_000_000, 375_000_000),
+    wormhole_type("V928", WormholeDestination::Drifter(DrifterHive::Vidette), 16, 750_000_000, 375_000_000),
+    wormhole_type("C414", WormholeDestination::Drifter(DrifterHive::Conflux), 16, 750_000_000, 375_000_000),
+    wormhole_type("R259", WormholeDestination::Drifter(DrifterHive::Redoubt), 16, 750_000_000, 375_000_000),
+];
+
+impl WormholeTypeInfo {
+    /// Looks up a wormhole type by its code, case insensitive.
+    pub fn from_code(code: &str) -> Option<&'static WormholeTypeInfo> {
+        WORMHOLE_TYPES
+            .iter()
+            .find(|wh| wh.code.eq_ignore_ascii_case(code.trim()))
+    }
+
+    /// Returns true for the exit side of a wormhole.
+    pub fn is_k162(&self) -> bool {
+        self.code == "K162"
+    }
+
+    /// Returns the largest ship size that fits through the wormhole, derived from the
+    /// maximum jump mass.
+    pub fn max_ship_size(&self) -> WormholeMaxShipSize {
+        match self.max_jump_mass {
+            None => WormholeMaxShipSize::Unknown,
+            Some(mass) if mass <= 5_000_000 => WormholeMaxShipSize::Small,
+            Some(mass) if mass <= 62_000_000 => WormholeMaxShipSize::Medium,
+            Some(mass) if mass <= 375_000_000 => WormholeMaxShipSize::Large,
+            Some(mass) if mass <= 1_000_000_000 => WormholeMaxShipSize::XLarge,
+            Some(_) => WormholeMaxShipSize::Capital,
+        }
+    }
+
+    /// Creates a freshly spawned wormhole of this type.
+    pub fn wormhole(&self, signature: &str, spawned: DateTime<Utc>) -> Wormhole {
+        Wormhole {
+            expires: spawned + chrono::Duration::hours(i64::from(self.lifetime_hours)),
+            remaining_hours: self.lifetime_hours,
+            signature: signature.to_string(),
+            exit_signature: None,
+            max_ship_size: self.max_ship_size(),
+            shattered: None,
+        }
+    }
+
+    /// Returns the type on the other side of a wormhole. The other side of any
+    /// wormhole is a K162, the other side of a K162 is the given origin type.
+    pub fn pair(
+        &self,
+        origin: Option<&'static WormholeTypeInfo>,
+    ) -> Option<&'static WormholeTypeInfo> {
+        if self.is_k162() {
+            origin
+        } else {
+            Self::from_code("K162")
+        }
+    }
+}
+
 /// Defines a system class. A system is either part of
 /// the known space (SystemClass::KSpace) or wormhole space
-/// (SystemClass::WSpace).
+/// (SystemClass::WSpace). The SDE also contains abyssal
+/// deadspace pockets (SystemClass::Abyssal) and other systems
+/// that cannot be reached by players (SystemClass::Void).
 ///
 /// A System reference can be casted into this.
 /// # Example
@@ -263,6 +963,8 @@ pub enum WormholeType {
 ///         z: 1.17469e+17_f64,
 ///     },
 ///     security: 0.9459.into(),
+///     region_name: "The Forge".to_string(),
+///     faction_id: Some(500001),
 /// };
 /// assert_eq!(SystemClass::from(&jita), SystemClass::KSpace);
 /// ```
@@ -270,30 +972,62 @@ pub enum WormholeType {
 pub enum SystemClass {
     KSpace,
     WSpace,
+    Abyssal,
+    Void,
 }
 
-impl From<System> for SystemClass {
-    fn from(s: System) -> Self {
-        match s.id {
+impl SystemClass {
+    /// Returns false for abyssal and void systems, which have no stargates and
+    /// cannot be reached by jump drives or wormholes.
+    pub fn is_navigable(&self) -> bool {
+        matches!(self, Self::KSpace | Self::WSpace)
+    }
+}
+
+impl From<&SystemId> for SystemClass {
+    fn from(id: &SystemId) -> Self {
+        match id {
             SystemId(0..=30999999) => Self::KSpace,
             SystemId(31000000..=31999999) => Self::WSpace,
-            _ => panic!("unknown space."),
+            SystemId(32000000..=32999999) => Self::Abyssal,
+            _ => Self::Void,
         }
     }
 }
 
+impl From<System> for SystemClass {
+    fn from(s: System) -> Self {
+        Self::from(&s.id)
+    }
+}
+
 impl From<&System> for SystemClass {
     fn from(s: &System) -> Self {
-        match s.id {
-            SystemId(0..=30999999) => Self::KSpace,
-            SystemId(31000000..=31999999) => Self::WSpace,
-            _ => panic!("unknown space."),
-        }
+        Self::from(&s.id)
     }
 }
 
+/// What an id from ESI refers to, as returned by `Universe::resolve_any()`.
+///
+/// ESI mixes ids of different categories, e.g. the location of a killmail or the
+/// results of `/universe/ids/`. The category follows from the range of the id.
+#[derive(Debug, Clone, Copy)]
+pub enum Entity<'a> {
+    Region(u32),
+    Constellation(u32),
+    System(&'a System),
+    /// A NPC station or outpost.
+    Station(u32),
+    /// A player owned Upwell structure.
+    Structure(u64),
+    /// Ids of other categories, such as characters or items, and systems that are
+    /// not part of the universe.
+    Unknown(u64),
+}
+
 /// Describes the coordinate of a system in Eve Online.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Coordinate {
     pub x: f64,
     pub y: f64,
@@ -308,6 +1042,7 @@ impl From<(f64, f64, f64)> for Coordinate {
 
 /// Describe a system.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct System {
     // The ID of a system. Coorespondes to the field mapSolarSystems.solarSystemID in the SDE.
     pub id: SystemId,
@@ -319,20 +1054,70 @@ pub struct System {
     pub security: Security,
     // The name of the region the system is in.
     pub region_name: String,
+    // The faction owning the system. Coorespondes to the field mapSolarSystems.factionID in the SDE.
+    // Only set for empire space and NPC nullsec, player-claimable space has no faction.
+    pub faction_id: Option<u32>,
 }
 
 impl System {
-    fn to_point(&self) -> [f64; 3] {
-        [self.coordinate.x, self.coordinate.y, self.coordinate.z]
+    /// Creates a system from its fields, prefer this over a struct literal as `System`
+    /// may gain fields.
+    pub fn new(
+        id: SystemId,
+        name: String,
+        coordinate: Coordinate,
+        security: Security,
+        region_name: String,
+        faction_id: Option<u32>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            coordinate,
+            security,
+            region_name,
+            faction_id,
+        }
     }
 
-    fn point_distance(&self, point: &[f64; 3]) -> Meters {
-        let d_x = self.coordinate.x - point[0];
-        let d_y = self.coordinate.y - point[1];
-        let d_z = self.coordinate.z - point[2];
-        let distance = (d_x * d_x + d_y * d_y + d_z * d_z).sqrt();
-        // We must return the squared distance!
-        Meters(distance)
+    /// Returns whether the system is NPC or sovereign nullsec. Returns `None` for
+    /// highsec, lowsec and wormhole space.
+    pub fn nullsec_class(&self) -> Option<NullsecClass> {
+        match (SystemClass::from(self), SecurityClass::from(self.security)) {
+            (SystemClass::KSpace, SecurityClass::Nullsec) => match self.faction_id {
+                Some(_) => Some(NullsecClass::Npc),
+                None => Some(NullsecClass::Sovereign),
+            },
+            _ => None,
+        }
+    }
+
+    /// The name of the system in the language, falling back to the English name if
+    /// there is no translation. See `locale`.
+    pub fn localized_name<'a>(
+        &'a self,
+        names: &'a crate::locale::LocalizedNames,
+        language: &str,
+    ) -> &'a str {
+        names.get(&self.id, language).unwrap_or(&self.name)
+    }
+
+    /// Returns false for abyssal and void systems. `Universe` avoids them by default.
+    pub fn is_navigable(&self) -> bool {
+        SystemClass::from(self).is_navigable()
+    }
+
+    fn to_point(&self) -> [f64; 3] {
+        [self.coordinate.x, self.coordinate.y, self.coordinate.z]
+    }
+
+    fn point_distance(&self, point: &[f64; 3]) -> Meters {
+        let d_x = self.coordinate.x - point[0];
+        let d_y = self.coordinate.y - point[1];
+        let d_z = self.coordinate.z - point[2];
+        let distance = (d_x * d_x + d_y * d_y + d_z * d_z).sqrt();
+        // We must return the squared distance!
+        Meters(distance)
     }
 
     pub fn distance(&self, other: &System) -> Meters {
@@ -430,15 +1215,56 @@ impl FromIterator<Connection> for AdjacentMap {
     }
 }
 
+impl AdjacentMap {
+    /// Returns the ids of all connected systems as a flat, sorted list of from/to pairs.
+    fn pairs(&self) -> Vec<u32> {
+        let mut pairs = self
+            .0
+            .values()
+            .flatten()
+            .map(|conn| (conn.from.0, conn.to.0))
+            .collect_vec();
+        pairs.sort_unstable();
+        pairs
+            .into_iter()
+            .flat_map(|(from, to)| [from, to])
+            .collect()
+    }
+}
+
+pub(crate) const FINGERPRINT_SEED: u64 = 0xcbf29ce484222325;
+
+/// FNV-1a hash over the given values. Unlike std's `DefaultHasher` it is stable
+/// across Rust releases, so it can be persisted as a universe version.
+pub(crate) fn fingerprint(seed: u64, values: impl IntoIterator<Item = u32>) -> u64 {
+    let mut hash = seed;
+    for byte in values.into_iter().flat_map(u32::to_le_bytes) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
 // TODO: Implement conversions between those
 
 #[derive(Debug, PartialOrd, PartialEq, Copy, Clone)]
 pub struct Lightyears(pub f64);
 
+/// Meters in a lightyear. Coordinates in the SDE are in meters, see `coordinates`.
+pub const METERS_PER_LIGHTYEAR: f64 = 9_460_730_472_580_800.0;
+
+/// Meters in an astronomical unit, the unit of warp distances.
+pub const METERS_PER_AU: f64 = 149_597_871_000.0;
+
 impl From<Lightyears> for Meters {
     fn from(other: Lightyears) -> Self {
-        const LY_IN_KM: f64 = 9_460_730_472_580.8;
-        Meters(other.0 * LY_IN_KM * 1_000.0)
+        Meters(other.0 * METERS_PER_LIGHTYEAR)
+    }
+}
+
+impl From<Meters> for Lightyears {
+    fn from(other: Meters) -> Self {
+        Lightyears(other.0 / METERS_PER_LIGHTYEAR)
     }
 }
 
@@ -495,8 +1321,7 @@ pub struct Au(pub f64);
 
 impl From<Au> for Meters {
     fn from(other: Au) -> Self {
-        const AU_TO_KM: f64 = 149_597_871.0;
-        Meters(other.0 * AU_TO_KM * 1_000.0)
+        Meters(other.0 * METERS_PER_AU)
     }
 }
 
@@ -526,6 +1351,43 @@ pub trait Navigatable {
     fn get_system(&self, id: &SystemId) -> Option<&System>;
     fn get_connections(&self, from: &SystemId) -> Option<Vec<Connection>>;
     fn get_systems_by_range(&self, from: &SystemId, range: Meters) -> Option<Vec<&System>>;
+
+    /// Returns all systems, in no particular order. Features iterating all systems,
+    /// e.g. `diff`, `analysis` or `placement`, rely on it.
+    fn all_systems(&self) -> Vec<&System>;
+
+    /// Returns the systems directly reachable from a system, each only once.
+    fn neighbors(&self, from: &SystemId) -> Vec<SystemId> {
+        self.get_connections(from)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|conn| conn.to)
+            .unique()
+            .collect()
+    }
+
+    /// Returns a connection leading from one system to another, if there is any.
+    fn connection_between(&self, from: &SystemId, to: &SystemId) -> Option<Connection> {
+        self.get_connections(from)?
+            .into_iter()
+            .find(|conn| conn.to == *to)
+    }
+
+    /// Returns user annotations for the connection between two systems.
+    fn get_annotations(&self, _from: &SystemId, _to: &SystemId) -> &[Annotation] {
+        &[]
+    }
+
+    /// Returns a fingerprint of the systems and connections. The version changes
+    /// whenever a system or connection is added or removed.
+    fn version(&self) -> u64 {
+        0
+    }
+
+    /// Returns true if pathfinding should avoid the system unless told otherwise.
+    fn avoided_by_default(&self, _id: &SystemId) -> bool {
+        false
+    }
 }
 
 pub trait Galaxy {
@@ -533,7 +1395,7 @@ pub trait Galaxy {
     fn systems(&self) -> Vec<&System>;
 
     #[cfg(feature = "search")]
-    fn search<'a>(&'a self, query: &str) -> anyhow::Result<Vec<&'a System>>;
+    fn search<'a>(&'a self, query: &str) -> anyhow::Result<Vec<crate::search::SearchHit<'a>>>;
     #[cfg(feature = "search")]
     fn search_one<'a>(&'a self, query: &str) -> Option<&'a System>;
 }
@@ -548,20 +1410,34 @@ pub trait Galaxy {
 ///
 /// # Example
 /// ```
-/// use std::env;
-/// use neweden::source::sqlite::DatabaseBuilder;
-/// use neweden::Navigatable;
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::{Navigatable, System};
 ///
-/// let uri = std::env::var("SQLITE_URI").unwrap();
-/// let universe = DatabaseBuilder::new(&uri).build().unwrap();
+/// // usually loaded from a data source, e.g. `source::sqlite::DatabaseBuilder`
+/// let universe = UniverseBuilder::default()
+///     .system(System {
+///         id: 30000142.into(),
+///         name: "Jita".to_string(),
+///         coordinate: (0.0, 0.0, 0.0).into(),
+///         security: 0.9.into(),
+///         region_name: "The Forge".to_string(),
+///         faction_id: None,
+///     })
+///     .build();
 /// let system_id = 30000142.into(); // returns a SystemId
 ///
-/// println!("{:?}", universe.get_system(&system_id).unwrap().name); // Jita
+/// assert_eq!("Jita", universe.get_system(&system_id).unwrap().name);
 /// ```
 pub struct Universe {
     pub(crate) systems: SystemMap,
     pub(crate) connections: AdjacentMap,
     pub(crate) rtree: rstar::RTree<System>,
+    pub(crate) version: u64,
+    pub(crate) hierarchy: crate::hierarchy::Hierarchy,
+    pub(crate) stations: crate::stations::Stations,
+    pub(crate) stargates: crate::stargates::Stargates,
+    pub(crate) celestials: crate::celestials::Celestials,
+    pub(crate) wormhole_classes: crate::overlay::Overlay<SystemId, WormholeClass>,
     #[cfg(feature = "search")]
     pub(crate) index: crate::search::SearchIndex,
 }
@@ -576,15 +1452,156 @@ impl Universe {
         #[cfg(feature = "search")]
         let index = crate::search::SearchIndex::new(systems.systems()).unwrap();
 
+        let mut ids = systems.systems.keys().map(|id| id.0).collect_vec();
+        ids.sort_unstable();
+        let version = fingerprint(fingerprint(FINGERPRINT_SEED, ids), connections.pairs());
+
         Self {
             systems,
             connections,
             rtree: rstar::RTree::bulk_load(spatial_data),
+            version,
+            hierarchy: Default::default(),
+            stations: Default::default(),
+            stargates: Default::default(),
+            celestials: Default::default(),
+            wormhole_classes: Default::default(),
             #[cfg(feature = "search")]
             index,
         }
     }
 
+    /// Attaches the regions and constellations of the systems. Data sources that know
+    /// them attach them while loading, e.g. `DatabaseBuilder` from the SDE.
+    pub fn with_hierarchy(mut self, hierarchy: crate::hierarchy::Hierarchy) -> Self {
+        self.hierarchy = hierarchy;
+        self
+    }
+
+    /// The regions and constellations of the systems, empty if the data source
+    /// doesn't know them.
+    pub fn hierarchy(&self) -> &crate::hierarchy::Hierarchy {
+        &self.hierarchy
+    }
+
+    /// Attaches the NPC stations of the systems, see `stations::Stations`.
+    pub fn with_stations(mut self, stations: crate::stations::Stations) -> Self {
+        self.stations = stations;
+        self
+    }
+
+    /// All NPC stations, empty if the data source doesn't know them.
+    pub fn stations(&self) -> &crate::stations::Stations {
+        &self.stations
+    }
+
+    /// The NPC stations of a system, e.g. to find docking options along a route.
+    pub fn stations_in_system(&self, id: &SystemId) -> Vec<&crate::stations::Station> {
+        self.stations.in_system(id).collect()
+    }
+
+    /// Attaches the individual stargates of the systems, see `stargates::Stargates`.
+    pub fn with_stargates(mut self, stargates: crate::stargates::Stargates) -> Self {
+        self.stargates = stargates;
+        self
+    }
+
+    /// All stargates with their positions, empty if the data source doesn't know them.
+    pub fn stargates(&self) -> &crate::stargates::Stargates {
+        &self.stargates
+    }
+
+    pub fn stargates_in_system(&self, id: &SystemId) -> Vec<&crate::stargates::Stargate> {
+        self.stargates.in_system(id).collect()
+    }
+
+    /// Attaches the planets, moons and belts of the systems, see
+    /// `celestials::Celestials`.
+    pub fn with_celestials(mut self, celestials: crate::celestials::Celestials) -> Self {
+        self.celestials = celestials;
+        self
+    }
+
+    /// All celestials, empty if the data source doesn't know them.
+    pub fn all_celestials(&self) -> &crate::celestials::Celestials {
+        &self.celestials
+    }
+
+    /// The planets, moons and belts of a system.
+    pub fn celestials(&self, id: &SystemId) -> Vec<&crate::celestials::Celestial> {
+        self.celestials.in_system(id).collect()
+    }
+
+    /// Attaches the classes of the wormhole systems.
+    pub fn with_wormhole_classes(
+        mut self,
+        classes: crate::overlay::Overlay<SystemId, WormholeClass>,
+    ) -> Self {
+        self.wormhole_classes = classes;
+        self
+    }
+
+    /// The class of a wormhole system, `None` for known space or if the data source
+    /// doesn't know the classes.
+    pub fn wormhole_class(&self, id: &SystemId) -> Option<WormholeClass> {
+        self.wormhole_classes.get(id).copied()
+    }
+
+    pub fn get_region(&self, id: &RegionId) -> Option<Region> {
+        self.hierarchy.region(id)
+    }
+
+    pub fn get_constellation(&self, id: &ConstellationId) -> Option<Constellation> {
+        self.hierarchy.constellation(id)
+    }
+
+    /// The region of a system.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::builder::UniverseBuilder;
+    /// use neweden::hierarchy::Hierarchy;
+    /// use neweden::RegionId;
+    ///
+    /// let mut hierarchy = Hierarchy::default();
+    /// hierarchy.insert_region(10000002.into(), "The Forge");
+    /// hierarchy.insert_constellation(20000020.into(), 10000002.into(), "Kimotoro");
+    /// hierarchy.insert_system(30000142.into(), 20000020.into());
+    ///
+    /// let universe = UniverseBuilder::default().build().with_hierarchy(hierarchy);
+    /// let region = universe.region_of(&30000142.into()).unwrap();
+    /// assert_eq!((RegionId(10000002), "The Forge"), (region.id, region.name.as_str()));
+    /// ```
+    pub fn region_of(&self, id: &SystemId) -> Option<Region> {
+        self.hierarchy.region(&self.hierarchy.region_of(id)?)
+    }
+
+    pub fn constellation_of(&self, id: &SystemId) -> Option<Constellation> {
+        self.hierarchy
+            .constellation(&self.hierarchy.constellation_of(id)?)
+    }
+
+    /// The systems of a region that are part of the universe, sorted by id.
+    pub fn systems_in_region(&self, id: &RegionId) -> Vec<&System> {
+        self.hierarchy
+            .systems_in_region(id)
+            .iter()
+            .filter_map(|system| self.systems.get(*system))
+            .collect()
+    }
+
+    /// All regions, sorted by id.
+    pub fn regions(&self) -> Vec<Region> {
+        self.hierarchy
+            .regions()
+            .into_iter()
+            .map(|(id, name)| Region {
+                id,
+                name: name.to_string(),
+            })
+            .collect()
+    }
+
     /// Extend the universe with new connections. This is useful to add additional
     /// connections, for example wormholes and find paths. The extended universe will
     /// reuse the systems from the existing universe and only take space for new connections.
@@ -592,24 +1609,325 @@ impl Universe {
         ExtendedUniverse::new(self, connections)
     }
 
+    /// Creates a new universe containing only the systems matching the predicate and
+    /// the connections between them, e.g. a highsec-only or k-space-only universe.
+    /// The hierarchy, stations, stargates, celestials and wormhole classes are cut
+    /// down to the remaining systems.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::builder::UniverseBuilder;
+    /// use neweden::{Galaxy, SecurityClass};
+    ///
+    /// let universe = UniverseBuilder::default().build();
+    /// let highsec = universe.filter(|s| SecurityClass::from(s.security) == SecurityClass::Highsec);
+    /// assert!(highsec.systems().is_empty());
+    /// ```
+    pub fn filter(&self, predicate: impl Fn(&System) -> bool) -> Universe {
+        let systems = self
+            .systems
+            .systems
+            .values()
+            .filter(|system| predicate(system))
+            .cloned()
+            .collect::<SystemMap>();
+        let connections = self
+            .connections
+            .0
+            .values()
+            .flatten()
+            .filter(|conn| systems.get(conn.from).is_some() && systems.get(conn.to).is_some())
+            .cloned()
+            .collect::<AdjacentMap>();
+        let stations = self
+            .stations
+            .iter()
+            .filter(|station| systems.get(station.system).is_some())
+            .cloned()
+            .collect();
+        let stargates = self
+            .stargates
+            .iter()
+            .filter(|stargate| {
+                systems.get(stargate.system).is_some()
+                    && systems.get(stargate.destination).is_some()
+            })
+            .cloned()
+            .collect();
+        let celestials = self
+            .celestials
+            .iter()
+            .filter(|celestial| systems.get(celestial.system).is_some())
+            .cloned()
+            .collect();
+        let mut wormhole_classes = crate::overlay::Overlay::default();
+        for (id, class) in self.wormhole_classes.iter() {
+            if systems.get(*id).is_some() {
+                wormhole_classes.insert(*id, *class);
+            }
+        }
+        let hierarchy = self.hierarchy.subset(|id| systems.get(*id).is_some());
+        Universe::new(systems, connections)
+            .with_hierarchy(hierarchy)
+            .with_stations(crate::stations::Stations::new(stations))
+            .with_stargates(crate::stargates::Stargates::new(stargates))
+            .with_celestials(crate::celestials::Celestials::new(celestials))
+            .with_wormhole_classes(wormhole_classes)
+    }
+
+    /// Creates a new universe containing only the systems of the given regions, as
+    /// known to the hierarchy of this universe, e.g. to test region-scoped tools on a
+    /// small universe. See `filter()` for what else is kept.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::builder::UniverseBuilder;
+    /// use neweden::{Galaxy, RegionId};
+    ///
+    /// let universe = UniverseBuilder::default().build();
+    /// let delve = universe.subset_regions(&[RegionId(10000060), RegionId(10000050)]);
+    /// assert!(delve.systems().is_empty());
+    /// ```
+    pub fn subset_regions(&self, regions: &[RegionId]) -> Universe {
+        self.filter(|system| {
+            self.hierarchy
+                .region_of(&system.id)
+                .is_some_and(|region| regions.contains(&region))
+        })
+    }
+
+    /// Creates a new universe containing only the systems of the given
+    /// constellations, as known to the hierarchy of this universe. See `filter()`
+    /// for what else is kept.
+    pub fn subset_constellations(&self, constellations: &[ConstellationId]) -> Universe {
+        self.filter(|system| {
+            self.hierarchy
+                .constellation_of(&system.id)
+                .is_some_and(|constellation| constellations.contains(&constellation))
+        })
+    }
+
+    /// Combines the systems and connections of both universes, e.g. k-space from the
+    /// SDE with wormhole space loaded from another source. `conflict` decides what
+    /// happens to systems, connections and side tables such as stations present in
+    /// both, see `merge::Conflict`.
+    pub fn merge(
+        &self,
+        other: &Universe,
+        conflict: crate::merge::Conflict,
+    ) -> Result<Universe, crate::merge::MergeError> {
+        crate::merge::merge(self, other, conflict)
+    }
+
+    /// Summarizes the systems and stargates of a region, see `analysis::RegionReport`.
+    /// Returns `None` if there are no systems in the region.
+    pub fn region_report(&self, region: &str) -> Option<crate::analysis::RegionReport> {
+        crate::analysis::RegionReport::with_stations(self, region, &self.stations)
+    }
+
+    /// Compares the universe to a newer one, e.g. loaded from the SDE of the next
+    /// expansion, see `diff::UniverseDiff`.
+    pub fn diff(&self, other: &Universe) -> crate::diff::UniverseDiff {
+        crate::diff::UniverseDiff::new(self, other)
+    }
+
+    /// Returns a new universe with the changes of `delta` applied, e.g. to patch in a
+    /// renamed system without loading the whole universe again.
+    pub fn apply(&self, delta: crate::diff::UniverseDelta) -> Universe {
+        delta.apply(self)
+    }
+
+    /// Computes diameter, path lengths, degrees and clustering of the whole universe
+    /// and of each region, see `analysis::UniverseMetrics`.
+    pub fn graph_metrics(&self) -> crate::analysis::UniverseMetrics {
+        crate::analysis::UniverseMetrics::new(self)
+    }
+
+    /// Looks up many systems by id at once, e.g. for the systems of a batch of
+    /// killmails. The result has one entry per id, in the same order.
+    pub fn resolve_ids(&self, ids: &[u32]) -> Vec<Option<&System>> {
+        ids.iter().map(|id| self.systems.get(*id)).collect()
+    }
+
+    /// Finds out what an id from ESI refers to. Systems are returned only if they
+    /// are part of the universe, other categories are told apart by their id range.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::builder::UniverseBuilder;
+    /// use neweden::{Entity, System};
+    ///
+    /// let universe = UniverseBuilder::default()
+    ///     .system(System {
+    ///         id: 30000142.into(),
+    ///         name: "Jita".to_string(),
+    ///         coordinate: (0.0, 0.0, 0.0).into(),
+    ///         security: 0.9459.into(),
+    ///         region_name: "The Forge".to_string(),
+    ///         faction_id: None,
+    ///     })
+    ///     .build();
+    /// assert!(matches!(universe.resolve_any(30000142), Entity::System(jita) if jita.name == "Jita"));
+    /// assert!(matches!(universe.resolve_any(60003760), Entity::Station(60003760)));
+    /// assert!(matches!(universe.resolve_any(1035466617946), Entity::Structure(_)));
+    /// ```
+    pub fn resolve_any(&self, id: u64) -> Entity<'_> {
+        match id {
+            10000000..=14999999 => Entity::Region(id as u32),
+            20000000..=24999999 => Entity::Constellation(id as u32),
+            30000000..=34999999 => match self.systems.get(id as u32) {
+                Some(system) => Entity::System(system),
+                None => Entity::Unknown(id),
+            },
+            60000000..=64999999 => Entity::Station(id as u32),
+            1_000_000_000_000.. => Entity::Structure(id),
+            _ => Entity::Unknown(id),
+        }
+    }
+
+    /// Looks up many systems by name at once. Names are compared with
+    /// `names::matches` and the result has one entry per name, in the same order.
+    ///
+    /// # Example
+    /// ```
+    /// use neweden::builder::UniverseBuilder;
+    /// use neweden::System;
+    ///
+    /// let universe = UniverseBuilder::default()
+    ///     .system(System {
+    ///         id: 30000142.into(),
+    ///         name: "Jita".to_string(),
+    ///         coordinate: (0.0, 0.0, 0.0).into(),
+    ///         security: 0.9459.into(),
+    ///         region_name: "The Forge".to_string(),
+    ///         faction_id: None,
+    ///     })
+    ///     .build();
+    /// let found = universe.resolve_names(&["jita", "Amarr"]);
+    /// assert_eq!(Some(30000142), found[0].map(|system| system.id.0));
+    /// assert!(found[1].is_none());
+    /// ```
+    pub fn resolve_names(&self, names: &[&str]) -> Vec<Option<&System>> {
+        let wanted = names
+            .iter()
+            .map(|name| crate::names::normalize(name))
+            .collect::<Vec<_>>();
+        let lookup = wanted.iter().map(String::as_str).collect::<HashSet<_>>();
+        // only normalize the names of all systems once per batch
+        let mut found = HashMap::new();
+        for system in self.systems.systems.values() {
+            let name = crate::names::normalize(&system.name);
+            if lookup.contains(name.as_str()) {
+                found.entry(name).or_insert(system);
+            }
+        }
+        wanted.iter().map(|name| found.get(name).copied()).collect()
+    }
+
+    /// Rebuilds the search index to also find systems by their translated names.
+    #[cfg(feature = "search")]
+    pub fn index_localized_names(
+        &mut self,
+        names: &crate::locale::LocalizedNames,
+    ) -> anyhow::Result<()> {
+        self.index = self.index.rebuild(
+            self.systems.systems(),
+            |system| {
+                names
+                    .names(&system.id)
+                    .map(|(_, name)| name.to_string())
+                    .collect()
+            },
+            crate::search::default_threads(),
+        )?;
+        Ok(())
+    }
+
+    /// Rebuilds the search index using the given number of indexing threads, at most
+    /// 8. Universes index their systems with one thread per core when they are
+    /// created. Translated names indexed with `index_localized_names()` are kept.
     #[cfg(feature = "search")]
-    pub fn search<'a>(&'a self, query: &str) -> anyhow::Result<Vec<&'a System>> {
+    pub fn reindex(&mut self, threads: usize) -> anyhow::Result<()> {
+        self.index = self.index.reindex(self.systems.systems(), threads)?;
+        Ok(())
+    }
+
+    /// Returns true if the name of the region or constellation of the system starts
+    /// with `name`. Falls back to the region name of the system without a hierarchy.
+    #[cfg(feature = "search")]
+    fn in_area_named(&self, id: &SystemId, name: &str) -> bool {
+        use crate::search::matches_qualifier;
+        let region = self
+            .systems
+            .get(*id)
+            .map(|system| system.region_name.as_str());
+        region.is_some_and(|region| matches_qualifier(region, name))
+            || self
+                .region_of(id)
+                .is_some_and(|region| matches_qualifier(&region.name, name))
+            || self
+                .constellation_of(id)
+                .is_some_and(|constellation| matches_qualifier(&constellation.name, name))
+    }
+
+    /// Replaces the boosts used to rank search results, see `search::SearchBoosts`.
+    #[cfg(feature = "search")]
+    pub fn set_search_boosts(&mut self, boosts: crate::search::SearchBoosts) {
+        self.index.boosts = boosts;
+    }
+
+    /// Searches systems by name. A region or constellation after a comma narrows
+    /// down the results, e.g. "Hek, Metropolis".
+    #[cfg(feature = "search")]
+    pub fn search<'a>(&'a self, query: &str) -> anyhow::Result<Vec<crate::search::SearchHit<'a>>> {
+        self.search_recent(query, &[])
+    }
+
+    /// Like `search()`, but boosts the systems the caller recently picked, see
+    /// `search::SearchBoosts::recent()`. Keeping track of them is up to the caller,
+    /// e.g. per user of a web application.
+    #[cfg(feature = "search")]
+    pub fn search_recent<'a>(
+        &'a self,
+        query: &str,
+        recent: &[SystemId],
+    ) -> anyhow::Result<Vec<crate::search::SearchHit<'a>>> {
+        let (name, qualifier) = crate::search::split_qualifier(query);
+        let within = qualifier.map(|qualifier| {
+            self.systems
+                .systems
+                .keys()
+                .copied()
+                .filter(|id| self.in_area_named(id, qualifier))
+                .collect::<std::collections::HashSet<_>>()
+        });
         self.index
-            .search(query)?
+            .search(name, recent, within.as_ref())?
             .into_iter()
             .map(|result| {
-                self.systems
+                let system = self
+                    .systems
                     .get(result.id)
-                    .ok_or(anyhow::anyhow!("Missing system {}", result.id))
+                    .ok_or(anyhow::anyhow!("Missing system {}", result.id))?;
+                Ok(crate::search::SearchHit {
+                    system,
+                    region: self
+                        .hierarchy
+                        .region_of(&system.id)
+                        .and_then(|id| self.hierarchy.region_ref(&id)),
+                    score: result.score,
+                })
             })
             .collect::<anyhow::Result<Vec<_>>>()
     }
 
     #[cfg(feature = "search")]
+    /// The best match of the query.
     pub fn search_one<'a>(&'a self, query: &str) -> Option<&'a System> {
         self.search(query)
             .ok()
-            .and_then(|systems| systems.into_iter().next())
+            .and_then(|hits| hits.into_iter().next())
+            .map(|hit| hit.system)
     }
 }
 
@@ -629,7 +1947,7 @@ impl Galaxy for Universe {
     }
 
     #[cfg(feature = "search")]
-    fn search<'a>(&'a self, query: &str) -> anyhow::Result<Vec<&'a System>> {
+    fn search<'a>(&'a self, query: &str) -> anyhow::Result<Vec<crate::search::SearchHit<'a>>> {
         self.search(query)
     }
 
@@ -648,12 +1966,26 @@ impl Navigatable for Universe {
         self.connections.0.get(from).cloned()
     }
 
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Abyssal and void systems are never routed through.
+    fn avoided_by_default(&self, id: &SystemId) -> bool {
+        !SystemClass::from(id).is_navigable()
+    }
+
+    fn all_systems(&self) -> Vec<&System> {
+        self.systems.systems()
+    }
+
     fn get_systems_by_range<'a>(&self, from: &SystemId, range: Meters) -> Option<Vec<&System>> {
         // it is very important that we use KM, since all distances in the database are in KM, because CCP.
         let system = self.get_system(from)?;
         let systems = self
             .rtree
             .locate_within_distance(system.to_point(), range.0 * range.0)
+            .filter(|s| s.is_navigable())
             .filter(|s| match SecurityClass::from(s.security) {
                 SecurityClass::Lowsec | SecurityClass::Nullsec => true,
                 SecurityClass::Highsec => false,
@@ -668,39 +2000,183 @@ impl Navigatable for Universe {
 ///
 /// # Example
 /// ```
-/// use std::env;
-/// use neweden::source::sqlite::DatabaseBuilder;
+/// use neweden::builder::UniverseBuilder;
 /// use neweden::navigation::PathBuilder;
 /// use neweden::Navigatable;
-/// use neweden::{Connection, ConnectionType, WormholeType};
+/// use neweden::{Connection, ConnectionType, System, Wormhole, WormholeMaxShipSize};
 ///
-/// let uri = std::env::var("SQLITE_URI").unwrap();
+/// let system = |id: u32, name: &str| System {
+///     id: id.into(),
+///     name: name.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: 0.5.into(),
+///     region_name: "".to_string(),
+///     faction_id: None,
+/// };
 /// let wormholes = vec![Connection {
 ///     from: 30002718.into(), // Rancer
 ///     to: 30000049.into(),  // Camal
-///     type_: ConnectionType::Wormhole(WormholeType::VeryLarge),
+///     r#type: ConnectionType::Wormhole(Wormhole {
+///         expires: chrono::Utc::now(),
+///         remaining_hours: 16,
+///         signature: "ABC-123".to_string(),
+///         exit_signature: None,
+///         max_ship_size: WormholeMaxShipSize::Large,
+///         shattered: None,
+///     }),
 /// }];
-/// let universe = DatabaseBuilder::new(&uri).build().unwrap();
+/// let universe = UniverseBuilder::default()
+///     .system(system(30002718, "Rancer"))
+///     .system(system(30000049, "Camal"))
+///     .build();
 /// let extended = universe.extend(wormholes.into()); // make into an adjacent map and pass into extend()
 /// let path = PathBuilder::new(&extended)
 ///     .waypoint(extended.get_system(&30002718.into()).unwrap()) // from Rancer
 ///     .waypoint(extended.get_system(&30000049.into()).unwrap()) // to Camal
-///     .build() // returns an iterator
-///     .collect::<Vec<_>>();
-/// assert_eq!(2, path.len()); // direct jump through our wormhole
+///     .build() // returns a path
+///     .unwrap();
+/// assert_eq!(1, path.jumps()); // direct jump through our wormhole
 /// ```
 #[derive(Debug)]
 pub struct ExtendedUniverse<'a, U> {
     pub(crate) universe: &'a U,
     pub(crate) connections: AdjacentMap,
+    pub(crate) annotations: HashMap<(SystemId, SystemId), Vec<Annotation>>,
+    pub(crate) overlays: HashMap<TypeId, Box<dyn Any>>,
+    pub(crate) signatures: HashMap<(SystemId, String), (SystemId, usize)>,
+    pub(crate) avoid: HashSet<SystemId>,
+    pub(crate) structures: Vec<crate::structures::Structure>,
 }
 
 impl<'a, U: Galaxy + Navigatable> ExtendedUniverse<'a, U> {
     pub fn new(universe: &'a U, connections: AdjacentMap) -> Self {
+        let mut signatures = HashMap::new();
+        for (from, adjacent) in &connections.0 {
+            for (i, conn) in adjacent.iter().enumerate() {
+                if let ConnectionType::Wormhole(wh) = &conn.r#type {
+                    signatures.insert((conn.from, normalize_signature(&wh.signature)), (*from, i));
+                    if let Some(exit) = &wh.exit_signature {
+                        signatures.insert((conn.to, normalize_signature(exit)), (*from, i));
+                    }
+                }
+            }
+        }
+
         Self {
             universe,
             connections,
+            annotations: Default::default(),
+            overlays: Default::default(),
+            signatures,
+            avoid: Default::default(),
+            structures: Default::default(),
+        }
+    }
+
+    /// Loads bridges, wormholes, Ansiblex gates and avoided systems from an extensions
+    /// file, see the `config` module for the format.
+    #[cfg(feature = "config")]
+    pub fn from_config(
+        universe: &'a U,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Self, crate::config::ConfigError> {
+        crate::config::ExtensionsConfig::load(path)?.apply(universe)
+    }
+
+    /// Avoid the system in all paths built on this universe.
+    pub fn avoid(&mut self, id: SystemId) {
+        self.avoid.insert(id);
+    }
+
+    /// No longer avoid a system avoided with `avoid()`, e.g. one listed in an
+    /// extensions file. Returns false if the system wasn't avoided. To ignore the
+    /// avoided systems for a single path, see `PathBuilder::default_avoid()`.
+    pub fn unavoid(&mut self, id: &SystemId) -> bool {
+        self.avoid.remove(id)
+    }
+
+    /// Looks up a wormhole connection by the signature ID of either of its ends in
+    /// the given system. Signatures are compared case insensitive.
+    pub fn connection_by_signature(
+        &self,
+        system: &SystemId,
+        signature: &str,
+    ) -> Option<&Connection> {
+        let (from, i) = self
+            .signatures
+            .get(&(*system, normalize_signature(signature)))?;
+        self.connections.0.get(from)?.get(*i)
+    }
+
+    /// Returns all mapped wormhole connections with a signature in the given system.
+    pub fn signatures_in(&self, system: &SystemId) -> Vec<(&str, &Connection)> {
+        let mut result = self
+            .signatures
+            .iter()
+            .filter(|((id, _), _)| id == system)
+            .filter_map(|((_, signature), (from, i))| {
+                Some((signature.as_str(), self.connections.0.get(from)?.get(*i)?))
+            })
+            .collect_vec();
+        result.sort_by_key(|(signature, _)| *signature);
+        result
+    }
+
+    /// Returns the overlay storing values of type `T`, if any values were added.
+    pub fn overlay<T: 'static>(&self) -> Option<&Overlay<SystemId, T>> {
+        self.overlays
+            .get(&TypeId::of::<T>())
+            .and_then(|overlay| overlay.downcast_ref())
+    }
+
+    /// Returns the overlay storing values of type `T`, creating it if needed.
+    pub fn overlay_mut<T: 'static>(&mut self) -> &mut Overlay<SystemId, T> {
+        self.overlays
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Overlay::<SystemId, T>::default()))
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// Attach an annotation to the connection from `from` to `to`.
+    pub fn annotate(&mut self, from: SystemId, to: SystemId, annotation: Annotation) {
+        self.annotations
+            .entry((from, to))
+            .or_default()
+            .push(annotation);
+    }
+
+    /// Registers a player-owned structure, e.g. fetched from ESI. Ansiblex gates also
+    /// add a connection to their destination.
+    pub fn add_structure(&mut self, structure: crate::structures::Structure) {
+        if let crate::structures::StructureKind::Ansiblex { destination } = structure.kind {
+            self.connections
+                .0
+                .entry(structure.system)
+                .or_default()
+                .push(Connection {
+                    from: structure.system,
+                    to: destination,
+                    r#type: ConnectionType::Ansiblex,
+                });
         }
+        self.structures.push(structure);
+    }
+
+    pub fn structures(&self) -> &[crate::structures::Structure] {
+        &self.structures
+    }
+
+    pub fn structure(&self, id: u64) -> Option<&crate::structures::Structure> {
+        self.structures.iter().find(|structure| structure.id == id)
+    }
+
+    /// The registered structures in a system.
+    pub fn structures_in(&self, system: &SystemId) -> Vec<&crate::structures::Structure> {
+        self.structures
+            .iter()
+            .filter(|structure| structure.system == *system)
+            .collect()
     }
 }
 
@@ -723,7 +2199,7 @@ impl<'u, U: Galaxy> Galaxy for ExtendedUniverse<'u, U> {
     }
 
     #[cfg(feature = "search")]
-    fn search<'a>(&'a self, query: &str) -> anyhow::Result<Vec<&'a System>> {
+    fn search<'a>(&'a self, query: &str) -> anyhow::Result<Vec<crate::search::SearchHit<'a>>> {
         self.universe.search(query)
     }
 
@@ -757,17 +2233,644 @@ impl<'b, U: Navigatable> Navigatable for ExtendedUniverse<'b, U> {
     fn get_systems_by_range<'a>(&self, from: &SystemId, range: Meters) -> Option<Vec<&System>> {
         self.universe.get_systems_by_range(from, range)
     }
+
+    fn all_systems(&self) -> Vec<&System> {
+        self.universe.all_systems()
+    }
+
+    fn avoided_by_default(&self, id: &SystemId) -> bool {
+        self.avoid.contains(id) || self.universe.avoided_by_default(id)
+    }
+
+    fn get_annotations(&self, from: &SystemId, to: &SystemId) -> &[Annotation] {
+        match self.annotations.get(&(*from, *to)) {
+            Some(annotations) => annotations,
+            None => self.universe.get_annotations(from, to),
+        }
+    }
+
+    fn version(&self) -> u64 {
+        let version = fingerprint(self.universe.version(), self.connections.pairs());
+        if self.avoid.is_empty() {
+            return version;
+        }
+        // avoided systems change the routes, u32::MAX separates them from the pairs
+        let mut avoid = self.avoid.iter().map(|id| id.0).collect_vec();
+        avoid.sort_unstable();
+        fingerprint(version, std::iter::once(u32::MAX).chain(avoid))
+    }
+}
+
+/// A view of a universe that hides all systems not matching a predicate and all
+/// connections leading into or out of hidden systems. Unlike `Universe::filter()`
+/// nothing is copied or scanned up front, the predicate is applied to the systems
+/// as they are looked up.
+///
+/// Predicates can't be compared, so the caller names each filter with a key. The
+/// key is part of the view's `version()`, e.g. to keep routes cached by
+/// `cache::RouteCache` for different filters apart. Views with the same key must
+/// hide the same systems.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::{Navigatable, SecurityClass, System, UniverseView};
+///
+/// let universe = UniverseBuilder::default()
+///     .system(System {
+///         id: 30000142.into(),
+///         name: "Jita".to_string(),
+///         coordinate: (0.0, 0.0, 0.0).into(),
+///         security: 0.95.into(),
+///         region_name: "The Forge".to_string(),
+///         faction_id: None,
+///     })
+///     .build();
+/// let lowsec = UniverseView::filtered(&universe, "lowsec", |s| {
+///     SecurityClass::from(s.security) == SecurityClass::Lowsec
+/// });
+/// assert!(lowsec.get_system(&30000142.into()).is_none());
+/// assert!(universe.get_system(&30000142.into()).is_some());
+/// ```
+pub struct UniverseView<'a, U: ?Sized> {
+    universe: &'a U,
+    predicate: Box<dyn Fn(&System) -> bool + 'a>,
+    version: u64,
+}
+
+impl<'a, U: Navigatable + ?Sized> UniverseView<'a, U> {
+    pub fn filtered(universe: &'a U, key: &str, predicate: impl Fn(&System) -> bool + 'a) -> Self {
+        Self {
+            universe,
+            version: fingerprint(universe.version(), key.bytes().map(u32::from)),
+            predicate: Box::new(predicate),
+        }
+    }
+
+    fn is_visible(&self, id: &SystemId) -> bool {
+        self.universe
+            .get_system(id)
+            .is_some_and(|system| (self.predicate)(system))
+    }
+}
+
+impl<U: ?Sized> std::fmt::Debug for UniverseView<'_, U> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UniverseView")
+            .field("version", &self.version)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<U: Galaxy + Navigatable + ?Sized> Galaxy for UniverseView<'_, U> {
+    fn systems(&self) -> Vec<&System> {
+        self.all_systems()
+    }
+
+    fn connections(&self) -> Vec<(SystemId, SystemId)> {
+        self.universe
+            .connections()
+            .into_iter()
+            .filter(|(from, to)| self.is_visible(from) && self.is_visible(to))
+            .collect()
+    }
+
+    #[cfg(feature = "search")]
+    fn search<'a>(&'a self, query: &str) -> anyhow::Result<Vec<crate::search::SearchHit<'a>>> {
+        Ok(self
+            .universe
+            .search(query)?
+            .into_iter()
+            .filter(|hit| (self.predicate)(hit.system))
+            .collect())
+    }
+
+    #[cfg(feature = "search")]
+    fn search_one<'a>(&'a self, query: &str) -> Option<&'a System> {
+        self.universe
+            .search_one(query)
+            .filter(|system| (self.predicate)(system))
+    }
+}
+
+impl<U: Navigatable + ?Sized> Navigatable for UniverseView<'_, U> {
+    fn get_system(&self, id: &SystemId) -> Option<&System> {
+        self.universe
+            .get_system(id)
+            .filter(|system| (self.predicate)(system))
+    }
+
+    fn get_connections(&self, from: &SystemId) -> Option<Vec<Connection>> {
+        if !self.is_visible(from) {
+            return None;
+        }
+        let connections = self.universe.get_connections(from)?;
+        Some(
+            connections
+                .into_iter()
+                .filter(|conn| self.is_visible(&conn.to))
+                .collect(),
+        )
+    }
+
+    fn get_systems_by_range(&self, from: &SystemId, range: Meters) -> Option<Vec<&System>> {
+        if !self.is_visible(from) {
+            return None;
+        }
+        let systems = self.universe.get_systems_by_range(from, range)?;
+        Some(
+            systems
+                .into_iter()
+                .filter(|system| (self.predicate)(system))
+                .collect(),
+        )
+    }
+
+    fn all_systems(&self) -> Vec<&System> {
+        self.universe
+            .all_systems()
+            .into_iter()
+            .filter(|system| (self.predicate)(system))
+            .collect()
+    }
+
+    fn get_annotations(&self, from: &SystemId, to: &SystemId) -> &[Annotation] {
+        self.universe.get_annotations(from, to)
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn avoided_by_default(&self, id: &SystemId) -> bool {
+        self.universe.avoided_by_default(id)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixtures::system;
+
+    #[test]
+    fn test_navigatable_defaults() {
+        struct Empty;
+        impl Navigatable for Empty {
+            fn get_system(&self, _: &SystemId) -> Option<&System> {
+                None
+            }
+            fn get_connections(&self, _: &SystemId) -> Option<Vec<Connection>> {
+                None
+            }
+            fn get_systems_by_range(&self, _: &SystemId, _: Meters) -> Option<Vec<&System>> {
+                None
+            }
+            fn all_systems(&self) -> Vec<&System> {
+                Vec::new()
+            }
+        }
+        assert!(Empty.neighbors(&30000142.into()).is_empty());
+    }
 
     #[test]
     fn test_ship_range_calculation() {
         let ly = JumpdriveShip::Titan(JumpdriveSkills::new(5, 1)).into();
         assert_eq!(Lightyears(6.0), ly);
     }
+
+    #[test]
+    fn test_connection_by_signature() {
+        let universe = crate::builder::UniverseBuilder::default().build();
+        let wormhole = Connection {
+            from: 31000001.into(),
+            to: 30000142.into(),
+            r#type: ConnectionType::Wormhole(Wormhole {
+                expires: Utc::now(),
+                remaining_hours: 4,
+                signature: "ABC-123".to_string(),
+                exit_signature: Some("XYZ-987".to_string()),
+                max_ship_size: WormholeMaxShipSize::XLarge,
+                shattered: None,
+            }),
+        };
+        let extended = ExtendedUniverse::new(&universe, vec![wormhole].into());
+
+        let conn = extended
+            .connection_by_signature(&31000001.into(), "abc-123")
+            .unwrap();
+        assert_eq!(SystemId(30000142), conn.to);
+        let conn = extended
+            .connection_by_signature(&30000142.into(), "XYZ-987")
+            .unwrap();
+        assert_eq!(SystemId(31000001), conn.from);
+        assert!(
+            extended
+                .connection_by_signature(&30000142.into(), "ABC-123")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_filter() {
+        let gate = |from: u32, to: u32| Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: ConnectionType::Stargate(StargateType::Local),
+        };
+        let universe = crate::builder::UniverseBuilder::default()
+            .system(system(1).with_security(0.9))
+            .system(system(2).with_security(0.1))
+            .system(system(3).with_security(0.7))
+            .connection(gate(1, 2))
+            .connection(gate(2, 3))
+            .connection(gate(3, 1))
+            .build();
+
+        let highsec =
+            universe.filter(|s| SecurityClass::from(s.security) == SecurityClass::Highsec);
+        assert_eq!(2, highsec.systems().len());
+        assert_eq!(vec![(SystemId(3), SystemId(1))], highsec.connections());
+        assert!(highsec.get_system(&2.into()).is_none());
+        assert_eq!(2, highsec.all_systems().len());
+        assert_eq!(vec![SystemId(1)], highsec.neighbors(&3.into()));
+        assert!(highsec.neighbors(&1.into()).is_empty());
+        assert!(universe.connection_between(&2.into(), &3.into()).is_some());
+        assert!(universe.connection_between(&3.into(), &2.into()).is_none());
+
+        let is_highsec = |s: &System| SecurityClass::from(s.security) == SecurityClass::Highsec;
+        let view = UniverseView::filtered(&universe, "highsec", is_highsec);
+        assert_eq!(2, view.all_systems().len());
+        assert_eq!(highsec.connections(), view.connections());
+        assert!(view.get_system(&2.into()).is_none());
+        assert!(view.get_connections(&1.into()).unwrap().is_empty());
+        assert_eq!(vec![SystemId(1)], view.neighbors(&3.into()));
+        assert_ne!(universe.version(), view.version());
+        let same = UniverseView::filtered(&universe, "highsec", is_highsec);
+        assert_eq!(view.version(), same.version());
+        let lowsec = UniverseView::filtered(&universe, "lowsec", |s| {
+            SecurityClass::from(s.security) == SecurityClass::Lowsec
+        });
+        assert_ne!(view.version(), lowsec.version());
+    }
+
+    #[test]
+    fn test_subset() {
+        let gate = |from: u32, to: u32| Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: ConnectionType::Stargate(StargateType::Local),
+        };
+        let universe = crate::builder::UniverseBuilder::default()
+            .system(system(1).with_security(-0.3).in_region("Delve"))
+            .system(system(2).with_security(-0.3).in_region("Delve"))
+            .system(system(3).with_security(-0.3).in_region("Querious"))
+            .system(system(4).with_security(-0.3).in_region("Period Basis"))
+            .connection(gate(1, 2))
+            .connection(gate(2, 3))
+            .connection(gate(3, 4))
+            .build();
+
+        let mut hierarchy = crate::hierarchy::Hierarchy::default();
+        hierarchy.insert_region(10000060.into(), "Delve");
+        hierarchy.insert_region(10000050.into(), "Querious");
+        hierarchy.insert_constellation(20000696.into(), 10000060.into(), "O-EIMK");
+        hierarchy.insert_constellation(20000697.into(), 10000060.into(), "YS-GOP");
+        hierarchy.insert_constellation(20000580.into(), 10000050.into(), "8-TFDX");
+        hierarchy.insert_system(1.into(), 20000696.into());
+        hierarchy.insert_system(2.into(), 20000697.into());
+        hierarchy.insert_system(3.into(), 20000580.into());
+        let universe = universe.with_hierarchy(hierarchy);
+
+        let subset = universe.subset_regions(&[RegionId(10000060), RegionId(10000050)]);
+        assert_eq!(3, subset.systems().len());
+        let mut connections = subset.connections();
+        connections.sort();
+        assert_eq!(
+            vec![(SystemId(1), SystemId(2)), (SystemId(2), SystemId(3))],
+            connections
+        );
+
+        let delve = universe.subset_regions(&[RegionId(10000060)]);
+        assert_eq!(vec![(SystemId(1), SystemId(2))], delve.connections());
+        assert_eq!(
+            Some(RegionId(10000060)),
+            delve.hierarchy().region_of(&1.into())
+        );
+        // the hierarchy only knows the remaining systems, regions and constellations
+        assert_eq!(2, delve.hierarchy().len());
+        assert_eq!(
+            vec![(RegionId(10000060), "Delve")],
+            delve.hierarchy().regions()
+        );
+        assert!(
+            delve
+                .hierarchy()
+                .constellation_name(&20000580.into())
+                .is_none()
+        );
+        let constellation =
+            universe.subset_constellations(&[ConstellationId(20000697), ConstellationId(20000580)]);
+        assert_eq!(
+            vec![(SystemId(2), SystemId(3))],
+            constellation.connections()
+        );
+    }
+
+    #[test]
+    fn test_drifter() {
+        let universe = crate::builder::UniverseBuilder::default()
+            .system(system(30000001).with_security(-1.0))
+            .system(system(31000001).with_security(-1.0))
+            .build();
+        let extended = crate::builder::ExtendedUniverseBuilder::new(&universe)
+            .drifter(
+                30000001.into(),
+                31000001.into(),
+                DrifterHive::Vidette,
+                "ABC-123",
+                Utc::now(),
+            )
+            .build();
+
+        let connections = extended.get_connections(&31000001.into()).unwrap();
+        assert_eq!(1, connections.len());
+        match &connections[0].r#type {
+            ConnectionType::Wormhole(wh) => {
+                assert_eq!(WormholeMaxShipSize::Large, wh.max_ship_size);
+                assert_eq!(16, wh.remaining_hours);
+            }
+            other => panic!("unexpected connection {other}"),
+        }
+        assert_eq!(
+            &[Annotation::Tag("Vidette".to_string())],
+            extended.get_annotations(&30000001.into(), &31000001.into())
+        );
+        for hive in DrifterHive::ALL {
+            assert_eq!(
+                WormholeDestination::Drifter(hive),
+                hive.wormhole_type().leads_to
+            );
+        }
+    }
+
+    #[test]
+    fn test_abyssal() {
+        let gate = |from: u32, to: u32| Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type: ConnectionType::Stargate(StargateType::Local),
+        };
+        let universe = crate::builder::UniverseBuilder::default()
+            .system(system(30000001).with_security(-0.99))
+            .system(system(32000001).with_security(-0.99))
+            .system(system(30000002).with_security(-0.99))
+            .connection(gate(30000001, 32000001))
+            .connection(gate(32000001, 30000002))
+            .build();
+
+        let abyssal = universe.get_system(&32000001.into()).unwrap();
+        assert_eq!(SystemClass::Abyssal, SystemClass::from(abyssal));
+        assert_eq!(SystemClass::Void, SystemClass::from(&SystemId(34000001)));
+        assert!(!abyssal.is_navigable());
+        assert!(universe.avoided_by_default(&abyssal.id));
+        let builder = || {
+            crate::navigation::PathBuilder::new(&universe)
+                .waypoint(universe.get_system(&30000001.into()).unwrap())
+                .waypoint(universe.get_system(&30000002.into()).unwrap())
+        };
+        assert!(builder().build().is_none());
+        // queryable when asked
+        assert_eq!(2, builder().default_avoid(false).build().unwrap().jumps());
+    }
+
+    #[test]
+    fn test_nullsec_class() {
+        assert_eq!(
+            None,
+            system(30000142)
+                .with_security(0.95)
+                .with_faction(Some(500001))
+                .nullsec_class()
+        );
+        assert_eq!(
+            Some(NullsecClass::Npc),
+            system(30003585)
+                .with_security(-0.05)
+                .with_faction(Some(500019))
+                .nullsec_class()
+        );
+        assert_eq!(
+            Some(NullsecClass::Sovereign),
+            system(30004759).with_security(-0.47).nullsec_class()
+        );
+        assert_eq!(None, system(31000005).with_security(-0.99).nullsec_class());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let universe = crate::builder::UniverseBuilder::default()
+            .system(system(30000142).named("Jita"))
+            .system(system(30002187).named("Amarr"))
+            .build();
+
+        fn names(found: Vec<Option<&System>>) -> Vec<Option<&str>> {
+            found
+                .into_iter()
+                .map(|system| system.map(|system| system.name.as_str()))
+                .collect()
+        }
+        assert_eq!(
+            vec![Some("Amarr"), None, Some("Jita"), Some("Amarr")],
+            names(universe.resolve_ids(&[30002187, 1, 30000142, 30002187]))
+        );
+        assert_eq!(
+            vec![Some("Jita"), Some("Amarr"), None, Some("Jita")],
+            names(universe.resolve_names(&["Jita", " amarr", "Dodixie", "JITA"]))
+        );
+
+        assert!(matches!(universe.resolve_any(30000142), Entity::System(_)));
+        assert!(matches!(universe.resolve_any(30000144), Entity::Unknown(_)));
+        assert!(matches!(
+            universe.resolve_any(10000002),
+            Entity::Region(10000002)
+        ));
+        assert!(matches!(
+            universe.resolve_any(20000020),
+            Entity::Constellation(20000020)
+        ));
+        assert!(matches!(
+            universe.resolve_any(61000990),
+            Entity::Station(61000990)
+        ));
+        assert!(matches!(
+            universe.resolve_any(1035466617946),
+            Entity::Structure(1035466617946)
+        ));
+        // characters
+        assert!(matches!(
+            universe.resolve_any(2112625428),
+            Entity::Unknown(_)
+        ));
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_reindex() {
+        // enough systems for several batches
+        let mut universe = (0..2500)
+            .fold(crate::builder::UniverseBuilder::default(), |builder, i| {
+                builder.system(system(30000000 + i).named(&format!("Sys{i}")))
+            })
+            .build();
+        assert_eq!(
+            SystemId(30002042),
+            universe.search_one("Sys2042").unwrap().id
+        );
+        for threads in [0, 3, 20] {
+            universe.reindex(threads).unwrap();
+            assert_eq!(SystemId(30000017), universe.search_one("Sys17").unwrap().id);
+        }
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_search_boosts() {
+        let mut universe = crate::builder::UniverseBuilder::default()
+            .system(system(30000142).named("Jita"))
+            .system(system(30001000).named("Jin"))
+            .build();
+        let first = |universe: &Universe| universe.search("ji").unwrap()[0].system.id;
+        assert_eq!(SystemId(30000142), first(&universe));
+
+        universe.set_search_boosts(crate::search::SearchBoosts::none());
+        assert_eq!(SystemId(30001000), first(&universe));
+        universe.set_search_boosts(crate::search::SearchBoosts::none().recent(10.0));
+        // searching doesn't change the ranking of later searches
+        assert_eq!(SystemId(30000142), universe.search_one("jita").unwrap().id);
+        assert_eq!(SystemId(30001000), first(&universe));
+        let recent = [SystemId(30000142)];
+        assert_eq!(
+            SystemId(30000142),
+            universe.search_recent("ji", &recent).unwrap()[0].system.id
+        );
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_search_exact() {
+        let universe = crate::builder::UniverseBuilder::default()
+            .system(system(30004759).named("1DQ1-A").with_security(-0.5))
+            .system(system(30004760).named("1DQ1-AA").with_security(-0.5))
+            .system(system(30000142).named("Jita").with_security(-0.5))
+            .build();
+        let search = |query: &str| {
+            universe
+                .search(query)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.system.id.0)
+                .collect::<Vec<_>>()
+        };
+
+        // the exact name first, then the other matches
+        assert_eq!(vec![30004759, 30004760], search("1dq1-a"));
+        assert_eq!(vec![30004760, 30004759], search(" 1DQ1-AA "));
+        assert_eq!(2, search("1DQ1").len());
+        // a trailing comma is no qualifier
+        assert_eq!(vec![30004759, 30004760], search("1DQ1-A, "));
+        assert_eq!(f32::MAX, universe.search("1DQ1-A").unwrap()[0].score);
+        assert!(universe.search("1DQ1-A").unwrap()[1].score < f32::MAX);
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_search_syntax() {
+        let universe = crate::builder::UniverseBuilder::default()
+            .system(system(30004759).named("1DQ1-A").with_security(-0.5))
+            .system(system(30003504).named("Niarja").with_security(-0.5))
+            .build();
+
+        for query in [
+            "+1DQ", "-A", "\"1dq", "1dq1 AND", "name:1DQ", "(1DQ", "[*", "^~", "", ":",
+        ] {
+            assert!(universe.search(query).is_ok(), "{query}");
+        }
+        assert_eq!(
+            SystemId(30004759),
+            universe.search("\"1DQ1-A").unwrap()[0].system.id
+        );
+        assert_eq!(
+            SystemId(30003504),
+            universe.search_one("30003504").unwrap().id
+        );
+        // a typo still shares most ngrams
+        assert_eq!(
+            SystemId(30003504),
+            universe.search_one("Niraja").unwrap().id
+        );
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_search_qualifier() {
+        let mut hierarchy = crate::hierarchy::Hierarchy::default();
+        hierarchy.insert_region(10000042.into(), "Metropolis");
+        hierarchy.insert_constellation(20000304.into(), 10000042.into(), "Hed");
+        hierarchy.insert_system(30002053.into(), 20000304.into());
+        let universe = crate::builder::UniverseBuilder::default()
+            .system(system(30002053).named("Hek").in_region(""))
+            .system(system(30001000).named("Heks").in_region("Domain"))
+            .build()
+            .with_hierarchy(hierarchy);
+        let ids = |query: &str| {
+            universe
+                .search(query)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.system.id.0)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(2, ids("Hek").len());
+        assert_eq!(vec![30002053], ids("Hek, Metropolis"));
+        assert_eq!(vec![30002053], ids("hek,metro"));
+        assert_eq!(vec![30002053], ids("Hek, Hed"));
+        let hit = &universe.search("Hek").unwrap()[0];
+        assert_eq!(
+            Some("Metropolis"),
+            hit.region.map(|region| region.name.as_str())
+        );
+        assert_eq!(f32::MAX, hit.score);
+        assert!(universe.search("Heks").unwrap()[0].region.is_none());
+        assert_eq!(vec![30001000], ids("Heks, domain"));
+        assert_eq!(2, ids("Hek, ").len());
+        assert!(ids("Hek, The Forge").is_empty());
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_search_qualifier_candidates() {
+        // more similar names outside the region than the index returns candidates
+        let universe = (0..60)
+            .fold(crate::builder::UniverseBuilder::default(), |builder, i| {
+                builder.system(system(30001000 + i).named(&format!("Hek {i}")))
+            })
+            .system(system(30002053).named("Hekataimos").in_region("Metropolis"))
+            .build();
+
+        assert!(
+            !universe
+                .search("Hek")
+                .unwrap()
+                .iter()
+                .any(|hit| hit.system.id == SystemId(30002053))
+        );
+        let hits = universe.search("Hek, Metropolis").unwrap();
+        assert_eq!(
+            vec![SystemId(30002053)],
+            hits.iter().map(|hit| hit.system.id).collect::<Vec<_>>()
+        );
+    }
 }
 
 #[cfg(all(test, feature = "sqlite"))]
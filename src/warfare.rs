@@ -0,0 +1,215 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! Faction warfare status of systems and route presets for militia pilots, e.g.
+//! FW logistics and plexing tools that want to stay in friendly space and away from
+//! the enemy's frontlines.
+//!
+//! The status is kept in an `Overlay`, usually attached to an `ExtendedUniverse`
+//! and refreshed from ESI's `/fw/systems/`.
+
+use crate::navigation::PathBuilder;
+use crate::overlay::Overlay;
+use crate::types;
+
+/// Extra cost of entering a system held by another militia, in jumps. A route
+/// through friendly space may be up to this many jumps longer, or take as long as
+/// this many stargate jumps with `Preference::Fastest`.
+pub const ENEMY_PENALTY: u32 = 5;
+
+/// The four empire militias.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Militia {
+    Amarr,
+    Caldari,
+    Gallente,
+    Minmatar,
+}
+
+impl Militia {
+    pub fn faction_id(&self) -> u32 {
+        match self {
+            Self::Caldari => 500001,
+            Self::Minmatar => 500002,
+            Self::Amarr => 500003,
+            Self::Gallente => 500004,
+        }
+    }
+
+    pub fn from_faction_id(id: u32) -> Option<Self> {
+        match id {
+            500001 => Some(Self::Caldari),
+            500002 => Some(Self::Minmatar),
+            500003 => Some(Self::Amarr),
+            500004 => Some(Self::Gallente),
+            _ => None,
+        }
+    }
+
+    /// The militia fighting over the same warzone.
+    pub fn enemy(&self) -> Self {
+        match self {
+            Self::Amarr => Self::Minmatar,
+            Self::Minmatar => Self::Amarr,
+            Self::Caldari => Self::Gallente,
+            Self::Gallente => Self::Caldari,
+        }
+    }
+}
+
+/// How close a system is to the other side's space. Frontline systems border
+/// enemy held systems and are where most fighting happens.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Adjacency {
+    Frontline,
+    CommandOperations,
+    Rearguard,
+}
+
+/// Status of a faction warfare system.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FwStatus {
+    pub occupier: Militia,
+    pub adjacency: Adjacency,
+    /// Victory points needed to flip the system, between 0.0 and 1.0.
+    pub contested: f32,
+}
+
+/// Prefers systems held by `militia` and avoids enemy frontline systems. Systems
+/// outside the warzones are treated like friendly systems.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::navigation::PathBuilder;
+/// use neweden::overlay::Overlay;
+/// use neweden::warfare::{prefer_militia, Adjacency, FwStatus, Militia};
+/// use neweden::{Connection, ConnectionType, Navigatable, StargateType, System};
+///
+/// let system = |id: u32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: 0.3.into(),
+///     region_name: "Black Rise".to_string(),
+///     faction_id: None,
+/// };
+/// let gate = |from: u32, to: u32| Connection {
+///     from: from.into(),
+///     to: to.into(),
+///     r#type: ConnectionType::Stargate(StargateType::Local),
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(30002813))
+///     .system(system(30002814))
+///     .connection(gate(30002813, 30002814))
+///     .build();
+/// let mut statuses = Overlay::default();
+/// statuses.insert(
+///     30002814.into(),
+///     FwStatus { occupier: Militia::Gallente, adjacency: Adjacency::Frontline, contested: 0.2 },
+/// );
+/// let builder = PathBuilder::new(&universe)
+///     .waypoint(universe.get_system(&30002813.into()).unwrap())
+///     .waypoint(universe.get_system(&30002814.into()).unwrap());
+/// assert!(prefer_militia(builder, &statuses, Militia::Caldari).build().is_none());
+/// ```
+pub fn prefer_militia<'a>(
+    builder: PathBuilder<'a>,
+    statuses: &'a Overlay<types::SystemId, FwStatus>,
+    militia: Militia,
+) -> PathBuilder<'a> {
+    let enemy = militia.enemy();
+    builder
+        .avoid_where(move |system| {
+            statuses.get(&system.id).is_some_and(|status| {
+                status.occupier == enemy && status.adjacency == Adjacency::Frontline
+            })
+        })
+        .penalize_where(
+            move |system| {
+                statuses
+                    .get(&system.id)
+                    .is_some_and(|status| status.occupier != militia)
+            },
+            ENEMY_PENALTY,
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+    use crate::navigation::{Preference, TravelTimes};
+    use crate::types::Navigatable;
+
+    fn status(occupier: Militia, adjacency: Adjacency) -> FwStatus {
+        FwStatus {
+            occupier,
+            adjacency,
+            contested: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_prefer_militia() {
+        // 1 - 2 - 6 is short, 1 - 3 - 4 - 5 - 6 stays in Caldari space
+        let mut builder = UniverseBuilder::default();
+        for id in 1..=6 {
+            builder = builder.system(system(id));
+        }
+        for (a, b) in [(1, 2), (2, 6), (1, 3), (3, 4), (4, 5), (5, 6)] {
+            for (from, to) in [(a, b), (b, a)] {
+                builder = builder.connection(types::Connection {
+                    from: from.into(),
+                    to: to.into(),
+                    r#type: types::ConnectionType::Stargate(types::StargateType::Local),
+                });
+            }
+        }
+        let universe = builder.build();
+        let mut statuses = Overlay::default();
+        statuses.insert(2.into(), status(Militia::Gallente, Adjacency::Rearguard));
+        for id in 3..=5 {
+            statuses.insert(id.into(), status(Militia::Caldari, Adjacency::Frontline));
+        }
+
+        let route = |militia, preference| {
+            let builder = PathBuilder::new(&universe)
+                .prefer(preference)
+                .waypoint(universe.get_system(&1.into()).unwrap())
+                .waypoint(universe.get_system(&6.into()).unwrap());
+            prefer_militia(builder, &statuses, militia)
+                .build()
+                .unwrap()
+                .systems()
+                .map(|system| system.id.0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![1, 3, 4, 5, 6],
+            route(Militia::Caldari, Preference::Shortest)
+        );
+        assert_eq!(
+            vec![1, 2, 6],
+            route(Militia::Gallente, Preference::Shortest)
+        );
+        // the penalty is in jumps, not seconds
+        let fastest = Preference::Fastest(TravelTimes::default());
+        assert_eq!(vec![1, 3, 4, 5, 6], route(Militia::Caldari, fastest));
+        statuses.insert(2.into(), status(Militia::Caldari, Adjacency::Frontline));
+        // both routes pass the Caldari frontline
+        let builder = PathBuilder::new(&universe)
+            .waypoint(universe.get_system(&1.into()).unwrap())
+            .waypoint(universe.get_system(&6.into()).unwrap());
+        assert!(
+            prefer_militia(builder, &statuses, Militia::Gallente)
+                .build()
+                .is_none()
+        );
+        assert_eq!(Militia::Amarr, Militia::from_faction_id(500003).unwrap());
+    }
+}
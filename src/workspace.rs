@@ -0,0 +1,408 @@
+/*
+ * Copyright (c) 2019. David "Tiran'Sol" Soria Parra
+ * All rights reserved.
+ */
+
+//! A base universe together with named sets of additional connections, e.g. the
+//! alliance's Ansiblex network and the current wormhole chain, and a cache of the
+//! routes computed through them.
+//!
+//! `ExtendedUniverse` borrows the universe it extends, so the workspace keeps the
+//! connections of each layer and builds extended universes from the layers on demand.
+//!
+//! Frequently used queries can be saved as named `Preset`s and run by name, e.g. to
+//! offer user-defined shortcuts in a bot. With the `serde` feature presets can be
+//! persisted using `Workspace::presets()` and `Workspace::set_presets()`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::builder::ExtendedUniverseBuilder;
+use crate::navigation::{PathBuilder, Route};
+use crate::rules;
+use crate::types;
+
+/// Connections and avoided systems added to the base universe under a name.
+#[derive(Debug, Clone, Default)]
+pub struct Extension {
+    pub connections: Vec<types::Connection>,
+    pub avoid: Vec<types::SystemId>,
+}
+
+/// A saved route query, e.g. a jump freighter run from Jita to 1DQ1-A through the
+/// alliance's Ansiblex network.
+///
+/// # Example
+/// ```
+/// use neweden::rules::{ShipProfile, ShipSize};
+/// use neweden::workspace::Preset;
+///
+/// let preset = Preset::new(30000142.into(), 30004759.into())
+///     .layer("ansiblex")
+///     .ship(ShipProfile::new(ShipSize::Capital).highsec(true));
+/// assert_eq!(vec!["ansiblex".to_string()], preset.layers);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Preset {
+    pub from: types::SystemId,
+    pub to: types::SystemId,
+    /// Names of the extensions to route through.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub layers: Vec<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ship: Option<rules::ShipProfile>,
+}
+
+impl Preset {
+    pub fn new(from: types::SystemId, to: types::SystemId) -> Self {
+        Self {
+            from,
+            to,
+            layers: Vec::new(),
+            ship: None,
+        }
+    }
+
+    pub fn layer(mut self, name: &str) -> Self {
+        self.layers.push(name.to_string());
+        self
+    }
+
+    /// Only uses systems and connections the ship can use.
+    pub fn ship(mut self, profile: rules::ShipProfile) -> Self {
+        self.ship = Some(profile);
+        self
+    }
+}
+
+type RouteKey = (
+    Vec<String>,
+    Option<rules::ShipProfile>,
+    types::SystemId,
+    types::SystemId,
+);
+
+/// Manages a universe, its extensions and cached routes as one unit.
+///
+/// # Example
+/// ```
+/// use neweden::builder::UniverseBuilder;
+/// use neweden::workspace::Workspace;
+/// use neweden::{Connection, ConnectionType, System};
+///
+/// let system = |id: u32| System {
+///     id: id.into(),
+///     name: id.to_string(),
+///     coordinate: (0.0, 0.0, 0.0).into(),
+///     security: (-0.4).into(),
+///     region_name: "Delve".to_string(),
+///     faction_id: None,
+/// };
+/// let universe = UniverseBuilder::default()
+///     .system(system(30004759))
+///     .system(system(30004760))
+///     .build();
+/// let mut workspace = Workspace::new(universe);
+/// workspace.extend(
+///     "ansiblex",
+///     [Connection {
+///         from: 30004759.into(),
+///         to: 30004760.into(),
+///         r#type: ConnectionType::Ansiblex,
+///     }],
+/// );
+///
+/// assert!(workspace.route(&[], &30004759.into(), &30004760.into()).is_none());
+/// let route = workspace.route(&["ansiblex"], &30004759.into(), &30004760.into());
+/// assert_eq!(1, route.unwrap().hops.len());
+/// ```
+pub struct Workspace {
+    universe: types::Universe,
+    extensions: BTreeMap<String, Extension>,
+    presets: BTreeMap<String, Preset>,
+    routes: Mutex<HashMap<RouteKey, Option<Route>>>,
+}
+
+impl Workspace {
+    pub fn new(universe: types::Universe) -> Self {
+        Self {
+            universe,
+            extensions: Default::default(),
+            presets: Default::default(),
+            routes: Default::default(),
+        }
+    }
+
+    pub fn universe(&self) -> &types::Universe {
+        &self.universe
+    }
+
+    /// Replaces the base universe, e.g. after loading a new SDE. Extensions are
+    /// kept, cached routes are dropped.
+    pub fn refresh(&mut self, universe: types::Universe) {
+        self.universe = universe;
+        self.clear_cache();
+    }
+
+    /// Adds connections to the named extension, creating it if necessary.
+    pub fn extend(&mut self, name: &str, connections: impl IntoIterator<Item = types::Connection>) {
+        self.extensions
+            .entry(name.to_string())
+            .or_default()
+            .connections
+            .extend(connections);
+        self.clear_cache();
+    }
+
+    /// Avoids the system by default whenever the named extension is used.
+    pub fn avoid(&mut self, name: &str, id: types::SystemId) {
+        self.extensions
+            .entry(name.to_string())
+            .or_default()
+            .avoid
+            .push(id);
+        self.clear_cache();
+    }
+
+    /// Replaces the named extension, e.g. with a freshly scanned wormhole chain.
+    pub fn replace(&mut self, name: &str, extension: Extension) -> Option<Extension> {
+        let old = self.extensions.insert(name.to_string(), extension);
+        self.clear_cache();
+        old
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Extension> {
+        let old = self.extensions.remove(name);
+        self.clear_cache();
+        old
+    }
+
+    pub fn extension(&self, name: &str) -> Option<&Extension> {
+        self.extensions.get(name)
+    }
+
+    /// Names of all extensions in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.extensions.keys().map(String::as_str)
+    }
+
+    /// The base universe extended with the named extensions. Unknown names are ignored.
+    pub fn view(&self, names: &[&str]) -> types::ExtendedUniverse<'_, types::Universe> {
+        let mut builder = ExtendedUniverseBuilder::new(&self.universe);
+        for extension in names.iter().filter_map(|name| self.extensions.get(*name)) {
+            for connection in &extension.connections {
+                builder = builder.connection(connection.clone());
+            }
+            for id in &extension.avoid {
+                builder = builder.avoid(*id);
+            }
+        }
+        builder.build()
+    }
+
+    /// The shortest route through the base universe and the named extensions.
+    /// Routes are cached until the universe or any extension changes.
+    pub fn route(
+        &self,
+        names: &[&str],
+        from: &types::SystemId,
+        to: &types::SystemId,
+    ) -> Option<Route> {
+        self.cached_route(names, None, from, to)
+    }
+
+    fn cached_route(
+        &self,
+        names: &[&str],
+        ship: Option<&rules::ShipProfile>,
+        from: &types::SystemId,
+        to: &types::SystemId,
+    ) -> Option<Route> {
+        let mut names = names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        let key = (names, ship.cloned(), *from, *to);
+        if let Some(route) = self.routes.lock().unwrap().get(&key) {
+            return route.clone();
+        }
+
+        let names = key.0.iter().map(String::as_str).collect::<Vec<_>>();
+        let route = self.shortest(&names, ship, from, to);
+        self.routes.lock().unwrap().insert(key, route.clone());
+        route
+    }
+
+    fn shortest(
+        &self,
+        names: &[&str],
+        ship: Option<&rules::ShipProfile>,
+        from: &types::SystemId,
+        to: &types::SystemId,
+    ) -> Option<Route> {
+        let universe = self.view(names);
+        let from = types::Navigatable::get_system(&universe, from)?;
+        let to = types::Navigatable::get_system(&universe, to)?;
+        let mut builder = PathBuilder::new(&universe).waypoint(from).waypoint(to);
+        if let Some(ship) = ship {
+            builder = builder.ship(ship.clone());
+        }
+        Some(builder.build()?.to_route())
+    }
+
+    /// Saves a query under a name, replacing the preset with the same name.
+    pub fn save_preset(&mut self, name: &str, preset: Preset) -> Option<Preset> {
+        self.presets.insert(name.to_string(), preset)
+    }
+
+    pub fn remove_preset(&mut self, name: &str) -> Option<Preset> {
+        self.presets.remove(name)
+    }
+
+    pub fn preset(&self, name: &str) -> Option<&Preset> {
+        self.presets.get(name)
+    }
+
+    /// All presets by name, e.g. to persist them.
+    pub fn presets(&self) -> &BTreeMap<String, Preset> {
+        &self.presets
+    }
+
+    /// Replaces all presets, e.g. with presets loaded from disk.
+    pub fn set_presets(&mut self, presets: BTreeMap<String, Preset>) {
+        self.presets = presets;
+    }
+
+    /// Runs the named preset. Returns `None` if there is no such preset or no route.
+    /// Routes are cached like the routes of `route()`.
+    pub fn run_preset(&self, name: &str) -> Option<Route> {
+        let preset = self.presets.get(name)?;
+        let names = preset.layers.iter().map(String::as_str).collect::<Vec<_>>();
+        self.cached_route(&names, preset.ship.as_ref(), &preset.from, &preset.to)
+    }
+
+    /// Number of cached routes, including cached misses.
+    pub fn cached(&self) -> usize {
+        self.routes.lock().unwrap().len()
+    }
+
+    pub fn clear_cache(&self) {
+        self.routes.lock().unwrap().clear();
+    }
+
+    #[cfg(feature = "search")]
+    pub fn search(&self, query: &str) -> anyhow::Result<Vec<crate::search::SearchHit<'_>>> {
+        self.universe.search(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::UniverseBuilder;
+    use crate::fixtures::system;
+
+    fn connection(from: u32, to: u32, r#type: types::ConnectionType) -> types::Connection {
+        types::Connection {
+            from: from.into(),
+            to: to.into(),
+            r#type,
+        }
+    }
+
+    #[test]
+    fn test_workspace() {
+        // 1 - 2 - 3 - 4 by stargates
+        let mut builder = UniverseBuilder::default().system(system(1));
+        for id in 2..=4 {
+            builder = builder.system(system(id)).connection(connection(
+                id - 1,
+                id,
+                types::ConnectionType::Stargate(types::StargateType::Local),
+            ));
+        }
+        let mut workspace = Workspace::new(builder.build());
+        workspace.extend(
+            "ansiblex",
+            [connection(1, 3, types::ConnectionType::Ansiblex)],
+        );
+        workspace.avoid("intel", 3.into());
+
+        let jumps = |workspace: &Workspace, names: &[&str]| {
+            workspace
+                .route(names, &1.into(), &4.into())
+                .map(|route| route.hops.len())
+        };
+        assert_eq!(Some(3), jumps(&workspace, &[]));
+        assert_eq!(Some(2), jumps(&workspace, &["ansiblex"]));
+        assert_eq!(Some(2), jumps(&workspace, &["ansiblex", "ansiblex"]));
+        assert_eq!(None, jumps(&workspace, &["ansiblex", "intel"]));
+        assert_eq!(3, workspace.cached());
+        assert_eq!(
+            vec!["ansiblex", "intel"],
+            workspace.names().collect::<Vec<_>>()
+        );
+
+        workspace.remove("ansiblex");
+        assert_eq!(0, workspace.cached());
+        assert_eq!(None, jumps(&workspace, &["ansiblex", "intel"]));
+
+        workspace.refresh(UniverseBuilder::default().system(system(1)).build());
+        assert_eq!(None, jumps(&workspace, &[]));
+    }
+
+    #[test]
+    fn test_presets() {
+        // 1 - 2 - 3 by stargates in nullsec, 1 - 3 by a wormhole too small for capitals
+        let mut workspace = Workspace::new(
+            UniverseBuilder::default()
+                .system(system(1).with_security(-0.4))
+                .system(system(2).with_security(-0.4))
+                .system(system(3).with_security(-0.4))
+                .connection(connection(
+                    1,
+                    2,
+                    types::ConnectionType::Stargate(types::StargateType::Local),
+                ))
+                .connection(connection(
+                    2,
+                    3,
+                    types::ConnectionType::Stargate(types::StargateType::Local),
+                ))
+                .build(),
+        );
+        let wormhole = types::WormholeTypeInfo::from_code("B274").unwrap();
+        workspace.extend(
+            "chain",
+            [connection(
+                1,
+                3,
+                types::ConnectionType::Wormhole(wormhole.wormhole("ABC-123", chrono::Utc::now())),
+            )],
+        );
+
+        let run = Preset::new(1.into(), 3.into()).layer("chain");
+        workspace.save_preset("run", run.clone());
+        workspace.save_preset(
+            "capital run",
+            run.ship(rules::ShipProfile::new(rules::ShipSize::Capital)),
+        );
+        assert_eq!(1, workspace.run_preset("run").unwrap().hops.len());
+        assert_eq!(2, workspace.run_preset("capital run").unwrap().hops.len());
+        assert_eq!(2, workspace.cached());
+        assert!(workspace.run_preset("unknown").is_none());
+
+        // presets can be saved and restored as a whole
+        let presets = workspace.presets().clone();
+        workspace.remove_preset("run");
+        assert!(workspace.preset("run").is_none());
+        workspace.set_presets(presets);
+        assert_eq!(
+            vec!["capital run", "run"],
+            workspace.presets().keys().collect::<Vec<_>>()
+        );
+    }
+}